@@ -0,0 +1,135 @@
+// asyncio variants of the field readers. The read runs on a pool of Rust
+// threads and settles an asyncio Future on the caller's event loop, so an
+// ASGI handler can await many parses at once without blocking the loop or
+// starting Python threads. The workers hold the GIL only to start the read,
+// which releases it at once, and to hand the result over.
+
+use pyo3::prelude::*;
+use pyo3::types::{PyCFunction, PyDict, PyTuple};
+use pyo3::IntoPyObjectExt;
+use std::sync::OnceLock;
+
+use crate::options::{self, Options};
+use crate::shutdown;
+use crate::stats;
+
+fn pool() -> &'static rayon::ThreadPool {
+    static POOL: OnceLock<rayon::ThreadPool> = OnceLock::new();
+    POOL.get_or_init(|| {
+        rayon::ThreadPoolBuilder::new()
+            .thread_name(|i| format!("accelerator-async-{}", i))
+            .build()
+            .expect("cannot start the async reader threads")
+    })
+}
+
+// Settle `future` with the read's outcome; called on the loop's own thread,
+// and a no-op if the awaiting task was cancelled meanwhile
+#[pyfunction]
+fn resolve(future: &Bound<'_, PyAny>, value: Bound<'_, PyAny>, failed: bool) -> PyResult<()> {
+    if future.call_method0("cancelled")?.is_truthy()? {
+        return Ok(());
+    }
+    future.call_method1(if failed { "set_exception" } else { "set_result" }, (value,))?;
+    Ok(())
+}
+
+// Run `read` with `options` on the pool and return an asyncio Future, on the
+// running event loop, for its result. Raises RuntimeError outside a
+// coroutine. Cancelling the Future cancels the CancelToken in `options`, if
+// there is one.
+pub fn spawn<'py, F>(py: Python<'py>, options: Option<Options>, read: F) -> PyResult<Bound<'py, PyAny>>
+where
+    F: FnOnce(Python<'_>, Option<Options>) -> PyResult<Py<PyAny>> + Send + 'static,
+{
+    shutdown::drain_at_exit(py)?;
+    let event_loop = py.import("asyncio")?.call_method0("get_running_loop")?;
+    let future = event_loop.call_method0("create_future")?;
+    if let Some(token) = options::cancel_token(&options) {
+        let on_done = PyCFunction::new_closure(py, None, None, move |args: &Bound<'_, PyTuple>, _: Option<&Bound<'_, PyDict>>| -> PyResult<()> {
+            if args.get_item(0)?.call_method0("cancelled")?.is_truthy()? {
+                token.cancel();
+            }
+            Ok(())
+        })?;
+        future.call_method1("add_done_callback", (on_done,))?;
+    }
+    let (event_loop, pending) = (event_loop.unbind(), future.clone().unbind());
+    pool().spawn(move || {
+        shutdown::attach(|py| {
+            let (value, failed) = match read(py, options) {
+                Ok(value) => (value, false),
+                Err(e) => (e.into_value(py).into_any(), true),
+            };
+            let event_loop = event_loop.bind(py);
+            // Nobody awaits on a loop that has been closed meanwhile
+            let handed_over = match event_loop.call_method0("is_closed").and_then(|closed| closed.is_truthy()) {
+                Ok(true) => Ok(()),
+                _ => wrap_pyfunction!(resolve, py).and_then(|resolve| {
+                    event_loop.call_method1("call_soon_threadsafe", (resolve, &pending, value, failed)).map(drop)
+                }),
+            };
+            if let Err(e) = handed_over {
+                e.write_unraisable(py, Some(pending.bind(py)));
+            }
+        });
+    });
+    Ok(future)
+}
+
+/// parse_scalar_field() as an awaitable: `await parse_scalar_field_async(path)`
+/// inside a coroutine. The parse runs on a Rust thread pool and the
+/// returned asyncio Future resolves on the running event loop, so many
+/// parses can be gathered concurrently. Cancelling the awaiting task stops
+/// the parse if `options` carry a CancelToken.
+#[pyfunction]
+#[pyo3(signature = (path, lenient = false, allow_magnitude = false, options = None))]
+pub fn parse_scalar_field_async<'py>(
+    py: Python<'py>,
+    path: String,
+    lenient: bool,
+    allow_magnitude: bool,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyAny>> {
+    spawn(py, options, move |py, options| crate::parse_scalar_field(py, path, lenient, allow_magnitude, options)?.into_py_any(py))
+}
+
+/// read_scalar_field() as an awaitable; see parse_scalar_field_async.
+#[pyfunction]
+#[pyo3(signature = (path, lenient = false, allow_magnitude = false, options = None))]
+pub fn read_scalar_field_async<'py>(
+    py: Python<'py>,
+    path: String,
+    lenient: bool,
+    allow_magnitude: bool,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyAny>> {
+    spawn(py, options, move |py, options| crate::read_scalar_field(py, path, lenient, allow_magnitude, options)?.into_py_any(py))
+}
+
+/// parse_vector_field() as an awaitable; see parse_scalar_field_async.
+#[pyfunction]
+#[pyo3(signature = (path, lenient = false, options = None))]
+pub fn parse_vector_field_async<'py>(py: Python<'py>, path: String, lenient: bool, options: Option<Options>) -> PyResult<Bound<'py, PyAny>> {
+    spawn(py, options, move |py, options| crate::parse_vector_field(py, path, lenient, options)?.into_py_any(py))
+}
+
+/// read_vector_field() as an awaitable; see parse_scalar_field_async.
+#[pyfunction]
+#[pyo3(signature = (path, lenient = false, options = None))]
+pub fn read_vector_field_async<'py>(py: Python<'py>, path: String, lenient: bool, options: Option<Options>) -> PyResult<Bound<'py, PyAny>> {
+    spawn(py, options, move |py, options| crate::read_vector_field(py, path, lenient, options)?.into_py_any(py))
+}
+
+/// field_stats() as an awaitable; see parse_scalar_field_async.
+#[pyfunction]
+#[pyo3(signature = (path, cell_zone = None, cell_set = None, options = None))]
+pub fn field_stats_async<'py>(
+    py: Python<'py>,
+    path: String,
+    cell_zone: Option<String>,
+    cell_set: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyAny>> {
+    spawn(py, options, move |py, options| stats::field_stats(py, path, cell_zone, cell_set, options)?.into_py_any(py))
+}
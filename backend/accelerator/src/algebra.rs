@@ -0,0 +1,552 @@
+// Expressions over volume fields for derived field output, e.g.
+// `0.5*magSqr(U)*rho` or `(U & vector(1,0,0))/mag(U)`. The grammar follows
+// calc.rs (+ - * / with parentheses, unary signs, math functions) plus
+// OpenFOAM's `&` (inner product) and `^` (cross product), which bind like *
+// and /. Fields are evaluated over their cell values followed by every
+// patch's face values, so the result carries a matching boundaryField.
+
+use numpy::{IntoPyArray, PyArrayMethods};
+use pyo3::exceptions::{PyFileNotFoundError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::dict::write_atomic;
+use crate::field::{calculated_patches, load_field, load_mesh, load_patches, mag_sqr, type_name, vol_field_values, FieldOutput, FieldValue};
+use crate::header::Dimensions;
+
+enum Expr {
+    Number(f64),
+    Field(String),
+    Neg(Box<Expr>),
+    Binary(u8, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_ws(&mut self) {
+        while self.text.get(self.pos).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_ws();
+        if self.text.get(self.pos) == Some(&c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error<T>(&self, msg: &str) -> Result<T, String> {
+        Err(format!("{} at offset {}", msg, self.pos))
+    }
+
+    fn take_while(&mut self, f: impl Fn(u8) -> bool) -> &str {
+        let start = self.pos;
+        while self.text.get(self.pos).is_some_and(|&b| f(b)) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.text[start..self.pos]).unwrap_or("")
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut value = self.term()?;
+        loop {
+            self.skip_ws();
+            match self.text.get(self.pos) {
+                Some(&op @ (b'+' | b'-')) => {
+                    self.pos += 1;
+                    value = Expr::Binary(op, Box::new(value), Box::new(self.term()?));
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut value = self.unary()?;
+        loop {
+            self.skip_ws();
+            match self.text.get(self.pos) {
+                Some(&op @ (b'*' | b'/' | b'&' | b'^')) => {
+                    self.pos += 1;
+                    value = Expr::Binary(op, Box::new(value), Box::new(self.unary()?));
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat(b'-') {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        if self.eat(b'+') {
+            return self.unary();
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        self.skip_ws();
+        let Some(&c) = self.text.get(self.pos) else {
+            return self.error("unexpected end of expression");
+        };
+        if c == b'(' {
+            self.pos += 1;
+            let value = self.expr()?;
+            return if self.eat(b')') { Ok(value) } else { self.error("expected ')'") };
+        }
+        if c.is_ascii_digit() || c == b'.' {
+            let start = self.pos;
+            self.take_while(|b| b.is_ascii_digit() || b == b'.');
+            if matches!(self.text.get(self.pos), Some(b'e' | b'E')) {
+                self.pos += 1;
+                if matches!(self.text.get(self.pos), Some(b'+' | b'-')) {
+                    self.pos += 1;
+                }
+                self.take_while(|b| b.is_ascii_digit());
+            }
+            return match std::str::from_utf8(&self.text[start..self.pos]).ok().and_then(|s| s.parse().ok()) {
+                Some(v) => Ok(Expr::Number(v)),
+                None => self.error("malformed number"),
+            };
+        }
+        // Field names may contain dots (alpha.water) and colons
+        let name = self.take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b':')).to_string();
+        if name.is_empty() {
+            return self.error(&format!("unexpected '{}'", c as char));
+        }
+        if !self.eat(b'(') {
+            return Ok(if name == "pi" { Expr::Number(std::f64::consts::PI) } else { Expr::Field(name) });
+        }
+        let mut args = Vec::new();
+        if !self.eat(b')') {
+            loop {
+                args.push(self.expr()?);
+                if self.eat(b')') {
+                    break;
+                }
+                if !self.eat(b',') {
+                    return self.error("expected ',' or ')'");
+                }
+            }
+        }
+        Ok(Expr::Call(name, args))
+    }
+}
+
+fn parse_expression(expr: &str) -> Result<Expr, String> {
+    let mut parser = Parser { text: expr.as_bytes(), pos: 0 };
+    let value = parser.expr()?;
+    parser.skip_ws();
+    if parser.pos != parser.text.len() {
+        return parser.error("unexpected trailing input");
+    }
+    Ok(value)
+}
+
+// Intermediate result: `width` components per value, either one value for
+// every cell and boundary face or a single uniform value. Dimensions are
+// None for plain numbers, which combine with anything.
+#[derive(Clone)]
+struct Operand {
+    width: usize,
+    uniform: bool,
+    values: Vec<f64>,
+    dimensions: Option<Dimensions>,
+}
+
+impl Operand {
+    fn at(&self, i: usize) -> &[f64] {
+        if self.uniform { &self.values } else { &self.values[i * self.width..(i + 1) * self.width] }
+    }
+}
+
+fn scale_dimensions(d: Option<Dimensions>, factor: f64) -> Option<Dimensions> {
+    d.map(|d| d.map(|x| x * factor))
+}
+
+fn sum_dimensions(a: Option<Dimensions>, b: Option<Dimensions>, sign: f64) -> Option<Dimensions> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(std::array::from_fn(|k| a[k] + sign * b[k])),
+        (a, None) => a,
+        (None, b) => scale_dimensions(b, sign),
+    }
+}
+
+fn same_dimensions(what: &str, a: Option<Dimensions>, b: Option<Dimensions>) -> Result<Option<Dimensions>, String> {
+    match (a, b) {
+        (Some(a), Some(b)) if a != b => Err(format!("{}: inconsistent dimensions {:?} and {:?}", what, a, b)),
+        (a, b) => Ok(a.or(b)),
+    }
+}
+
+// symmTensor components (xx xy xz yy yz zz) as a full tensor
+fn full_tensor(s: &[f64]) -> [f64; 9] {
+    [s[0], s[1], s[2], s[1], s[3], s[4], s[2], s[4], s[5]]
+}
+
+fn component_index(width: usize, name: &str) -> Option<usize> {
+    let names: &[&str] = match width {
+        3 => &["x", "y", "z"],
+        6 => &["xx", "xy", "xz", "yy", "yz", "zz"],
+        9 => &["xx", "xy", "xz", "yx", "yy", "yz", "zx", "zy", "zz"],
+        _ => return None,
+    };
+    names.iter().position(|&n| n == name)
+}
+
+fn type_label(width: usize) -> &'static str {
+    type_name(width).unwrap_or("value")
+}
+
+struct Evaluator<F> {
+    n: usize,
+    load: F,
+    fields: HashMap<String, Operand>,
+}
+
+impl<F: FnMut(&str) -> Result<Option<Operand>, String>> Evaluator<F> {
+    // Applies `f` per value; the result is uniform only if every input is
+    fn map(&self, inputs: &[&Operand], width: usize, dimensions: Option<Dimensions>, f: impl Fn(&[&[f64]], &mut [f64])) -> Operand {
+        let uniform = inputs.iter().all(|o| o.uniform);
+        let n = if uniform { 1 } else { self.n };
+        let mut values = vec![0.0; n * width];
+        let mut args = Vec::with_capacity(inputs.len());
+        for (i, out) in values.chunks_exact_mut(width).enumerate() {
+            args.clear();
+            args.extend(inputs.iter().map(|o| o.at(i)));
+            f(&args, out);
+        }
+        Operand { width, uniform, values, dimensions }
+    }
+
+    fn field(&mut self, name: &str) -> Result<Operand, String> {
+        if let Some(op) = self.fields.get(name) {
+            return Ok(op.clone());
+        }
+        let op = match (self.load)(name)? {
+            Some(op) => op,
+            // U.x, sigma.xy
+            None => {
+                let (base, comp) = name.rsplit_once('.').ok_or_else(|| format!("no field '{}'", name))?;
+                let base = self.field(base).map_err(|_| format!("no field '{}'", name))?;
+                let k = component_index(base.width, comp)
+                    .ok_or_else(|| format!("'{}' is not a component of a {}", comp, type_label(base.width)))?;
+                self.map(&[&base], 1, base.dimensions, |a, out| out[0] = a[0][k])
+            }
+        };
+        self.fields.insert(name.to_string(), op.clone());
+        Ok(op)
+    }
+
+    fn binary(&self, op: u8, a: &Operand, b: &Operand) -> Result<Operand, String> {
+        let (wa, wb) = (a.width, b.width);
+        let mismatch = || format!("cannot apply '{}' to {} and {}", op as char, type_label(wa), type_label(wb));
+        Ok(match op {
+            b'+' | b'-' => {
+                if wa != wb {
+                    return Err(mismatch());
+                }
+                let dims = same_dimensions(&format!("'{}'", op as char), a.dimensions, b.dimensions)?;
+                let sign = if op == b'+' { 1.0 } else { -1.0 };
+                self.map(&[a, b], wa, dims, |x, out| {
+                    for (k, o) in out.iter_mut().enumerate() {
+                        *o = x[0][k] + sign * x[1][k];
+                    }
+                })
+            }
+            b'*' => {
+                let dims = sum_dimensions(a.dimensions, b.dimensions, 1.0);
+                match (wa, wb) {
+                    (1, w) => self.map(&[a, b], w, dims, |x, out| out.iter_mut().zip(x[1]).for_each(|(o, v)| *o = x[0][0] * v)),
+                    (w, 1) => self.map(&[a, b], w, dims, |x, out| out.iter_mut().zip(x[0]).for_each(|(o, v)| *o = v * x[1][0])),
+                    // Outer product
+                    (3, 3) => self.map(&[a, b], 9, dims, |x, out| {
+                        for (k, o) in out.iter_mut().enumerate() {
+                            *o = x[0][k / 3] * x[1][k % 3];
+                        }
+                    }),
+                    _ => return Err(mismatch()),
+                }
+            }
+            b'/' => {
+                if wb != 1 {
+                    return Err(mismatch());
+                }
+                let dims = sum_dimensions(a.dimensions, b.dimensions, -1.0);
+                self.map(&[a, b], wa, dims, |x, out| out.iter_mut().zip(x[0]).for_each(|(o, v)| *o = v / x[1][0]))
+            }
+            b'&' => {
+                let dims = sum_dimensions(a.dimensions, b.dimensions, 1.0);
+                let tensor = |v: &[f64]| if v.len() == 6 { full_tensor(v) } else { std::array::from_fn(|k| v[k]) };
+                match (wa, wb) {
+                    (3, 3) => self.map(&[a, b], 1, dims, |x, out| out[0] = (0..3).map(|k| x[0][k] * x[1][k]).sum()),
+                    (6 | 9, 3) => self.map(&[a, b], 3, dims, |x, out| {
+                        let t = tensor(x[0]);
+                        for (i, o) in out.iter_mut().enumerate() {
+                            *o = (0..3).map(|k| t[i * 3 + k] * x[1][k]).sum();
+                        }
+                    }),
+                    (3, 6 | 9) => self.map(&[a, b], 3, dims, |x, out| {
+                        let t = tensor(x[1]);
+                        for (j, o) in out.iter_mut().enumerate() {
+                            *o = (0..3).map(|k| x[0][k] * t[k * 3 + j]).sum();
+                        }
+                    }),
+                    (6 | 9, 6 | 9) => self.map(&[a, b], 9, dims, |x, out| {
+                        let (s, t) = (tensor(x[0]), tensor(x[1]));
+                        for (ij, o) in out.iter_mut().enumerate() {
+                            *o = (0..3).map(|k| s[ij / 3 * 3 + k] * t[k * 3 + ij % 3]).sum();
+                        }
+                    }),
+                    _ => return Err(mismatch()),
+                }
+            }
+            b'^' => {
+                if (wa, wb) != (3, 3) {
+                    return Err(mismatch());
+                }
+                let dims = sum_dimensions(a.dimensions, b.dimensions, 1.0);
+                self.map(&[a, b], 3, dims, |x, out| {
+                    let (u, v) = (x[0], x[1]);
+                    out[0] = u[1] * v[2] - u[2] * v[1];
+                    out[1] = u[2] * v[0] - u[0] * v[2];
+                    out[2] = u[0] * v[1] - u[1] * v[0];
+                })
+            }
+            _ => return Err(format!("unknown operator '{}'", op as char)),
+        })
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr]) -> Result<Operand, String> {
+        let args = args.iter().map(|a| self.eval(a)).collect::<Result<Vec<_>, _>>()?;
+        let arity = |n: usize| {
+            if args.len() == n { Ok(()) } else { Err(format!("{}() takes {} argument(s), got {}", name, n, args.len())) }
+        };
+        let scalar = |a: &Operand| {
+            if a.width == 1 { Ok(()) } else { Err(format!("{}() needs a scalar, got a {}", name, type_label(a.width))) }
+        };
+        Ok(match name {
+            "mag" | "magSqr" => {
+                arity(1)?;
+                let a = &args[0];
+                if name == "mag" {
+                    self.map(&[a], 1, a.dimensions, |x, out| out[0] = mag_sqr(x[0]).sqrt())
+                } else {
+                    self.map(&[a], 1, scale_dimensions(a.dimensions, 2.0), |x, out| out[0] = mag_sqr(x[0]))
+                }
+            }
+            "sqr" => {
+                arity(1)?;
+                let a = &args[0];
+                let dims = scale_dimensions(a.dimensions, 2.0);
+                match a.width {
+                    1 => self.map(&[a], 1, dims, |x, out| out[0] = x[0][0] * x[0][0]),
+                    // Outer product with itself, a symmTensor
+                    3 => self.map(&[a], 6, dims, |x, out| {
+                        let v = x[0];
+                        out.copy_from_slice(&[v[0] * v[0], v[0] * v[1], v[0] * v[2], v[1] * v[1], v[1] * v[2], v[2] * v[2]]);
+                    }),
+                    w => return Err(format!("sqr() needs a scalar or vector, got a {}", type_label(w))),
+                }
+            }
+            "sqrt" => {
+                arity(1)?;
+                scalar(&args[0])?;
+                self.map(&[&args[0]], 1, scale_dimensions(args[0].dimensions, 0.5), |x, out| out[0] = x[0][0].sqrt())
+            }
+            "pow" => {
+                arity(2)?;
+                scalar(&args[0])?;
+                scalar(&args[1])?;
+                let (a, e) = (&args[0], &args[1]);
+                let dims = if e.uniform { scale_dimensions(a.dimensions, e.values[0]) } else { a.dimensions.map(|_| [0.0; 7]) };
+                self.map(&[a, e], 1, dims, |x, out| out[0] = x[0][0].powf(x[1][0]))
+            }
+            "exp" | "log" | "log10" | "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "sinh" | "cosh" | "tanh" => {
+                arity(1)?;
+                scalar(&args[0])?;
+                let f: fn(f64) -> f64 = match name {
+                    "exp" => f64::exp,
+                    "log" => f64::ln,
+                    "log10" => f64::log10,
+                    "sin" => f64::sin,
+                    "cos" => f64::cos,
+                    "tan" => f64::tan,
+                    "asin" => f64::asin,
+                    "acos" => f64::acos,
+                    "atan" => f64::atan,
+                    "sinh" => f64::sinh,
+                    "cosh" => f64::cosh,
+                    _ => f64::tanh,
+                };
+                let dims = args[0].dimensions.map(|_| [0.0; 7]);
+                self.map(&[&args[0]], 1, dims, |x, out| out[0] = f(x[0][0]))
+            }
+            "abs" => {
+                arity(1)?;
+                let a = &args[0];
+                self.map(&[a], a.width, a.dimensions, |x, out| out.iter_mut().zip(x[0]).for_each(|(o, v)| *o = v.abs()))
+            }
+            "min" | "max" => {
+                arity(2)?;
+                let (a, b) = (&args[0], &args[1]);
+                if a.width != b.width {
+                    return Err(format!("{}() of {} and {}", name, type_label(a.width), type_label(b.width)));
+                }
+                let dims = same_dimensions(&format!("{}()", name), a.dimensions, b.dimensions)?;
+                let f: fn(f64, f64) -> f64 = if name == "min" { f64::min } else { f64::max };
+                self.map(&[a, b], a.width, dims, |x, out| {
+                    for (k, o) in out.iter_mut().enumerate() {
+                        *o = f(x[0][k], x[1][k]);
+                    }
+                })
+            }
+            "tr" => {
+                arity(1)?;
+                let a = &args[0];
+                let diagonal: [usize; 3] = match a.width {
+                    6 => [0, 3, 5],
+                    9 => [0, 4, 8],
+                    w => return Err(format!("tr() needs a tensor, got a {}", type_label(w))),
+                };
+                self.map(&[a], 1, a.dimensions, |x, out| out[0] = diagonal.iter().map(|&k| x[0][k]).sum())
+            }
+            "vector" => {
+                arity(3)?;
+                let mut dims = None;
+                for a in &args {
+                    scalar(a)?;
+                    dims = same_dimensions("vector()", dims, a.dimensions)?;
+                }
+                self.map(&[&args[0], &args[1], &args[2]], 3, dims, |x, out| {
+                    for (o, v) in out.iter_mut().zip(x) {
+                        *o = v[0];
+                    }
+                })
+            }
+            _ => return Err(format!("unknown function '{}'", name)),
+        })
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Operand, String> {
+        match expr {
+            Expr::Number(v) => Ok(Operand { width: 1, uniform: true, values: vec![*v], dimensions: None }),
+            Expr::Field(name) => self.field(name),
+            Expr::Neg(a) => {
+                let a = self.eval(a)?;
+                Ok(self.map(&[&a], a.width, a.dimensions, |x, out| out.iter_mut().zip(x[0]).for_each(|(o, v)| *o = -v)))
+            }
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (self.eval(a)?, self.eval(b)?);
+                self.binary(*op, &a, &b)
+            }
+            Expr::Call(name, args) => self.call(name, args),
+        }
+    }
+}
+
+// Evaluates `expression` over the volume fields of `time` and writes the
+// result as field `name` there. Returns the path written, n_cells, the
+// result's width and its cell values.
+fn write_expression(
+    root: &Path,
+    time: &str,
+    name: &str,
+    expression: &str,
+    dimensions: Option<Dimensions>,
+    binary: bool,
+) -> PyResult<(String, usize, usize, Vec<f64>)> {
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(PyValueError::new_err(format!("'{}' is not a valid field name", name)));
+    }
+    let dir = root.join(time);
+    if !dir.is_dir() {
+        return Err(PyFileNotFoundError::new_err(format!("{}: no such time directory", dir.display())));
+    }
+    let expr = parse_expression(expression).map_err(|e| PyValueError::new_err(format!("{}: {}", expression, e)))?;
+    let mesh = load_mesh(root, None)?;
+    let patches = load_patches(root, None)?;
+    let n = mesh.n_cells + patches.iter().map(|p| p.n_faces).sum::<usize>();
+
+    let load = |field: &str| -> Result<Option<Operand>, String> {
+        let path = dir.join(field);
+        let error = |e: String| format!("{}: {}", path.display(), e);
+        let Some(file) = load_field(&path).map_err(|e| e.to_string())? else {
+            return Ok(None);
+        };
+        let values = vol_field_values(&mesh, &patches, &file).map_err(error)?;
+        Ok(Some(Operand { width: file.width, uniform: false, values, dimensions: file.dimensions }))
+    };
+    let mut evaluator = Evaluator { n, load, fields: HashMap::new() };
+    let result = evaluator.eval(&expr).map_err(|e| PyValueError::new_err(format!("{}: {}", expression, e)))?;
+    let width = result.width;
+    let values = if result.uniform { result.values.repeat(n) } else { result.values };
+    if let Some(i) = values.iter().position(|v| !v.is_finite()) {
+        let item = i / width;
+        let place = if item < mesh.n_cells { format!("cell {}", item) } else { format!("boundary face {}", item - mesh.n_cells) };
+        return Err(PyValueError::new_err(format!("{}: non-finite value at {}", expression, place)));
+    }
+
+    let split = mesh.n_cells * width;
+    let output = FieldOutput {
+        kind: "vol",
+        object: name.to_string(),
+        location: time.to_string(),
+        dimensions: dimensions.or(result.dimensions).unwrap_or([0.0; 7]),
+        width,
+        internal: FieldValue::NonUniform(values[..split].to_vec()),
+        patches: calculated_patches(&patches, &values[split..], width),
+        binary,
+    };
+    let path = dir.join(name);
+    write_atomic(&path, output.to_bytes())?;
+    let mut cells = values;
+    cells.truncate(split);
+    Ok((path.to_string_lossy().into_owned(), mesh.n_cells, width, cells))
+}
+
+/// Evaluates a field expression such as `0.5*magSqr(U)*rho` over the volume
+/// fields of time directory `time` and writes the result there as field
+/// `name` (calculated patches holding the boundary values), so ParaView and
+/// the other readers pick it up. Operators are + - * / with parentheses,
+/// `&` (inner product) and `^` (cross product); functions are mag, magSqr,
+/// sqr, sqrt, pow, exp, log, log10, the trigonometric and hyperbolic
+/// functions, abs, min, max, tr and vector(x, y, z). Components are `U.x`,
+/// `R.xy`. Dimensions follow the algebra unless `dimensions` is given.
+/// Returns a dict with the `path` written and the cell `values`
+/// ((n_cells,) or (n_cells, components)).
+#[pyfunction]
+#[pyo3(signature = (case_root, time, name, expression, dimensions = None, binary = false))]
+pub fn write_field_expression<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    name: String,
+    expression: String,
+    dimensions: Option<Dimensions>,
+    binary: bool,
+) -> PyResult<Bound<'py, PyDict>> {
+    let (path, n_cells, width, values) =
+        py.detach(|| write_expression(Path::new(&case_root), &time, &name, &expression, dimensions, binary))?;
+    let out = PyDict::new(py);
+    out.set_item("path", path)?;
+    let array = values.into_pyarray(py);
+    if width == 1 {
+        out.set_item("values", array)?;
+    } else {
+        out.set_item("values", array.reshape([n_cells, width])?)?;
+    }
+    Ok(out)
+}
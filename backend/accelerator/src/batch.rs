@@ -0,0 +1,46 @@
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+use rayon::prelude::*;
+
+use crate::options::{self, Options};
+use crate::source::open_field_file;
+use crate::{check_internal_count, find_internal_field, header_width, internal_field_means, is_empty_list};
+
+// Per-component means of one field file; any failure reads as missing so a
+// single bad file doesn't sink the whole batch. An empty list (`0()`) gives
+// NaNs.
+pub fn field_means(path: &str) -> Option<Vec<f64>> {
+    let data = open_field_file(path).ok()??;
+    let field = find_internal_field(&data)?;
+    check_internal_count(&data, &field).ok()?;
+    if is_empty_list(&field) {
+        return Some(vec![f64::NAN; header_width(&data).unwrap_or(1)]);
+    }
+    internal_field_means(&field)
+}
+
+// Scalars become floats, vectors 3-tuples and tensors lists of components
+pub fn means_to_py<'py>(py: Python<'py>, means: Option<Vec<f64>>) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match means {
+        None => py.None().into_bound(py),
+        Some(m) if m.len() == 1 => m[0].into_pyobject(py)?.into_any(),
+        Some(m) if m.len() == 3 => PyTuple::new(py, m)?.into_any(),
+        Some(m) => m.into_pyobject(py)?.into_any(),
+    })
+}
+
+/// Parse many field files in parallel with the GIL released. Returns a dict
+/// mapping each path to its mean: a float for scalar fields, an (x, y, z)
+/// tuple for vectors, a list of components for tensors, or None if the file
+/// is missing or unparsable. Empty lists (`0()`) give NaN means.
+#[pyfunction]
+#[pyo3(signature = (paths, options = None))]
+pub fn parse_fields<'py>(py: Python<'py>, paths: Vec<String>, options: Option<Options>) -> PyResult<Bound<'py, PyDict>> {
+    let results: Vec<Option<Vec<f64>>> = options::detach(py, &options, || paths.par_iter().map(|p| field_means(p)).collect());
+
+    let out = PyDict::new(py);
+    for (path, means) in paths.into_iter().zip(results) {
+        out.set_item(path, means_to_py(py, means)?)?;
+    }
+    Ok(out)
+}
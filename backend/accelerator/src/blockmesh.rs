@@ -0,0 +1,347 @@
+use numpy::{IntoPyArray, PyArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::dict::{load_dict, value_to_py, Dict, Value};
+
+// Vertex labels of the six hex faces, for "(block face)" patch entries
+const HEX_FACES: [[usize; 4]; 6] = [[0, 4, 7, 3], [1, 2, 6, 5], [0, 1, 5, 4], [3, 7, 6, 2], [0, 3, 2, 1], [4, 5, 6, 7]];
+
+struct Block {
+    vertices: Vec<usize>,
+    zone: Option<String>,
+    cells: [usize; 3],
+    grading_type: Option<String>,
+    grading: Vec<Value>,
+}
+
+struct Edge {
+    kind: String,
+    start: usize,
+    end: usize,
+    points: Vec<[f64; 3]>,
+    // arc given by its centre ("arc 0 1 origin (0 0 0)")
+    origin: bool,
+}
+
+struct Patch {
+    name: String,
+    kind: String,
+    faces: Vec<[usize; 4]>,
+}
+
+struct BlockMesh {
+    scale: f64,
+    vertices: Vec<[f64; 3]>,
+    blocks: Vec<Block>,
+    edges: Vec<Edge>,
+    patches: Vec<Patch>,
+    default_patch: Option<(String, String)>,
+}
+
+fn point(v: &Value) -> Option<[f64; 3]> {
+    match v {
+        Value::List(items) if items.len() == 3 => Some([items[0].number()?, items[1].number()?, items[2].number()?]),
+        _ => None,
+    }
+}
+
+// Entries such as "vertices ( ... );" are a single list value
+fn list_entry<'a>(dict: &'a Dict, key: &str) -> &'a [Value] {
+    match dict.stream(key) {
+        Some([Value::List(items)]) => items,
+        _ => &[],
+    }
+}
+
+struct Labels {
+    names: HashMap<String, usize>,
+    n_vertices: usize,
+}
+
+impl Labels {
+    // A vertex index, or a name given with "name v0 (x y z)"
+    fn get(&self, v: &Value) -> Result<usize, String> {
+        let label = match v {
+            Value::Word(w) => w.parse().ok().or_else(|| self.names.get(w).copied()),
+            _ => None,
+        };
+        match label {
+            Some(l) if l < self.n_vertices => Ok(l),
+            Some(l) => Err(format!("vertex {} out of range ({} vertices)", l, self.n_vertices)),
+            None => Err(format!("expected a vertex label, got {:?}", v)),
+        }
+    }
+
+    fn list(&self, v: &Value) -> Result<Vec<usize>, String> {
+        match v {
+            Value::List(items) => items.iter().map(|i| self.get(i)).collect(),
+            _ => Err(format!("expected a list of vertex labels, got {:?}", v)),
+        }
+    }
+}
+
+fn parse_vertices(items: &[Value]) -> Result<(Vec<[f64; 3]>, Labels), String> {
+    let mut vertices = Vec::new();
+    let mut names = HashMap::new();
+    let mut iter = items.iter();
+    while let Some(item) = iter.next() {
+        match item {
+            Value::Word(w) if w == "name" => {
+                if let Some(Value::Word(name)) = iter.next() {
+                    names.insert(name.clone(), vertices.len());
+                }
+            }
+            // "project (x y z) (geometry)": the point before projection
+            Value::Word(w) if w == "project" => {}
+            Value::List(items) if items.iter().all(|v| matches!(v, Value::Word(w) if w.parse::<f64>().is_err())) => {}
+            _ => vertices.push(point(item).ok_or_else(|| format!("bad vertex {:?}", item))?),
+        }
+    }
+    let labels = Labels { names, n_vertices: vertices.len() };
+    Ok((vertices, labels))
+}
+
+fn parse_blocks(items: &[Value], labels: &Labels) -> Result<Vec<Block>, String> {
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < items.len() {
+        // hex (labels) [zone] (nx ny nz) [simpleGrading|edgeGrading (...)]
+        if !matches!(&items[i], Value::Word(w) if w == "hex") {
+            return Err(format!("expected 'hex' in blocks, got {:?}", items[i]));
+        }
+        let vertices = labels.list(items.get(i + 1).ok_or("truncated block")?)?;
+        if vertices.len() != 8 {
+            return Err(format!("hex block needs 8 vertices, got {}", vertices.len()));
+        }
+        i += 2;
+        let zone = match items.get(i) {
+            Some(Value::Word(w)) => {
+                i += 1;
+                Some(w.clone())
+            }
+            _ => None,
+        };
+        let cells = match items.get(i) {
+            Some(Value::List(n)) if n.len() == 3 => {
+                let mut cells = [0usize; 3];
+                for (c, v) in cells.iter_mut().zip(n) {
+                    *c = v.number().filter(|x| *x >= 1.0).ok_or("bad block cell count")? as usize;
+                }
+                cells
+            }
+            other => return Err(format!("expected block cell counts, got {:?}", other)),
+        };
+        i += 1;
+        let (grading_type, grading) = match (items.get(i), items.get(i + 1)) {
+            (Some(Value::Word(w)), Some(Value::List(g))) if w.ends_with("Grading") => {
+                i += 2;
+                (Some(w.clone()), g.clone())
+            }
+            _ => (None, Vec::new()),
+        };
+        blocks.push(Block { vertices, zone, cells, grading_type, grading });
+    }
+    Ok(blocks)
+}
+
+fn parse_edges(items: &[Value], labels: &Labels) -> Result<Vec<Edge>, String> {
+    let mut edges = Vec::new();
+    let mut i = 0;
+    while i < items.len() {
+        let Value::Word(kind) = &items[i] else {
+            return Err(format!("expected an edge type, got {:?}", items[i]));
+        };
+        let start = labels.get(items.get(i + 1).ok_or("truncated edge")?)?;
+        let end = labels.get(items.get(i + 2).ok_or("truncated edge")?)?;
+        i += 3;
+        let mut origin = false;
+        if matches!(items.get(i), Some(Value::Word(w)) if w == "origin") {
+            origin = true;
+            i += 1;
+            // optional scale factor before the centre
+            if items.get(i).and_then(Value::number).is_some() {
+                i += 1;
+            }
+        }
+        let points = match items.get(i) {
+            Some(v @ Value::List(list)) => {
+                i += 1;
+                match point(v) {
+                    Some(p) => vec![p],
+                    // Interpolation points, or geometry names for "project"
+                    None => list.iter().filter_map(point).collect(),
+                }
+            }
+            _ => Vec::new(),
+        };
+        edges.push(Edge { kind: kind.clone(), start, end, points, origin });
+    }
+    Ok(edges)
+}
+
+fn parse_faces(v: &Value, labels: &Labels, blocks: &[Block]) -> Result<Vec<[usize; 4]>, String> {
+    let Value::List(faces) = v else {
+        return Err(format!("expected a list of faces, got {:?}", v));
+    };
+    faces
+        .iter()
+        .map(|face| {
+            let f = labels.list(face).or_else(|_| match face {
+                // "(block face)" addressing
+                Value::List(bf) if bf.len() == 2 => Ok(bf.iter().filter_map(Value::number).map(|x| x as usize).collect()),
+                _ => Err(format!("bad face {:?}", face)),
+            })?;
+            match *f.as_slice() {
+                [a, b, c, d] => Ok([a, b, c, d]),
+                [block, face] => {
+                    let block = blocks.get(block).ok_or_else(|| format!("face refers to missing block {}", block))?;
+                    let local = HEX_FACES.get(face).ok_or_else(|| format!("hex face {} out of range", face))?;
+                    Ok(local.map(|l| block.vertices[l]))
+                }
+                _ => Err(format!("patch faces need 4 vertices, got {}", f.len())),
+            }
+        })
+        .collect()
+}
+
+fn parse_patches(dict: &Dict, labels: &Labels, blocks: &[Block]) -> Result<Vec<Patch>, String> {
+    let mut patches = Vec::new();
+    // boundary ( name { type wall; faces (...); } ... );
+    let items = list_entry(dict, "boundary");
+    let mut i = 0;
+    while i < items.len() {
+        match (&items[i], items.get(i + 1)) {
+            (Value::Word(name), Some(Value::Dict(d))) => {
+                let faces = match d.stream("faces") {
+                    Some([faces]) => parse_faces(faces, labels, blocks)?,
+                    _ => Vec::new(),
+                };
+                let kind = d.word("type").unwrap_or("patch").to_string();
+                patches.push(Patch { name: name.clone(), kind, faces });
+                i += 2;
+            }
+            other => return Err(format!("bad boundary entry {:?}", other.0)),
+        }
+    }
+    // Older form: patches ( type name (faces) ... );
+    let items = list_entry(dict, "patches");
+    for chunk in items.chunks(3) {
+        match chunk {
+            [Value::Word(kind), Value::Word(name), faces] => patches.push(Patch {
+                name: name.clone(),
+                kind: kind.clone(),
+                faces: parse_faces(faces, labels, blocks)?,
+            }),
+            _ => return Err(format!("bad patches entry {:?}", chunk.first())),
+        }
+    }
+    Ok(patches)
+}
+
+fn parse_blockmesh_dict(dict: &Dict) -> Result<BlockMesh, String> {
+    let scale = ["scale", "convertToMeters"]
+        .iter()
+        .find_map(|k| dict.number(k))
+        .unwrap_or(1.0);
+    let (vertices, labels) = parse_vertices(list_entry(dict, "vertices"))?;
+    if vertices.is_empty() {
+        return Err("no vertices".into());
+    }
+    let blocks = parse_blocks(list_entry(dict, "blocks"), &labels)?;
+    let edges = parse_edges(list_entry(dict, "edges"), &labels)?;
+    let patches = parse_patches(dict, &labels, &blocks)?;
+    let default_patch = dict.sub("defaultPatch").map(|d| {
+        (d.word("name").unwrap_or("defaultFaces").to_string(), d.word("type").unwrap_or("empty").to_string())
+    });
+    let scale_point = |p: [f64; 3]| p.map(|x| x * scale);
+    Ok(BlockMesh {
+        scale,
+        vertices: vertices.into_iter().map(scale_point).collect(),
+        blocks,
+        edges: edges
+            .into_iter()
+            .map(|e| Edge { points: e.points.into_iter().map(scale_point).collect(), ..e })
+            .collect(),
+        patches,
+        default_patch,
+    })
+}
+
+fn points_to_py<'py>(py: Python<'py>, points: &[[f64; 3]]) -> PyResult<Bound<'py, PyAny>> {
+    let flat: Vec<f64> = points.iter().flatten().copied().collect();
+    Ok(flat.into_pyarray(py).reshape([points.len(), 3])?.into_any())
+}
+
+/// Read system/blockMeshDict (or the given file) for a preview of the block
+/// structure. Returns a dict with `vertices` (N, 3) already multiplied by
+/// `scale`/`convertToMeters`, `blocks` (vertex labels, zone, cell counts and
+/// grading as written), `edges` (type, end labels and interpolation points,
+/// which for `arc ... origin` is the centre), `patches` (name, type and
+/// faces (F, 4)) and `default_patch`. #include, $variables and #calc
+/// arithmetic are resolved first. Returns None if the file is missing.
+#[pyfunction]
+pub fn parse_blockmesh<'py>(py: Python<'py>, path: String) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let parsed = py.detach(|| -> PyResult<Option<BlockMesh>> {
+        let Some(dict) = load_dict(&path)? else {
+            return Ok(None);
+        };
+        parse_blockmesh_dict(&dict)
+            .map(Some)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", Path::new(&path).display(), e)))
+    })?;
+    let Some(mesh) = parsed else {
+        return Ok(None);
+    };
+
+    let out = PyDict::new(py);
+    out.set_item("scale", mesh.scale)?;
+    out.set_item("vertices", points_to_py(py, &mesh.vertices)?)?;
+
+    let blocks = PyList::empty(py);
+    for b in &mesh.blocks {
+        let d = PyDict::new(py);
+        d.set_item("vertices", &b.vertices)?;
+        d.set_item("zone", &b.zone)?;
+        d.set_item("cells", (b.cells[0], b.cells[1], b.cells[2]))?;
+        d.set_item("grading_type", &b.grading_type)?;
+        d.set_item("grading", value_to_py(py, &Value::List(b.grading.clone()))?)?;
+        blocks.append(d)?;
+    }
+    out.set_item("blocks", blocks)?;
+
+    let edges = PyList::empty(py);
+    for e in &mesh.edges {
+        let d = PyDict::new(py);
+        d.set_item("type", &e.kind)?;
+        d.set_item("start", e.start)?;
+        d.set_item("end", e.end)?;
+        d.set_item("points", points_to_py(py, &e.points)?)?;
+        d.set_item("origin", e.origin)?;
+        edges.append(d)?;
+    }
+    out.set_item("edges", edges)?;
+
+    let patches = PyList::empty(py);
+    for p in &mesh.patches {
+        let d = PyDict::new(py);
+        d.set_item("name", &p.name)?;
+        d.set_item("type", &p.kind)?;
+        let flat: Vec<i64> = p.faces.iter().flatten().map(|&l| l as i64).collect();
+        d.set_item("faces", flat.into_pyarray(py).reshape([p.faces.len(), 4])?)?;
+        patches.append(d)?;
+    }
+    out.set_item("patches", patches)?;
+
+    let default_patch = mesh.default_patch.map(|(name, kind)| -> PyResult<Bound<PyDict>> {
+        let d = PyDict::new(py);
+        d.set_item("name", name)?;
+        d.set_item("type", kind)?;
+        Ok(d)
+    });
+    out.set_item("default_patch", default_patch.transpose()?)?;
+    Ok(Some(out))
+}
@@ -0,0 +1,329 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use numpy::{IntoPyArray, PyArrayMethods};
+use pyo3::exceptions::{PyFileNotFoundError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyTuple};
+use rayon::prelude::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::dict::{
+    dict_error, dict_to_py, expand_macros, format_entry, format_key, format_scalar, format_value, indent_at, parse_text, stream_to_py,
+    value_to_py, write_atomic, Dict, EntryValue, Value,
+};
+use crate::errors;
+use crate::field::{type_name, PatchEntry};
+use crate::find_internal_field;
+use crate::header::parse_header;
+use crate::options::{self, Options};
+use crate::source::{open_field_file, resolve_field_path, FieldData};
+use crate::time::time_dir;
+use crate::InternalField;
+
+// Entries holding field values, written as "uniform X" when given a plain
+// number or list
+const FIELD_VALUE_ENTRIES: [&str; 8] =
+    ["value", "inletValue", "outletValue", "refValue", "refGradient", "gradient", "valueFraction", "freestreamValue"];
+
+// Field file text with the body of a nonuniform internalField cut out. The
+// boundary conditions are all that's needed and the internal list is most
+// of the file (and not text at all in binary files).
+fn text_without_internal_field(data: &[u8]) -> String {
+    let cut = internal_list_range(data);
+    let mut text = String::with_capacity(data.len() - cut.len());
+    push_bytes(&mut text, &data[..cut.start]);
+    push_bytes(&mut text, &data[cut.end..]);
+    text
+}
+
+// Append `bytes` as text one byte per byte: invalid UTF-8 (a Latin-1 degree
+// sign in a comment, say) becomes '?', so offsets into the text are offsets
+// into the file
+fn push_bytes(text: &mut String, bytes: &[u8]) {
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        text.extend(std::iter::repeat_n('?', chunk.invalid().len()));
+    }
+}
+
+// Byte range of a nonuniform internalField's list body (empty if uniform)
+fn internal_list_range(data: &[u8]) -> std::ops::Range<usize> {
+    match find_internal_field(data) {
+        Some(InternalField::NonUniform(list)) => {
+            let start = list.as_ptr() as usize - data.as_ptr() as usize;
+            start..start + list.len()
+        }
+        _ => 0..0,
+    }
+}
+
+// boundaryField of one field file, macros expanded. None if the file is
+// missing or isn't a geometric field.
+pub fn read_boundary_field(path: &Path) -> Result<Option<Dict>, String> {
+    let Some(data) = open_field_file(&path.to_string_lossy()).map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    let is_field = parse_header(&data).is_some_and(|h| h.get("class").is_some_and(|c| c.contains("Field")));
+    if !is_field {
+        return Ok(None);
+    }
+    let dict = parse_text(&text_without_internal_field(&data)).and_then(|d| expand_macros(&d, path))?;
+    Ok(dict.sub("boundaryField").cloned())
+}
+
+// Parsed boundaryField of each file in a time directory, by field name
+type ParsedFields = Vec<(String, Result<Option<Dict>, String>)>;
+
+// "uniform X" is X and "nonuniform List<...> (...)" an array of (n,) or
+// (n, components); anything else converts as parse_dict would
+fn bc_value_to_py<'py>(py: Python<'py>, values: &[Value]) -> PyResult<Bound<'py, PyAny>> {
+    match values {
+        [Value::Word(u), v] if u == "uniform" => value_to_py(py, v),
+        [Value::Word(u), .., Value::List(items)] if u == "nonuniform" => {
+            let scalars: Option<Vec<f64>> = items.iter().map(Value::number).collect();
+            if let Some(s) = scalars {
+                return Ok(s.into_pyarray(py).into_any());
+            }
+            let width = match items.first() {
+                Some(Value::List(c)) => c.len(),
+                _ => 0,
+            };
+            let rows: Option<Vec<f64>> = items
+                .iter()
+                .map(|item| match item {
+                    Value::List(c) if c.len() == width => c.iter().map(Value::number).collect::<Option<Vec<f64>>>(),
+                    _ => None,
+                })
+                .collect::<Option<Vec<Vec<f64>>>>()
+                .map(|rows| rows.concat());
+            match rows {
+                Some(flat) if width > 0 => Ok(flat.into_pyarray(py).reshape([items.len(), width])?.into_any()),
+                _ => stream_to_py(py, values),
+            }
+        }
+        _ => stream_to_py(py, values),
+    }
+}
+
+pub fn boundary_to_py<'py>(py: Python<'py>, boundary: &Dict) -> PyResult<Bound<'py, PyDict>> {
+    let out = PyDict::new(py);
+    for patch in &boundary.entries {
+        let EntryValue::Dict(entries) = &patch.value else {
+            continue;
+        };
+        let p = PyDict::new(py);
+        for entry in &entries.entries {
+            if entry.key.starts_with('#') {
+                continue;
+            }
+            match &entry.value {
+                EntryValue::Dict(d) => p.set_item(&entry.key, dict_to_py(py, d)?)?,
+                EntryValue::Stream(values) => p.set_item(&entry.key, bc_value_to_py(py, values)?)?,
+            }
+        }
+        out.set_item(&patch.key, p)?;
+    }
+    Ok(out)
+}
+
+/// Boundary conditions of every field in a time directory, as
+/// {field: {patch: {"type": ..., "value": ..., ...}}}. Every entry of each
+/// patch is kept; `uniform` values come back as a number or list and
+/// `nonuniform` ones as numpy arrays. Patch names are as written, including
+/// regex keys such as "(inlet|outlet)". #include and $variables are
+/// resolved. Files are parsed in parallel; one that can't be parsed maps to
+/// None. A missing time directory gives an empty dict. With `region`, the
+/// fields in <time>/<region> are read.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, region = None, options = None))]
+pub fn read_boundary_conditions<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let dir = time_dir(Path::new(&case_root), &time, region.as_deref());
+    let parsed = options::detach(py, &options, || -> std::io::Result<ParsedFields> {
+        let mut files: Vec<(String, PathBuf)> = Vec::new();
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(e) => e,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let name = name.strip_suffix(".gz").unwrap_or(&name).to_string();
+            files.push((name, entry.path()));
+        }
+        files.sort();
+        Ok(files.into_par_iter().map(|(name, path)| (name, read_boundary_field(&path))).collect())
+    })?;
+
+    let out = PyDict::new(py);
+    for (name, result) in parsed {
+        match result {
+            Ok(Some(boundary)) => out.set_item(name, boundary_to_py(py, &boundary)?)?,
+            Ok(None) => {}
+            Err(_) => out.set_item(name, py.None())?,
+        }
+    }
+    Ok(out)
+}
+
+fn is_number(value: &Bound<PyAny>) -> bool {
+    !value.is_instance_of::<PyBool>() && (value.is_instance_of::<PyInt>() || value.is_instance_of::<PyFloat>())
+}
+
+// numpy arrays become "nonuniform List<type> N(...)"; plain numbers and
+// lists for value-like entries become "uniform X"
+fn format_bc_value(key: &str, value: &Bound<PyAny>, indent: &str) -> PyResult<String> {
+    if value.hasattr("ndim")? && value.hasattr("tolist")? {
+        let rows = value.call_method0("tolist")?;
+        let (kind, items): (&str, Vec<String>) = match rows.extract::<Vec<f64>>() {
+            Ok(scalars) => ("scalar", scalars.into_iter().map(format_scalar).collect()),
+            Err(_) => {
+                let rows: Vec<Vec<f64>> = rows.extract()?;
+                let width = rows.first().map_or(3, Vec::len);
+                let kind = match width {
+                    2 => "vector2D",
+                    3 => "vector",
+                    6 => "symmTensor",
+                    9 => "tensor",
+                    w => return Err(PyValueError::new_err(format!("'{}': {} components per value is not a field type", key, w))),
+                };
+                let items = rows
+                    .iter()
+                    .map(|r| format_value(&PyTuple::new(value.py(), r)?.into_any(), indent))
+                    .collect::<PyResult<_>>()?;
+                (kind, items)
+            }
+        };
+        return Ok(format!("nonuniform List<{}> \n{}\n(\n{}\n)\n", kind, items.len(), items.join("\n")));
+    }
+    let is_list = value.is_instance_of::<PyList>() || value.is_instance_of::<PyTuple>();
+    let numeric_list = is_list && value.try_iter()?.all(|v| v.is_ok_and(|v| is_number(&v)));
+    let text = format_value(value, indent)?;
+    if FIELD_VALUE_ENTRIES.contains(&key) && (is_number(value) || numeric_list) {
+        return Ok(format!("uniform {}", text));
+    }
+    Ok(text)
+}
+
+fn format_patch_entry(key: &str, value: &Bound<PyAny>, inner: &str) -> PyResult<String> {
+    if value.is_instance_of::<PyDict>() {
+        format_entry(key, value, inner)
+    } else {
+        Ok(format!("{}{:<15} {};", inner, format_key(key), format_bc_value(key, value, inner)?))
+    }
+}
+
+fn format_patch_body(entries: &Bound<PyDict>, indent: &str) -> PyResult<String> {
+    let inner = format!("{}    ", indent);
+    let mut body = String::from("{\n");
+    for (k, v) in entries.iter() {
+        body.push_str(&format_patch_entry(&k.str()?.to_string(), &v, &inner)?);
+        body.push('\n');
+    }
+    body.push_str(indent);
+    body.push('}');
+    Ok(body)
+}
+
+// A patch block for FieldOutput: entries formatted as set_boundary_condition
+// writes them, except numpy arrays, which are kept as values so binary
+// files get binary lists
+pub fn patch_entries(entries: &Bound<PyDict>) -> PyResult<Vec<PatchEntry>> {
+    let mut out = Vec::new();
+    for (k, v) in entries.iter() {
+        let key = k.str()?.to_string();
+        if v.hasattr("ndim")? && v.hasattr("tolist")? {
+            let rows = v.call_method0("tolist")?;
+            let (width, values) = match rows.extract::<Vec<f64>>() {
+                Ok(scalars) => (1, scalars),
+                Err(_) => {
+                    let rows: Vec<Vec<f64>> = rows.extract()?;
+                    let width = rows.first().map_or(3, Vec::len);
+                    if type_name(width).is_none() || rows.iter().any(|r| r.len() != width) {
+                        return Err(PyValueError::new_err(format!("'{}': {} components per value is not a field type", key, width)));
+                    }
+                    (width, rows.concat())
+                }
+            };
+            out.push(PatchEntry::Values(key, width, values));
+        } else {
+            out.push(PatchEntry::Text(format_patch_entry(&key, &v, "        ")?));
+        }
+    }
+    Ok(out)
+}
+
+/// Replace one patch's block in boundaryField of a field file with
+/// `entries`, e.g. set_boundary_condition("0/U", "inlet", {"type":
+/// "fixedValue", "value": [1, 0, 0]}). Numbers and lists given for value,
+/// inletValue, refValue, gradient and similar entries are written as
+/// `uniform`, numpy arrays as `nonuniform` lists, strings as written. The
+/// patch is added if missing. Only the patch block changes; internalField
+/// and the other patches stay byte-identical. Compressed (.gz) files are
+/// rewritten compressed; binary ones raise UnsupportedFormat, as the values
+/// would be written as ASCII. The file is replaced atomically.
+#[pyfunction]
+pub fn set_boundary_condition(field_path: String, patch: String, entries: &Bound<PyDict>) -> PyResult<()> {
+    let path = resolve_field_path(Path::new(&field_path))
+        .ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no such field file", field_path)))?;
+    let Some(data) = open_field_file(&path.to_string_lossy())? else {
+        return Err(PyValueError::new_err(format!("{}: empty field file", field_path)));
+    };
+    errors::check_ascii(&field_path, &data)?;
+    let compressed = matches!(data, FieldData::Decompressed(_));
+
+    // Parse with the internal list cut out, then map offsets back; the text
+    // keeps every other byte at its offset
+    let cut = internal_list_range(&data);
+    let text = text_without_internal_field(&data);
+    let original = |offset: usize| if offset >= cut.start { offset + cut.len() } else { offset };
+    let root = parse_text(&text).map_err(|e| dict_error(&field_path, &text, &e))?;
+    let (Some(boundary), Some(patches)) = (root.get("boundaryField"), root.sub("boundaryField")) else {
+        return Err(PyValueError::new_err(format!("{}: no boundaryField", field_path)));
+    };
+
+    let (range, replacement) = match patches.get(&patch) {
+        Some(existing) => {
+            let indent = indent_at(&text, existing.span.start);
+            match &existing.value {
+                EntryValue::Dict(_) => (existing.value_span.clone(), format_patch_body(entries, indent)?),
+                EntryValue::Stream(_) => {
+                    let block = format!("{}\n{}{}", format_key(&patch), indent, format_patch_body(entries, indent)?);
+                    (existing.span.clone(), block)
+                }
+            }
+        }
+        None => {
+            let indent = match patches.entries.last() {
+                Some(last) => indent_at(&text, last.span.start).to_string(),
+                None => format!("{}    ", indent_at(&text, boundary.span.start)),
+            };
+            let at = patches.entries.last().map_or(patches.body_span.start, |e| e.span.end);
+            let block = format!("\n{}{}\n{}{}", indent, format_key(&patch), indent, format_patch_body(entries, &indent)?);
+            (at..at, block)
+        }
+    };
+
+    let (start, end) = (original(range.start), original(range.end));
+    let mut out = Vec::with_capacity(data.len() + replacement.len());
+    out.extend_from_slice(&data[..start]);
+    out.extend_from_slice(replacement.as_bytes());
+    out.extend_from_slice(&data[end..]);
+    if compressed {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&out)?;
+        out = encoder.finish()?;
+    }
+    write_atomic(&path, out)?;
+    Ok(())
+}
@@ -0,0 +1,156 @@
+// In-process cache of reader results, so the dashboard's repeated polls of
+// a field that hasn't changed skip the parse. Entries are keyed by the
+// reader, its arguments and the resolved file, and stamped with the file's
+// mtime and size: a file rewritten by the solver gets a new stamp and is
+// parsed again.
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::any::Any;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use crate::options;
+use crate::source::resolve_field_path;
+
+// Entries are weighed by the size of their file, about the memory of the
+// values read from it
+const MAX_ENTRIES: usize = 128;
+const MAX_BYTES: u64 = 256 << 20;
+
+// A file's mtime and size
+pub type Stamp = (SystemTime, u64);
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Key {
+    reader: &'static str,
+    path: PathBuf,
+    args: u64,
+}
+
+struct Entry {
+    stamp: Stamp,
+    value: Arc<dyn Any + Send + Sync>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<Key, Entry>,
+    bytes: u64,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl Cache {
+    fn get(&mut self, key: &Key, stamp: Stamp) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.clock += 1;
+        match self.entries.get_mut(key) {
+            Some(entry) if entry.stamp == stamp => {
+                entry.last_used = self.clock;
+                self.hits += 1;
+                Some(entry.value.clone())
+            }
+            _ => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, key: Key, stamp: Stamp, value: Arc<dyn Any + Send + Sync>) {
+        if stamp.1 > MAX_BYTES {
+            return;
+        }
+        self.remove(&key);
+        // Evict least recently used entries until the new one fits
+        while self.entries.len() >= MAX_ENTRIES || self.bytes + stamp.1 > MAX_BYTES {
+            let Some(oldest) = self.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone()) else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+        self.bytes += stamp.1;
+        self.entries.insert(key, Entry { stamp, value, last_used: self.clock });
+    }
+
+    fn remove(&mut self, key: &Key) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= entry.stamp.1;
+        }
+    }
+}
+
+static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+
+fn cache() -> &'static Mutex<Cache> {
+    CACHE.get_or_init(|| Mutex::new(Cache::default()))
+}
+
+pub fn stamp(path: &Path) -> Option<Stamp> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+// The result of `reader` on the file at `path`, from the cache when the
+// file is unchanged since it was last read with the same `args`. Errors
+// aren't cached, nor are results for files that changed while being read.
+// Options(cache=False) bypasses the cache.
+pub fn cached<T, A, F>(reader: &'static str, path: &str, args: A, read: F) -> PyResult<T>
+where
+    T: Clone + Send + Sync + 'static,
+    A: Hash,
+    F: FnOnce() -> PyResult<T>,
+{
+    let settings = options::current();
+    let resolved = resolve_field_path(Path::new(path));
+    let (Some(resolved), true) = (resolved, settings.cache) else {
+        return read();
+    };
+    let Some(before) = stamp(&resolved) else {
+        return read();
+    };
+    // The format override changes what's read from the same bytes
+    let mut hasher = DefaultHasher::new();
+    (args, settings.binary).hash(&mut hasher);
+    let key = Key { reader, path: resolved, args: hasher.finish() };
+
+    if let Some(hit) = cache().lock().unwrap().get(&key, before) {
+        if let Some(value) = hit.downcast_ref::<T>() {
+            return Ok(value.clone());
+        }
+    }
+    let value = read()?;
+    if stamp(&key.path) == Some(before) {
+        cache().lock().unwrap().insert(key, before, Arc::new(value.clone()));
+    }
+    Ok(value)
+}
+
+/// Empty the cache of reader results. The scalar, vector and tensor field
+/// readers, field_stats, vector_field_stats and check_finite keep their
+/// results for files that haven't changed since (mtime and size), so
+/// repeated polls return without parsing.
+#[pyfunction]
+pub fn cache_clear() {
+    let mut cache = cache().lock().unwrap();
+    *cache = Cache::default();
+}
+
+/// Reader result cache statistics: `hits`, `misses`, `entries` and `bytes`
+/// (the summed size of the cached files).
+#[pyfunction]
+pub fn cache_info(py: Python) -> PyResult<Bound<PyDict>> {
+    let cache = cache().lock().unwrap();
+    let info = PyDict::new(py);
+    info.set_item("hits", cache.hits)?;
+    info.set_item("misses", cache.misses)?;
+    info.set_item("entries", cache.entries.len())?;
+    info.set_item("bytes", cache.bytes)?;
+    Ok(info)
+}
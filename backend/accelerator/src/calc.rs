@@ -0,0 +1,166 @@
+// Scalar arithmetic for #calc / #eval dictionary entries. Only the subset
+// tutorials use for parameterisation is supported: + - * / with parentheses,
+// unary signs, $variables and the common math functions. Anything else (C++
+// statements, field expressions) is reported as unsupported and the entry is
+// left as written.
+
+struct Calc<'a, F> {
+    text: &'a [u8],
+    pos: usize,
+    resolve: F,
+}
+
+impl<F: Fn(&str) -> Option<f64>> Calc<'_, F> {
+    fn skip_ws(&mut self) {
+        while self.text.get(self.pos).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_ws();
+        if self.text.get(self.pos) == Some(&c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat(b'+') {
+                value += self.term()?;
+            } else if self.eat(b'-') {
+                value -= self.term()?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat(b'*') {
+                value *= self.unary()?;
+            } else if self.eat(b'/') {
+                value /= self.unary()?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Option<f64> {
+        if self.eat(b'-') {
+            return Some(-self.unary()?);
+        }
+        if self.eat(b'+') {
+            return self.unary();
+        }
+        self.primary()
+    }
+
+    fn take_while(&mut self, f: impl Fn(u8) -> bool) -> &str {
+        let start = self.pos;
+        while self.text.get(self.pos).is_some_and(|&b| f(b)) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.text[start..self.pos]).unwrap_or("")
+    }
+
+    fn args(&mut self) -> Option<Vec<f64>> {
+        let mut args = Vec::new();
+        if self.eat(b')') {
+            return Some(args);
+        }
+        loop {
+            args.push(self.expr()?);
+            if self.eat(b')') {
+                return Some(args);
+            }
+            if !self.eat(b',') {
+                return None;
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Option<f64> {
+        self.skip_ws();
+        let c = *self.text.get(self.pos)?;
+        if c == b'(' {
+            self.pos += 1;
+            let value = self.expr()?;
+            return self.eat(b')').then_some(value);
+        }
+        if c == b'$' {
+            self.pos += 1;
+            let name = if self.eat(b'{') {
+                let name = self.take_while(|b| b != b'}').to_string();
+                self.eat(b'}').then_some(name)?
+            } else {
+                self.take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b':' | b'/'))
+                    .to_string()
+            };
+            return (self.resolve)(&format!("${}", name));
+        }
+        if c.is_ascii_digit() || c == b'.' {
+            let start = self.pos;
+            self.take_while(|b| b.is_ascii_digit() || b == b'.');
+            if matches!(self.text.get(self.pos), Some(b'e' | b'E')) {
+                self.pos += 1;
+                if matches!(self.text.get(self.pos), Some(b'+' | b'-')) {
+                    self.pos += 1;
+                }
+                self.take_while(|b| b.is_ascii_digit());
+            }
+            return std::str::from_utf8(&self.text[start..self.pos]).ok()?.parse().ok();
+        }
+        let ident = self.take_while(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b':').to_string();
+        // Foam::, std:: and constant::mathematical:: qualifiers
+        let name = ident.rsplit("::").next().unwrap_or("");
+        let args = if self.eat(b'(') { self.args()? } else { Vec::new() };
+        let f = |i: usize| args.get(i).copied();
+        Some(match (name, args.len()) {
+            ("pi", 0) => std::f64::consts::PI,
+            ("e", 0) => std::f64::consts::E,
+            ("sqrt", 1) => f(0)?.sqrt(),
+            ("sqr", 1) => f(0)? * f(0)?,
+            ("pow", 2) => f(0)?.powf(f(1)?),
+            ("exp", 1) => f(0)?.exp(),
+            ("log", 1) => f(0)?.ln(),
+            ("log10", 1) => f(0)?.log10(),
+            ("sin", 1) => f(0)?.sin(),
+            ("cos", 1) => f(0)?.cos(),
+            ("tan", 1) => f(0)?.tan(),
+            ("asin", 1) => f(0)?.asin(),
+            ("acos", 1) => f(0)?.acos(),
+            ("atan", 1) => f(0)?.atan(),
+            ("atan2", 2) => f(0)?.atan2(f(1)?),
+            ("abs" | "mag" | "fabs", 1) => f(0)?.abs(),
+            ("min", 2) => f(0)?.min(f(1)?),
+            ("max", 2) => f(0)?.max(f(1)?),
+            ("floor", 1) => f(0)?.floor(),
+            ("ceil", 1) => f(0)?.ceil(),
+            ("round", 1) => f(0)?.round(),
+            ("degToRad", 1) => f(0)?.to_radians(),
+            ("radToDeg", 1) => f(0)?.to_degrees(),
+            ("label" | "int" | "long", 1) => f(0)?.trunc(),
+            ("scalar" | "double" | "float", 1) => f(0)?,
+            _ => return None,
+        })
+    }
+}
+
+// Value of `expr`, or None if it uses anything outside the supported subset
+// or an unresolved variable
+pub fn eval_scalar(expr: &str, resolve: impl Fn(&str) -> Option<f64>) -> Option<f64> {
+    // A trailing ';' is common inside #calc strings
+    let expr = expr.trim().trim_end_matches(';');
+    let mut calc = Calc { text: expr.as_bytes(), pos: 0, resolve };
+    let value = calc.expr()?;
+    calc.skip_ws();
+    (calc.pos == calc.text.len() && value.is_finite()).then_some(value)
+}
@@ -0,0 +1,239 @@
+// A case held open from Python. Time directory and field listings are kept
+// until their directory changes, and the mesh with its geometry and
+// point-location tree is built on first use and kept until the mesh files
+// change, so the Python layer doesn't have to carry that state between
+// calls.
+
+use numpy::{IntoPyArray, PyArrayMethods};
+use pyo3::exceptions::PyFileNotFoundError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::errors::{parse_error, FieldNotFound};
+use crate::field::{load_field, load_mesh, FieldValue};
+use crate::mesh::{load_boundary, mesh_dir, patch_to_dict};
+use crate::options::{self, Options};
+use crate::probe::{mesh_stamp, probe_dict, probe_field, probe_method, CellLocator};
+use crate::source::resolve_field_path;
+use crate::stats::{field_stats, FieldStats};
+use crate::time::{field_classes, time_dir, time_dirs};
+
+// A directory listing and the directory's mtime when it was taken
+type Listing = (Option<SystemTime>, Vec<String>);
+
+fn dir_mtime(dir: &Path) -> Option<SystemTime> {
+    std::fs::metadata(dir).and_then(|m| m.modified()).ok()
+}
+
+/// A case directory opened once and queried repeatedly: `times()`,
+/// `fields(time)`, `field(time, name)` and `mesh()`. Listings and the mesh
+/// (with its point-location tree) are cached on the object and refreshed
+/// when the files change. `options` is used for every read. With `region`,
+/// the Case is that region of a multi-region case: its mesh in
+/// constant/<region>/polyMesh and its fields in <time>/<region>.
+#[pyclass(frozen, module = "accelerator")]
+pub struct Case {
+    #[pyo3(get)]
+    root: String,
+    #[pyo3(get)]
+    region: Option<String>,
+    options: Option<Options>,
+    times: Mutex<Option<Listing>>,
+    fields: Mutex<HashMap<String, Listing>>,
+    locator: Mutex<Option<(Option<SystemTime>, Arc<CellLocator>)>>,
+}
+
+impl Case {
+    fn path(&self) -> &Path {
+        Path::new(&self.root)
+    }
+
+    fn time_dir(&self, time: &str) -> PathBuf {
+        time_dir(self.path(), time, self.region.as_deref())
+    }
+
+    // The mesh and its locator, rebuilt when the mesh files change
+    fn locator(&self) -> PyResult<Arc<CellLocator>> {
+        let stamp = mesh_stamp(&mesh_dir(self.path(), self.region.as_deref()));
+        if let Some((cached, locator)) = &*self.locator.lock().unwrap() {
+            if *cached == stamp {
+                return Ok(locator.clone());
+            }
+        }
+        let locator = Arc::new(CellLocator::build(load_mesh(self.path(), self.region.as_deref())?));
+        *self.locator.lock().unwrap() = Some((stamp, locator.clone()));
+        Ok(locator)
+    }
+}
+
+#[pymethods]
+impl Case {
+    #[new]
+    #[pyo3(signature = (case_root, region = None, options = None))]
+    fn new(case_root: String, region: Option<String>, options: Option<Options>) -> PyResult<Self> {
+        if !Path::new(&case_root).is_dir() {
+            return Err(PyFileNotFoundError::new_err(format!("{}: no such case directory", case_root)));
+        }
+        Ok(Case {
+            root: case_root,
+            region,
+            options,
+            times: Mutex::new(None),
+            fields: Mutex::new(HashMap::new()),
+            locator: Mutex::new(None),
+        })
+    }
+
+    /// Time directory names, sorted numerically.
+    fn times(&self, py: Python) -> PyResult<Vec<String>> {
+        options::detach(py, &self.options, || {
+            let stamp = dir_mtime(self.path());
+            if let Some((cached, times)) = &*self.times.lock().unwrap() {
+                if *cached == stamp {
+                    return Ok(times.clone());
+                }
+            }
+            let times: Vec<String> = time_dirs(self.path())?.into_iter().map(|(_, name)| name).collect();
+            *self.times.lock().unwrap() = Some((stamp, times.clone()));
+            Ok(times)
+        })
+    }
+
+    /// Names of the fields written at `time`, sorted.
+    fn fields(&self, py: Python, time: String) -> PyResult<Vec<String>> {
+        options::detach(py, &self.options, || {
+            let dir = self.time_dir(&time);
+            let stamp = dir_mtime(&dir);
+            if stamp.is_none() {
+                return Err(PyFileNotFoundError::new_err(format!("{}: no such time directory", dir.display())));
+            }
+            if let Some((cached, names)) = self.fields.lock().unwrap().get(&time) {
+                if *cached == stamp {
+                    return Ok(names.clone());
+                }
+            }
+            let names = field_classes(&dir)?.into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+            self.fields.lock().unwrap().insert(time, (stamp, names.clone()));
+            Ok(names)
+        })
+    }
+
+    /// The field `name` at `time`. Nothing is read until the Field is
+    /// queried; raises FieldNotFound if there is no such file.
+    fn field(slf: &Bound<'_, Self>, time: String, name: String) -> PyResult<Field> {
+        let path = slf.get().time_dir(&time).join(&name);
+        if resolve_field_path(&path).is_none() {
+            return Err(FieldNotFound::new_err(format!("{}: no such field file", path.display())));
+        }
+        Ok(Field { case: slf.clone().unbind(), time, name, path })
+    }
+
+    /// The mesh as a dict: `points` (n_points, 3), the topology arrays
+    /// `face_offsets`, `face_labels`, `owner` and `neighbour`, the counts
+    /// `n_points`, `n_faces`, `n_internal_faces` and `n_cells`, and
+    /// `patches` as read_boundary gives them.
+    fn mesh<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let (locator, patches) = options::detach(py, &self.options, || -> PyResult<_> {
+            Ok((self.locator()?, load_boundary(&mesh_dir(self.path(), self.region.as_deref()))?.unwrap_or_default()))
+        })?;
+        let mesh = &locator.mesh;
+        let labels = |v: &[usize]| v.iter().map(|&x| x as i64).collect::<Vec<i64>>();
+        let out = PyDict::new(py);
+        out.set_item("n_points", mesh.points.len())?;
+        out.set_item("n_faces", mesh.n_faces())?;
+        out.set_item("n_internal_faces", mesh.neighbour.len())?;
+        out.set_item("n_cells", mesh.n_cells)?;
+        let points: Vec<f64> = mesh.points.iter().flatten().copied().collect();
+        out.set_item("points", points.into_pyarray(py).reshape([mesh.points.len(), 3])?)?;
+        out.set_item("face_offsets", labels(&mesh.face_offsets).into_pyarray(py))?;
+        out.set_item("face_labels", labels(&mesh.face_labels).into_pyarray(py))?;
+        out.set_item("owner", labels(&mesh.owner).into_pyarray(py))?;
+        out.set_item("neighbour", labels(&mesh.neighbour).into_pyarray(py))?;
+        let patches: Vec<_> = patches.into_iter().map(|p| patch_to_dict(py, p)).collect::<PyResult<_>>()?;
+        out.set_item("patches", patches)?;
+        Ok(out)
+    }
+
+    fn __repr__(&self) -> String {
+        match &self.region {
+            Some(region) => format!("Case('{}', region='{}')", self.root, region),
+            None => format!("Case('{}')", self.root),
+        }
+    }
+}
+
+/// One field of a Case at one time, read on demand.
+#[pyclass(frozen, module = "accelerator")]
+pub struct Field {
+    #[pyo3(get)]
+    case: Py<Case>,
+    #[pyo3(get)]
+    time: String,
+    #[pyo3(get)]
+    name: String,
+    path: PathBuf,
+}
+
+#[pymethods]
+impl Field {
+    #[getter]
+    fn path(&self) -> String {
+        self.path.to_string_lossy().into_owned()
+    }
+
+    /// The internalField values: (n,) for scalars, (n, components)
+    /// otherwise, with uniform values expanded over the mesh.
+    fn values<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let case = self.case.get();
+        let (values, width) = options::detach(py, &case.options, || -> PyResult<(Vec<f64>, usize)> {
+            let file = load_field(&self.path)?
+                .ok_or_else(|| FieldNotFound::new_err(format!("{}: no such field file", self.path.display())))?;
+            let width = file.width;
+            let values = match file.internal {
+                Some(FieldValue::NonUniform(v)) if v.len() % width == 0 => Some(v),
+                Some(uniform @ FieldValue::Uniform(_)) => {
+                    let mesh = &case.locator()?.mesh;
+                    let n = match file.class.as_str() {
+                        c if c.starts_with("surface") => mesh.neighbour.len(),
+                        c if c.starts_with("point") => mesh.points.len(),
+                        _ => mesh.n_cells,
+                    };
+                    uniform.expand(n, width)
+                }
+                _ => None,
+            };
+            let values = values.ok_or_else(|| parse_error(format!("{}: no readable internalField", self.path.display())))?;
+            Ok((values, width))
+        })?;
+        let rows = values.len() / width;
+        Ok(if width == 1 {
+            values.into_pyarray(py).into_any()
+        } else {
+            values.into_pyarray(py).reshape([rows, width])?.into_any()
+        })
+    }
+
+    /// field_stats() of this field, optionally over one cellZone or cellSet.
+    #[pyo3(signature = (cell_zone = None, cell_set = None))]
+    fn stats(&self, py: Python, cell_zone: Option<String>, cell_set: Option<String>) -> PyResult<Option<FieldStats>> {
+        field_stats(py, self.path(), cell_zone, cell_set, self.case.get().options.clone())
+    }
+
+    /// probe_points() of this field, using the case's cached locator.
+    #[pyo3(signature = (points, method = "linear".to_string()))]
+    fn probe<'py>(&self, py: Python<'py>, points: Vec<[f64; 3]>, method: String) -> PyResult<Bound<'py, PyDict>> {
+        let linear = probe_method(&method)?;
+        let case = self.case.get();
+        let (values, cells, width) =
+            options::detach(py, &case.options, || probe_field(&*case.locator()?, case.path(), case.region.as_deref(), &self.path, &points, linear))?;
+        probe_dict(py, values, cells, width)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Field('{}' at {} of '{}')", self.name, self.time, self.case.get().root)
+    }
+}
@@ -0,0 +1,64 @@
+// Parallel parsing of one large ASCII list: the body is cut into chunks at
+// value boundaries, each chunk is parsed on its own thread, and the partial
+// results are merged in order.
+
+use memchr::memchr;
+use rayon::prelude::*;
+
+use crate::for_each_number;
+
+// Below this a chunk isn't worth a thread
+const MIN_CHUNK_BYTES: usize = 1 << 20;
+
+// Cut `body` into about one chunk per thread. Cuts fall between values so
+// every chunk starts at component 0: after a value's ')' when `width` > 1,
+// else at whitespace. Comments must already be blanked out.
+pub fn value_chunks(body: &[u8], width: usize) -> Vec<&[u8]> {
+    let n = rayon::current_num_threads().min(body.len() / MIN_CHUNK_BYTES).max(1);
+    let mut chunks = Vec::with_capacity(n);
+    let mut start = 0;
+    for k in 1..n {
+        let target = (body.len() * k / n).max(start);
+        let cut = if width > 1 {
+            memchr(b')', &body[target..]).map(|i| target + i + 1)
+        } else {
+            body[target..].iter().position(|b| b.is_ascii_whitespace()).map(|i| target + i)
+        };
+        let Some(cut) = cut else { break };
+        chunks.push(&body[start..cut]);
+        start = cut;
+    }
+    chunks.push(&body[start..]);
+    chunks
+}
+
+// Parse each chunk of `body` with `f` in parallel and merge the results in
+// order
+pub fn fold_chunks<T, F, M>(body: &[u8], width: usize, f: F, merge: M) -> T
+where
+    T: Send,
+    F: Fn(&[u8]) -> T + Sync,
+    M: Fn(T, T) -> T + Sync,
+{
+    let body = crate::scan::uncommented(body);
+    let mut parts: Vec<T> = value_chunks(&body, width).into_par_iter().map(&f).collect();
+    let first = parts.remove(0);
+    parts.into_iter().fold(first, merge)
+}
+
+// Every number in `body`, in order
+pub fn parse_values(body: &[u8], width: usize) -> Vec<f64> {
+    fold_chunks(
+        body,
+        width,
+        |chunk| {
+            let mut values = Vec::with_capacity(chunk.len() / 8);
+            for_each_number(chunk, |v| values.push(v));
+            values
+        },
+        |mut a, b| {
+            a.extend_from_slice(&b);
+            a
+        },
+    )
+}
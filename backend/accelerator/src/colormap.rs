@@ -0,0 +1,106 @@
+// Colour maps for baking field values into vertex colours and images. Each
+// map is nine evenly spaced sRGB stops, linearly interpolated, which is
+// within a shade of the matplotlib originals the frontend's legends are
+// drawn from. A name ending in "_r" gives the map reversed.
+
+use numpy::{AllowTypeChange, PyArrayLike1};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rayon::prelude::*;
+
+type Stops = [[u8; 3]; 9];
+
+// Colour of values that aren't finite
+pub const NAN_RGBA: [u8; 4] = [128, 128, 128, 255];
+
+const MAPS: &[(&str, Stops)] = &[
+    ("viridis", [[68, 1, 84], [71, 45, 123], [59, 82, 139], [44, 114, 142], [33, 145, 140], [40, 174, 128], [94, 201, 98], [173, 220, 48], [253, 231, 37]]),
+    ("plasma", [[13, 8, 135], [76, 2, 161], [126, 3, 168], [169, 35, 149], [204, 71, 120], [229, 107, 93], [248, 149, 64], [253, 197, 39], [240, 249, 33]]),
+    ("inferno", [[0, 0, 4], [31, 12, 72], [85, 15, 109], [136, 34, 106], [186, 54, 85], [227, 89, 51], [249, 142, 9], [249, 203, 53], [252, 255, 164]]),
+    ("magma", [[0, 0, 4], [28, 16, 68], [79, 18, 123], [129, 37, 129], [181, 54, 122], [229, 80, 100], [251, 135, 97], [254, 194, 135], [252, 253, 191]]),
+    ("cividis", [[0, 34, 78], [18, 53, 112], [59, 73, 108], [87, 93, 109], [112, 113, 115], [138, 134, 120], [165, 156, 116], [195, 179, 105], [254, 232, 56]]),
+    ("coolwarm", [[59, 76, 192], [98, 130, 234], [141, 176, 254], [184, 208, 249], [221, 221, 221], [245, 196, 173], [244, 154, 123], [222, 96, 77], [180, 4, 38]]),
+    ("turbo", [[48, 18, 59], [70, 98, 215], [54, 170, 249], [26, 228, 182], [114, 254, 94], [200, 239, 52], [250, 186, 57], [246, 107, 25], [122, 4, 3]]),
+    ("jet", [[0, 0, 128], [0, 0, 255], [0, 128, 255], [0, 255, 255], [128, 255, 128], [255, 255, 0], [255, 128, 0], [255, 0, 0], [128, 0, 0]]),
+    ("gray", [[0, 0, 0], [32, 32, 32], [64, 64, 64], [96, 96, 96], [128, 128, 128], [159, 159, 159], [191, 191, 191], [223, 223, 223], [255, 255, 255]]),
+];
+
+pub struct Colormap {
+    stops: &'static Stops,
+    reversed: bool,
+}
+
+impl Colormap {
+    // The map called `name`, in any case, optionally reversed with "_r"
+    pub fn find(name: &str) -> PyResult<Colormap> {
+        let lower = name.to_ascii_lowercase();
+        let (base, reversed) = match lower.strip_suffix("_r") {
+            Some(base) => (base, true),
+            None => (lower.as_str(), false),
+        };
+        let base = if base == "grey" { "gray" } else { base };
+        MAPS.iter().find(|(n, _)| *n == base).map(|(_, stops)| Colormap { stops, reversed }).ok_or_else(|| {
+            let names: Vec<&str> = MAPS.iter().map(|(n, _)| *n).collect();
+            PyValueError::new_err(format!("unknown colormap '{}': use one of {}", name, names.join(", ")))
+        })
+    }
+
+    // Colour of `value` with [lo, hi] spanning the map; values outside are
+    // clamped to its ends
+    pub fn rgba(&self, value: f64, lo: f64, hi: f64) -> [u8; 4] {
+        if !value.is_finite() {
+            return NAN_RGBA;
+        }
+        let t = if hi > lo { ((value - lo) / (hi - lo)).clamp(0.0, 1.0) } else { 0.5 };
+        let t = if self.reversed { 1.0 - t } else { t };
+        let x = t * (self.stops.len() - 1) as f64;
+        let i = (x as usize).min(self.stops.len() - 2);
+        let f = x - i as f64;
+        let (a, b) = (self.stops[i], self.stops[i + 1]);
+        let mix = |k: usize| (a[k] as f64 + f * (b[k] as f64 - a[k] as f64)).round() as u8;
+        [mix(0), mix(1), mix(2), 255]
+    }
+}
+
+// Finite minimum and maximum of `values`, if any are finite
+pub fn finite_range(values: &[f64]) -> Option<(f64, f64)> {
+    values.iter().filter(|v| v.is_finite()).fold(None, |r, &v| Some(r.map_or((v, v), |(lo, hi): (f64, f64)| (lo.min(v), hi.max(v)))))
+}
+
+/// Colours of `values` through colormap `cmap` (viridis, plasma, inferno,
+/// magma, cividis, coolwarm, turbo, jet or gray; "_r" reverses) as packed
+/// RGBA8 bytes, four per value, ready for a WebGL colour buffer or an
+/// image. `vmin` and `vmax` span the map, by default the finite range of
+/// the values; values outside are clamped to its ends. Values that aren't
+/// finite are grey.
+#[pyfunction]
+#[pyo3(signature = (values, cmap = "viridis", vmin = None, vmax = None))]
+pub fn apply_colormap<'py>(
+    py: Python<'py>,
+    values: PyArrayLike1<'py, f64, AllowTypeChange>,
+    cmap: &str,
+    vmin: Option<f64>,
+    vmax: Option<f64>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let colormap = Colormap::find(cmap)?;
+    let values = values.as_array();
+    let values: Vec<f64> = values.iter().copied().collect();
+    let rgba = py.detach(|| {
+        let (lo, hi) = match (vmin, vmax) {
+            (Some(lo), Some(hi)) => (lo, hi),
+            _ => {
+                let (lo, hi) = finite_range(&values).unwrap_or((0.0, 0.0));
+                (vmin.unwrap_or(lo), vmax.unwrap_or(hi))
+            }
+        };
+        let mut rgba = vec![0u8; 4 * values.len()];
+        rgba.par_chunks_mut(4 * 4096).zip(values.par_chunks(4096)).for_each(|(out, values)| {
+            for (px, &v) in out.chunks_exact_mut(4).zip(values) {
+                px.copy_from_slice(&colormap.rgba(v, lo, hi));
+            }
+        });
+        rgba
+    });
+    Ok(PyBytes::new(py, &rgba))
+}
@@ -0,0 +1,390 @@
+// Monitored quantities as tables for analytics notebooks and report
+// downloads. Arrow record batches are built from the parsed Vecs without
+// copying and handed over through the Arrow PyCapsule interface, so pyarrow,
+// pandas and polars take the buffers as they are; the same batch writes out
+// as Parquet. Field statistics over a whole case also go to CSV or JSON.
+
+use arrow_array::ffi::{to_ffi, FFI_ArrowSchema};
+use arrow_array::ffi_stream::FFI_ArrowArrayStream;
+use arrow_array::{Array, ArrayRef, Float64Array, RecordBatch, RecordBatchIterator, UInt64Array};
+use arrow_schema::{ArrowError, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, GzipLevel};
+use parquet::file::properties::WriterProperties;
+use pyo3::exceptions::{PyFileNotFoundError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyCapsule, PyDict};
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::CString;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::dict::write_atomic;
+use crate::export::{json_number, json_string};
+use crate::index::field_summaries;
+use crate::log::read_log_steps;
+use crate::options::{self, Options};
+use crate::postprocess::parse_probes;
+use crate::time::{field_classes, time_dir, time_dirs};
+
+fn arrow_error(e: ArrowError) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+// Names for the components of a `width`-wide value, as the sampled-set
+// reader names them
+fn component_names(name: &str, width: usize) -> Vec<String> {
+    match width {
+        1 => vec![name.to_string()],
+        3 => ["x", "y", "z"].map(|c| format!("{}_{}", name, c)).to_vec(),
+        _ => (0..width).map(|i| format!("{}_{}", name, i)).collect(),
+    }
+}
+
+// Columns of a table under construction, with string metadata for the schema
+#[derive(Default)]
+struct Columns {
+    fields: Vec<Field>,
+    arrays: Vec<ArrayRef>,
+    metadata: HashMap<String, String>,
+}
+
+impl Columns {
+    fn push(&mut self, name: impl Into<String>, array: ArrayRef) {
+        self.fields.push(Field::new(name, array.data_type().clone(), array.null_count() > 0));
+        self.arrays.push(array);
+    }
+
+    fn floats(&mut self, name: impl Into<String>, values: Vec<f64>) {
+        self.push(name, Arc::new(Float64Array::from(values)));
+    }
+
+    fn optional_floats(&mut self, name: impl Into<String>, values: Vec<Option<f64>>) {
+        self.push(name, Arc::new(Float64Array::from(values)));
+    }
+
+    fn batch(self) -> PyResult<Table> {
+        let schema = Arc::new(Schema::new_with_metadata(self.fields, self.metadata));
+        Ok(Table { batch: RecordBatch::try_new(schema, self.arrays).map_err(arrow_error)? })
+    }
+}
+
+/// A table of monitored quantities as an Arrow record batch. Pass it to
+/// pyarrow.table() or pyarrow.record_batch(), polars.DataFrame() or
+/// pandas via pyarrow: it implements the Arrow PyCapsule interface, so the
+/// columns are shared rather than copied. to_parquet() writes it to a file.
+#[pyclass(frozen, module = "accelerator")]
+pub struct Table {
+    batch: RecordBatch,
+}
+
+#[pymethods]
+impl Table {
+    fn __arrow_c_schema__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyCapsule>> {
+        let schema = FFI_ArrowSchema::try_from(self.batch.schema().as_ref()).map_err(arrow_error)?;
+        PyCapsule::new(py, schema, Some(CString::new("arrow_schema").unwrap()))
+    }
+
+    /// The batch as a struct array through the Arrow C data interface. A
+    /// requested schema is ignored: the columns are only ever float64 and
+    /// uint64, which every consumer takes.
+    #[pyo3(signature = (requested_schema = None))]
+    fn __arrow_c_array__<'py>(&self, py: Python<'py>, requested_schema: Option<Py<PyAny>>) -> PyResult<(Bound<'py, PyCapsule>, Bound<'py, PyCapsule>)> {
+        let _ = requested_schema;
+        let data = arrow_array::StructArray::from(self.batch.clone()).into_data();
+        let (array, schema) = to_ffi(&data).map_err(arrow_error)?;
+        Ok((
+            PyCapsule::new(py, schema, Some(CString::new("arrow_schema").unwrap()))?,
+            PyCapsule::new(py, array, Some(CString::new("arrow_array").unwrap()))?,
+        ))
+    }
+
+    #[pyo3(signature = (requested_schema = None))]
+    fn __arrow_c_stream__<'py>(&self, py: Python<'py>, requested_schema: Option<Py<PyAny>>) -> PyResult<Bound<'py, PyCapsule>> {
+        let _ = requested_schema;
+        let reader = RecordBatchIterator::new([Ok(self.batch.clone())], self.batch.schema());
+        let stream = FFI_ArrowArrayStream::new(Box::new(reader));
+        PyCapsule::new(py, stream, Some(CString::new("arrow_array_stream").unwrap()))
+    }
+
+    /// Write the table to a Parquet file at `path`, compressed with
+    /// "snappy" (the default), "gzip" or "none". The file is replaced
+    /// atomically.
+    #[pyo3(signature = (path, compression = "snappy"))]
+    fn to_parquet(&self, py: Python<'_>, path: String, compression: &str) -> PyResult<()> {
+        let compression = match compression {
+            "snappy" => Compression::SNAPPY,
+            "gzip" => Compression::GZIP(GzipLevel::default()),
+            "none" => Compression::UNCOMPRESSED,
+            other => return Err(PyValueError::new_err(format!("compression must be 'snappy', 'gzip' or 'none', not '{}'", other))),
+        };
+        py.detach(|| {
+            let props = WriterProperties::builder().set_compression(compression).build();
+            let mut data = Vec::new();
+            let mut writer = ArrowWriter::try_new(&mut data, self.batch.schema(), Some(props)).map_err(|e| PyValueError::new_err(e.to_string()))?;
+            writer.write(&self.batch).map_err(|e| PyValueError::new_err(e.to_string()))?;
+            writer.close().map_err(|e| PyValueError::new_err(e.to_string()))?;
+            Ok(write_atomic(Path::new(&path), data)?)
+        })
+    }
+
+    #[getter]
+    fn num_rows(&self) -> usize {
+        self.batch.num_rows()
+    }
+
+    #[getter]
+    fn column_names(&self) -> Vec<String> {
+        self.batch.schema().fields().iter().map(|f| f.name().clone()).collect()
+    }
+
+    /// The schema's metadata, e.g. the probe locations of probes_table().
+    #[getter]
+    fn metadata(&self) -> HashMap<String, String> {
+        self.batch.schema().metadata().clone().into_iter().collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.batch.num_rows()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Table(rows={}, columns={:?})", self.batch.num_rows(), self.column_names())
+    }
+}
+
+/// The residual history of a solver log as a Table with one row per time
+/// step: `time`, then `<field>_initial`, `<field>_final` and
+/// `<field>_iterations` for every field solved (null in steps that didn't
+/// solve it; the first solve of a step when there are several correctors),
+/// the step's `continuity_local`, `continuity_global` and
+/// `continuity_cumulative` errors, and the cumulative `execution_time` and
+/// `clock_time`. Raises FileNotFoundError if the log is missing or empty.
+#[pyfunction]
+pub fn residuals_table(py: Python<'_>, path: String) -> PyResult<Table> {
+    py.detach(|| {
+        let steps = read_log_steps(&path)?.ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no solver log", path)))?;
+        let mut fields: Vec<&String> = steps.iter().flat_map(|s| s.residuals.keys()).collect();
+        fields.sort();
+        fields.dedup();
+
+        let mut columns = Columns::default();
+        columns.floats("time", steps.iter().map(|s| s.time).collect());
+        for field in fields {
+            let solves: Vec<_> = steps.iter().map(|s| s.residuals.get(field)).collect();
+            columns.optional_floats(format!("{}_initial", field), solves.iter().map(|r| r.map(|r| r.initial_residual)).collect());
+            columns.optional_floats(format!("{}_final", field), solves.iter().map(|r| r.map(|r| r.final_residual)).collect());
+            let iterations: UInt64Array = solves.iter().map(|r| r.map(|r| r.iterations)).collect();
+            columns.push(format!("{}_iterations", field), Arc::new(iterations));
+        }
+        if steps.iter().any(|s| s.continuity.is_some()) {
+            for (i, name) in ["continuity_local", "continuity_global", "continuity_cumulative"].into_iter().enumerate() {
+                columns.optional_floats(name, steps.iter().map(|s| s.continuity.map(|c| [c.0, c.1, c.2][i])).collect());
+            }
+        }
+        columns.optional_floats("execution_time", steps.iter().map(|s| s.execution_time).collect());
+        columns.optional_floats("clock_time", steps.iter().map(|s| s.clock_time).collect());
+        columns.batch()
+    })
+}
+
+/// A probes function-object file (e.g. postProcessing/probes/0/U) as a
+/// Table: `time`, then a column per probe named after the file, "U0" (or
+/// "U0_x", "U0_y", "U0_z" for vectors and "U0_0" onwards for tensors), and
+/// so on for each probe. The probe locations are in the schema metadata
+/// under "locations", as "x y z" per probe separated by ";". Raises
+/// FileNotFoundError if the file is missing.
+#[pyfunction]
+pub fn probes_table(py: Python<'_>, path: String) -> PyResult<Table> {
+    py.detach(|| {
+        let text = match std::fs::read(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(PyFileNotFoundError::new_err(format!("{}: no probes file", path))),
+            Err(e) => return Err(e.into()),
+        };
+        let p = parse_probes(&String::from_utf8_lossy(&text));
+        let name = Path::new(&path).file_name().map_or("probe".into(), |n| n.to_string_lossy().into_owned());
+        let width = p.width.max(1);
+        let stride = p.n_probes * width;
+
+        let mut columns = Columns::default();
+        columns.floats("time", p.times);
+        for probe in 0..p.n_probes {
+            for (k, column) in component_names(&format!("{}{}", name, probe), width).into_iter().enumerate() {
+                columns.floats(column, p.values.iter().skip(probe * width + k).step_by(stride.max(1)).copied().collect());
+            }
+        }
+        let locations: Vec<String> = p.locations.chunks(3).map(|l| l.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" ")).collect();
+        columns.metadata.insert("locations".into(), locations.join(";"));
+        columns.batch()
+    })
+}
+
+/// Per-time statistics of `field_name` over a case's time directories as a
+/// Table, from the same index as field_history(): `time`, `count`, `min`,
+/// `max` and `std` (of magnitudes for vectors and tensors) and the mean,
+/// as `mean` for scalars and one column per component otherwise ("mean_x",
+/// "mean_y", "mean_z" for vectors). With `region`, <time>/<region> is read.
+#[pyfunction]
+#[pyo3(signature = (case_root, field_name, region = None, options = None))]
+pub fn field_stats_table(py: Python<'_>, case_root: String, field_name: String, region: Option<String>, options: Option<Options>) -> PyResult<Table> {
+    let field = match region {
+        Some(region) => format!("{}/{}", region, field_name),
+        None => field_name,
+    };
+    options::detach(py, &options, || {
+        let summaries = field_summaries(Path::new(&case_root), &field)?;
+        // The first time step fixes the field rank, as in field_history()
+        let width = summaries.first().map_or(1, |(_, s)| s.means.len());
+        let summaries: Vec<_> = summaries.into_iter().filter(|(_, s)| s.means.len() == width).collect();
+
+        let mut columns = Columns::default();
+        columns.floats("time", summaries.iter().map(|(t, _)| *t).collect());
+        columns.push("count", Arc::new(UInt64Array::from(summaries.iter().map(|(_, s)| s.count).collect::<Vec<u64>>())));
+        columns.floats("min", summaries.iter().map(|(_, s)| s.min).collect());
+        columns.floats("max", summaries.iter().map(|(_, s)| s.max).collect());
+        columns.floats("std", summaries.iter().map(|(_, s)| s.std).collect());
+        for (k, name) in component_names("mean", width).into_iter().enumerate() {
+            columns.floats(name, summaries.iter().map(|(_, s)| s.means[k]).collect());
+        }
+        columns.batch()
+    })
+}
+
+// One value of a CSV row: empty for none, "nan" and "inf" as pandas reads them
+fn csv_number(v: Option<f64>) -> String {
+    match v {
+        Some(v) if v.is_nan() => "nan".into(),
+        Some(v) if v.is_infinite() => if v > 0.0 { "inf" } else { "-inf" }.into(),
+        Some(v) => v.to_string(),
+        None => String::new(),
+    }
+}
+
+// A CSV field, quoted if it needs to be
+fn csv_text(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Statistics of the volume fields `fields` (every volume field written at
+/// any time if None) in every time directory of a case, written to `path`
+/// in one go for the "export report" download. Each row is one field at
+/// one time: time, field, count, min, max, std and the mean, with min, max
+/// and std taken over magnitudes for vectors and tensors, as in
+/// field_history(). `format` is "csv" or "json", by default from the
+/// extension of `path` (CSV unless it ends in .json). In CSV the mean of a
+/// scalar is the `mean` column and a vector's components go in `mean_x`,
+/// `mean_y` and `mean_z` (`mean_0` onwards for tensors), blank where they
+/// don't apply; JSON has a list of records whose `mean` is a number or a
+/// list. Rows are sorted by time, then in the order of `fields`. Summaries
+/// come from the index field_history() keeps. With `region`,
+/// <time>/<region> is read. The file is replaced atomically. Returns a
+/// dict with the `path`, the `fields` and the number of `rows` written.
+#[pyfunction]
+#[pyo3(signature = (case_root, fields, path, format = None, region = None, options = None))]
+pub fn export_case_summary<'py>(
+    py: Python<'py>,
+    case_root: String,
+    fields: Option<Vec<String>>,
+    path: String,
+    format: Option<String>,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let json = match format.as_deref() {
+        Some("json") => true,
+        Some("csv") => false,
+        Some(other) => return Err(PyValueError::new_err(format!("format must be 'csv' or 'json', not '{}'", other))),
+        None => path.to_ascii_lowercase().ends_with(".json"),
+    };
+    let (fields, rows) = options::detach(py, &options, || -> PyResult<(Vec<String>, usize)> {
+        let root = Path::new(&case_root);
+        let fields = match fields {
+            Some(fields) => fields,
+            None => {
+                let mut names = BTreeSet::new();
+                for (_, time) in time_dirs(root)? {
+                    let dir = time_dir(root, &time, region.as_deref());
+                    names.extend(field_classes(&dir)?.into_iter().filter(|(_, class)| class.starts_with("vol")).map(|(name, _)| name));
+                }
+                names.into_iter().collect()
+            }
+        };
+
+        // (time, field index, summary), each field at its own rank
+        let mut rows = Vec::new();
+        let mut widths = BTreeSet::new();
+        for (f, field) in fields.iter().enumerate() {
+            options::check_cancelled()?;
+            let key = match &region {
+                Some(region) => format!("{}/{}", region, field),
+                None => field.clone(),
+            };
+            let summaries = field_summaries(root, &key)?;
+            let width = summaries.first().map_or(1, |(_, s)| s.means.len());
+            widths.insert(width);
+            rows.extend(summaries.into_iter().filter(|(_, s)| s.means.len() == width).map(|(t, s)| (t, f, s)));
+        }
+        rows.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+        let mut out = String::new();
+        if json {
+            let records: Vec<String> = rows
+                .iter()
+                .map(|(t, f, s)| {
+                    let mean = match &s.means[..] {
+                        [mean] => json_number(*mean),
+                        means => format!("[{}]", means.iter().map(|m| json_number(*m)).collect::<Vec<_>>().join(",")),
+                    };
+                    format!(
+                        r#"{{"time":{},"field":{},"count":{},"min":{},"max":{},"std":{},"mean":{}}}"#,
+                        json_number(*t),
+                        json_string(&fields[*f]),
+                        s.count,
+                        json_number(s.min),
+                        json_number(s.max),
+                        json_number(s.std),
+                        mean
+                    )
+                })
+                .collect();
+            out.push_str(&format!("[\n{}\n]\n", records.join(",\n")));
+        } else {
+            // A column per component name of every rank present
+            let mut means: Vec<String> = Vec::new();
+            for &width in &widths {
+                for name in component_names("mean", width) {
+                    if !means.contains(&name) {
+                        means.push(name);
+                    }
+                }
+            }
+            out.push_str(&format!("time,field,count,min,max,std,{}\n", means.join(",")));
+            for (t, f, s) in &rows {
+                let names = component_names("mean", s.means.len());
+                let cells: Vec<String> = means.iter().map(|m| csv_number(names.iter().position(|n| n == m).map(|k| s.means[k]))).collect();
+                out.push_str(&format!(
+                    "{},{},{},{},{},{},{}\n",
+                    csv_number(Some(*t)),
+                    csv_text(&fields[*f]),
+                    s.count,
+                    csv_number(Some(s.min)),
+                    csv_number(Some(s.max)),
+                    csv_number(Some(s.std)),
+                    cells.join(",")
+                ));
+            }
+        }
+        write_atomic(Path::new(&path), out)?;
+        Ok((fields, rows.len()))
+    })?;
+    let result = PyDict::new(py);
+    result.set_item("path", &path)?;
+    result.set_item("fields", fields)?;
+    result.set_item("rows", rows)?;
+    Ok(result)
+}
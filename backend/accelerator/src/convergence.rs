@@ -0,0 +1,373 @@
+// Convergence checks for steady-state runs, so a run can be stopped once it
+// has settled instead of at a fixed endTime. Each criterion is judged on
+// the solver log or a function object's output and reported with the
+// numbers it was judged on, so the UI can show why a run is, or isn't, done.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::log::{read_log_steps, LogStep};
+use crate::postprocess::read_function_column;
+
+/// A monitored quantity that has to level off: column `column` of function
+/// object `function` (its postProcessing directory, e.g. "forceCoeffs1"
+/// and "Cd") may vary by at most `tolerance` over its last `window`
+/// samples, relative to their mean unless `relative` is False.
+#[pyclass(frozen, get_all, module = "accelerator")]
+#[derive(Clone)]
+pub struct Plateau {
+    pub function: String,
+    pub column: String,
+    pub tolerance: f64,
+    pub window: usize,
+    pub relative: bool,
+}
+
+#[pymethods]
+impl Plateau {
+    #[new]
+    #[pyo3(signature = (function, column, tolerance, window = 100, relative = true))]
+    fn new(function: String, column: String, tolerance: f64, window: usize, relative: bool) -> PyResult<Self> {
+        if tolerance.is_nan() || tolerance < 0.0 {
+            return Err(PyValueError::new_err("tolerance must not be negative"));
+        }
+        if window < 2 {
+            return Err(PyValueError::new_err("window must be at least 2 samples"));
+        }
+        Ok(Plateau { function, column, tolerance, window, relative })
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Plateau(function={:?}, column={:?}, tolerance={}, window={}, relative={})",
+            self.function,
+            self.column,
+            self.tolerance,
+            self.window,
+            if self.relative { "True" } else { "False" }
+        )
+    }
+}
+
+/// What check_convergence() requires of a run.
+///
+/// `residuals` maps field names to the initial residual each must stay
+/// below; a name also covers its components, so "U" applies to Ux, Uy and
+/// Uz unless they have limits of their own. `residual` is the limit for
+/// every other field the solver reports. Residuals must be below their
+/// limits for the last `steps` steps in which the field was solved.
+/// `continuity` bounds the time step continuity error (sum local) over the
+/// last `steps` steps. `plateaus` lists Plateau monitors. `log` names the
+/// solver log, relative to the case; by default it is the most recently
+/// written log or log.* file in the case that has time steps.
+#[pyclass(frozen, get_all, module = "accelerator")]
+#[derive(Clone)]
+pub struct ConvergenceCriteria {
+    pub residuals: BTreeMap<String, f64>,
+    pub residual: Option<f64>,
+    pub steps: usize,
+    pub continuity: Option<f64>,
+    pub plateaus: Vec<Plateau>,
+    pub log: Option<String>,
+}
+
+#[pymethods]
+impl ConvergenceCriteria {
+    #[new]
+    #[pyo3(signature = (*, residuals = None, residual = None, steps = 10, continuity = None, plateaus = None, log = None))]
+    fn new(
+        residuals: Option<BTreeMap<String, f64>>,
+        residual: Option<f64>,
+        steps: usize,
+        continuity: Option<f64>,
+        plateaus: Option<Vec<Plateau>>,
+        log: Option<String>,
+    ) -> PyResult<Self> {
+        let residuals = residuals.unwrap_or_default();
+        let plateaus = plateaus.unwrap_or_default();
+        if residuals.is_empty() && residual.is_none() && continuity.is_none() && plateaus.is_empty() {
+            return Err(PyValueError::new_err("give at least one of residuals, residual, continuity or plateaus"));
+        }
+        if residuals.values().chain(&residual).chain(&continuity).any(|&limit| limit.is_nan() || limit <= 0.0) {
+            return Err(PyValueError::new_err("residual and continuity limits must be positive"));
+        }
+        if steps == 0 {
+            return Err(PyValueError::new_err("steps must be at least 1"));
+        }
+        Ok(ConvergenceCriteria { residuals, residual, steps, continuity, plateaus, log })
+    }
+
+    fn __repr__(&self) -> String {
+        let py_opt = |v: Option<String>| v.unwrap_or_else(|| "None".to_string());
+        format!(
+            "ConvergenceCriteria(residuals={:?}, residual={}, steps={}, continuity={}, plateaus={}, log={})",
+            self.residuals,
+            py_opt(self.residual.map(|r| r.to_string())),
+            self.steps,
+            py_opt(self.continuity.map(|c| c.to_string())),
+            self.plateaus.len(),
+            py_opt(self.log.as_ref().map(|l| format!("{:?}", l)))
+        )
+    }
+}
+
+// One criterion as judged: `value` against `limit`, over `samples` of the
+// `required` steps or samples. A missing solver log is reported as a
+// failed "log" check.
+#[pyclass(frozen, get_all, module = "accelerator")]
+#[derive(Clone)]
+pub struct ConvergenceCheck {
+    // "residual", "continuity", "plateau" or "log"
+    pub criterion: String,
+    // Field, or function/column for a plateau
+    pub name: String,
+    pub passed: bool,
+    pub value: Option<f64>,
+    pub limit: Option<f64>,
+    pub samples: usize,
+    pub required: usize,
+    pub detail: String,
+}
+
+#[pymethods]
+impl ConvergenceCheck {
+    fn __repr__(&self) -> String {
+        format!(
+            "ConvergenceCheck(criterion={:?}, name={:?}, passed={})",
+            self.criterion,
+            self.name,
+            if self.passed { "True" } else { "False" }
+        )
+    }
+}
+
+// The outcome of check_convergence()
+#[pyclass(frozen, get_all, module = "accelerator")]
+#[derive(Clone)]
+pub struct ConvergenceVerdict {
+    pub converged: bool,
+    // Time of the last step in the log
+    pub time: Option<f64>,
+    pub log: Option<String>,
+    pub checks: Vec<ConvergenceCheck>,
+}
+
+#[pymethods]
+impl ConvergenceVerdict {
+    fn __repr__(&self) -> String {
+        let passed = self.checks.iter().filter(|c| c.passed).count();
+        format!(
+            "ConvergenceVerdict(converged={}, time={}, passed={}/{})",
+            if self.converged { "True" } else { "False" },
+            self.time.map_or_else(|| "None".to_string(), |t| t.to_string()),
+            passed,
+            self.checks.len()
+        )
+    }
+}
+
+// Log and log.* files in the case root, most recently written first
+fn case_logs(case_root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(case_root) else {
+        return Vec::new();
+    };
+    let mut logs: Vec<(SystemTime, PathBuf)> = entries
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().into_string().ok()?;
+            let meta = e.metadata().ok()?;
+            let modified = meta.modified().ok()?;
+            (meta.is_file() && (name == "log" || name.starts_with("log."))).then(|| (modified, e.path()))
+        })
+        .collect();
+    logs.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    logs.into_iter().map(|(_, path)| path).collect()
+}
+
+// The solver log's steps: the named log, or the newest one with any
+fn solver_steps(case_root: &Path, log: Option<&str>) -> std::io::Result<Option<(PathBuf, Vec<LogStep>)>> {
+    let candidates = match log {
+        Some(log) => vec![case_root.join(log)],
+        None => case_logs(case_root),
+    };
+    for path in candidates {
+        if let Some(steps) = read_log_steps(&path.to_string_lossy())? {
+            if !steps.is_empty() {
+                return Ok(Some((path, steps)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// Whether limit `name` covers `field`: the field itself or one of its
+// components
+fn covers(name: &str, field: &str) -> bool {
+    field == name || field.strip_prefix(name).is_some_and(|c| matches!(c, "x" | "y" | "z"))
+}
+
+// Limit for `field`: its own, then that of the field it is a component of,
+// then the default
+fn residual_limit(criteria: &ConvergenceCriteria, field: &str) -> Option<f64> {
+    let component = criteria.residuals.iter().filter(|(name, _)| covers(name, field)).min_by_key(|(name, _)| field.len() - name.len());
+    component.map(|(_, &limit)| limit).or(criteria.residual)
+}
+
+fn residual_checks(criteria: &ConvergenceCriteria, steps: &[LogStep], checks: &mut Vec<ConvergenceCheck>) {
+    let mut fields: BTreeMap<&str, f64> = BTreeMap::new();
+    for step in steps {
+        for field in step.residuals.keys() {
+            if let Some(limit) = residual_limit(criteria, field) {
+                fields.insert(field, limit);
+            }
+        }
+    }
+    for (&field, &limit) in &fields {
+        // Initial residuals of the steps that solved the field, newest first
+        let mut history = steps.iter().rev().filter_map(|s| s.residuals.get(field)).map(|r| r.initial_residual);
+        let last = history.next();
+        let below = last.into_iter().chain(history).take_while(|&r| r < limit).take(criteria.steps).count();
+        let passed = below >= criteria.steps;
+        let detail = match last {
+            Some(r) if passed => format!("{} initial residual {:.3e} below {:.3e} for the last {} steps", field, r, limit, below),
+            Some(r) if below > 0 => {
+                format!("{} initial residual {:.3e} below {:.3e} for {} of {} steps", field, r, limit, below, criteria.steps)
+            }
+            Some(r) => format!("{} initial residual {:.3e} is not below {:.3e}", field, r, limit),
+            None => format!("{} has no residuals", field),
+        };
+        checks.push(ConvergenceCheck {
+            criterion: "residual".to_string(),
+            name: field.to_string(),
+            passed,
+            value: last,
+            limit: Some(limit),
+            samples: below,
+            required: criteria.steps,
+            detail,
+        });
+    }
+    // Named fields the log never reports can't have converged
+    for (name, &limit) in &criteria.residuals {
+        if !fields.keys().any(|field| covers(name, field)) {
+            checks.push(ConvergenceCheck {
+                criterion: "residual".to_string(),
+                name: name.clone(),
+                passed: false,
+                value: None,
+                limit: Some(limit),
+                samples: 0,
+                required: criteria.steps,
+                detail: format!("{} is not solved in the log", name),
+            });
+        }
+    }
+}
+
+fn continuity_check(limit: f64, required: usize, steps: &[LogStep]) -> ConvergenceCheck {
+    let recent: Vec<f64> = steps.iter().rev().filter_map(|s| s.continuity).map(|(local, _, _)| local.abs()).take(required).collect();
+    let worst = recent.iter().copied().fold(None, |m: Option<f64>, v| Some(m.map_or(v, |m| m.max(v))));
+    let passed = recent.len() >= required && worst.is_some_and(|w| w <= limit);
+    let detail = match worst {
+        None => "the log has no continuity errors".to_string(),
+        Some(_) if recent.len() < required => format!("only {} of {} steps report continuity errors", recent.len(), required),
+        Some(w) if passed => format!("continuity error (sum local) at most {:.3e} over the last {} steps, within {:.3e}", w, required, limit),
+        Some(w) => format!("continuity error (sum local) reached {:.3e} over the last {} steps, above {:.3e}", w, required, limit),
+    };
+    ConvergenceCheck {
+        criterion: "continuity".to_string(),
+        name: "sum_local".to_string(),
+        passed,
+        value: worst,
+        limit: Some(limit),
+        samples: recent.len(),
+        required,
+        detail,
+    }
+}
+
+fn plateau_check(case_root: &Path, plateau: &Plateau) -> std::io::Result<ConvergenceCheck> {
+    let name = format!("{}/{}", plateau.function, plateau.column);
+    let check = |value: Option<f64>, samples: usize, passed: bool, detail: String| ConvergenceCheck {
+        criterion: "plateau".to_string(),
+        name: name.clone(),
+        passed,
+        value,
+        limit: Some(plateau.tolerance),
+        samples,
+        required: plateau.window,
+        detail,
+    };
+    let Some((_, values)) = read_function_column(case_root, &plateau.function, &plateau.column)? else {
+        return Ok(check(None, 0, false, format!("{} has no {} output", plateau.function, plateau.column)));
+    };
+    let recent: Vec<f64> = values.iter().rev().copied().filter(|v| v.is_finite()).take(plateau.window).collect();
+    if recent.len() < plateau.window {
+        return Ok(check(None, recent.len(), false, format!("{} has {} of {} samples", name, recent.len(), plateau.window)));
+    }
+    let (lo, hi) = recent.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    let mean = recent.iter().sum::<f64>() / recent.len() as f64;
+    let spread = if plateau.relative { (hi - lo) / mean.abs().max(f64::MIN_POSITIVE) } else { hi - lo };
+    let passed = spread <= plateau.tolerance;
+    let detail = format!(
+        "{} varies by {:.3e}{} about {:.6e} over its last {} samples, {} {:.3e}",
+        name,
+        spread,
+        if plateau.relative { " of its mean" } else { "" },
+        mean,
+        plateau.window,
+        if passed { "within" } else { "above" },
+        plateau.tolerance
+    );
+    Ok(check(Some(spread), recent.len(), passed, detail))
+}
+
+/// Judge whether a run has converged by `criteria` (a
+/// ConvergenceCriteria), from its solver log and function object output.
+/// Returns a ConvergenceVerdict: `converged` is True when every criterion
+/// passes, and `checks` holds a ConvergenceCheck per field, continuity
+/// bound and plateau with the value judged, its limit, how many of the
+/// required steps or samples it held for and a readable `detail`. A
+/// criterion without data (no log, a field not solved, too few samples)
+/// fails. Poll it during a steady run and call request_stop() once it
+/// converges.
+#[pyfunction]
+pub fn check_convergence(py: Python, case_root: String, criteria: ConvergenceCriteria) -> PyResult<ConvergenceVerdict> {
+    py.detach(|| {
+        let root = Path::new(&case_root);
+        let mut checks = Vec::new();
+        let mut verdict = ConvergenceVerdict { converged: false, time: None, log: None, checks: Vec::new() };
+        let wants_log = !criteria.residuals.is_empty() || criteria.residual.is_some() || criteria.continuity.is_some();
+        if wants_log {
+            match solver_steps(root, criteria.log.as_deref())? {
+                Some((path, steps)) => {
+                    verdict.time = steps.last().map(|s| s.time);
+                    verdict.log = Some(path.to_string_lossy().into_owned());
+                    residual_checks(&criteria, &steps, &mut checks);
+                    if let Some(limit) = criteria.continuity {
+                        checks.push(continuity_check(limit, criteria.steps, &steps));
+                    }
+                }
+                None => checks.push(ConvergenceCheck {
+                    criterion: "log".to_string(),
+                    name: criteria.log.clone().unwrap_or_else(|| "log".to_string()),
+                    passed: false,
+                    value: None,
+                    limit: None,
+                    samples: 0,
+                    required: criteria.steps,
+                    detail: "no solver log with time steps".to_string(),
+                }),
+            }
+        }
+        for plateau in &criteria.plateaus {
+            checks.push(plateau_check(root, plateau)?);
+        }
+        verdict.converged = !checks.is_empty() && checks.iter().all(|c| c.passed);
+        verdict.checks = checks;
+        Ok(verdict)
+    })
+}
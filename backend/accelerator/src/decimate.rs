@@ -0,0 +1,418 @@
+// Quadric-error decimation of triangle surfaces for the browser, after
+// Garland and Heckbert with the threshold sweep of Forstmann's "Fast Quadric
+// Mesh Simplification": rather than a global priority queue, each pass
+// collapses every edge whose error is below a threshold that grows from pass
+// to pass, which keeps memory at a few flat arrays for 20M-triangle walls.
+// Per-vertex field values ride along: a jump in the value across an edge adds
+// to its cost, so colour gradients keep their triangles where the geometry
+// alone would let them go, and a collapsed vertex takes the value
+// interpolated along the edge.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::geometry::{add, cross, dot, mag, scale, sub, Vec3};
+use crate::options::{self, Options, Progress};
+use crate::surface::{surface_from_py, surface_to_py, TriSurface};
+
+// Sweeps before giving up on the target; the threshold has grown by 1e14 by then
+const PASSES: usize = 100;
+
+// How much more moving off the outline of an open surface costs than moving
+// off the surface
+const BORDER_WEIGHT: f64 = 1e3;
+
+// Squared distance (as a fraction of the surface's size) that a full-range
+// jump in the field costs
+const FIELD_WEIGHT: f64 = 4e-4;
+
+// Symmetric 4x4 error quadric, upper triangle row by row
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(n: Vec3, d: f64) -> Quadric {
+        let [a, b, c] = n;
+        Quadric([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d])
+    }
+
+    fn add(self, other: Quadric) -> Quadric {
+        let mut m = self.0;
+        for (a, b) in m.iter_mut().zip(other.0) {
+            *a += b;
+        }
+        Quadric(m)
+    }
+
+    fn det(&self, [a11, a12, a13, a21, a22, a23, a31, a32, a33]: [usize; 9]) -> f64 {
+        let m = &self.0;
+        m[a11] * m[a22] * m[a33] + m[a13] * m[a21] * m[a32] + m[a12] * m[a23] * m[a31]
+            - m[a13] * m[a22] * m[a31]
+            - m[a11] * m[a23] * m[a32]
+            - m[a12] * m[a21] * m[a33]
+    }
+
+    fn error(&self, [x, y, z]: Vec3) -> f64 {
+        let m = &self.0;
+        m[0] * x * x + 2.0 * m[1] * x * y + 2.0 * m[2] * x * z + 2.0 * m[3] * x + m[4] * y * y + 2.0 * m[5] * y * z + 2.0 * m[6] * y
+            + m[7] * z * z
+            + 2.0 * m[8] * z
+            + m[9]
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Triangle {
+    v: [usize; 3],
+    // Collapse error of each edge (v[j], v[j + 1]) and their minimum
+    err: [f64; 4],
+    normal: Vec3,
+    deleted: bool,
+    // Touched in this pass
+    dirty: bool,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Vertex {
+    p: Vec3,
+    value: f64,
+    q: Quadric,
+    // This vertex's run of `refs`
+    start: usize,
+    count: usize,
+    border: bool,
+}
+
+// A corner of a triangle: which triangle, and which of its three vertices
+#[derive(Clone, Copy)]
+struct Ref {
+    tri: usize,
+    corner: usize,
+}
+
+struct Decimator {
+    tris: Vec<Triangle>,
+    verts: Vec<Vertex>,
+    refs: Vec<Ref>,
+    // Weight of the field term, zero without values
+    field_weight: f64,
+}
+
+fn unit(v: Vec3) -> Vec3 {
+    let m = mag(v);
+    if m > 0.0 {
+        scale(v, 1.0 / m)
+    } else {
+        v
+    }
+}
+
+impl Decimator {
+    fn field_cost(&self, a: &Vertex, b: &Vertex) -> f64 {
+        let d = a.value - b.value;
+        if d.is_finite() {
+            self.field_weight * d * d
+        } else {
+            0.0
+        }
+    }
+
+    // Where the collapse of edge (i, j) puts the merged vertex, and its cost
+    fn collapse(&self, i: usize, j: usize) -> (f64, Vec3) {
+        let (a, b) = (&self.verts[i], &self.verts[j]);
+        let q = a.q.add(b.q);
+        let det = q.det([0, 1, 2, 1, 4, 5, 2, 5, 7]);
+        let field = self.field_cost(a, b);
+        // Border edges stay on the border: only their ends and midpoint will do
+        if det.abs() > 1e-12 && !(a.border && b.border) {
+            let p = [
+                -q.det([1, 2, 3, 4, 5, 6, 5, 7, 8]) / det,
+                q.det([0, 2, 3, 1, 5, 6, 2, 7, 8]) / det,
+                -q.det([0, 1, 3, 1, 4, 6, 2, 5, 8]) / det,
+            ];
+            return (q.error(p) + field, p);
+        }
+        let mid = scale(add(a.p, b.p), 0.5);
+        [a.p, b.p, mid].into_iter().map(|p| (q.error(p) + field, p)).fold((f64::INFINITY, mid), |best, c| if c.0 < best.0 { c } else { best })
+    }
+
+    fn edge_errors(&self, v: [usize; 3]) -> [f64; 4] {
+        let e = [0, 1, 2].map(|j| self.collapse(v[j], v[(j + 1) % 3]).0);
+        [e[0], e[1], e[2], e[0].min(e[1]).min(e[2])]
+    }
+
+    // Whether moving vertex `i` to `p` (merging in `j`) would fold one of its
+    // other triangles over or squash it flat; marks the triangles that the
+    // collapse deletes, those shared with `j`
+    fn flips(&self, p: Vec3, i: usize, j: usize, deleted: &mut Vec<bool>) -> bool {
+        let v = &self.verts[i];
+        deleted.clear();
+        deleted.resize(v.count, false);
+        for (r, gone) in self.refs[v.start..v.start + v.count].iter().zip(deleted.iter_mut()) {
+            let t = &self.tris[r.tri];
+            if t.deleted {
+                continue;
+            }
+            let id1 = t.v[(r.corner + 1) % 3];
+            let id2 = t.v[(r.corner + 2) % 3];
+            if id1 == j || id2 == j {
+                *gone = true;
+                continue;
+            }
+            let d1 = unit(sub(self.verts[id1].p, p));
+            let d2 = unit(sub(self.verts[id2].p, p));
+            if dot(d1, d2).abs() > 0.999 {
+                return true;
+            }
+            if dot(unit(cross(d1, d2)), t.normal) < 0.2 {
+                return true;
+            }
+        }
+        false
+    }
+
+    // Point vertex `v`'s surviving triangles at `i`, dropping the collapsed
+    // ones, and append their refs for `i`'s new run
+    fn relink(&mut self, i: usize, v: usize, deleted: &[bool], removed: &mut usize) {
+        let start = self.verts[v].start;
+        for (k, &gone) in deleted.iter().enumerate() {
+            let r = self.refs[start + k];
+            if self.tris[r.tri].deleted {
+                continue;
+            }
+            if gone {
+                self.tris[r.tri].deleted = true;
+                *removed += 1;
+                continue;
+            }
+            let mut t = self.tris[r.tri];
+            t.v[r.corner] = i;
+            t.dirty = true;
+            t.err = self.edge_errors(t.v);
+            let [a, b, c] = t.v.map(|x| self.verts[x].p);
+            t.normal = unit(cross(sub(b, a), sub(c, a)));
+            self.tris[r.tri] = t;
+            self.refs.push(r);
+        }
+    }
+
+    // Drop deleted triangles and rebuild every vertex's run of refs; on the
+    // first call also set up the quadrics, errors and border flags
+    fn rebuild(&mut self, first: bool) {
+        if !first {
+            self.tris.retain(|t| !t.deleted);
+        }
+        if first {
+            for t in &mut self.tris {
+                let [a, b, c] = t.v.map(|x| self.verts[x].p);
+                t.normal = unit(cross(sub(b, a), sub(c, a)));
+                let q = Quadric::plane(t.normal, -dot(t.normal, a));
+                for &x in &t.v {
+                    self.verts[x].q = self.verts[x].q.add(q);
+                }
+            }
+        }
+
+        for v in &mut self.verts {
+            v.count = 0;
+        }
+        for t in &self.tris {
+            for &x in &t.v {
+                self.verts[x].count += 1;
+            }
+        }
+        let mut start = 0;
+        for v in &mut self.verts {
+            v.start = start;
+            start += v.count;
+            v.count = 0;
+        }
+        self.refs.clear();
+        self.refs.resize(start, Ref { tri: 0, corner: 0 });
+        for (k, t) in self.tris.iter().enumerate() {
+            for (corner, &x) in t.v.iter().enumerate() {
+                let v = &mut self.verts[x];
+                self.refs[v.start + v.count] = Ref { tri: k, corner };
+                v.count += 1;
+            }
+        }
+
+        // A border vertex has a neighbour it shares only one triangle with.
+        // Each border edge also gets a stiff quadric for the plane standing
+        // on it, so the outline and its corners stay put.
+        if first {
+            let mut neighbours: Vec<(usize, usize, usize)> = Vec::new();
+            for i in 0..self.verts.len() {
+                neighbours.clear();
+                let v = self.verts[i];
+                for r in &self.refs[v.start..v.start + v.count] {
+                    for &x in &self.tris[r.tri].v {
+                        match neighbours.iter_mut().find(|(id, _, _)| *id == x) {
+                            Some((_, n, _)) => *n += 1,
+                            None => neighbours.push((x, 1, r.tri)),
+                        }
+                    }
+                }
+                self.verts[i].border = neighbours.iter().any(|&(_, n, _)| n == 1);
+                for &(x, _, tri) in neighbours.iter().filter(|&&(x, n, _)| n == 1 && x > i) {
+                    let (a, b) = (self.verts[i].p, self.verts[x].p);
+                    let n = unit(cross(sub(b, a), self.tris[tri].normal));
+                    let q = Quadric::plane(n, -dot(n, a));
+                    let q = Quadric(q.0.map(|m| m * BORDER_WEIGHT));
+                    self.verts[i].q = self.verts[i].q.add(q);
+                    self.verts[x].q = self.verts[x].q.add(q);
+                }
+            }
+            // Edge errors depend on the border flags
+            for k in 0..self.tris.len() {
+                self.tris[k].err = self.edge_errors(self.tris[k].v);
+            }
+        }
+    }
+
+    fn run(&mut self, target: usize, progress: &Progress) -> PyResult<()> {
+        let total = self.tris.len();
+        let mut removed = 0;
+        let (mut deleted0, mut deleted1) = (Vec::new(), Vec::new());
+        for pass in 0..PASSES {
+            progress.step();
+            options::check_cancelled()?;
+            if total - removed <= target {
+                break;
+            }
+            if pass % 5 == 0 {
+                self.rebuild(pass == 0);
+            }
+            for t in &mut self.tris {
+                t.dirty = false;
+            }
+            let threshold = 1e-9 * ((pass + 3) as f64).powi(7);
+            for k in 0..self.tris.len() {
+                let t = self.tris[k];
+                if t.deleted || t.dirty || t.err[3] > threshold {
+                    continue;
+                }
+                for j in 0..3 {
+                    if t.err[j] >= threshold {
+                        continue;
+                    }
+                    let (i0, i1) = (t.v[j], t.v[(j + 1) % 3]);
+                    if self.verts[i0].border != self.verts[i1].border {
+                        continue;
+                    }
+                    let (_, p) = self.collapse(i0, i1);
+                    if self.flips(p, i0, i1, &mut deleted0) || self.flips(p, i1, i0, &mut deleted1) {
+                        continue;
+                    }
+
+                    let (a, b) = (self.verts[i0], self.verts[i1]);
+                    let edge = sub(b.p, a.p);
+                    let f = (dot(sub(p, a.p), edge) / dot(edge, edge).max(f64::MIN_POSITIVE)).clamp(0.0, 1.0);
+                    self.verts[i0].value = match (a.value.is_finite(), b.value.is_finite()) {
+                        (true, true) => a.value + f * (b.value - a.value),
+                        (false, true) => b.value,
+                        _ => a.value,
+                    };
+                    self.verts[i0].p = p;
+                    self.verts[i0].q = a.q.add(b.q);
+
+                    let start = self.refs.len();
+                    self.relink(i0, i0, &deleted0, &mut removed);
+                    self.relink(i0, i1, &deleted1, &mut removed);
+                    let count = self.refs.len() - start;
+                    // Reuse i0's old run when the new one fits
+                    if count <= self.verts[i0].count {
+                        let old = self.verts[i0].start;
+                        self.refs.copy_within(start..start + count, old);
+                        self.refs.truncate(start);
+                    } else {
+                        self.verts[i0].start = start;
+                    }
+                    self.verts[i0].count = count;
+                    break;
+                }
+                if total - removed <= target {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The surviving triangles and the vertices they use, renumbered
+    fn into_surface(self, extent: Vec3, size: f64, has_values: bool) -> TriSurface {
+        let mut local = vec![u32::MAX; self.verts.len()];
+        let mut points = Vec::new();
+        let mut values = Vec::new();
+        let mut triangles = Vec::new();
+        for t in self.tris.iter().filter(|t| !t.deleted) {
+            triangles.push(t.v.map(|x| {
+                if local[x] == u32::MAX {
+                    local[x] = points.len() as u32;
+                    points.push(add(scale(self.verts[x].p, size), extent));
+                    values.push(self.verts[x].value);
+                }
+                local[x]
+            }));
+        }
+        TriSurface { points, triangles, values: has_values.then_some(values) }
+    }
+}
+
+pub fn decimate(surface: TriSurface, target: usize, progress: &Progress) -> PyResult<TriSurface> {
+    if surface.triangles.len() <= target {
+        return Ok(surface);
+    }
+    // Work in a box of unit diagonal so the error thresholds mean the same
+    // for a micro-channel and a ship hull
+    let (lo, hi) = surface.points.iter().fold(([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]), |(lo, hi), p| {
+        ([lo[0].min(p[0]), lo[1].min(p[1]), lo[2].min(p[2])], [hi[0].max(p[0]), hi[1].max(p[1]), hi[2].max(p[2])])
+    });
+    let size = mag(sub(hi, lo)).max(f64::MIN_POSITIVE);
+    let has_values = surface.values.is_some();
+    let values = surface.values.unwrap_or_default();
+    let (vmin, vmax) = crate::colormap::finite_range(&values).unwrap_or((0.0, 0.0));
+    let span = vmax - vmin;
+    let field_weight = if span > 0.0 { FIELD_WEIGHT / (span * span) } else { 0.0 };
+
+    let verts = surface
+        .points
+        .iter()
+        .enumerate()
+        .map(|(i, &p)| Vertex { p: scale(sub(p, lo), 1.0 / size), value: values.get(i).copied().unwrap_or(f64::NAN), ..Vertex::default() })
+        .collect();
+    let tris = surface
+        .triangles
+        .iter()
+        .map(|t| Triangle { v: t.map(|x| x as usize), err: [0.0; 4], normal: [0.0; 3], deleted: false, dirty: false })
+        .collect();
+    let mut decimator = Decimator { tris, verts, refs: Vec::new(), field_weight };
+    decimator.run(target, progress)?;
+    Ok(decimator.into_surface(lo, size, has_values))
+}
+
+/// Reduce a surface from extract_patch_surface(), slice_plane() or
+/// isosurface() to about `target_triangles` triangles by quadric-error edge
+/// collapse, for streaming to the browser. Flat and smooth regions go first;
+/// the outline of open surfaces is kept, and so are the triangles across
+/// which the sampled `values` change sharply, so the colouring survives.
+/// Collapses that would fold a triangle over are skipped, so a surface may
+/// end up somewhat above the target. Progress counts the sweeps over the
+/// surface. Returns a new surface in the layout of extract_patch_surface(),
+/// with normals recomputed.
+#[pyfunction]
+#[pyo3(signature = (surface, target_triangles, options = None))]
+pub fn decimate_surface<'py>(py: Python<'py>, surface: &Bound<'py, PyDict>, target_triangles: usize, options: Option<Options>) -> PyResult<Bound<'py, PyDict>> {
+    if target_triangles == 0 {
+        return Err(PyValueError::new_err("target_triangles must be positive"));
+    }
+    let surface = surface_from_py(surface)?;
+    let reduced = options::detach(py, &options, || {
+        let progress = Progress::start(PASSES);
+        let reduced = decimate(surface, target_triangles, &progress);
+        progress.finish();
+        reduced
+    })?;
+    surface_to_py(py, reduced)
+}
@@ -0,0 +1,303 @@
+use numpy::{IntoPyArray, PyArrayMethods};
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use std::ops::{Deref, Range};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::errors;
+use crate::field::{parse_field_file, type_name, FieldValue};
+use crate::header::header_end;
+use crate::list::{read_label_list, skip_ws_comments};
+use crate::mesh::owner_note_count;
+use crate::options::{self, Options, Progress};
+use crate::source::{open_field_file, FieldData};
+
+fn numbered_dirs(case_root: &Path, parse: impl Fn(&str) -> Option<usize>) -> std::io::Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    let entries = match std::fs::read_dir(case_root) {
+        Ok(e) => e,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name();
+        if let Some(n) = name.to_str().and_then(&parse) {
+            if entry.file_type()?.is_dir() {
+                dirs.push((n, entry.path()));
+            }
+        }
+    }
+    dirs.sort_by_key(|(n, _)| *n);
+    Ok(dirs.into_iter().map(|(_, p)| p).collect())
+}
+
+// processor0..processorN directories of a decomposed case, in rank order
+pub fn processor_dirs(case_root: &Path) -> std::io::Result<Vec<PathBuf>> {
+    numbered_dirs(case_root, |n| n.strip_prefix("processor")?.parse().ok())
+}
+
+// processors<N> directories written by the collated file handler. Distributed
+// runs may split ranks over several, e.g. processors4_0-1 and processors4_2-3.
+pub fn collated_dirs(case_root: &Path) -> std::io::Result<Vec<PathBuf>> {
+    numbered_dirs(case_root, |n| {
+        let rest = n.strip_prefix("processors")?;
+        match rest.split_once('_') {
+            // Order split directories by their first rank
+            Some((_, ranks)) => ranks.split('-').next()?.parse().ok(),
+            None => rest.parse::<usize>().ok().map(|_| 0),
+        }
+    })
+}
+
+// Split a decomposedBlockData file into its per-rank blocks. After the outer
+// header each rank is stored as a char list, `N ( <N raw bytes> )`, holding
+// that rank's complete file text.
+pub fn collated_blocks(data: &[u8]) -> Vec<Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut pos = header_end(data);
+    loop {
+        pos = skip_ws_comments(data, pos);
+        let digits = data[pos..].iter().take_while(|b| b.is_ascii_digit()).count();
+        let size: usize = match std::str::from_utf8(&data[pos..pos + digits]).ok().and_then(|s| s.parse().ok()) {
+            Some(n) => n,
+            None => break,
+        };
+        pos = skip_ws_comments(data, pos + digits);
+        if data.get(pos) != Some(&b'(') || pos + 1 + size > data.len() {
+            break;
+        }
+        blocks.push(pos + 1..pos + 1 + size);
+        pos += size + 2;
+    }
+    blocks
+}
+
+// One rank's copy of a file: either a whole processorN file or a block of a
+// collated file shared between ranks
+pub struct RankData {
+    data: Arc<FieldData>,
+    range: Range<usize>,
+}
+
+impl Deref for RankData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data[self.range.clone()]
+    }
+}
+
+// Per-rank contents of `rel` (e.g. "0.5/p" or "constant/polyMesh/owner"),
+// from processorN directories if present and collated processorsN files
+// otherwise. Ranks lacking the file are None.
+pub fn decomposed_files(case_root: &Path, rel: &Path) -> std::io::Result<Vec<Option<RankData>>> {
+    let procs = processor_dirs(case_root)?;
+    if !procs.is_empty() {
+        return procs
+            .iter()
+            .map(|p| {
+                Ok(open_field_file(&p.join(rel).to_string_lossy())?.map(|d| {
+                    let len = d.len();
+                    RankData { data: Arc::new(d), range: 0..len }
+                }))
+            })
+            .collect();
+    }
+
+    let mut ranks = Vec::new();
+    for dir in collated_dirs(case_root)? {
+        if let Some(data) = open_field_file(&dir.join(rel).to_string_lossy())? {
+            let data = Arc::new(data);
+            for range in collated_blocks(&data) {
+                ranks.push(Some(RankData { data: data.clone(), range }));
+            }
+        }
+    }
+    Ok(ranks)
+}
+
+// Sum and number of values of one rank's scalar internalField, ASCII or
+// binary. A uniform value is weighted by the rank's cell count when the mesh
+// records it, and a nonuniform list is checked against that count.
+fn rank_sum_count(rank: usize, field: &[u8], owner: Option<&RankData>) -> PyResult<Option<(f64, usize)>> {
+    let file = parse_field_file(field).map_err(|e| errors::parse_error(format!("processor{}: {}", rank, e)))?;
+    if file.width != 1 {
+        let kind = type_name(file.width).unwrap_or("non-scalar");
+        return Err(PyTypeError::new_err(format!("processor{}: {} field given to a scalar mean", rank, kind)));
+    }
+    let n_cells = owner.and_then(|o| owner_note_count(o, "nCells"));
+    match file.internal {
+        Some(FieldValue::NonUniform(values)) => {
+            if let Some(n) = n_cells.filter(|&n| n != values.len()) {
+                return Err(errors::count_error(
+                    format!("processor{}: internalField has {} values but the mesh has {} cells", rank, values.len(), n),
+                    n,
+                    values.len(),
+                ));
+            }
+            Ok(Some((values.iter().sum(), values.len())))
+        }
+        Some(FieldValue::Uniform(value)) => {
+            let n = n_cells.unwrap_or(1);
+            Ok(Some((value[0] * n as f64, n)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Mean of a scalar field over all ranks of a decomposed case (processor*
+/// directories or collated processors* files), weighted by each rank's cell
+/// count, without reconstructPar. Rank files may be ASCII or binary; vector
+/// and tensor fields raise TypeError, and a rank whose internalField length
+/// disagrees with its mesh raises FieldCountError. Returns (mean, count), or
+/// None if no rank has the field.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field, options = None))]
+pub fn parse_scalar_field_parallel_case(
+    py: Python,
+    case_root: String,
+    time: String,
+    field: String,
+    options: Option<Options>,
+) -> PyResult<Option<(f64, usize)>> {
+    options::detach(py, &options, || {
+        let root = Path::new(&case_root);
+        let fields = decomposed_files(root, &Path::new(&time).join(&field))?;
+        let owners = decomposed_files(root, Path::new("constant/polyMesh/owner"))?;
+
+        let progress = Progress::start(fields.len());
+        let (sum, count) = fields
+            .par_iter()
+            .enumerate()
+            .map(|(rank, f)| match f {
+                Some(f) => rank_sum_count(rank, f, owners.get(rank).and_then(|o| o.as_ref())),
+                None => Ok(None),
+            })
+            .inspect(|_| progress.step())
+            .try_fold(|| (0.0, 0), |acc, r| r.map(|r| r.map_or(acc, |(s, c)| (acc.0 + s, acc.1 + c))))
+            .try_reduce(|| (0.0, 0), |a, b| Ok((a.0 + b.0, a.1 + b.1)))?;
+
+        if count == 0 {
+            return Ok(None);
+        }
+        Ok(Some((sum / count as f64, count)))
+    })
+}
+
+// One rank's contribution to a reconstructed field
+struct RankField {
+    addressing: Vec<i64>,
+    values: Vec<f64>,
+    width: usize,
+}
+
+// A rank's field, ASCII or binary, expanded over its cells
+fn read_rank_field(rank: usize, addressing: &[u8], field: &[u8]) -> PyResult<RankField> {
+    let addressing = read_label_list(addressing)
+        .ok_or_else(|| errors::parse_error(format!("processor{}: unreadable cellProcAddressing", rank)))?;
+    let file = parse_field_file(field).map_err(|e| errors::parse_error(format!("processor{}: {}", rank, e)))?;
+    let width = file.width;
+    let internal = file.internal.ok_or_else(|| errors::parse_error(format!("processor{}: no readable internalField", rank)))?;
+    let values = internal.expand(addressing.len(), width).ok_or_else(|| {
+        let found = match &internal {
+            FieldValue::NonUniform(v) => v.len() / width,
+            FieldValue::Uniform(_) => 1,
+        };
+        errors::count_error(
+            format!("processor{}: field has {} values but cellProcAddressing lists {} cells", rank, found, addressing.len()),
+            addressing.len(),
+            found,
+        )
+    })?;
+    Ok(RankField { addressing, values, width })
+}
+
+/// Reconstruct a decomposed field into one global array using each rank's
+/// cellProcAddressing, like reconstructPar but in memory. Both processor*
+/// directories and collated processors* files are supported. Returns a 1-D
+/// array for scalar fields and (nCells, components) otherwise, or None if no
+/// rank has the field. Rank files may be ASCII or binary. Raises
+/// FieldNotFound naming the processor when some ranks have the field and
+/// others don't, and ValueError for cellProcAddressing labels outside the
+/// mesh.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field, options = None))]
+pub fn reconstruct_field<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    field: String,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyAny>>> {
+    let result = options::detach(py, &options, || -> PyResult<Option<(Vec<f64>, usize)>> {
+        let root = Path::new(&case_root);
+        let fields = decomposed_files(root, &Path::new(&time).join(&field))?;
+        let addressing = decomposed_files(root, Path::new("constant/polyMesh/cellProcAddressing"))?;
+
+        if fields.iter().all(Option::is_none) {
+            return Ok(None);
+        }
+        if addressing.len() != fields.len() {
+            return Err(PyValueError::new_err(format!(
+                "{} ranks have cellProcAddressing but the case has {}",
+                addressing.len(),
+                fields.len()
+            )));
+        }
+
+        let progress = Progress::start(fields.len());
+        let ranks = fields
+            .par_iter()
+            .zip(addressing.par_iter())
+            .enumerate()
+            .map(|(rank, pair)| {
+                options::check_cancelled()?;
+                match pair {
+                    (Some(f), Some(a)) => read_rank_field(rank, a, f),
+                    // A rank without the field would leave its cells unset
+                    (None, _) => Err(errors::FieldNotFound::new_err(format!("processor{}: no field {}/{}", rank, time, field))),
+                    (_, None) => Err(errors::FieldNotFound::new_err(format!(
+                        "processor{}: no constant/polyMesh/cellProcAddressing",
+                        rank
+                    ))),
+                }
+            })
+            .inspect(|_| progress.step())
+            .collect::<PyResult<Vec<_>>>()?;
+
+        let width = ranks[0].width;
+        if ranks.iter().any(|r| r.width != width) {
+            return Err(PyValueError::new_err("processor fields disagree on the number of components"));
+        }
+
+        // Each cell belongs to exactly one rank
+        let n_cells = ranks.iter().map(|r| r.addressing.len()).sum::<usize>();
+        let mut global = vec![0.0; n_cells * width];
+        for (i, rank) in ranks.iter().enumerate() {
+            for (local, &cell) in rank.addressing.iter().enumerate() {
+                if cell < 0 || cell as usize >= n_cells {
+                    return Err(PyValueError::new_err(format!(
+                        "processor{}: cellProcAddressing label {} out of range for {} cells",
+                        i, cell, n_cells
+                    )));
+                }
+                let cell = cell as usize;
+                global[cell * width..(cell + 1) * width]
+                    .copy_from_slice(&rank.values[local * width..(local + 1) * width]);
+            }
+        }
+        Ok(Some((global, width)))
+    })?;
+
+    match result {
+        Some((values, 1)) => Ok(Some(values.into_pyarray(py).into_any())),
+        Some((values, width)) => {
+            let rows = values.len() / width;
+            Ok(Some(values.into_pyarray(py).reshape([rows, width])?.into_any()))
+        }
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,269 @@
+// Fields derived from the written ones on the fly (gradients and what is
+// built from them) for when the solver didn't write them.
+
+use numpy::{IntoPyArray, PyArray1, PyArrayMethods};
+use pyo3::exceptions::{PyFileNotFoundError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::Path;
+
+use crate::dict::load_dict;
+use crate::field::{load_field, load_mesh, load_patches, select_patches, FieldFile};
+use crate::geometry::{dot, mag, scale, sub, MeshGeometry};
+use crate::mesh::{Patch, PolyMesh};
+use crate::options::{self, Options};
+use crate::surface::cell_values;
+
+// Green-Gauss cell gradients: (1/V) sum of face value times Sf. Internal
+// face values are interpolated linearly between the cell centres; boundary
+// faces take the patch value, or the cell value where the patch has none
+// (zeroGradient); empty patches don't contribute. Each cell gets 3 * width
+// components, OpenFOAM's grad ordering (d/dx of every component, then d/dy,
+// then d/dz).
+pub fn green_gauss(mesh: &PolyMesh, geom: &MeshGeometry, patches: &[Patch], file: &FieldFile) -> Result<Vec<f64>, String> {
+    let width = file.width;
+    let cells = cell_values(mesh, file)?;
+    let mut grad = vec![0.0; mesh.n_cells * 3 * width];
+    let mut add_flux = |cell: usize, sf: [f64; 3], value: &[f64], sign: f64| {
+        for (i, s) in sf.iter().enumerate() {
+            for (j, v) in value.iter().enumerate() {
+                grad[cell * 3 * width + i * width + j] += sign * s * v;
+            }
+        }
+    };
+
+    let mut face_value = vec![0.0; width];
+    for f in 0..mesh.neighbour.len() {
+        let (o, n) = (mesh.owner[f], mesh.neighbour[f]);
+        let sf = geom.face_areas[f];
+        let to_owner = dot(sf, sub(geom.face_centres[f], geom.cell_centres[o]));
+        let to_neighbour = dot(sf, sub(geom.cell_centres[n], geom.face_centres[f]));
+        let w = if to_owner + to_neighbour != 0.0 { to_neighbour / (to_owner + to_neighbour) } else { 0.5 };
+        for (k, v) in face_value.iter_mut().enumerate() {
+            *v = w * cells[o * width + k] + (1.0 - w) * cells[n * width + k];
+        }
+        add_flux(o, sf, &face_value, 1.0);
+        add_flux(n, sf, &face_value, -1.0);
+    }
+    for patch in patches.iter().filter(|p| p.patch_type != "empty") {
+        let values = file.patch_face_values(mesh, patch)?;
+        for (i, value) in values.chunks_exact(width).enumerate() {
+            let f = patch.start_face + i;
+            add_flux(mesh.owner[f], geom.face_areas[f], value, 1.0);
+        }
+    }
+
+    for (c, volume) in geom.cell_volumes.iter().enumerate() {
+        let scale = if *volume > 0.0 { 1.0 / volume } else { 0.0 };
+        for g in &mut grad[c * 3 * width..(c + 1) * 3 * width] {
+            *g *= scale;
+        }
+    }
+    Ok(grad)
+}
+
+// Mesh, geometry, patches and one field of a case
+pub fn load_case_field(case_root: &Path, time: &str, field: &str) -> PyResult<(PolyMesh, MeshGeometry, Vec<Patch>, FieldFile)> {
+    let path = case_root.join(time).join(field);
+    let file = load_field(&path)?
+        .ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no such field file", path.display())))?;
+    let mesh = load_mesh(case_root, None)?;
+    let geom = MeshGeometry::compute(&mesh);
+    Ok((mesh, geom, load_patches(case_root, None)?, file))
+}
+
+/// Green-Gauss gradient of a volume field, computed the way OpenFOAM's
+/// `Gauss linear` scheme does: (n_cells, 3) for a scalar field and
+/// (n_cells, 9) for a vector field, in OpenFOAM's tensor order (xx, xy, xz,
+/// yx, ...), where component ij is d(field_j)/dx_i. Boundary faces use the
+/// patch values, or the adjacent cell value for patches without one.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field, options = None))]
+pub fn compute_gradient<'py>(py: Python<'py>, case_root: String, time: String, field: String, options: Option<Options>) -> PyResult<Bound<'py, PyAny>> {
+    let (grad, columns) = options::detach(py, &options, || -> PyResult<(Vec<f64>, usize)> {
+        let root = Path::new(&case_root);
+        let (mesh, geom, patches, file) = load_case_field(root, &time, &field)?;
+        let grad = green_gauss(&mesh, &geom, &patches, &file)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", root.join(&time).join(&field).display(), e)))?;
+        Ok((grad, 3 * file.width))
+    })?;
+    let n = grad.len() / columns;
+    Ok(grad.into_pyarray(py).reshape([n, columns])?.into_any())
+}
+
+// Cell velocity gradients of a vector field, 9 components per cell
+fn velocity_gradient(case_root: &Path, time: &str, field: &str) -> PyResult<Vec<[f64; 9]>> {
+    let (mesh, geom, patches, file) = load_case_field(case_root, time, field)?;
+    let path = case_root.join(time).join(field);
+    if file.width != 3 {
+        return Err(PyValueError::new_err(format!("{}: {} is not a vector field", path.display(), file.class)));
+    }
+    let grad = green_gauss(&mesh, &geom, &patches, &file).map_err(|e| PyValueError::new_err(format!("{}: {}", path.display(), e)))?;
+    Ok(grad.chunks_exact(9).map(|g| g.try_into().unwrap()).collect())
+}
+
+/// Vorticity (curl) of a velocity field from its Green-Gauss gradient.
+/// Returns a dict with `vorticity` (n_cells, 3) and its `magnitude`
+/// (n_cells,).
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field = "U".to_string(), options = None))]
+pub fn compute_vorticity<'py>(py: Python<'py>, case_root: String, time: String, field: String, options: Option<Options>) -> PyResult<Bound<'py, PyDict>> {
+    let (vorticity, magnitude) = options::detach(py, &options, || -> PyResult<(Vec<f64>, Vec<f64>)> {
+        let grad = velocity_gradient(Path::new(&case_root), &time, &field)?;
+        let mut vorticity = Vec::with_capacity(grad.len() * 3);
+        let mut magnitude = Vec::with_capacity(grad.len());
+        for g in grad {
+            // g[3 * i + j] is d(U_j)/dx_i
+            let w = [g[5] - g[7], g[6] - g[2], g[1] - g[3]];
+            vorticity.extend_from_slice(&w);
+            magnitude.push(mag(w));
+        }
+        Ok((vorticity, magnitude))
+    })?;
+    let out = PyDict::new(py);
+    let n = magnitude.len();
+    out.set_item("vorticity", vorticity.into_pyarray(py).reshape([n, 3])?)?;
+    out.set_item("magnitude", magnitude.into_pyarray(py))?;
+    Ok(out)
+}
+
+/// Q-criterion of a velocity field per cell, 0.5 * (tr(grad U)^2 -
+/// tr(grad U & grad U)) as OpenFOAM's Q function object computes it;
+/// positive where rotation dominates strain. Returns (n_cells,).
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field = "U".to_string(), options = None))]
+pub fn compute_q_criterion<'py>(py: Python<'py>, case_root: String, time: String, field: String, options: Option<Options>) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let q = options::detach(py, &options, || -> PyResult<Vec<f64>> {
+        let grad = velocity_gradient(Path::new(&case_root), &time, &field)?;
+        Ok(grad
+            .iter()
+            .map(|g| {
+                let trace = g[0] + g[4] + g[8];
+                let inner: f64 = (0..3).flat_map(|i| (0..3).map(move |j| (i, j))).map(|(i, j)| g[3 * i + j] * g[3 * j + i]).sum();
+                0.5 * (trace * trace - inner)
+            })
+            .collect())
+    })?;
+    Ok(q.into_pyarray(py))
+}
+
+// Kinematic viscosity from constant/transportProperties or, in newer
+// OpenFOAM versions, constant/physicalProperties: "nu 1e-05;", "nu [0 2 -1
+// 0 0 0 0] 1e-05;" or the older "nu nu [0 2 -1 0 0 0 0] 1e-05;"
+fn case_viscosity(case_root: &Path) -> PyResult<Option<f64>> {
+    for name in ["transportProperties", "physicalProperties"] {
+        let path = case_root.join("constant").join(name);
+        let Some(dict) = load_dict(&path.to_string_lossy())? else {
+            continue;
+        };
+        if let Some(nu) = dict.stream("nu").and_then(|values| values.last()?.number()) {
+            return Ok(Some(nu));
+        }
+    }
+    Ok(None)
+}
+
+struct WallMetrics {
+    shear: Vec<f64>,
+    shear_mag: Vec<f64>,
+    y: Vec<f64>,
+    y_plus: Vec<f64>,
+    centres: Vec<f64>,
+    areas: Vec<f64>,
+    nu: f64,
+    turbulent: bool,
+}
+
+fn wall_metrics_of(case_root: &Path, time: &str, patch: &str, nu: Option<f64>) -> PyResult<WallMetrics> {
+    let (mesh, geom, patches, u) = load_case_field(case_root, time, "U")?;
+    let u_path = case_root.join(time).join("U");
+    let error = |path: &Path, e: String| PyValueError::new_err(format!("{}: {}", path.display(), e));
+    if u.width != 3 {
+        return Err(error(&u_path, format!("{} is not a vector field", u.class)));
+    }
+    let nu = match nu {
+        Some(nu) => nu,
+        None => case_viscosity(case_root)?.ok_or_else(|| {
+            PyValueError::new_err("no nu in constant/transportProperties or constant/physicalProperties; pass nu")
+        })?,
+    };
+    let nut_path = case_root.join(time).join("nut");
+    let nut = load_field(&nut_path)?;
+    let cells = cell_values(&mesh, &u).map_err(|e| error(&u_path, e))?;
+
+    let mut m = WallMetrics {
+        shear: Vec::new(),
+        shear_mag: Vec::new(),
+        y: Vec::new(),
+        y_plus: Vec::new(),
+        centres: Vec::new(),
+        areas: Vec::new(),
+        nu,
+        turbulent: nut.is_some(),
+    };
+    for p in select_patches(&patches, patch)? {
+        let wall = u.patch_face_values(&mesh, p).map_err(|e| error(&u_path, e))?;
+        let nut_wall = match &nut {
+            Some(nut) => Some(nut.patch_face_values(&mesh, p).map_err(|e| error(&nut_path, e))?),
+            None => None,
+        };
+        for i in 0..p.n_faces {
+            let f = p.start_face + i;
+            let c = mesh.owner[f];
+            let sf = geom.face_areas[f];
+            let area = mag(sf);
+            let normal = scale(sf, 1.0 / area.max(1e-300));
+            // Wall-normal distance of the near-wall cell centre
+            let y = dot(sub(geom.face_centres[f], geom.cell_centres[c]), normal).abs().max(1e-300);
+            let du = sub([cells[3 * c], cells[3 * c + 1], cells[3 * c + 2]], [wall[3 * i], wall[3 * i + 1], wall[3 * i + 2]]);
+            let tangential = sub(du, scale(normal, dot(du, normal)));
+            let nu_eff = nu + nut_wall.as_ref().map_or(0.0, |n| n[i]);
+            // OpenFOAM's sign: the stress on the fluid, opposite to the
+            // near-wall flow
+            let shear = scale(tangential, -nu_eff / y);
+            let shear_mag = mag(shear);
+            m.shear.extend_from_slice(&shear);
+            m.shear_mag.push(shear_mag);
+            m.y.push(y);
+            m.y_plus.push(y * shear_mag.sqrt() / nu);
+            m.centres.extend_from_slice(&geom.face_centres[f]);
+            m.areas.push(area);
+        }
+    }
+    Ok(m)
+}
+
+/// Wall shear stress and y+ on a wall patch (or patch group) from U, the
+/// viscosity and, for turbulent cases, the nut wall values: the shear is
+/// nu_eff times the near-wall tangential velocity gradient, estimated from
+/// the wall-adjacent cell, and y+ = y * sqrt(|tau|) / nu with y the cell
+/// centre's distance from the wall. `nu` defaults to the value in
+/// constant/transportProperties (or physicalProperties). Stresses are
+/// kinematic (divided by density), with OpenFOAM's wallShearStress sign.
+/// Returns per-face arrays `wall_shear_stress` (F, 3), `tau_mag`, `y`,
+/// `y_plus` and `face_centres` (F, 3), plus `nu`, `turbulent` and an
+/// area-weighted `summary` of y+ (min, max, mean).
+#[pyfunction]
+#[pyo3(signature = (case_root, time, patch, nu = None, options = None))]
+pub fn wall_metrics<'py>(py: Python<'py>, case_root: String, time: String, patch: String, nu: Option<f64>, options: Option<Options>) -> PyResult<Bound<'py, PyDict>> {
+    let m = options::detach(py, &options, || wall_metrics_of(Path::new(&case_root), &time, &patch, nu))?;
+    let out = PyDict::new(py);
+    let n = m.y.len();
+    let summary = PyDict::new(py);
+    let total_area: f64 = m.areas.iter().sum();
+    if n > 0 {
+        summary.set_item("min", m.y_plus.iter().copied().fold(f64::INFINITY, f64::min))?;
+        summary.set_item("max", m.y_plus.iter().copied().fold(f64::NEG_INFINITY, f64::max))?;
+        let weighted: f64 = m.y_plus.iter().zip(&m.areas).map(|(y, a)| y * a).sum();
+        summary.set_item("mean", if total_area > 0.0 { weighted / total_area } else { f64::NAN })?;
+    }
+    out.set_item("wall_shear_stress", m.shear.into_pyarray(py).reshape([n, 3])?)?;
+    out.set_item("tau_mag", m.shear_mag.into_pyarray(py))?;
+    out.set_item("y", m.y.into_pyarray(py))?;
+    out.set_item("y_plus", m.y_plus.into_pyarray(py))?;
+    out.set_item("face_centres", m.centres.into_pyarray(py).reshape([n, 3])?)?;
+    out.set_item("nu", m.nu)?;
+    out.set_item("turbulent", m.turbulent)?;
+    out.set_item("summary", summary)?;
+    Ok(out)
+}
@@ -0,0 +1,1139 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::calc::eval_scalar;
+use crate::header::{parse_dimension_set, Dimensions};
+use crate::options::{self, Options};
+use crate::source::open_field_file;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    Word(String),
+    Str(String),
+    // #{ ... #} code block
+    Verbatim(String),
+    Punct(u8),
+}
+
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+pub struct Lexer<'a> {
+    text: &'a str,
+    pos: usize,
+    peeked: Option<Token>,
+}
+
+const PUNCT: &[u8] = b"{}()[];";
+
+fn number_start(bytes: &[u8]) -> bool {
+    match bytes.first() {
+        Some(b) if b.is_ascii_digit() => true,
+        Some(b'-' | b'+' | b'.') => bytes.get(1).is_some_and(|b| b.is_ascii_digit() || *b == b'.'),
+        _ => false,
+    }
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Lexer { text, pos: 0, peeked: None }
+    }
+
+    fn skip_ws_comments(&mut self) {
+        let bytes = self.text.as_bytes();
+        loop {
+            while self.pos < bytes.len() && bytes[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+            let rest = &self.text[self.pos..];
+            if rest.starts_with("//") {
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else if let Some(body) = rest.strip_prefix("/*") {
+                self.pos += body.find("*/").map_or(rest.len(), |i| i + 4);
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn lex(&mut self) -> Result<Option<Token>, String> {
+        self.skip_ws_comments();
+        let bytes = self.text.as_bytes();
+        let start = self.pos;
+        let Some(&c) = bytes.get(start) else {
+            return Ok(None);
+        };
+        let kind = if c == b'"' {
+            let mut i = start + 1;
+            let mut value = String::new();
+            loop {
+                match bytes.get(i) {
+                    None => return Err(format!("unterminated string at byte {}", start)),
+                    Some(b'"') => break,
+                    Some(b'\\') if bytes.get(i + 1) == Some(&b'"') => {
+                        value.push('"');
+                        i += 2;
+                    }
+                    Some(_) => {
+                        let ch = self.text[i..].chars().next().unwrap_or('\0');
+                        value.push(ch);
+                        i += ch.len_utf8();
+                    }
+                }
+            }
+            self.pos = i + 1;
+            TokenKind::Str(value)
+        } else if self.text[start..].starts_with("#{") {
+            let end = self.text[start + 2..]
+                .find("#}")
+                .ok_or_else(|| format!("unterminated #{{ block at byte {}", start))?;
+            self.pos = start + 2 + end + 2;
+            TokenKind::Verbatim(self.text[start + 2..start + 2 + end].to_string())
+        } else if PUNCT.contains(&c) {
+            self.pos += 1;
+            TokenKind::Punct(c)
+        } else {
+            // Keywords such as div(phi,U) keep their balanced parentheses;
+            // numbers stop at '(' so "3(1 2 3)" splits into a count and a list
+            let number = number_start(&bytes[start..]);
+            let mut depth = 0usize;
+            let mut i = start;
+            while let Some(&b) = bytes.get(i) {
+                // ${name} variable references and #eval{ ... } keep their braces
+                if (b == b'$' && bytes.get(i + 1) == Some(&b'{')) || (b == b'{' && &self.text[start..i] == "#eval") {
+                    if let Some(close) = self.text[i..].find('}') {
+                        i += close + 1;
+                        continue;
+                    }
+                }
+                if b.is_ascii_whitespace() || matches!(b, b'{' | b'}' | b'[' | b']' | b';' | b'"') {
+                    break;
+                }
+                if b == b'(' {
+                    if number {
+                        break;
+                    }
+                    depth += 1;
+                } else if b == b')' {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                }
+                i += 1;
+            }
+            self.pos = i;
+            TokenKind::Word(self.text[start..i].to_string())
+        };
+        Ok(Some(Token { kind, span: start..self.pos }))
+    }
+
+    pub fn next(&mut self) -> Result<Option<Token>, String> {
+        match self.peeked.take() {
+            Some(t) => Ok(Some(t)),
+            None => self.lex(),
+        }
+    }
+
+    pub fn peek(&mut self) -> Result<Option<&Token>, String> {
+        if self.peeked.is_none() {
+            self.peeked = self.lex()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn peek_punct(&mut self, p: u8) -> Result<bool, String> {
+        Ok(matches!(self.peek()?, Some(Token { kind: TokenKind::Punct(q), .. }) if *q == p))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Value {
+    Word(String),
+    Str(String),
+    Verbatim(String),
+    List(Vec<Value>),
+    Dict(Dict),
+    Dims(Dimensions),
+}
+
+#[derive(Clone, Debug)]
+pub enum EntryValue {
+    Dict(Dict),
+    // Everything between the keyword and its ';'
+    Stream(Vec<Value>),
+}
+
+// One keyword entry. `value_span` covers the value text (the braces for a
+// sub-dict, the tokens before ';' otherwise) and `span` the whole entry, so
+// edits can splice the original text and leave the rest untouched.
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub key: String,
+    pub value: EntryValue,
+    pub span: Range<usize>,
+    pub value_span: Range<usize>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Dict {
+    pub entries: Vec<Entry>,
+    // Text between the braces, or the whole file for the top level
+    pub body_span: Range<usize>,
+}
+
+impl Dict {
+    // Last entry wins, as in OpenFOAM
+    pub fn get(&self, key: &str) -> Option<&Entry> {
+        self.entries.iter().rev().find(|e| e.key == key)
+    }
+
+    pub fn stream(&self, key: &str) -> Option<&[Value]> {
+        match &self.get(key)?.value {
+            EntryValue::Stream(values) => Some(values),
+            EntryValue::Dict(_) => None,
+        }
+    }
+
+    pub fn sub(&self, key: &str) -> Option<&Dict> {
+        match &self.get(key)?.value {
+            EntryValue::Dict(d) => Some(d),
+            EntryValue::Stream(_) => None,
+        }
+    }
+
+    // A single word or string value
+    pub fn word(&self, key: &str) -> Option<&str> {
+        match self.stream(key)? {
+            [Value::Word(w) | Value::Str(w)] => Some(w),
+            _ => None,
+        }
+    }
+
+    pub fn number(&self, key: &str) -> Option<f64> {
+        self.stream(key)?.first()?.number()
+    }
+}
+
+impl Value {
+    pub fn number(&self) -> Option<f64> {
+        match self {
+            Value::Word(w) => w.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+// Integer words directly before a list are element counts ("3(1 2 3)",
+// "List<scalar> 5(...)") and carry no information of their own. Only a
+// count that matches the list's length is dropped, so values such as the
+// leading 1 of "(1 ((0.5 0.5 2) (0.5 0.5 0.5)) 1)" survive.
+fn push_list(values: &mut Vec<Value>, list: Vec<Value>) {
+    let n = values.len();
+    let is_count = matches!(values.last(), Some(Value::Word(w)) if w.parse::<usize>() == Ok(list.len()));
+    let after_list_type = n >= 2 && matches!(&values[n - 2], Value::Word(w) if w.starts_with("List<"));
+    if is_count && (n == 1 || after_list_type) {
+        values.pop();
+    }
+    values.push(Value::List(list));
+}
+
+fn parse_dims(lexer: &mut Lexer) -> Result<Value, String> {
+    let mut words = Vec::new();
+    loop {
+        match lexer.next()? {
+            Some(Token { kind: TokenKind::Punct(b']'), .. }) => break,
+            Some(Token { kind: TokenKind::Word(w), .. }) => words.push(w),
+            Some(t) => return Err(format!("unexpected token in dimension set at byte {}", t.span.start)),
+            None => return Err("unterminated dimension set".into()),
+        }
+    }
+    let body = words.join(" ");
+    // Unit expressions the numeric/SI parser doesn't know stay as text
+    Ok(parse_dimension_set(&body).map_or_else(|| Value::Word(format!("[{}]", body)), Value::Dims))
+}
+
+// Value starting at `token`, recursing into lists, dimension sets and
+// anonymous dicts
+fn parse_value(lexer: &mut Lexer, token: Token) -> Result<Value, String> {
+    Ok(match token.kind {
+        TokenKind::Word(w) => Value::Word(w),
+        TokenKind::Str(s) => Value::Str(s),
+        TokenKind::Verbatim(v) => Value::Verbatim(v),
+        TokenKind::Punct(b'(') => Value::List(parse_list(lexer)?),
+        TokenKind::Punct(b'[') => parse_dims(lexer)?,
+        TokenKind::Punct(b'{') => Value::Dict(parse_body(lexer, Some(token.span.start))?),
+        TokenKind::Punct(p) => return Err(format!("unexpected '{}' at byte {}", p as char, token.span.start)),
+    })
+}
+
+fn parse_list(lexer: &mut Lexer) -> Result<Vec<Value>, String> {
+    let mut items = Vec::new();
+    loop {
+        let Some(token) = lexer.next()? else {
+            return Err("unterminated list".into());
+        };
+        match token.kind {
+            TokenKind::Punct(b')') => return Ok(items),
+            // Separators some writers put between list items
+            TokenKind::Punct(b';') => {}
+            TokenKind::Punct(b'(') => {
+                let list = parse_list(lexer)?;
+                push_list(&mut items, list);
+            }
+            _ => items.push(parse_value(lexer, token)?),
+        }
+    }
+}
+
+// Directives that take a single argument and no ';'
+fn directive_arity(key: &str) -> usize {
+    match key {
+        "#include" | "#includeEtc" | "#includeIfPresent" | "#includeFunc" | "#includeModel" | "#inputMode"
+        | "#remove" | "#sinclude" => 1,
+        _ => 0,
+    }
+}
+
+// Entries up to the closing '}' (or end of input for the top level, when
+// `open` is None)
+fn parse_body(lexer: &mut Lexer, open: Option<usize>) -> Result<Dict, String> {
+    let mut dict = Dict {
+        entries: Vec::new(),
+        body_span: open.map_or(0, |o| o + 1)..0,
+    };
+    loop {
+        let Some(token) = lexer.next()? else {
+            if let Some(o) = open {
+                return Err(format!("unterminated dictionary opened at byte {}", o));
+            }
+            dict.body_span.end = lexer.text.len();
+            return Ok(dict);
+        };
+        let start = token.span.start;
+        let key = match token.kind {
+            TokenKind::Punct(b'}') if open.is_some() => {
+                dict.body_span.end = start;
+                return Ok(dict);
+            }
+            TokenKind::Punct(b';') => continue,
+            TokenKind::Word(w) => w,
+            TokenKind::Str(s) => s,
+            _ => return Err(format!("expected a keyword at byte {}", start)),
+        };
+
+        let arity = directive_arity(&key);
+        if arity > 0 {
+            let mut values = Vec::new();
+            let mut end = token.span.end;
+            for _ in 0..arity {
+                let arg = lexer.next()?.ok_or_else(|| format!("{} without argument", key))?;
+                values.push(parse_value(lexer, arg)?);
+                end = lexer.pos;
+            }
+            let value_span = token.span.end..end;
+            if lexer.peek_punct(b';')? {
+                end = lexer.next()?.map_or(end, |t| t.span.end);
+            }
+            dict.entries.push(Entry { key, value: EntryValue::Stream(values), span: start..end, value_span });
+            continue;
+        }
+
+        if lexer.peek_punct(b'{')? {
+            let brace = lexer.next()?.map_or(0, |t| t.span.start);
+            let sub = parse_body(lexer, Some(brace))?;
+            let value_end = sub.body_span.end + 1;
+            // Tolerate a stray ';' after the closing brace
+            let mut end = value_end;
+            if lexer.peek_punct(b';')? {
+                end = lexer.next()?.map_or(end, |t| t.span.end);
+            }
+            dict.entries.push(Entry {
+                key,
+                value: EntryValue::Dict(sub),
+                span: start..end,
+                value_span: brace..value_end,
+            });
+            continue;
+        }
+
+        let mut values = Vec::new();
+        let value_start = lexer.peek()?.map_or(token.span.end, |t| t.span.start);
+        let mut value_end = value_start;
+        let end = loop {
+            let Some(t) = lexer.next()? else {
+                return Err(format!("entry '{}' is missing its ';'", key));
+            };
+            match t.kind {
+                TokenKind::Punct(b';') => break t.span.end,
+                TokenKind::Punct(b'}') if open.is_some() => {
+                    return Err(format!("entry '{}' is missing its ';' at byte {}", key, t.span.start));
+                }
+                TokenKind::Punct(b'(') => {
+                    let list = parse_list(lexer)?;
+                    push_list(&mut values, list);
+                }
+                _ => values.push(parse_value(lexer, t)?),
+            }
+            value_end = lexer.pos;
+        };
+        dict.entries.push(Entry {
+            key,
+            value: EntryValue::Stream(values),
+            span: start..end,
+            value_span: value_start..value_end,
+        });
+    }
+}
+
+pub fn parse_text(text: &str) -> Result<Dict, String> {
+    parse_body(&mut Lexer::new(text), None)
+}
+
+// 1-based line of a byte offset, for error messages
+pub fn line_of(text: &str, offset: usize) -> usize {
+    text.as_bytes()[..offset.min(text.len())].iter().filter(|&&b| b == b'\n').count() + 1
+}
+
+// Error messages carry "byte N"; report the line instead
+pub fn dict_error(path: &str, text: &str, message: &str) -> PyErr {
+    let line = message
+        .rsplit_once("byte ")
+        .and_then(|(_, n)| n.parse::<usize>().ok())
+        .map(|b| format!(" (line {})", line_of(text, b)))
+        .unwrap_or_default();
+    let message = message.rsplit_once(" at byte ").map_or(message, |(m, _)| m);
+    PyValueError::new_err(format!("{}: {}{}", Path::new(path).display(), message, line))
+}
+
+// Nested #include chains deeper than this are taken to be cycles
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+struct Expander {
+    case_root: PathBuf,
+    depth: usize,
+}
+
+// Add an entry the way OpenFOAM's default merge mode does: a sub-dict given
+// twice is merged, any other repeated keyword replaces the earlier value
+fn merge_entry(scope: &mut Vec<Entry>, entry: Entry) {
+    match scope.iter_mut().rev().find(|e| e.key == entry.key) {
+        Some(old) => match (&mut old.value, entry.value) {
+            (EntryValue::Dict(a), EntryValue::Dict(b)) => {
+                for e in b.entries {
+                    merge_entry(&mut a.entries, e);
+                }
+            }
+            (value, new) => *value = new,
+        },
+        None => scope.push(entry),
+    }
+}
+
+fn find_scoped<'a>(entries: &'a [Entry], parts: &[&str]) -> Option<&'a EntryValue> {
+    let (first, rest) = parts.split_first()?;
+    let entry = entries.iter().rev().find(|e| e.key == *first)?;
+    match (&entry.value, rest) {
+        (value, []) => Some(value),
+        (EntryValue::Dict(d), rest) => find_scoped(&d.entries, rest),
+        _ => None,
+    }
+}
+
+// Resolve "$name", "${name}", "$a.b" (scoped), "$:a.b" or "$/a/b" (from the
+// top level) and "$..name" (from the enclosing dict) against the entries
+// seen so far, innermost scope first
+fn lookup(scopes: &[Vec<Entry>], reference: &str) -> Option<EntryValue> {
+    let name = reference.strip_prefix('$')?;
+    let name = name.strip_prefix('{').and_then(|n| n.strip_suffix('}')).unwrap_or(name);
+    if let Some(path) = name.strip_prefix(':').or_else(|| name.strip_prefix('/')) {
+        let parts: Vec<&str> = path.split(['.', '/']).collect();
+        return find_scoped(scopes.first()?, &parts).cloned();
+    }
+    let dots = name.bytes().take_while(|&b| b == b'.').count();
+    let (scopes, name) = match dots {
+        0 => (scopes, name),
+        n => (&scopes[..scopes.len().saturating_sub(n - 1).max(1)], &name[n..]),
+    };
+    for scope in scopes.iter().rev() {
+        if let Some(value) = find_scoped(scope, &[name]) {
+            return Some(value.clone());
+        }
+        let parts: Vec<&str> = name.split('.').collect();
+        if parts.len() > 1 {
+            if let Some(value) = find_scoped(scope, &parts) {
+                return Some(value.clone());
+            }
+        }
+    }
+    None
+}
+
+fn is_reference(w: &str) -> bool {
+    w.len() > 1 && w.starts_with('$')
+}
+
+impl Expander {
+    // Environment variables and the <case>/<system>/<constant> tags allowed
+    // in include paths
+    fn include_path(&self, dir: &Path, name: &str) -> PathBuf {
+        let mut name = name
+            .replace("<case>", &self.case_root.to_string_lossy())
+            .replace("<system>", &self.case_root.join("system").to_string_lossy())
+            .replace("<constant>", &self.case_root.join("constant").to_string_lossy());
+        for var in ["FOAM_CASE", "FOAM_CASESRC"] {
+            for form in [format!("${{{}}}", var), format!("${}", var)] {
+                name = name.replace(&form, &self.case_root.to_string_lossy());
+            }
+        }
+        let path = Path::new(&name);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            dir.join(path)
+        }
+    }
+
+    fn include(&mut self, path: &Path, scopes: &mut Vec<Vec<Entry>>) -> Result<(), String> {
+        if self.depth >= MAX_INCLUDE_DEPTH {
+            return Err(format!("#include nested too deeply at {}", path.display()));
+        }
+        let data = std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        let text = String::from_utf8_lossy(&data);
+        let included = parse_text(&text).map_err(|e| {
+            let at = e.rsplit_once("byte ").and_then(|(_, n)| n.parse::<usize>().ok());
+            let message = e.rsplit_once(" at byte ").map_or(e.as_str(), |(m, _)| m);
+            match at {
+                Some(b) => format!("{}: {} (line {})", path.display(), message, line_of(&text, b)),
+                None => format!("{}: {}", path.display(), message),
+            }
+        })?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        self.depth += 1;
+        let result = self.expand_entries(&included.entries, dir, scopes);
+        self.depth -= 1;
+        result
+    }
+
+    fn expand_values(&mut self, values: &[Value], dir: &Path, scopes: &mut Vec<Vec<Entry>>) -> Result<Vec<Value>, String> {
+        let mut out = Vec::with_capacity(values.len());
+        let mut iter = values.iter().peekable();
+        while let Some(value) = iter.next() {
+            // #calc "expr", #eval "expr" and #eval{ expr }
+            let expr = match (value, iter.peek()) {
+                (Value::Word(w), Some(Value::Str(e))) if w == "#calc" || w == "#eval" => Some(e.as_str()),
+                (Value::Word(w), _) => w.strip_prefix("#eval{").and_then(|e| e.strip_suffix('}')),
+                _ => None,
+            };
+            if let Some(expr) = expr {
+                let resolve = |name: &str| match lookup(scopes, name) {
+                    Some(EntryValue::Stream(vs)) => match vs.as_slice() {
+                        [Value::Word(w)] => w.parse::<f64>().ok(),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                if let Some(result) = eval_scalar(expr, resolve) {
+                    if !matches!(value, Value::Word(w) if w.starts_with("#eval{")) {
+                        iter.next();
+                    }
+                    out.push(Value::Word(format_scalar(result)));
+                    continue;
+                }
+            }
+            match value {
+                Value::Word(w) if is_reference(w) => match lookup(scopes, w) {
+                    Some(EntryValue::Stream(vs)) => out.extend(vs),
+                    Some(EntryValue::Dict(d)) => out.push(Value::Dict(d)),
+                    // Left as written so the rest of the file still loads
+                    None => out.push(value.clone()),
+                },
+                Value::List(items) => out.push(Value::List(self.expand_values(items, dir, scopes)?)),
+                Value::Dict(d) => out.push(Value::Dict(self.expand_dict(d, dir, scopes)?)),
+                _ => out.push(value.clone()),
+            }
+        }
+        Ok(out)
+    }
+
+    fn expand_dict(&mut self, dict: &Dict, dir: &Path, scopes: &mut Vec<Vec<Entry>>) -> Result<Dict, String> {
+        scopes.push(Vec::new());
+        let result = self.expand_entries(&dict.entries, dir, scopes);
+        let entries = scopes.pop().unwrap_or_default();
+        result?;
+        Ok(Dict { entries, body_span: dict.body_span.clone() })
+    }
+
+    // Expand `entries` into the innermost scope
+    fn expand_entries(&mut self, entries: &[Entry], dir: &Path, scopes: &mut Vec<Vec<Entry>>) -> Result<(), String> {
+        for entry in entries {
+            let args = match &entry.value {
+                EntryValue::Stream(values) => values.as_slice(),
+                EntryValue::Dict(_) => &[],
+            };
+            let arg = match args.first() {
+                Some(Value::Str(s) | Value::Word(s)) => Some(s.as_str()),
+                _ => None,
+            };
+            match (entry.key.as_str(), arg) {
+                ("#include" | "#includeIfPresent" | "#sinclude", Some(name)) => {
+                    let path = self.include_path(dir, name);
+                    if path.is_file() || entry.key == "#include" {
+                        self.include(&path, scopes)?;
+                    }
+                    continue;
+                }
+                ("#includeEtc", Some(name)) => {
+                    let etc = ["FOAM_ETC", "WM_PROJECT_DIR"].iter().find_map(|var| {
+                        let mut dir = PathBuf::from(std::env::var_os(var)?);
+                        if *var == "WM_PROJECT_DIR" {
+                            dir.push("etc");
+                        }
+                        Some(dir.join(name)).filter(|p| p.is_file())
+                    });
+                    // Without an OpenFOAM installation the directive is kept
+                    // (and skipped like any other) rather than failing the load
+                    match etc {
+                        Some(path) => self.include(&path, scopes)?,
+                        None => merge_entry(scopes.last_mut().unwrap(), entry.clone()),
+                    }
+                    continue;
+                }
+                ("#remove", _) => {
+                    let mut keys = Vec::new();
+                    for value in args {
+                        match value {
+                            Value::Word(w) | Value::Str(w) => keys.push(w.clone()),
+                            Value::List(items) => keys.extend(items.iter().filter_map(|v| match v {
+                                Value::Word(w) | Value::Str(w) => Some(w.clone()),
+                                _ => None,
+                            })),
+                            _ => {}
+                        }
+                    }
+                    scopes.last_mut().unwrap().retain(|e| !keys.contains(&e.key));
+                    continue;
+                }
+                _ => {}
+            }
+            // "$name;" on its own merges the referenced sub-dict's entries
+            if is_reference(&entry.key) && args.is_empty() {
+                if let Some(EntryValue::Dict(d)) = lookup(scopes, &entry.key) {
+                    for e in d.entries {
+                        merge_entry(scopes.last_mut().unwrap(), e);
+                    }
+                    continue;
+                }
+            }
+            let value = match &entry.value {
+                EntryValue::Dict(d) => EntryValue::Dict(self.expand_dict(d, dir, scopes)?),
+                // "key $dict;" makes key a copy of that sub-dict. #calc and
+                // #eval arithmetic is evaluated; other code passes through.
+                EntryValue::Stream(values) => {
+                    let mut values = self.expand_values(values, dir, scopes)?;
+                    match values.as_mut_slice() {
+                        [Value::Dict(d)] => EntryValue::Dict(std::mem::take(d)),
+                        _ => EntryValue::Stream(values),
+                    }
+                }
+            };
+            merge_entry(scopes.last_mut().unwrap(), Entry { value, ..entry.clone() });
+        }
+        Ok(())
+    }
+}
+
+// Case directory of a dictionary file: the parent of system/, constant/ or
+// a time directory
+fn case_root_of(path: &Path) -> PathBuf {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let in_case_subdir = dir.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+        matches!(n, "system" | "constant") || n.parse::<f64>().is_ok()
+    });
+    match dir.parent() {
+        Some(root) if in_case_subdir => root.to_path_buf(),
+        _ => dir.to_path_buf(),
+    }
+}
+
+// Apply #include/#includeEtc/#includeIfPresent, #remove and $variable
+// substitution to a parsed dictionary read from `path`, and evaluate simple
+// #calc/#eval arithmetic. Unresolved variables and other code entries
+// (#codeStream, C++ in #calc) are kept as written.
+pub fn expand_macros(dict: &Dict, path: &Path) -> Result<Dict, String> {
+    let mut expander = Expander { case_root: case_root_of(path), depth: 0 };
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut scopes = vec![Vec::new()];
+    expander.expand_entries(&dict.entries, dir, &mut scopes)?;
+    Ok(Dict { entries: scopes.pop().unwrap_or_default(), body_span: dict.body_span.clone() })
+}
+
+// Read, parse and macro-expand a dictionary file; None if it is missing
+pub fn load_dict(path: &str) -> PyResult<Option<Dict>> {
+    let Some(data) = open_field_file(path)? else {
+        return Ok(None);
+    };
+    let text = String::from_utf8_lossy(&data);
+    parse_text(&text)
+        .and_then(|d| expand_macros(&d, Path::new(path)))
+        .map(Some)
+        .map_err(|e| dict_error(path, &text, &e))
+}
+
+/// A dimensioned value such as `nu [0 2 -1 0 0 0 0] 1e-05;`
+#[pyclass(frozen, get_all)]
+pub struct Dimensioned {
+    pub name: Option<String>,
+    pub dimensions: (f64, f64, f64, f64, f64, f64, f64),
+    pub value: Py<PyAny>,
+}
+
+#[pymethods]
+impl Dimensioned {
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        let value = self.value.bind(py).repr()?;
+        let name = self.name.as_ref().map_or("None".to_string(), |n| format!("{:?}", n));
+        Ok(format!("Dimensioned(name={}, dimensions={:?}, value={})", name, self.dimensions, value))
+    }
+}
+
+fn word_to_py<'py>(py: Python<'py>, w: &str) -> PyResult<Bound<'py, PyAny>> {
+    if number_start(w.as_bytes()) || w.bytes().next().is_some_and(|b| b.is_ascii_digit()) {
+        if let Ok(i) = w.parse::<i64>() {
+            return Ok(i.into_pyobject(py)?.into_any());
+        }
+        if let Ok(f) = w.parse::<f64>() {
+            return Ok(f.into_pyobject(py)?.into_any());
+        }
+    }
+    Ok(w.into_pyobject(py)?.into_any())
+}
+
+pub fn value_to_py<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match value {
+        Value::Word(w) => word_to_py(py, w)?,
+        Value::Str(s) | Value::Verbatim(s) => s.into_pyobject(py)?.into_any(),
+        Value::List(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(value_to_py(py, item)?)?;
+            }
+            list.into_any()
+        }
+        Value::Dict(d) => dict_to_py(py, d)?.into_any(),
+        Value::Dims(d) => PyTuple::new(py, d)?.into_any(),
+    })
+}
+
+// An entry's values: nothing is None, one value is itself, a dimensioned
+// value becomes Dimensioned and anything else a list
+pub fn stream_to_py<'py>(py: Python<'py>, values: &[Value]) -> PyResult<Bound<'py, PyAny>> {
+    let dimensioned = match values {
+        [Value::Dims(d), v] => Some((None, d, v)),
+        [Value::Word(n), Value::Dims(d), v] => Some((Some(n.clone()), d, v)),
+        _ => None,
+    };
+    if let Some((name, d, v)) = dimensioned {
+        let value = value_to_py(py, v)?.unbind();
+        let dimensions = (d[0], d[1], d[2], d[3], d[4], d[5], d[6]);
+        return Ok(Bound::new(py, Dimensioned { name, dimensions, value })?.into_any());
+    }
+    match values {
+        [] => Ok(py.None().into_bound(py)),
+        [v] => value_to_py(py, v),
+        _ => value_to_py(py, &Value::List(values.to_vec())),
+    }
+}
+
+pub fn dict_to_py<'py>(py: Python<'py>, dict: &Dict) -> PyResult<Bound<'py, PyDict>> {
+    let out = PyDict::new(py);
+    for entry in &dict.entries {
+        // Directives aren't data
+        if entry.key.starts_with('#') {
+            continue;
+        }
+        let value = match &entry.value {
+            EntryValue::Dict(d) => dict_to_py(py, d)?.into_any(),
+            EntryValue::Stream(values) => stream_to_py(py, values)?,
+        };
+        out.set_item(&entry.key, value)?;
+    }
+    Ok(out)
+}
+
+/// Parse an OpenFOAM dictionary (controlDict, fvSchemes, fvSolution,
+/// transportProperties, ...) into nested dicts. Sub-dicts become dicts,
+/// lists become lists, numbers become int or float and other words str.
+/// An entry with several values (`div(phi,U) Gauss linear;`) becomes a list,
+/// a dimension set a 7-tuple and `nu [0 2 -1 0 0 0 0] 1e-05;` a Dimensioned.
+/// #include files are read relative to the dictionary and $variables
+/// substituted; #calc arithmetic is evaluated where it can be, other code
+/// entries are returned as written.
+/// Returns None if the file is missing; malformed input raises ValueError
+/// with the offending line.
+#[pyfunction]
+#[pyo3(signature = (path, options = None))]
+pub fn parse_dict<'py>(py: Python<'py>, path: String, options: Option<Options>) -> PyResult<Option<Bound<'py, PyDict>>> {
+    match options::detach(py, &options, || load_dict(&path))? {
+        Some(dict) => dict_to_py(py, &dict).map(Some),
+        None => Ok(None),
+    }
+}
+
+// Text that lexes back as a single word and can be written unquoted
+fn is_plain_word(s: &str) -> bool {
+    let mut depth = 0i32;
+    for b in s.bytes() {
+        match b {
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            b'{' | b'}' | b'[' | b']' | b';' | b'"' => return false,
+            b if b.is_ascii_whitespace() => return false,
+            _ => {}
+        }
+        if depth < 0 {
+            return false;
+        }
+    }
+    depth == 0 && !s.is_empty() && !s.starts_with('(') && !s.starts_with("//") && !s.starts_with("/*")
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\\\""))
+}
+
+pub fn format_key(key: &str) -> String {
+    if is_plain_word(key) {
+        key.to_string()
+    } else {
+        quote(key)
+    }
+}
+
+pub fn format_scalar(f: f64) -> String {
+    if f.is_finite() && f.fract() == 0.0 && f.abs() < 1e15 {
+        format!("{}", f as i64)
+    } else if f != 0.0 && f.is_finite() && (f.abs() < 1e-4 || f.abs() >= 1e15) {
+        format!("{:e}", f)
+    } else {
+        format!("{}", f)
+    }
+}
+
+fn format_dims(d: &[f64]) -> String {
+    let parts: Vec<String> = d.iter().map(|&e| format_scalar(e)).collect();
+    format!("[{}]", parts.join(" "))
+}
+
+// Python value as OpenFOAM text. Tuples of seven numbers are dimension sets,
+// matching what parse_dict returns for them.
+pub fn format_value(value: &Bound<PyAny>, indent: &str) -> PyResult<String> {
+    if value.is_none() {
+        return Ok(String::new());
+    }
+    if let Ok(b) = value.cast::<PyBool>() {
+        return Ok(if b.is_true() { "true" } else { "false" }.to_string());
+    }
+    if value.is_instance_of::<PyInt>() {
+        return Ok(value.str()?.to_string());
+    }
+    if let Ok(f) = value.cast::<PyFloat>() {
+        return Ok(format_scalar(f.value()));
+    }
+    if let Ok(s) = value.cast::<PyString>() {
+        let s = s.to_str()?;
+        // Multi-word values such as "Gauss linear" go in as written
+        let plain = !s.is_empty() && s.split_whitespace().all(is_plain_word);
+        return Ok(if plain { s.split_whitespace().collect::<Vec<_>>().join(" ") } else { quote(s) });
+    }
+    if let Ok(d) = value.cast::<Dimensioned>() {
+        let d = d.get();
+        let (a, b, c, e, f, g, h) = d.dimensions;
+        let dims = format_dims(&[a, b, c, e, f, g, h]);
+        let v = format_value(d.value.bind(value.py()), indent)?;
+        return Ok(match &d.name {
+            Some(name) => format!("{} {} {}", name, dims, v),
+            None => format!("{} {}", dims, v),
+        });
+    }
+    if let Ok(dict) = value.cast::<PyDict>() {
+        return format_block(dict, indent);
+    }
+    if let Ok(t) = value.cast::<PyTuple>() {
+        if t.len() == 7 && t.iter().all(|v| v.extract::<f64>().is_ok()) {
+            let d: Vec<f64> = t.iter().map(|v| v.extract()).collect::<PyResult<_>>()?;
+            return Ok(format_dims(&d));
+        }
+    }
+    if let Ok(items) = value.try_iter() {
+        let parts: Vec<String> = items.map(|v| format_value(&v?, indent)).collect::<PyResult<_>>()?;
+        return Ok(format!("({})", parts.join(" ")));
+    }
+    Err(PyValueError::new_err(format!("cannot write {} to a dictionary", value.get_type().name()?)))
+}
+
+// "key value;" or "key\n{\n...\n}" at `indent`, values aligned at column 16
+// as OpenFOAM writes them
+pub fn format_entry(key: &str, value: &Bound<PyAny>, indent: &str) -> PyResult<String> {
+    let key = format_key(key);
+    if value.is_instance_of::<PyDict>() {
+        return Ok(format!("{}{}\n{}{}", indent, key, indent, format_value(value, indent)?));
+    }
+    let text = format_value(value, indent)?;
+    Ok(if text.is_empty() { format!("{}{};", indent, key) } else { format!("{}{:<15} {};", indent, key, text) })
+}
+
+fn format_block(dict: &Bound<PyDict>, indent: &str) -> PyResult<String> {
+    let inner = format!("{}    ", indent);
+    let mut out = String::from("{\n");
+    for (k, v) in dict.iter() {
+        out.push_str(&format_entry(&k.str()?.to_string(), &v, &inner)?);
+        out.push('\n');
+    }
+    out.push_str(indent);
+    out.push('}');
+    Ok(out)
+}
+
+// Entry nested under the remaining `keys`, e.g. ["p", "tolerance"] gives a
+// "p { tolerance ...; }" block
+fn format_nested(keys: &[String], value: &Bound<PyAny>, indent: &str) -> PyResult<String> {
+    match keys {
+        [key] => format_entry(key, value, indent),
+        [key, rest @ ..] => {
+            let inner = format!("{}    ", indent);
+            Ok(format!(
+                "{}{}\n{}{{\n{}\n{}}}",
+                indent,
+                format_key(key),
+                indent,
+                format_nested(rest, value, &inner)?,
+                indent
+            ))
+        }
+        [] => Ok(String::new()),
+    }
+}
+
+// Leading whitespace of the line holding `offset`
+pub fn indent_at(text: &str, offset: usize) -> &str {
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = &text[line_start..offset];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+// Set the entry at `keys` in dictionary text, changing only that entry's
+// text. Missing entries (and missing parent sub-dicts) are appended after
+// the last entry of their parent.
+// End of a comment that trails an entry on its line, so an entry added after
+// it doesn't take the comment over
+fn past_line_comment(text: &str, pos: usize) -> usize {
+    let rest = &text[pos..];
+    let line = rest[..rest.find('\n').unwrap_or(rest.len())].trim_end_matches('\r');
+    let comment = line.trim_start();
+    if comment.starts_with("//") {
+        pos + line.len()
+    } else if let Some(end) = comment.strip_prefix("/*").and_then(|c| c.find("*/")) {
+        pos + (line.len() - comment.len()) + 2 + end + 2
+    } else {
+        pos
+    }
+}
+
+fn set_entry(text: &str, keys: &[String], value: &Bound<PyAny>) -> Result<String, String> {
+    let root = parse_text(text)?;
+    let mut parent = &root;
+    let mut parent_indent = String::new();
+    let mut is_root = true;
+    for (i, key) in keys.iter().enumerate() {
+        let last = i + 1 == keys.len();
+        let Some(entry) = parent.get(key) else {
+            // Insert after the last entry, or just inside the braces
+            let indent = match parent.entries.last() {
+                Some(e) => indent_at(text, e.span.start).to_string(),
+                None if is_root => String::new(),
+                None => format!("{}    ", parent_indent),
+            };
+            let at = parent.entries.last().map_or(parent.body_span.start, |e| past_line_comment(text, e.span.end));
+            let entry = format_nested(&keys[i..], value, &indent).map_err(|e| e.to_string())?;
+            let sep = if at == 0 { "" } else { "\n" };
+            return Ok(format!("{}{}{}{}", &text[..at], sep, entry, &text[at..]));
+        };
+        let indent = indent_at(text, entry.span.start).to_string();
+        match (&entry.value, last) {
+            (EntryValue::Dict(d), false) => {
+                parent = d;
+                parent_indent = indent;
+                is_root = false;
+            }
+            // A plain value keeps its keyword and alignment
+            (EntryValue::Stream(old), true) if !value.is_instance_of::<PyDict>() => {
+                let mut formatted = format_value(value, &indent).map_err(|e| e.to_string())?;
+                // "div(phi,U) Gauss linear;" stays a bare token sequence
+                if old.len() > 1 && value.is_instance_of::<PyList>() {
+                    formatted = formatted[1..formatted.len() - 1].to_string();
+                } else if matches!(old.as_slice(), [Value::Str(_)]) && value.is_instance_of::<PyString>() {
+                    formatted = quote(&value.extract::<String>().map_err(|e| e.to_string())?);
+                }
+                let span = &entry.value_span;
+                let pad = if span.is_empty() { " " } else { "" };
+                return Ok(format!("{}{}{}{}", &text[..span.start], pad, formatted, &text[span.end..]));
+            }
+            _ => {
+                let entry_text = format_nested(&keys[i..], value, &indent).map_err(|e| e.to_string())?;
+                let entry_text = entry_text.trim_start();
+                return Ok(format!("{}{}{}", &text[..entry.span.start], entry_text, &text[entry.span.end..]));
+            }
+        }
+    }
+    Ok(text.to_string())
+}
+
+// Replace a file via a temporary sibling and rename, so a solver reading it
+// never sees a half-written dictionary
+pub fn write_atomic(path: &Path, data: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let name = path.file_name().map_or("dict".into(), |n| n.to_string_lossy());
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    std::fs::write(&tmp, data)?;
+    if let Ok(meta) = std::fs::metadata(path) {
+        let _ = std::fs::set_permissions(&tmp, meta.permissions());
+    }
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+fn read_text(path: &str) -> PyResult<String> {
+    let data = std::fs::read(path)?;
+    String::from_utf8(data).map_err(|_| PyValueError::new_err(format!("{}: not valid UTF-8", path)))
+}
+
+// "solvers.p.tolerance" or ["solvers", "p", "tolerance"]
+fn key_path(key: &Bound<PyAny>) -> PyResult<Vec<String>> {
+    let keys: Vec<String> = match key.cast::<PyString>() {
+        Ok(s) => s.to_str()?.split('.').map(String::from).collect(),
+        Err(_) => key.extract()?,
+    };
+    if keys.is_empty() || keys.iter().any(String::is_empty) {
+        return Err(PyValueError::new_err("empty dictionary key"));
+    }
+    Ok(keys)
+}
+
+pub fn set_entry_in_file(path: &str, keys: &[String], value: &Bound<PyAny>) -> PyResult<()> {
+    let text = read_text(path)?;
+    let updated = set_entry(&text, keys, value).map_err(|e| dict_error(path, &text, &e))?;
+    write_atomic(Path::new(path), &updated)?;
+    Ok(())
+}
+
+/// Set one entry of an OpenFOAM dictionary file in place, e.g.
+/// set_dict_entry("system/controlDict", "endTime", 0.5) or
+/// set_dict_entry(path, "solvers.p.tolerance", 1e-7). The key is a dotted
+/// path or a list of keys. Only that entry's text changes; comments,
+/// includes and formatting elsewhere are kept. Missing entries and sub-dicts
+/// are added. The file is replaced atomically.
+#[pyfunction]
+pub fn set_dict_entry(path: String, key: &Bound<PyAny>, value: &Bound<PyAny>) -> PyResult<()> {
+    set_entry_in_file(&path, &key_path(key)?, value)
+}
+
+// Leaf (keys, value) pairs of a nested update dict
+fn leaves<'py>(
+    dict: &Bound<'py, PyDict>,
+    prefix: &mut Vec<String>,
+    out: &mut Vec<(Vec<String>, Bound<'py, PyAny>)>,
+) -> PyResult<()> {
+    for (k, v) in dict.iter() {
+        prefix.push(k.str()?.to_string());
+        match v.cast::<PyDict>() {
+            Ok(sub) if !sub.is_empty() => leaves(sub, prefix, out)?,
+            _ => out.push((prefix.clone(), v)),
+        }
+        prefix.pop();
+    }
+    Ok(())
+}
+
+/// Merge `data` into an OpenFOAM dictionary file. Nested dicts update
+/// sub-dicts entry by entry, so entries not mentioned in `data` are kept
+/// along with their comments and formatting; nothing is removed. A missing
+/// file is created from `data` under a FoamFile header naming it as the
+/// object, unless `data` has a FoamFile entry of its own. The file is
+/// replaced atomically.
+#[pyfunction]
+pub fn write_dict(path: String, data: &Bound<PyDict>) -> PyResult<()> {
+    if !Path::new(&path).exists() {
+        let mut text = String::new();
+        if !data.contains("FoamFile")? {
+            let object = Path::new(&path).file_name().map_or("dictionary".into(), |n| n.to_string_lossy());
+            text.push_str(&format!(
+                "FoamFile\n{{\n    version     2.0;\n    format      ascii;\n    class       dictionary;\n    object      {};\n}}\n\n",
+                object
+            ));
+        }
+        for (k, v) in data.iter() {
+            text.push_str(&format_entry(&k.str()?.to_string(), &v, "")?);
+            text.push('\n');
+        }
+        return Ok(write_atomic(Path::new(&path), &text)?);
+    }
+    let mut text = read_text(&path)?;
+    let mut updates = Vec::new();
+    leaves(data, &mut Vec::new(), &mut updates)?;
+    for (keys, value) in updates {
+        text = set_entry(&text, &keys, &value).map_err(|e| dict_error(&path, &text, &e))?;
+    }
+    Ok(write_atomic(Path::new(&path), &text)?)
+}
+
+const STOP_MODES: [&str; 4] = ["endTime", "writeNow", "noWriteNow", "nextWrite"];
+
+// Point controlDict's stopAt at `mode`, returning the value it replaced so a
+// caller can undo the request
+fn set_stop_at(py: Python, case_root: &str, mode: &str) -> PyResult<Option<String>> {
+    let path = Path::new(case_root).join("system").join("controlDict");
+    let path = path.to_string_lossy().into_owned();
+    let text = read_text(&path)?;
+    let dict = parse_text(&text).map_err(|e| dict_error(&path, &text, &e))?;
+    let previous = match dict.get("stopAt").map(|e| &e.value) {
+        Some(EntryValue::Stream(values)) => match values.as_slice() {
+            [Value::Word(w)] => Some(w.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    set_entry_in_file(&path, &["stopAt".to_string()], PyString::new(py, mode).as_any())?;
+    Ok(previous)
+}
+
+/// Ask a running solver to write the current time step and stop, by setting
+/// `stopAt writeNow;` in system/controlDict. OpenFOAM re-reads controlDict
+/// each step when runTimeModifiable is on. Returns the previous stopAt value.
+#[pyfunction]
+pub fn request_write_now(py: Python, case_root: String) -> PyResult<Option<String>> {
+    set_stop_at(py, &case_root, "writeNow")
+}
+
+/// Ask a running solver to stop by rewriting stopAt in system/controlDict:
+/// "writeNow" writes the current time first, "noWriteNow" stops without
+/// writing, "nextWrite" stops at the next scheduled write and "endTime"
+/// cancels an earlier request. Returns the previous stopAt value.
+#[pyfunction]
+#[pyo3(signature = (case_root, mode="nextWrite"))]
+pub fn request_stop(py: Python, case_root: String, mode: &str) -> PyResult<Option<String>> {
+    if !STOP_MODES.contains(&mode) {
+        return Err(PyValueError::new_err(format!(
+            "unknown stop mode '{}', expected one of {}",
+            mode,
+            STOP_MODES.join(", ")
+        )));
+    }
+    set_stop_at(py, &case_root, mode)
+}
@@ -0,0 +1,118 @@
+// Largest-Triangle-Three-Buckets downsampling (Steinarsson, 2013), which
+// thins a long history to a few thousand points that plot the same: the
+// first and last points are kept, and from each of the buckets in between
+// the point making the largest triangle with the point kept before it and
+// the mean of the next bucket. Several series sharing an x axis (the
+// columns of a table) are thinned together so they stay aligned, each
+// bucket keeping the point with the largest sum of triangle areas, every
+// series scaled to its own range so none dominates.
+
+use numpy::{AllowTypeChange, IntoPyArray, PyArray1, PyArrayLike1};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::colormap::finite_range;
+
+// Fewer points than this can't hold the first, last and one bucket
+pub const MIN_POINTS: usize = 3;
+
+pub fn check_points(n_out: usize) -> PyResult<()> {
+    if n_out < MIN_POINTS {
+        return Err(PyValueError::new_err(format!("at least {} points must be kept, not {}", MIN_POINTS, n_out)));
+    }
+    Ok(())
+}
+
+// Indices of the points kept out of `x.len()`, ascending. Values that aren't
+// finite add nothing to a triangle, so a gap never decides which point is
+// kept.
+pub fn lttb_indices(x: &[f64], series: &[&[f64]], n_out: usize) -> Vec<usize> {
+    let n = x.len();
+    if n_out >= n || n_out < MIN_POINTS {
+        return (0..n).collect();
+    }
+    let spans: Vec<f64> = series
+        .iter()
+        .map(|y| {
+            let (lo, hi) = finite_range(y).unwrap_or((0.0, 0.0));
+            if hi > lo { 1.0 / (hi - lo) } else { 0.0 }
+        })
+        .collect();
+    // Bucket edges over the points between the first and the last
+    let width = (n - 2) as f64 / (n_out - 2) as f64;
+    let edge = |b: usize| ((b as f64 * width) as usize + 1).min(n - 1);
+
+    let mut kept = Vec::with_capacity(n_out);
+    kept.push(0);
+    let mut a = 0;
+    let mut next_mean = vec![f64::NAN; series.len()];
+    for b in 0..n_out - 2 {
+        let (start, end) = (edge(b), edge(b + 1));
+        // Mean of the next bucket, or the last point after the final bucket
+        let (next_start, next_end) = if b + 1 < n_out - 2 { (end, edge(b + 2)) } else { (n - 1, n) };
+        let cx = x[next_start..next_end].iter().sum::<f64>() / (next_end - next_start) as f64;
+        for (mean, y) in next_mean.iter_mut().zip(series) {
+            let (sum, count) = y[next_start..next_end].iter().filter(|v| v.is_finite()).fold((0.0, 0usize), |(s, c), v| (s + v, c + 1));
+            *mean = if count > 0 { sum / count as f64 } else { f64::NAN };
+        }
+
+        let mut best = (start, -1.0);
+        for i in start..end {
+            let area: f64 = series
+                .iter()
+                .zip(&spans)
+                .zip(&next_mean)
+                .map(|((y, span), cy)| {
+                    let area = ((x[a] - cx) * (y[i] - y[a]) - (x[a] - x[i]) * (cy - y[a])).abs() * span;
+                    if area.is_finite() { area } else { 0.0 }
+                })
+                .sum();
+            if area > best.1 {
+                best = (i, area);
+            }
+        }
+        a = best.0;
+        kept.push(a);
+    }
+    kept.push(n - 1);
+    kept
+}
+
+// The entries of `values` at `kept`
+pub fn pick<T: Clone>(values: &[T], kept: &[usize]) -> Vec<T> {
+    kept.iter().map(|&i| values[i].clone()).collect()
+}
+
+// Residuals span decades and are plotted on a log axis, so they are thinned
+// by their logarithm
+pub fn log10_series(values: &[f64]) -> Vec<f64> {
+    values.iter().map(|&v| if v > 0.0 { v.log10() } else { f64::NAN }).collect()
+}
+
+type Series<'py> = (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray1<f64>>);
+
+/// Thin the series (`x`, `y`) to `n_out` points that plot the same, with
+/// Largest-Triangle-Three-Buckets: the first and last points are kept, and
+/// one point from each of `n_out` - 2 equal buckets in between, the one
+/// standing out most from its neighbours. `x` should be ascending, e.g.
+/// time. Series with no more than `n_out` points are returned whole.
+/// Returns (x, y) as arrays.
+#[pyfunction]
+pub fn downsample_lttb<'py>(
+    py: Python<'py>,
+    x: PyArrayLike1<'py, f64, AllowTypeChange>,
+    y: PyArrayLike1<'py, f64, AllowTypeChange>,
+    n_out: usize,
+) -> PyResult<Series<'py>> {
+    check_points(n_out)?;
+    let x: Vec<f64> = x.as_array().iter().copied().collect();
+    let y: Vec<f64> = y.as_array().iter().copied().collect();
+    if x.len() != y.len() {
+        return Err(PyValueError::new_err(format!("x has {} points but y has {}", x.len(), y.len())));
+    }
+    let (x, y) = py.detach(|| {
+        let kept = lttb_indices(&x, &[&y], n_out);
+        (pick(&x, &kept), pick(&y, &kept))
+    });
+    Ok((x.into_pyarray(py), y.into_pyarray(py)))
+}
@@ -0,0 +1,117 @@
+// Exception classes raised by the field readers, so callers can tell a
+// missing file from a broken one instead of getting None or zeros for both.
+// They subclass the built-in errors the readers raised before, so existing
+// `except FileNotFoundError` / `except ValueError` handlers keep working.
+
+use pyo3::exceptions::{PyException, PyFileNotFoundError, PyTypeError, PyValueError};
+use pyo3::create_exception;
+use pyo3::prelude::*;
+
+use crate::field::type_name;
+use crate::list::ListFormat;
+use crate::source::resolve_field_path;
+
+// OSError subclasses have their own object layout, which a #[pyclass]
+// can't extend, so this one is a plain exception type
+create_exception!(accelerator, FieldNotFound, PyFileNotFoundError, "Raised when a field file does not exist.");
+
+create_exception!(accelerator, Cancelled, PyException, "Raised when a read is called off through its Options' CancelToken.");
+
+/// Raised when a field file exists but its internalField can't be read:
+/// empty files, a missing or malformed internalField.
+#[pyclass(extends = PyValueError, subclass, module = "accelerator")]
+pub struct FieldParseError {
+    #[pyo3(get)]
+    message: String,
+}
+
+#[pymethods]
+impl FieldParseError {
+    #[new]
+    fn new(message: String) -> Self {
+        FieldParseError { message }
+    }
+
+    fn __str__(&self) -> String {
+        self.message.clone()
+    }
+}
+
+/// Raised for field files in a format the reader can't handle, such as
+/// binary files given to the ASCII readers.
+#[pyclass(extends = FieldParseError, module = "accelerator")]
+pub struct UnsupportedFormat;
+
+#[pymethods]
+impl UnsupportedFormat {
+    #[new]
+    fn new(message: String) -> PyClassInitializer<Self> {
+        PyClassInitializer::from(FieldParseError { message }).add_subclass(UnsupportedFormat)
+    }
+}
+
+/// Raised when a nonuniform list holds a different number of values than
+/// the count written before its '(', typically a file truncated or still
+/// being written. `expected` and `found` are in values (cells), not
+/// components.
+#[pyclass(extends = FieldParseError, module = "accelerator")]
+pub struct FieldCountError {
+    #[pyo3(get)]
+    expected: usize,
+    #[pyo3(get)]
+    found: usize,
+}
+
+#[pymethods]
+impl FieldCountError {
+    #[new]
+    fn new(message: String, expected: usize, found: usize) -> PyClassInitializer<Self> {
+        PyClassInitializer::from(FieldParseError { message }).add_subclass(FieldCountError { expected, found })
+    }
+}
+
+pub fn parse_error(message: String) -> PyErr {
+    PyErr::new::<FieldParseError, _>(message)
+}
+
+pub fn cancelled() -> PyErr {
+    Cancelled::new_err("the read was cancelled")
+}
+
+pub fn count_error(message: String, expected: usize, found: usize) -> PyErr {
+    PyErr::new::<FieldCountError, _>((message, expected, found))
+}
+
+// Why open_field_file() found nothing at `path`: no file, or an empty one
+pub fn missing_field(path: &str) -> PyErr {
+    if resolve_field_path(std::path::Path::new(path)).is_some() {
+        parse_error(format!("{}: empty field file", path))
+    } else {
+        FieldNotFound::new_err(format!("{}: no such field file", path))
+    }
+}
+
+// The legacy readers scan for ASCII numbers and would read garbage out of
+// a binary list
+pub fn check_ascii(path: &str, data: &[u8]) -> PyResult<()> {
+    if ListFormat::of(data).binary {
+        return Err(PyErr::new::<UnsupportedFormat, _>(format!("{}: binary field files are not supported by this reader", path)));
+    }
+    Ok(())
+}
+
+// A file that exists but gave no value: an error, or `fallback` when the
+// caller asked for the old lenient behaviour
+pub fn unreadable_field<T>(path: &str, lenient: bool, fallback: T) -> PyResult<T> {
+    if lenient {
+        Ok(fallback)
+    } else {
+        Err(parse_error(format!("{}: no readable internalField", path)))
+    }
+}
+
+// A scalar reader pointed at a vector or tensor field
+pub fn not_scalar(path: &str, width: usize) -> PyErr {
+    let kind = type_name(width).unwrap_or("non-scalar");
+    PyTypeError::new_err(format!("{}: {} field given to a scalar reader (pass allow_magnitude=True to read its magnitude)", path, kind))
+}
@@ -0,0 +1,1008 @@
+// Exporting a time step's volume fields for ParaView and friends without
+// foamToVTK. The polyMesh cells are mapped to VTK cell types once, and the
+// cell fields are written against that mapping.
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use numpy::{AllowTypeChange, PyArrayLike1};
+use pyo3::exceptions::{PyFileNotFoundError, PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyString};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::colormap::{finite_range, Colormap};
+use crate::dict::write_atomic;
+use crate::field::{load_field, load_mesh, load_patches, FieldFile};
+use crate::geometry::{add, cell_faces, scale};
+use crate::mesh::{Patch, PolyMesh};
+use crate::options::{self, Options, Progress};
+use crate::surface::{cell_values, surface_array, surface_from_py};
+use crate::time::{field_classes, time_dir, time_dirs};
+
+pub const VTK_TETRA: u8 = 10;
+pub const VTK_HEXAHEDRON: u8 = 12;
+pub const VTK_WEDGE: u8 = 13;
+pub const VTK_PYRAMID: u8 = 14;
+
+// The mesh's cells as VTK cells. Hexahedra, prisms, tetrahedra and pyramids
+// become the VTK cell of that shape; any other polyhedron is split into a
+// tetrahedron or pyramid per face (faces of more than four points fanned into
+// triangles) around an extra point at its centre, as foamToVTK does.
+pub struct VtkCells {
+    // The mesh points followed by the centres of split polyhedra
+    pub points: Vec<[f64; 3]>,
+    pub types: Vec<u8>,
+    // Cell i uses connectivity[offsets[i]..offsets[i + 1]]
+    pub offsets: Vec<usize>,
+    pub connectivity: Vec<usize>,
+    // The mesh cell each VTK cell comes from
+    pub cell_map: Vec<usize>,
+}
+
+// The point of `face` joined by an edge to `v` that is not in `base`
+fn partner(faces: &[Vec<usize>], base: &[usize], v: usize) -> Option<usize> {
+    let mut found = None;
+    for face in faces {
+        let n = face.len();
+        for i in 0..n {
+            let (a, b) = (face[i], face[(i + 1) % n]);
+            let other = if a == v { b } else if b == v { a } else { continue };
+            if base.contains(&other) {
+                continue;
+            }
+            match found {
+                Some(u) if u != other => return None,
+                _ => found = Some(other),
+            }
+        }
+    }
+    found
+}
+
+// VTK type and point order of a cell given its faces, each ordered so that its
+// normal points out of the cell; None for shapes without a VTK equivalent.
+// VTK wants the base of a tetrahedron, pyramid or hexahedron to face the rest
+// of the cell and the base of a wedge to face away from it.
+fn cell_shape(faces: &[Vec<usize>]) -> Option<(u8, Vec<usize>)> {
+    let tris = faces.iter().filter(|f| f.len() == 3).count();
+    let quads = faces.iter().filter(|f| f.len() == 4).count();
+    let inward = |f: &Vec<usize>| f.iter().rev().copied().collect::<Vec<usize>>();
+    let (kind, base, n_points) = match (faces.len(), tris, quads) {
+        (4, 4, 0) => (VTK_TETRA, inward(&faces[0]), 4),
+        (5, 4, 1) => (VTK_PYRAMID, inward(faces.iter().find(|f| f.len() == 4)?), 5),
+        (5, 2, 3) => (VTK_WEDGE, faces.iter().find(|f| f.len() == 3)?.clone(), 6),
+        (6, 0, 6) => (VTK_HEXAHEDRON, inward(&faces[0]), 8),
+        _ => return None,
+    };
+    let unique: HashSet<usize> = faces.iter().flatten().copied().collect();
+    if unique.len() != n_points || base.iter().collect::<HashSet<_>>().len() != base.len() {
+        return None;
+    }
+    let mut order = base.clone();
+    match kind {
+        VTK_TETRA | VTK_PYRAMID => order.extend(unique.iter().filter(|p| !base.contains(p))),
+        _ => {
+            for &v in &base {
+                order.push(partner(faces, &base, v)?);
+            }
+        }
+    }
+    (order.iter().collect::<HashSet<_>>().len() == n_points).then_some((kind, order))
+}
+
+// The faces of cell `c`, each with its points ordered so that its normal
+// points out of the cell
+fn outward_faces(mesh: &PolyMesh, offsets: &[usize], faces: &[usize], c: usize) -> Vec<Vec<usize>> {
+    faces[offsets[c]..offsets[c + 1]]
+        .iter()
+        .map(|&f| {
+            let mut points = mesh.face(f).to_vec();
+            if mesh.owner[f] != c {
+                points.reverse();
+            }
+            points
+        })
+        .collect()
+}
+
+impl VtkCells {
+    pub fn build(mesh: &PolyMesh, cells: &[usize]) -> VtkCells {
+        let (offsets, faces) = cell_faces(mesh);
+        // Per cell: its VTK cells as (type, points), with usize::MAX standing
+        // for the cell's own centre point, and that centre if one is needed
+        type Split = (Vec<(u8, Vec<usize>)>, Option<[f64; 3]>);
+        let split: Vec<Split> = cells
+            .par_iter()
+            .map(|&c| {
+                let outward = outward_faces(mesh, &offsets, &faces, c);
+                if let Some(shape) = cell_shape(&outward) {
+                    return (vec![shape], None);
+                }
+                let unique: HashSet<usize> = outward.iter().flatten().copied().collect();
+                let centre = scale(unique.iter().fold([0.0; 3], |sum, &p| add(sum, mesh.points[p])), 1.0 / unique.len() as f64);
+                let centre_id = usize::MAX;
+                let mut parts = Vec::new();
+                for face in &outward {
+                    let r: Vec<usize> = face.iter().rev().copied().collect();
+                    match r.len() {
+                        4 => parts.push((VTK_PYRAMID, vec![r[0], r[1], r[2], r[3], centre_id])),
+                        n => {
+                            for i in 1..n - 1 {
+                                parts.push((VTK_TETRA, vec![r[0], r[i], r[i + 1], centre_id]));
+                            }
+                        }
+                    }
+                }
+                (parts, Some(centre))
+            })
+            .collect();
+
+        let mut out = VtkCells { points: mesh.points.clone(), types: Vec::new(), offsets: vec![0], connectivity: Vec::new(), cell_map: Vec::new() };
+        for (&c, (parts, centre)) in cells.iter().zip(split) {
+            let centre_id = out.points.len();
+            if let Some(centre) = centre {
+                out.points.push(centre);
+            }
+            for (kind, points) in parts {
+                out.types.push(kind);
+                out.connectivity.extend(points.into_iter().map(|p| if p == usize::MAX { centre_id } else { p }));
+                out.offsets.push(out.connectivity.len());
+                out.cell_map.push(c);
+            }
+        }
+        out
+    }
+
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+}
+
+// A cell field to export: name, components and one tuple per mesh cell, with
+// the file it came from for its patch values
+pub struct CellArray {
+    pub name: String,
+    pub width: usize,
+    pub values: Vec<f64>,
+    pub file: FieldFile,
+}
+
+// `names`, or else every volume field of a time directory
+pub fn volume_fields(dir: &Path, names: Option<Vec<String>>) -> std::io::Result<Vec<String>> {
+    match names {
+        Some(names) => Ok(names),
+        None => Ok(field_classes(dir)?.into_iter().filter(|(_, class)| class.starts_with("vol")).map(|(name, _)| name).collect()),
+    }
+}
+
+// The volume fields `names` of a time directory, checked against the mesh.
+// Reports a progress step per field.
+pub fn load_cell_arrays(dir: &Path, mesh: &PolyMesh, names: Vec<String>, progress: &Progress) -> PyResult<Vec<CellArray>> {
+    let mut arrays = Vec::with_capacity(names.len());
+    for name in names {
+        options::check_cancelled()?;
+        let path = dir.join(&name);
+        let file = load_field(&path)?.ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no such field file", path.display())))?;
+        let values = cell_values(mesh, &file).map_err(|e| PyValueError::new_err(format!("{}: {}", path.display(), e)))?;
+        arrays.push(CellArray { name, width: file.width, values, file });
+        progress.step();
+    }
+    Ok(arrays)
+}
+
+// Legacy .vtk text of an unstructured grid, binary (big-endian) or ASCII
+fn legacy_vtk(title: &str, cells: &VtkCells, arrays: &[CellArray], binary: bool) -> Vec<u8> {
+    let mut out = Vec::new();
+    let floats = |out: &mut Vec<u8>, values: &mut dyn Iterator<Item = f64>, per_line: usize| {
+        if binary {
+            for v in values {
+                out.extend_from_slice(&(v as f32).to_be_bytes());
+            }
+        } else {
+            for (i, v) in values.enumerate() {
+                let _ = write!(out, "{}{}", v as f32, if (i + 1) % per_line == 0 { '\n' } else { ' ' });
+            }
+        }
+        out.push(b'\n');
+    };
+    let ints = |out: &mut Vec<u8>, values: &mut dyn Iterator<Item = usize>| {
+        if binary {
+            for v in values {
+                out.extend_from_slice(&(v as i32).to_be_bytes());
+            }
+        } else {
+            let text: Vec<String> = values.map(|v| v.to_string()).collect();
+            out.extend_from_slice(text.join(" ").as_bytes());
+        }
+        out.push(b'\n');
+    };
+
+    let _ = write!(out, "# vtk DataFile Version 3.0\n{}\n{}\nDATASET UNSTRUCTURED_GRID\n", title, if binary { "BINARY" } else { "ASCII" });
+    let _ = writeln!(out, "POINTS {} float", cells.points.len());
+    floats(&mut out, &mut cells.points.iter().flatten().copied(), 3);
+
+    let _ = writeln!(out, "CELLS {} {}", cells.len(), cells.len() + cells.connectivity.len());
+    let mut legacy = Vec::with_capacity(cells.len() + cells.connectivity.len());
+    for w in cells.offsets.windows(2) {
+        legacy.push(w[1] - w[0]);
+        legacy.extend_from_slice(&cells.connectivity[w[0]..w[1]]);
+    }
+    ints(&mut out, &mut legacy.into_iter());
+    let _ = writeln!(out, "CELL_TYPES {}", cells.len());
+    ints(&mut out, &mut cells.types.iter().map(|&t| t as usize));
+
+    let _ = writeln!(out, "CELL_DATA {}\nFIELD attributes {}", cells.len(), arrays.len() + 1);
+    let _ = writeln!(out, "cellID 1 {} int", cells.len());
+    ints(&mut out, &mut cells.cell_map.iter().copied());
+    for array in arrays {
+        let _ = writeln!(out, "{} {} {} float", array.name, array.width, cells.len());
+        let w = array.width;
+        floats(&mut out, &mut cells.cell_map.iter().flat_map(|&c| array.values[c * w..(c + 1) * w].iter().copied()), w);
+    }
+    out
+}
+
+/// Write time step `time` of a case to `out_path` as a legacy .vtk
+/// unstructured grid that ParaView opens directly, without foamToVTK. The
+/// volume fields `fields` (every volume field of the time directory if
+/// None) become cell data, along with a cellID array of the OpenFOAM cell
+/// numbers. Polyhedra other than hexahedra, prisms, tetrahedra and pyramids
+/// are split around their centre as foamToVTK does. Binary unless `binary`
+/// is false. Progress counts the mesh and each field. Returns a dict with
+/// the `path` written and the `points` and `cells` counts.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, fields, out_path, region = None, binary = true, options = None))]
+#[allow(clippy::too_many_arguments)]
+pub fn export_vtk<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    fields: Option<Vec<String>>,
+    out_path: String,
+    region: Option<String>,
+    binary: bool,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let (points, cells) = options::detach(py, &options, || -> PyResult<(usize, usize)> {
+        let root = Path::new(&case_root);
+        let dir = time_dir(root, &time, region.as_deref());
+        let fields = volume_fields(&dir, fields)?;
+        let progress = Progress::start(1 + fields.len());
+        let mesh = load_mesh(root, region.as_deref())?;
+        let all: Vec<usize> = (0..mesh.n_cells).collect();
+        let cells = VtkCells::build(&mesh, &all);
+        progress.step();
+        let arrays = load_cell_arrays(&dir, &mesh, fields, &progress)?;
+        let title = format!("{} time {}", root.file_name().map_or(case_root.clone(), |n| n.to_string_lossy().into_owned()), time);
+        write_atomic(Path::new(&out_path), legacy_vtk(&title, &cells, &arrays, binary))?;
+        Ok((cells.points.len(), cells.len()))
+    })?;
+    let out = PyDict::new(py);
+    out.set_item("path", &out_path)?;
+    out.set_item("points", points)?;
+    out.set_item("cells", cells)?;
+    Ok(out)
+}
+
+// ------------------------------------------------------------- XML .vtu/.vtm
+
+// Values per compressed block, as VTK writes them
+const XML_BLOCK: usize = 1 << 15;
+
+fn base64_encode(bytes: &[u8], out: &mut Vec<u8>) {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            out.push(if i <= chunk.len() { ALPHABET[(n >> (18 - 6 * i) & 63) as usize] } else { b'=' });
+        }
+    }
+}
+
+// DataArrays of one XML file, either appended after the XML as raw bytes or
+// inline as base64, each optionally split into zlib-compressed blocks. Block
+// headers are UInt64.
+struct XmlArrays {
+    appended: Option<Vec<u8>>,
+    compress: bool,
+}
+
+impl XmlArrays {
+    // Header and payload of one array's bytes
+    fn blocks(&self, bytes: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut header: Vec<u64> = Vec::new();
+        let payload = if self.compress {
+            let chunks: Vec<&[u8]> = bytes.chunks(XML_BLOCK).collect();
+            header.extend([chunks.len() as u64, XML_BLOCK as u64, chunks.last().map_or(0, |c| c.len()) as u64]);
+            let mut payload = Vec::new();
+            for chunk in chunks {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                let _ = encoder.write_all(chunk);
+                let compressed = encoder.finish().unwrap_or_default();
+                header.push(compressed.len() as u64);
+                payload.extend(compressed);
+            }
+            payload
+        } else {
+            header.push(bytes.len() as u64);
+            bytes.to_vec()
+        };
+        (header.iter().flat_map(|h| h.to_le_bytes()).collect(), payload)
+    }
+
+    // A <DataArray> element for `bytes` of `type_name`
+    fn array(&mut self, name: &str, type_name: &str, components: usize, bytes: &[u8]) -> Vec<u8> {
+        let (header, payload) = self.blocks(bytes);
+        let mut out = format!(r#"<DataArray type="{}" Name="{}" NumberOfComponents="{}" "#, type_name, name, components).into_bytes();
+        match &mut self.appended {
+            Some(data) => {
+                let _ = writeln!(out, r#"format="appended" offset="{}"/>"#, data.len());
+                data.extend(header);
+                data.extend(payload);
+            }
+            None => {
+                out.extend_from_slice(br#"format="binary">"#);
+                // VTK encodes the header of compressed data on its own
+                if self.compress {
+                    base64_encode(&header, &mut out);
+                    base64_encode(&payload, &mut out);
+                } else {
+                    base64_encode(&[header, payload].concat(), &mut out);
+                }
+                out.extend_from_slice(b"</DataArray>\n");
+            }
+        }
+        out
+    }
+
+    fn floats(&mut self, name: &str, components: usize, values: impl Iterator<Item = f64>) -> Vec<u8> {
+        let bytes: Vec<u8> = values.flat_map(|v| (v as f32).to_le_bytes()).collect();
+        self.array(name, "Float32", components, &bytes)
+    }
+
+    fn ints(&mut self, name: &str, values: impl Iterator<Item = usize>) -> Vec<u8> {
+        let bytes: Vec<u8> = values.flat_map(|v| (v as i64).to_le_bytes()).collect();
+        self.array(name, "Int64", 1, &bytes)
+    }
+
+    // The whole file around `body`, the <Piece> of a `kind` dataset
+    fn file(self, kind: &str, body: Vec<u8>) -> Vec<u8> {
+        let compressor = if self.compress { r#" compressor="vtkZLibDataCompressor""# } else { "" };
+        let mut out = format!(
+            "<?xml version=\"1.0\"?>\n<VTKFile type=\"{}\" version=\"1.0\" byte_order=\"LittleEndian\" header_type=\"UInt64\"{}>\n<{}>\n",
+            kind, compressor, kind
+        )
+        .into_bytes();
+        out.extend(body);
+        let _ = writeln!(out, "</{}>", kind);
+        if let Some(data) = self.appended {
+            out.extend_from_slice(b"<AppendedData encoding=\"raw\">\n_");
+            out.extend(data);
+            out.extend_from_slice(b"\n</AppendedData>\n");
+        }
+        out.extend_from_slice(b"</VTKFile>\n");
+        out
+    }
+}
+
+fn vtu(cells: &VtkCells, arrays: &[CellArray], mut xml: XmlArrays) -> Vec<u8> {
+    let mut body = format!("<Piece NumberOfPoints=\"{}\" NumberOfCells=\"{}\">\n<Points>\n", cells.points.len(), cells.len()).into_bytes();
+    body.extend(xml.floats("Points", 3, cells.points.iter().flatten().copied()));
+    body.extend_from_slice(b"</Points>\n<Cells>\n");
+    body.extend(xml.ints("connectivity", cells.connectivity.iter().copied()));
+    body.extend(xml.ints("offsets", cells.offsets[1..].iter().copied()));
+    body.extend(xml.array("types", "UInt8", 1, &cells.types));
+    body.extend_from_slice(b"</Cells>\n<CellData>\n");
+    body.extend(xml.ints("cellID", cells.cell_map.iter().copied()));
+    for array in arrays {
+        let w = array.width;
+        body.extend(xml.floats(&array.name, w, cells.cell_map.iter().flat_map(|&c| array.values[c * w..(c + 1) * w].iter().copied())));
+    }
+    body.extend_from_slice(b"</CellData>\n</Piece>\n");
+    xml.file("UnstructuredGrid", body)
+}
+
+// A patch as a .vtp polygon surface with its face values of each field
+fn patch_vtp(mesh: &PolyMesh, patch: &Patch, arrays: &[CellArray], mut xml: XmlArrays) -> Result<Vec<u8>, String> {
+    let faces: Vec<&[usize]> = (patch.start_face..patch.start_face + patch.n_faces).map(|f| mesh.face(f)).collect();
+    // The patch's own points, numbered in order of first use
+    let mut local = std::collections::HashMap::new();
+    let mut points = Vec::new();
+    let connectivity: Vec<usize> = faces
+        .iter()
+        .flat_map(|f| f.iter())
+        .map(|&p| {
+            *local.entry(p).or_insert_with(|| {
+                points.push(mesh.points[p]);
+                points.len() - 1
+            })
+        })
+        .collect();
+    let offsets = faces.iter().scan(0, |end, f| {
+        *end += f.len();
+        Some(*end)
+    });
+
+    let mut body = format!(
+        "<Piece NumberOfPoints=\"{}\" NumberOfVerts=\"0\" NumberOfLines=\"0\" NumberOfStrips=\"0\" NumberOfPolys=\"{}\">\n<Points>\n",
+        points.len(),
+        faces.len()
+    )
+    .into_bytes();
+    body.extend(xml.floats("Points", 3, points.iter().flatten().copied()));
+    body.extend_from_slice(b"</Points>\n<Polys>\n");
+    body.extend(xml.ints("connectivity", connectivity.into_iter()));
+    body.extend(xml.ints("offsets", offsets));
+    body.extend_from_slice(b"</Polys>\n<CellData>\n");
+    for array in arrays {
+        let values = array.file.patch_face_values(mesh, patch)?;
+        body.extend(xml.floats(&array.name, array.width, values.into_iter()));
+    }
+    body.extend_from_slice(b"</CellData>\n</Piece>\n");
+    Ok(xml.file("PolyData", body))
+}
+
+// Patches worth a block of their own: named ones, or all with faces except
+// empty (2-D) and processor boundaries
+fn export_patches(all: Vec<Patch>, names: Option<Vec<String>>) -> PyResult<Vec<Patch>> {
+    match names {
+        None => Ok(all.into_iter().filter(|p| p.n_faces > 0 && !matches!(p.patch_type.as_str(), "empty" | "processor" | "processorCyclic")).collect()),
+        Some(names) => {
+            let mut chosen = Vec::new();
+            for name in names {
+                let patch = all.iter().find(|p| p.name == name).ok_or_else(|| PyKeyError::new_err(format!("no patch '{}' in the mesh", name)))?;
+                chosen.push(patch.clone());
+            }
+            Ok(chosen)
+        }
+    }
+}
+
+/// Write time step `time` of a case as VTK XML for ParaView and trame/vtk.js
+/// viewers. An `out_path` ending in .vtu gets the volume mesh with the
+/// fields as cell data, as export_vtk() does. One ending in .vtm gets a
+/// multiblock dataset: the volume as <stem>/internal.vtu and each patch
+/// (those named in `patches`, or all but empty and processor ones) as
+/// <stem>/boundary/<patch>.vtp with the fields' patch values. Data is
+/// stored as raw bytes appended after the XML (`encoding="appended"`) or
+/// inline as base64 (`"base64"`), zlib-compressed unless `compress` is
+/// false. Progress counts the mesh, each field and each patch. Returns a
+/// dict with the `path`, the `points` and `cells` of the volume mesh and
+/// every file written as `files`.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, fields, out_path, patches = None, encoding = "appended", compress = true, region = None, options = None))]
+#[allow(clippy::too_many_arguments)]
+pub fn export_vtu<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    fields: Option<Vec<String>>,
+    out_path: String,
+    patches: Option<Vec<String>>,
+    encoding: &str,
+    compress: bool,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let appended = match encoding {
+        "appended" => true,
+        "base64" => false,
+        other => return Err(PyValueError::new_err(format!("unknown encoding '{}': use 'appended' or 'base64'", other))),
+    };
+    let xml = || XmlArrays { appended: appended.then(Vec::new), compress };
+    let out = PathBuf::from(&out_path);
+    let multiblock = match out.extension().and_then(|e| e.to_str()) {
+        Some("vtm") => true,
+        Some("vtu") if patches.is_none() => false,
+        Some("vtu") => return Err(PyValueError::new_err("patches are written as blocks of a .vtm; give a .vtm out_path")),
+        _ => return Err(PyValueError::new_err(format!("{}: out_path must end in .vtu or .vtm", out_path))),
+    };
+
+    let (points, cells, files) = options::detach(py, &options, || -> PyResult<(usize, usize, Vec<String>)> {
+        let root = Path::new(&case_root);
+        let dir = time_dir(root, &time, region.as_deref());
+        let fields = volume_fields(&dir, fields)?;
+        let patches = if multiblock { export_patches(load_patches(root, region.as_deref())?, patches)? } else { Vec::new() };
+        let progress = Progress::start(1 + fields.len() + patches.len());
+        let mesh = load_mesh(root, region.as_deref())?;
+        let all: Vec<usize> = (0..mesh.n_cells).collect();
+        let cells = VtkCells::build(&mesh, &all);
+        progress.step();
+        let arrays = load_cell_arrays(&dir, &mesh, fields, &progress)?;
+
+        if !multiblock {
+            write_atomic(&out, vtu(&cells, &arrays, xml()))?;
+            return Ok((cells.points.len(), cells.len(), vec![out_path.clone()]));
+        }
+        let stem = out.file_stem().map_or("export".into(), |s| s.to_string_lossy().into_owned());
+        let blocks = out.with_extension("");
+        std::fs::create_dir_all(blocks.join("boundary"))?;
+        let mut files = Vec::new();
+        let internal = blocks.join("internal.vtu");
+        write_atomic(&internal, vtu(&cells, &arrays, xml()))?;
+        files.push(internal.to_string_lossy().into_owned());
+
+        let mut vtm = format!(
+            "<?xml version=\"1.0\"?>\n<VTKFile type=\"vtkMultiBlockDataSet\" version=\"1.0\" byte_order=\"LittleEndian\">\n<vtkMultiBlockDataSet>\n<DataSet index=\"0\" name=\"internal\" file=\"{}/internal.vtu\"/>\n<Block index=\"1\" name=\"boundary\">\n",
+            stem
+        );
+        for (i, patch) in patches.iter().enumerate() {
+            options::check_cancelled()?;
+            let path = blocks.join("boundary").join(format!("{}.vtp", patch.name));
+            let data = patch_vtp(&mesh, patch, &arrays, xml()).map_err(|e| PyValueError::new_err(format!("{}: {}", dir.display(), e)))?;
+            write_atomic(&path, data)?;
+            files.push(path.to_string_lossy().into_owned());
+            vtm.push_str(&format!("<DataSet index=\"{}\" name=\"{}\" file=\"{}/boundary/{}.vtp\"/>\n", i, patch.name, stem, patch.name));
+            progress.step();
+        }
+        vtm.push_str("</Block>\n</vtkMultiBlockDataSet>\n</VTKFile>\n");
+        write_atomic(&out, vtm)?;
+        files.push(out_path.clone());
+        Ok((cells.points.len(), cells.len(), files))
+    })?;
+    let result = PyDict::new(py);
+    result.set_item("path", &out_path)?;
+    result.set_item("points", points)?;
+    result.set_item("cells", cells)?;
+    result.set_item("files", files)?;
+    Ok(result)
+}
+
+// ------------------------------------------------------------------- glTF
+
+const GL_FLOAT: u32 = 5126;
+const GL_UNSIGNED_BYTE: u32 = 5121;
+const GL_UNSIGNED_INT: u32 = 5125;
+const GL_ARRAY_BUFFER: u32 = 34962;
+const GL_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+pub fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub fn json_number(v: f64) -> String {
+    if v.is_finite() {
+        format!("{}", v)
+    } else {
+        "null".into()
+    }
+}
+
+// A triangle surface with optional normals and RGBA8 vertex colours
+pub struct GltfMesh {
+    pub positions: Vec<f32>,
+    pub normals: Option<Vec<f32>>,
+    pub colours: Option<Vec<[u8; 4]>>,
+    pub indices: Vec<u32>,
+    // JSON object stored as the mesh's extras
+    pub extras: String,
+}
+
+// The single binary buffer and its views and accessors, four-byte aligned
+#[derive(Default)]
+struct GltfBuffer {
+    data: Vec<u8>,
+    views: Vec<String>,
+    accessors: Vec<String>,
+}
+
+impl GltfBuffer {
+    // Append `bytes` as a view with an accessor over `count` elements; the
+    // index of the accessor
+    fn add(&mut self, bytes: &[u8], target: u32, component: u32, kind: &str, count: usize, extra: &str) -> usize {
+        let offset = self.data.len();
+        self.data.extend_from_slice(bytes);
+        self.data.resize(self.data.len().next_multiple_of(4), 0);
+        self.views.push(format!(r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#, offset, bytes.len(), target));
+        self.accessors.push(format!(
+            r#"{{"bufferView":{},"componentType":{},"count":{},"type":"{}"{}}}"#,
+            self.views.len() - 1,
+            component,
+            count,
+            kind,
+            extra
+        ));
+        self.accessors.len() - 1
+    }
+}
+
+// A .glb file: the JSON chunk padded with spaces and the BIN chunk with zeros
+pub fn glb(mesh: &GltfMesh) -> Vec<u8> {
+    let n = mesh.positions.len() / 3;
+    let mut buffer = GltfBuffer::default();
+
+    // POSITION needs its bounds
+    let (mut lo, mut hi) = ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]);
+    for p in mesh.positions.chunks_exact(3) {
+        for k in 0..3 {
+            lo[k] = lo[k].min(p[k]);
+            hi[k] = hi[k].max(p[k]);
+        }
+    }
+    let bounds = if n > 0 {
+        let list = |v: [f32; 3]| v.map(|x| json_number(x as f64)).join(",");
+        format!(r#","min":[{}],"max":[{}]"#, list(lo), list(hi))
+    } else {
+        String::new()
+    };
+    let floats = |v: &[f32]| v.iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<u8>>();
+    let mut attributes = vec![format!(r#""POSITION":{}"#, buffer.add(&floats(&mesh.positions), GL_ARRAY_BUFFER, GL_FLOAT, "VEC3", n, &bounds))];
+    if let Some(normals) = &mesh.normals {
+        attributes.push(format!(r#""NORMAL":{}"#, buffer.add(&floats(normals), GL_ARRAY_BUFFER, GL_FLOAT, "VEC3", n, "")));
+    }
+    if let Some(colours) = &mesh.colours {
+        let accessor = buffer.add(colours.as_flattened(), GL_ARRAY_BUFFER, GL_UNSIGNED_BYTE, "VEC4", n, r#","normalized":true"#);
+        attributes.push(format!(r#""COLOR_0":{}"#, accessor));
+    }
+    let indices: Vec<u8> = mesh.indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let index_accessor = buffer.add(&indices, GL_ELEMENT_ARRAY_BUFFER, GL_UNSIGNED_INT, "SCALAR", mesh.indices.len(), "");
+
+    // Unlit-looking matte white, so the vertex colours show as they are
+    let json = format!(
+        concat!(
+            r#"{{"asset":{{"version":"2.0","generator":"FOAMFlask accelerator"}},"scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"#,
+            r#""meshes":[{{"primitives":[{{"attributes":{{{}}},"indices":{},"material":0,"mode":4}}],"extras":{}}}],"#,
+            r#""materials":[{{"pbrMetallicRoughness":{{"baseColorFactor":[1,1,1,1],"metallicFactor":0,"roughnessFactor":1}},"doubleSided":true}}],"#,
+            r#""buffers":[{{"byteLength":{}}}],"bufferViews":[{}],"accessors":[{}]}}"#
+        ),
+        attributes.join(","),
+        index_accessor,
+        mesh.extras,
+        buffer.data.len(),
+        buffer.views.join(","),
+        buffer.accessors.join(",")
+    );
+    let mut json = json.into_bytes();
+    json.resize(json.len().next_multiple_of(4), b' ');
+
+    let mut out = Vec::with_capacity(28 + json.len() + buffer.data.len());
+    out.extend_from_slice(b"glTF");
+    out.extend_from_slice(&2u32.to_le_bytes());
+    out.extend_from_slice(&((28 + json.len() + buffer.data.len()) as u32).to_le_bytes());
+    out.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    out.extend_from_slice(b"JSON");
+    out.extend(json);
+    out.extend_from_slice(&(buffer.data.len() as u32).to_le_bytes());
+    out.extend_from_slice(b"BIN\0");
+    out.extend(buffer.data);
+    out
+}
+
+/// Write a surface from extract_patch_surface(), slice_plane() or
+/// isosurface() as a binary glTF (.glb) for three.js: positions, normals
+/// and triangles, with `field` baked into RGBA vertex colours through
+/// `colormap` (viridis, plasma, inferno, magma, cividis, coolwarm, turbo,
+/// jet or gray; "_r" reverses). `field` is the name of a per-vertex array
+/// in the surface, usually "values", or an array of its own; None writes
+/// the bare geometry. Colours span `range` (min, max), by default the
+/// finite range of the values; fix it to keep a legend steady across
+/// frames. Values that aren't finite are grey. The field's name and range
+/// are stored as the mesh's extras. Returns a dict with the `path`,
+/// `vertices`, `triangles` and the `range` used.
+#[pyfunction]
+#[pyo3(signature = (surface, field, colormap, out_path, range = None))]
+pub fn export_gltf<'py>(
+    py: Python<'py>,
+    surface: &Bound<'py, PyDict>,
+    field: Option<&Bound<'py, PyAny>>,
+    colormap: &str,
+    out_path: String,
+    range: Option<(f64, f64)>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let colormap = Colormap::find(colormap)?;
+    let geometry = surface_from_py(surface)?;
+    let normals: Option<Vec<f32>> = surface_array(surface, "normals")?.map(|v| v.into_iter().map(|v| v as f32).collect());
+    let (name, values): (Option<String>, Option<Vec<f64>>) = match field {
+        None => (None, None),
+        Some(f) if f.is_instance_of::<PyString>() => {
+            let key: String = f.extract()?;
+            let values = surface_array(surface, &key)?.ok_or_else(|| PyKeyError::new_err(format!("surface has no '{}'", key)))?;
+            (Some(key), Some(values))
+        }
+        Some(f) => {
+            let array: PyArrayLike1<f64, AllowTypeChange> = f.extract()?;
+            (None, Some(array.as_array().iter().copied().collect()))
+        }
+    };
+
+    let n = geometry.points.len();
+    if normals.as_ref().is_some_and(|v| v.len() != 3 * n) {
+        return Err(PyValueError::new_err("surface normals must hold 3 values per vertex"));
+    }
+    if let Some(values) = &values {
+        if values.len() != n {
+            return Err(PyValueError::new_err(format!("field has {} values for {} vertices", values.len(), n)));
+        }
+    }
+
+    let (vertices, triangles) = (n, geometry.triangles.len());
+    let range = py.detach(|| -> PyResult<Option<(f64, f64)>> {
+        let positions = geometry.points.iter().flatten().map(|&v| v as f32).collect();
+        let indices = geometry.triangles.into_iter().flatten().collect();
+        let range = range.or_else(|| values.as_deref().and_then(finite_range));
+        let colours = values.as_ref().map(|values| {
+            let (lo, hi) = range.unwrap_or((0.0, 0.0));
+            values.par_iter().map(|&v| colormap.rgba(v, lo, hi)).collect()
+        });
+        let mut extras = vec![];
+        if let Some(name) = &name {
+            extras.push(format!(r#""field":{}"#, json_string(name)));
+        }
+        if let Some((lo, hi)) = range.filter(|_| values.is_some()) {
+            extras.push(format!(r#""range":[{},{}]"#, json_number(lo), json_number(hi)));
+        }
+        let mesh = GltfMesh { positions, normals, colours, indices, extras: format!("{{{}}}", extras.join(",")) };
+        write_atomic(Path::new(&out_path), glb(&mesh))?;
+        Ok(range.filter(|_| values.is_some()))
+    })?;
+    let result = PyDict::new(py);
+    result.set_item("path", &out_path)?;
+    result.set_item("vertices", vertices)?;
+    result.set_item("triangles", triangles)?;
+    result.set_item("range", range)?;
+    Ok(result)
+}
+
+// ----------------------------------------------------------- EnSight Gold
+
+// One element type of an EnSight part: the cells (or patch faces) in it, in
+// file order, and their points numbered within the part from 0. Polygons
+// ("nsided") list their point counts in `counts`; polyhedra ("nfaced") their
+// face counts in `counts` and the point count of every face in `face_sizes`.
+struct EnsightBlock {
+    kind: &'static str,
+    elements: Vec<usize>,
+    counts: Vec<usize>,
+    face_sizes: Vec<usize>,
+    connectivity: Vec<usize>,
+}
+
+impl EnsightBlock {
+    fn new(kind: &'static str) -> EnsightBlock {
+        EnsightBlock { kind, elements: Vec::new(), counts: Vec::new(), face_sizes: Vec::new(), connectivity: Vec::new() }
+    }
+}
+
+// A part: the volume mesh or one patch, with the mesh points it uses
+struct EnsightPart {
+    name: String,
+    points: Vec<usize>,
+    blocks: Vec<EnsightBlock>,
+    // The patch of a boundary part
+    patch: Option<Patch>,
+}
+
+fn ensight_internal(mesh: &PolyMesh) -> EnsightPart {
+    let (offsets, faces) = cell_faces(mesh);
+    let shapes: Vec<(&'static str, Vec<Vec<usize>>)> = (0..mesh.n_cells)
+        .into_par_iter()
+        .map(|c| {
+            let outward = outward_faces(mesh, &offsets, &faces, c);
+            match cell_shape(&outward) {
+                Some((VTK_TETRA, order)) => ("tetra4", vec![order]),
+                Some((VTK_PYRAMID, order)) => ("pyramid5", vec![order]),
+                // EnSight's prism has its first triangle facing the second
+                Some((VTK_WEDGE, o)) => ("penta6", vec![vec![o[0], o[2], o[1], o[3], o[5], o[4]]]),
+                Some((_, order)) => ("hexa8", vec![order]),
+                None => ("nfaced", outward),
+            }
+        })
+        .collect();
+
+    let mut blocks: Vec<EnsightBlock> = ["tetra4", "pyramid5", "penta6", "hexa8", "nfaced"].into_iter().map(EnsightBlock::new).collect();
+    for (c, (kind, faces)) in shapes.into_iter().enumerate() {
+        let block = blocks.iter_mut().find(|b| b.kind == kind).unwrap();
+        block.elements.push(c);
+        if kind == "nfaced" {
+            block.counts.push(faces.len());
+            block.face_sizes.extend(faces.iter().map(|f| f.len()));
+        }
+        block.connectivity.extend(faces.into_iter().flatten());
+    }
+    blocks.retain(|b| !b.elements.is_empty());
+    EnsightPart { name: "internalMesh".into(), points: (0..mesh.points.len()).collect(), blocks, patch: None }
+}
+
+fn ensight_patch(mesh: &PolyMesh, patch: Patch) -> EnsightPart {
+    let mut local = std::collections::HashMap::new();
+    let mut points = Vec::new();
+    let mut blocks: Vec<EnsightBlock> = ["tria3", "quad4", "nsided"].into_iter().map(EnsightBlock::new).collect();
+    for i in 0..patch.n_faces {
+        let face = mesh.face(patch.start_face + i);
+        let block = &mut blocks[face.len().clamp(3, 5) - 3];
+        block.elements.push(i);
+        if block.kind == "nsided" {
+            block.counts.push(face.len());
+        }
+        block.connectivity.extend(face.iter().map(|&p| {
+            *local.entry(p).or_insert_with(|| {
+                points.push(p);
+                points.len() - 1
+            })
+        }));
+    }
+    blocks.retain(|b| !b.elements.is_empty());
+    EnsightPart { name: patch.name.clone(), points, blocks, patch: Some(patch) }
+}
+
+// An 80-byte EnSight string, padded with zeros
+fn ensight_string(out: &mut Vec<u8>, s: &str) {
+    let bytes = &s.as_bytes()[..s.len().min(79)];
+    out.extend_from_slice(bytes);
+    out.resize(out.len() + 80 - bytes.len(), 0);
+}
+
+fn ensight_ints(out: &mut Vec<u8>, values: impl Iterator<Item = usize>) {
+    for v in values {
+        out.extend_from_slice(&(v as i32).to_le_bytes());
+    }
+}
+
+fn ensight_floats(out: &mut Vec<u8>, values: impl Iterator<Item = f64>) {
+    for v in values {
+        out.extend_from_slice(&(v as f32).to_le_bytes());
+    }
+}
+
+fn ensight_geometry(title: &str, mesh: &PolyMesh, parts: &[EnsightPart]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for line in ["C Binary", title, "written by FOAMFlask", "node id off", "element id off"] {
+        ensight_string(&mut out, line);
+    }
+    for (i, part) in parts.iter().enumerate() {
+        ensight_string(&mut out, "part");
+        ensight_ints(&mut out, std::iter::once(i + 1));
+        ensight_string(&mut out, &part.name);
+        ensight_string(&mut out, "coordinates");
+        ensight_ints(&mut out, std::iter::once(part.points.len()));
+        for k in 0..3 {
+            ensight_floats(&mut out, part.points.iter().map(|&p| mesh.points[p][k]));
+        }
+        for block in &part.blocks {
+            ensight_string(&mut out, block.kind);
+            ensight_ints(&mut out, std::iter::once(block.elements.len()));
+            ensight_ints(&mut out, block.counts.iter().copied());
+            ensight_ints(&mut out, block.face_sizes.iter().copied());
+            ensight_ints(&mut out, block.connectivity.iter().map(|p| p + 1));
+        }
+    }
+    out
+}
+
+// EnSight's component order for each field width: symmetric tensors go
+// xx yy zz xy xz yz where OpenFOAM has xx xy xz yy yz zz
+fn ensight_components(width: usize) -> Option<(&'static str, &'static [usize])> {
+    match width {
+        1 => Some(("scalar", &[0])),
+        3 => Some(("vector", &[0, 1, 2])),
+        6 => Some(("tensor symm", &[0, 3, 5, 1, 2, 4])),
+        9 => Some(("tensor asym", &[0, 1, 2, 3, 4, 5, 6, 7, 8])),
+        _ => None,
+    }
+}
+
+fn ensight_variable(array: &CellArray, mesh: &PolyMesh, parts: &[EnsightPart]) -> Result<Vec<u8>, String> {
+    let (_, order) = ensight_components(array.width).ok_or_else(|| format!("{} has {} components", array.name, array.width))?;
+    let w = array.width;
+    let mut out = Vec::new();
+    ensight_string(&mut out, &array.name);
+    for (i, part) in parts.iter().enumerate() {
+        let face_values;
+        let values = match &part.patch {
+            Some(patch) => {
+                face_values = array.file.patch_face_values(mesh, patch)?;
+                &face_values
+            }
+            None => &array.values,
+        };
+        ensight_string(&mut out, "part");
+        ensight_ints(&mut out, std::iter::once(i + 1));
+        for block in &part.blocks {
+            ensight_string(&mut out, block.kind);
+            for &k in order {
+                ensight_floats(&mut out, block.elements.iter().map(|&e| values[e * w + k]));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Write the times `times` (every time directory if None) of a case to
+/// `out_dir` as an EnSight Gold case in C binary: <case>.case, one
+/// <case>.geo for the mesh and data/<index>/<field> for each field and
+/// time, with the fields (every volume field of the first time if None)
+/// per element. The volume mesh is one part with hexahedra, prisms,
+/// tetrahedra and pyramids as such and other cells as polyhedra ("nfaced"),
+/// so nothing is split; each patch (those named in `patches`, or all but
+/// empty and processor ones) is a part of its own with the fields' patch
+/// values. Every field must be present at every time. Progress counts the
+/// mesh and each field of each time. Returns a dict with the case file's
+/// `path`, the `parts` written, the `elements` of the volume part and the
+/// number of `times`.
+#[pyfunction]
+#[pyo3(signature = (case_root, times, fields, out_dir, patches = None, region = None, options = None))]
+#[allow(clippy::too_many_arguments)]
+pub fn export_ensight<'py>(
+    py: Python<'py>,
+    case_root: String,
+    times: Option<Vec<String>>,
+    fields: Option<Vec<String>>,
+    out_dir: String,
+    patches: Option<Vec<String>>,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let (path, parts, elements, n_times) = options::detach(py, &options, || -> PyResult<(String, Vec<String>, usize, usize)> {
+        let root = Path::new(&case_root);
+        let times: Vec<(f64, String)> = match times {
+            Some(names) => names
+                .into_iter()
+                .map(|name| name.parse::<f64>().map(|t| (t, name.clone())).map_err(|_| PyValueError::new_err(format!("'{}' is not a time", name))))
+                .collect::<PyResult<_>>()?,
+            None => time_dirs(root)?,
+        };
+        let Some((_, first)) = times.first() else {
+            return Err(PyValueError::new_err(format!("{}: no times to export", case_root)));
+        };
+        let fields = volume_fields(&time_dir(root, first, region.as_deref()), fields)?;
+        let patches = export_patches(load_patches(root, region.as_deref())?, patches)?;
+        let progress = Progress::start(1 + times.len() * fields.len());
+
+        let mesh = load_mesh(root, region.as_deref())?;
+        let mut parts = vec![ensight_internal(&mesh)];
+        parts.extend(patches.into_iter().map(|p| ensight_patch(&mesh, p)));
+        let name = root.canonicalize().ok().and_then(|r| r.file_name().map(|n| n.to_string_lossy().into_owned())).unwrap_or_else(|| "foam".into());
+        let out = Path::new(&out_dir);
+        std::fs::create_dir_all(out)?;
+        write_atomic(&out.join(format!("{}.geo", name)), ensight_geometry(&format!("{} mesh", name), &mesh, &parts))?;
+        progress.step();
+
+        // Each time's files go in data/<index>, the index padded to the
+        // width of the case file's wildcard
+        let digits = times.len().saturating_sub(1).to_string().len().max(4);
+        let mut kinds = vec![None; fields.len()];
+        for (t, (_, time)) in times.iter().enumerate() {
+            let dir = time_dir(root, time, region.as_deref());
+            let data = out.join("data").join(format!("{:0width$}", t, width = digits));
+            std::fs::create_dir_all(&data)?;
+            for (f, name) in fields.iter().enumerate() {
+                let array = load_cell_arrays(&dir, &mesh, vec![name.clone()], &progress)?.remove(0);
+                let bytes = ensight_variable(&array, &mesh, &parts).map_err(|e| PyValueError::new_err(format!("{}: {}", dir.join(name).display(), e)))?;
+                write_atomic(&data.join(name), bytes)?;
+                kinds[f] = ensight_components(array.width).map(|(kind, _)| kind);
+            }
+        }
+
+        let mut case = format!("FORMAT\ntype: ensight gold\n\nGEOMETRY\nmodel: {}.geo\n\nVARIABLE\n", name);
+        for (name, kind) in fields.iter().zip(&kinds) {
+            case.push_str(&format!("{} per element: 1 {} data/{}/{}\n", kind.unwrap_or("scalar"), name, "*".repeat(digits), name));
+        }
+        case.push_str(&format!("\nTIME\ntime set: 1\nnumber of steps: {}\nfilename start number: 0\nfilename increment: 1\ntime values:\n", times.len()));
+        for (t, _) in &times {
+            case.push_str(&format!("{}\n", t));
+        }
+        let path = out.join(format!("{}.case", name));
+        write_atomic(&path, case)?;
+        let elements = parts[0].blocks.iter().map(|b| b.elements.len()).sum();
+        Ok((path.to_string_lossy().into_owned(), parts.into_iter().map(|p| p.name).collect(), elements, times.len()))
+    })?;
+    let result = PyDict::new(py);
+    result.set_item("path", path)?;
+    result.set_item("parts", parts)?;
+    result.set_item("elements", elements)?;
+    result.set_item("times", n_times)?;
+    Ok(result)
+}
@@ -0,0 +1,819 @@
+// Byte-level reader for the internalField and per-patch values of a
+// geometric field file. Unlike the dictionary parser this copes with binary
+// lists anywhere in the file, so it is used where patch values are needed in
+// bulk (fluxes, patch averages).
+
+use numpy::{AllowTypeChange, IntoPyArray, PyArrayLike1, PyArrayLike2, PyArrayMethods};
+use pyo3::exceptions::{PyFileNotFoundError, PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyFloat, PyTuple};
+use regex::Regex;
+use std::path::Path;
+
+use crate::boundary::patch_entries;
+use crate::dict::{format_key, format_scalar, write_atomic};
+use crate::for_each_number;
+use crate::geometry::{face_centre_area, mag};
+use crate::header::{header_end, parse_dimensions, parse_header, Dimensions};
+use crate::list::{decode_scalars, list_body, matching_paren, reserve_bounded, skip_ws_comments, ListBody, ListFormat};
+use crate::mesh::{cell_count, load_boundary, load_labels, mesh_dir, Patch, PolyMesh};
+use crate::options::{self, Options};
+use crate::source::open_field_file;
+use crate::surface::cell_values;
+use crate::time::time_dir;
+
+// Flattened components of a field value
+#[derive(Clone, Debug)]
+pub enum FieldValue {
+    Uniform(Vec<f64>),
+    NonUniform(Vec<f64>),
+}
+
+impl FieldValue {
+    // Values for `n` items of `width` components
+    pub fn expand(&self, n: usize, width: usize) -> Option<Vec<f64>> {
+        match self {
+            FieldValue::Uniform(v) if v.len() == width => Some(v.repeat(n)),
+            FieldValue::NonUniform(v) if v.len() == n * width => Some(v.clone()),
+            _ => None,
+        }
+    }
+}
+
+pub struct FieldFile {
+    pub class: String,
+    pub dimensions: Option<Dimensions>,
+    pub width: usize,
+    pub internal: Option<FieldValue>,
+    // Byte range of the internalField value, for rewriting it in place
+    pub internal_span: Option<std::ops::Range<usize>>,
+    // boundaryField keys as written (possibly regexes) and their `value`
+    pub patches: Vec<(String, Option<FieldValue>)>,
+}
+
+// Components per value of a field class or List<type>
+pub fn type_width(name: &str) -> Option<usize> {
+    let name = name.trim_start_matches("List<").trim_end_matches('>').trim_end_matches("Field");
+    let name = name.trim_start_matches("vol").trim_start_matches("surface").trim_start_matches("point");
+    Some(match name.to_ascii_lowercase().as_str() {
+        "scalar" | "sphericaltensor" => 1,
+        "vector2d" => 2,
+        "vector" => 3,
+        "symmtensor" => 6,
+        "tensor" => 9,
+        _ => return None,
+    })
+}
+
+struct Scanner<'a> {
+    data: &'a [u8],
+    pos: usize,
+    fmt: ListFormat,
+}
+
+impl<'a> Scanner<'a> {
+    fn skip_ws(&mut self) {
+        self.pos = skip_ws_comments(self.data, self.pos);
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_ws();
+        self.data.get(self.pos).copied()
+    }
+
+    // A keyword or other word, with quotes removed from strings
+    fn word(&mut self) -> Option<&'a str> {
+        self.skip_ws();
+        let data = self.data;
+        let start = self.pos;
+        if data.get(start) == Some(&b'"') {
+            let end = start + 1 + data[start + 1..].iter().position(|b| *b == b'"')?;
+            self.pos = end + 1;
+            return std::str::from_utf8(&data[start + 1..end]).ok();
+        }
+        let mut depth = 0usize;
+        while let Some(&b) = data.get(self.pos) {
+            match b {
+                b'(' if self.pos > start && !data[start].is_ascii_digit() => depth += 1,
+                b')' if depth > 0 => depth -= 1,
+                b'{' | b'}' | b';' | b'(' | b')' | b'"' => break,
+                b if b.is_ascii_whitespace() => break,
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        (self.pos > start).then(|| std::str::from_utf8(&data[start..self.pos]).ok())?
+    }
+
+    fn skip_past(&mut self, c: u8) {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_line(&mut self) {
+        self.pos = self.data[self.pos..].iter().position(|b| *b == b'\n').map_or(self.data.len(), |i| self.pos + i);
+    }
+
+    // A `nonuniform List<type> N(...)` list body, binary or ASCII
+    fn list(&mut self, width: usize) -> Option<Vec<f64>> {
+        let (body, next) = list_body(self.data, self.pos, self.fmt, width * self.fmt.scalar_bytes)?;
+        self.pos = next;
+        match body {
+            ListBody::Items(_, bytes) if self.fmt.binary => Some(decode_scalars(bytes, self.fmt.scalar_bytes)),
+            ListBody::Items(count, bytes) => {
+                let mut values = Vec::with_capacity(reserve_bounded(count.saturating_mul(width), bytes.len()));
+                for_each_number(bytes, |v| values.push(v));
+                (values.len() == count.saturating_mul(width)).then_some(values)
+            }
+            ListBody::Uniform(count, value) => {
+                let mut item = Vec::with_capacity(width);
+                for_each_number(value, |v| item.push(v));
+                (item.len() == width).then(|| item.repeat(count))
+            }
+        }
+    }
+
+    // The value after a keyword, through its ';'. `internal` resolves
+    // `$internalField` references.
+    fn value(&mut self, width: usize, internal: Option<&FieldValue>) -> Option<FieldValue> {
+        let start = self.pos;
+        let value = match self.word() {
+            Some("uniform") => {
+                let end = self.pos + self.data[self.pos..].iter().position(|b| *b == b';')?;
+                let mut v = Vec::with_capacity(width);
+                for_each_number(&self.data[self.pos..end], |x| v.push(x));
+                self.pos = end;
+                Some(FieldValue::Uniform(v))
+            }
+            Some("nonuniform") => {
+                let save = self.pos;
+                let width = match self.word() {
+                    Some(t) if t.starts_with("List<") => type_width(t).unwrap_or(width),
+                    _ => {
+                        self.pos = save;
+                        width
+                    }
+                };
+                self.list(width).map(FieldValue::NonUniform)
+            }
+            Some("$internalField") => internal.cloned(),
+            _ => {
+                self.pos = start;
+                None
+            }
+        };
+        self.skip_entry();
+        value
+    }
+
+    // Skip the rest of an entry: through its ';', or a sub-dict's closing
+    // brace. Binary lists are stepped over by their declared size.
+    fn skip_entry(&mut self) {
+        loop {
+            match self.peek() {
+                None => return,
+                Some(b';') => {
+                    self.pos += 1;
+                    return;
+                }
+                Some(b'}') => return,
+                Some(b'{') => {
+                    self.pos += 1;
+                    self.skip_block();
+                    self.skip_past(b';');
+                    return;
+                }
+                Some(b'(') => match matching_paren(self.data, self.pos) {
+                    Some(end) => self.pos = end + 1,
+                    None => self.pos = self.data.len(),
+                },
+                Some(_) => match self.word() {
+                    Some("nonuniform") => {
+                        let save = self.pos;
+                        let width = match self.word() {
+                            Some(t) if t.starts_with("List<") => type_width(t).unwrap_or(1),
+                            _ => {
+                                self.pos = save;
+                                1
+                            }
+                        };
+                        if self.list(width).is_none() {
+                            self.pos = self.data.len();
+                        }
+                    }
+                    Some(_) => {}
+                    None => self.pos += 1,
+                },
+            }
+        }
+    }
+
+    // Entries up to and including the closing '}'
+    fn skip_block(&mut self) {
+        loop {
+            match self.peek() {
+                None => return,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return;
+                }
+                Some(b'#') => self.skip_line(),
+                Some(_) => {
+                    if self.word().is_none() {
+                        self.pos += 1;
+                    }
+                    self.skip_entry();
+                }
+            }
+        }
+    }
+
+    fn boundary(&mut self, width: usize, internal: Option<&FieldValue>) -> Vec<(String, Option<FieldValue>)> {
+        let mut patches = Vec::new();
+        if self.peek() != Some(b'{') {
+            return patches;
+        }
+        self.pos += 1;
+        loop {
+            match self.peek() {
+                None => return patches,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return patches;
+                }
+                Some(b'#') => self.skip_line(),
+                Some(_) => {
+                    let Some(name) = self.word() else {
+                        self.pos += 1;
+                        continue;
+                    };
+                    if self.peek() != Some(b'{') {
+                        self.skip_entry();
+                        continue;
+                    }
+                    self.pos += 1;
+                    let mut value = None;
+                    loop {
+                        match self.peek() {
+                            None => break,
+                            Some(b'}') => {
+                                self.pos += 1;
+                                break;
+                            }
+                            Some(b'#') => self.skip_line(),
+                            Some(_) => match self.word() {
+                                Some("value") => value = self.value(width, internal),
+                                Some(_) => self.skip_entry(),
+                                None => self.pos += 1,
+                            },
+                        }
+                    }
+                    patches.push((name.to_string(), value));
+                }
+            }
+        }
+    }
+}
+
+pub fn parse_field_file(data: &[u8]) -> Result<FieldFile, String> {
+    let header = parse_header(data).ok_or("no FoamFile header")?;
+    let class = header.get("class").unwrap_or("").to_string();
+    let width = type_width(&class).ok_or_else(|| format!("'{}' is not a field class", class))?;
+    let mut scanner = Scanner { data, pos: header_end(data), fmt: ListFormat::of(data) };
+    let mut internal = None;
+    let mut internal_span = None;
+    let mut patches = Vec::new();
+    while scanner.peek().is_some() {
+        if scanner.peek() == Some(b'#') {
+            scanner.skip_line();
+            continue;
+        }
+        match scanner.word() {
+            Some("internalField") => {
+                scanner.skip_ws();
+                let start = scanner.pos;
+                internal = scanner.value(width, None);
+                let end = if data.get(scanner.pos.wrapping_sub(1)) == Some(&b';') { scanner.pos - 1 } else { scanner.pos };
+                internal_span = Some(start..end.max(start));
+            }
+            Some("boundaryField") => patches = scanner.boundary(width, internal.as_ref()),
+            Some(_) => scanner.skip_entry(),
+            None => scanner.pos += 1,
+        }
+    }
+    Ok(FieldFile { class, dimensions: parse_dimensions(data), width, internal, internal_span, patches })
+}
+
+impl FieldFile {
+    // The boundaryField entry for a mesh patch: its own name, one of its
+    // groups, or a regex key such as "(inlet|outlet)" or ".*"
+    pub fn patch_value(&self, patch: &Patch) -> Option<&FieldValue> {
+        let exact = |name: &str| self.patches.iter().rev().find(|(k, _)| k == name);
+        let entry = exact(&patch.name)
+            .or_else(|| patch.in_groups.iter().find_map(|g| exact(g)))
+            .or_else(|| {
+                self.patches.iter().rev().find(|(k, _)| {
+                    Regex::new(&format!("^(?:{})$", k)).is_ok_and(|re| re.is_match(&patch.name))
+                })
+            })?;
+        entry.1.as_ref()
+    }
+
+    // Values on a patch's faces: its boundaryField value, or the owner cell
+    // values for a volume field patch without one (zeroGradient and the like)
+    pub fn patch_face_values(&self, mesh: &PolyMesh, patch: &Patch) -> Result<Vec<f64>, String> {
+        let width = self.width;
+        if let Some(value) = self.patch_value(patch) {
+            return value
+                .expand(patch.n_faces, width)
+                .ok_or_else(|| format!("patch '{}' value does not have {} faces", patch.name, patch.n_faces));
+        }
+        if !self.class.starts_with("vol") {
+            return Err(format!("patch '{}' has no value", patch.name));
+        }
+        let owners = mesh
+            .owner
+            .get(patch.start_face..patch.start_face + patch.n_faces)
+            .ok_or_else(|| format!("patch '{}' is outside the mesh", patch.name))?;
+        match &self.internal {
+            Some(FieldValue::Uniform(v)) if v.len() == width => Ok(v.repeat(patch.n_faces)),
+            Some(FieldValue::NonUniform(v)) if v.len() == mesh.n_cells * width => {
+                Ok(owners.iter().flat_map(|&c| v[c * width..(c + 1) * width].iter().copied()).collect())
+            }
+            _ => Err(format!("internalField does not have {} values", mesh.n_cells)),
+        }
+    }
+}
+
+pub fn load_field(path: &Path) -> PyResult<Option<FieldFile>> {
+    let Some(data) = open_field_file(&path.to_string_lossy())? else {
+        return Ok(None);
+    };
+    parse_field_file(&data).map(Some).map_err(|e| PyValueError::new_err(format!("{}: {}", path.display(), e)))
+}
+
+pub fn load_patches(case_root: &Path, region: Option<&str>) -> PyResult<Vec<Patch>> {
+    let dir = mesh_dir(case_root, region);
+    load_boundary(&dir)?
+        .ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no boundary file", dir.join("boundary").display())))
+}
+
+pub fn load_mesh(case_root: &Path, region: Option<&str>) -> PyResult<PolyMesh> {
+    let dir = mesh_dir(case_root, region);
+    PolyMesh::load(&dir)?.ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no mesh", dir.display())))
+}
+
+// The patch called `name`, or else the patches in group `name`
+pub fn select_patches<'a>(patches: &'a [Patch], name: &str) -> PyResult<Vec<&'a Patch>> {
+    let mut selected: Vec<&Patch> = patches.iter().filter(|p| p.name == name).collect();
+    if selected.is_empty() {
+        selected = patches.iter().filter(|p| p.in_groups.iter().any(|g| g == name)).collect();
+    }
+    if selected.is_empty() {
+        return Err(PyKeyError::new_err(format!("no patch or patch group '{}' in the mesh", name)));
+    }
+    Ok(selected)
+}
+
+/// Read a face field (surfaceScalarField, surfaceVectorField, ...) from
+/// <time>/<name> as one value per mesh face: the internal faces followed by
+/// each patch's faces in mesh order, (nFaces,) or (nFaces, components).
+/// Patches without a value (empty, processor) are NaN. Uses
+/// constant/polyMesh/boundary for the patch layout. Returns None if the
+/// field file is missing. With `region`, the field and mesh of that region
+/// are used.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, name, region = None, options = None))]
+pub fn read_surface_field<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    name: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyAny>>> {
+    let root = Path::new(&case_root);
+    let region = region.as_deref();
+    let path = time_dir(root, &time, region).join(&name);
+    let values = options::detach(py, &options, || -> PyResult<Option<(Vec<f64>, usize)>> {
+        let Some(field) = load_field(&path)? else {
+            return Ok(None);
+        };
+        let error = |msg: String| PyValueError::new_err(format!("{}: {}", path.display(), msg));
+        if !field.class.starts_with("surface") {
+            return Err(error(format!("{} is not a face field", field.class)));
+        }
+        let patches = load_patches(root, region)?;
+        let n_internal = match patches.iter().map(|p| p.start_face).min() {
+            Some(n) => n,
+            None => load_labels(&mesh_dir(root, region), "neighbour")?.map_or(0, |n| n.len()),
+        };
+        let width = field.width;
+        let internal = field.internal.as_ref().ok_or_else(|| error("no internalField".into()))?;
+        let mut values = internal
+            .expand(n_internal, width)
+            .ok_or_else(|| error(format!("internalField does not have {} values", n_internal)))?;
+        for patch in &patches {
+            let offset = patch.start_face * width;
+            values.resize(offset.max(values.len()), f64::NAN);
+            let patch_values = match field.patch_value(patch) {
+                Some(v) => v.expand(patch.n_faces, width).ok_or_else(|| {
+                    error(format!("patch '{}' value does not have {} faces", patch.name, patch.n_faces))
+                })?,
+                None => vec![f64::NAN; patch.n_faces * width],
+            };
+            values.truncate(offset);
+            values.extend(patch_values);
+        }
+        Ok(Some((values, width)))
+    })?;
+    let Some((values, width)) = values else {
+        return Ok(None);
+    };
+    let n = values.len() / width;
+    let array = values.into_pyarray(py);
+    Ok(Some(if width == 1 { array.into_any() } else { array.reshape([n, width])?.into_any() }))
+}
+
+/// Sum of a face field over a patch, by default the volumetric or mass flux
+/// `phi` (positive out of the domain, so inlets come out negative). `patch`
+/// can also name a patch group, in which case every patch in the group is
+/// summed. Errors if the field is missing or a patch has no value. With
+/// `region`, the field and mesh of that region are used.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, patch, field = "phi".to_string(), region = None, options = None))]
+pub fn patch_flux_sum(
+    py: Python,
+    case_root: String,
+    time: String,
+    patch: String,
+    field: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<f64> {
+    options::detach(py, &options, || {
+        let root = Path::new(&case_root);
+        let region = region.as_deref();
+        let path = time_dir(root, &time, region).join(&field);
+        let file = load_field(&path)?
+            .ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no such field file", path.display())))?;
+        let error = |msg: String| PyValueError::new_err(format!("{}: {}", path.display(), msg));
+        if !file.class.starts_with("surface") || file.width != 1 {
+            return Err(error(format!("{} is not a scalar face field", file.class)));
+        }
+        let patches = load_patches(root, region)?;
+        let mut sum = 0.0;
+        for p in select_patches(&patches, &patch)? {
+            let value = file.patch_value(p).ok_or_else(|| error(format!("patch '{}' has no value", p.name)))?;
+            let values = value
+                .expand(p.n_faces, 1)
+                .ok_or_else(|| error(format!("patch '{}' value does not have {} faces", p.name, p.n_faces)))?;
+            sum += values.iter().sum::<f64>();
+        }
+        Ok(sum)
+    })
+}
+
+/// Area-weighted mean of a field over a patch (or every patch in a patch
+/// group), e.g. the average outlet pressure. Uses the patch's boundaryField
+/// value where it has one; patches without a value (zeroGradient and the
+/// like) take the adjacent cell values. Returns a float for scalar fields
+/// and a tuple of component means otherwise. With `region`, the field and
+/// mesh of that region are used.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field, patch, region = None, options = None))]
+pub fn patch_average<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    field: String,
+    patch: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let mean = options::detach(py, &options, || -> PyResult<Vec<f64>> {
+        let root = Path::new(&case_root);
+        let region = region.as_deref();
+        let path = time_dir(root, &time, region).join(&field);
+        let file = load_field(&path)?
+            .ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no such field file", path.display())))?;
+        let error = |msg: String| PyValueError::new_err(format!("{}: {}", path.display(), msg));
+        let mesh = load_mesh(root, region)?;
+        let patches = load_patches(root, region)?;
+        let selected = select_patches(&patches, &patch)?;
+
+        let width = file.width;
+        let mut sums = vec![0.0; width];
+        let mut total_area = 0.0;
+        for p in selected {
+            let values = file.patch_face_values(&mesh, p).map_err(error)?;
+            for (i, tuple) in values.chunks_exact(width).enumerate() {
+                let area = mag(face_centre_area(&mesh.points, mesh.face(p.start_face + i)).1);
+                for (s, v) in sums.iter_mut().zip(tuple) {
+                    *s += area * v;
+                }
+                total_area += area;
+            }
+        }
+        if total_area <= 0.0 {
+            return Err(PyValueError::new_err(format!("patch '{}' has no faces", patch)));
+        }
+        Ok(sums.into_iter().map(|s| s / total_area).collect())
+    })?;
+    if let [value] = *mean {
+        return Ok(PyFloat::new(py, value).into_any());
+    }
+    Ok(PyTuple::new(py, mean)?.into_any())
+}
+
+const BANNER: &str = r"/*--------------------------------*- C++ -*----------------------------------*\
+| =========                 |                                                 |
+| \\      /  F ield         | OpenFOAM: The Open Source CFD Toolbox           |
+|  \\    /   O peration     |                                                 |
+|   \\  /    A nd           | Website:  www.openfoam.com                      |
+|    \\/     M anipulation  |                                                 |
+\*---------------------------------------------------------------------------*/
+";
+
+const SEPARATOR: &str = "// * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * //";
+
+const FOOTER: &str = "// ************************************************************************* //";
+
+// OpenFOAM type of values with `width` components
+pub fn type_name(width: usize) -> Option<&'static str> {
+    Some(match width {
+        1 => "scalar",
+        2 => "vector2D",
+        3 => "vector",
+        6 => "symmTensor",
+        9 => "tensor",
+        _ => return None,
+    })
+}
+
+// Squared magnitude of one value, counting a symmTensor's off-diagonal
+// components twice as the full tensor has them
+pub fn mag_sqr(v: &[f64]) -> f64 {
+    let sq: f64 = v.iter().map(|x| x * x).sum();
+    if v.len() == 6 { sq + v[1] * v[1] + v[2] * v[2] + v[4] * v[4] } else { sq }
+}
+
+// A boundaryField entry of a field being written
+pub enum PatchOutput {
+    // `type empty;`
+    Empty,
+    // `type calculated;` with the given value
+    Calculated(FieldValue),
+    // Entries of a patch block in order
+    Entries(Vec<PatchEntry>),
+}
+
+pub enum PatchEntry {
+    // A formatted entry, written as given
+    Text(String),
+    // Key, width and values of a nonuniform entry, written in the file's
+    // format
+    Values(String, usize, Vec<f64>),
+}
+
+// A geometric field to write as an OpenFOAM file
+pub struct FieldOutput {
+    // Class prefix: "vol", "surface" or "point"
+    pub kind: &'static str,
+    pub object: String,
+    pub location: String,
+    pub dimensions: Dimensions,
+    pub width: usize,
+    pub internal: FieldValue,
+    pub patches: Vec<(String, PatchOutput)>,
+    pub binary: bool,
+}
+
+// A field value as written after a keyword: `uniform X`, or a nonuniform
+// list of `width`-component items in ASCII or 64-bit binary
+pub fn format_field_value(value: &FieldValue, width: usize, binary: bool, out: &mut Vec<u8>) {
+    let kind = type_name(width).unwrap_or("scalar");
+    let tuple = |t: &[f64]| {
+        let parts: Vec<String> = t.iter().map(|&v| format_scalar(v)).collect();
+        if t.len() == 1 { parts[0].clone() } else { format!("({})", parts.join(" ")) }
+    };
+    match value {
+        FieldValue::Uniform(v) => out.extend_from_slice(format!("uniform {}", tuple(v)).as_bytes()),
+        FieldValue::NonUniform(v) => {
+            let n = v.len() / width.max(1);
+            if binary {
+                out.extend_from_slice(format!("nonuniform List<{}> {}(", kind, n).as_bytes());
+                for x in v {
+                    out.extend_from_slice(&x.to_le_bytes());
+                }
+                out.push(b')');
+            } else {
+                out.extend_from_slice(format!("nonuniform List<{}> \n{}\n(\n", kind, n).as_bytes());
+                for t in v.chunks_exact(width.max(1)) {
+                    out.extend_from_slice(tuple(t).as_bytes());
+                    out.push(b'\n');
+                }
+                out.push(b')');
+            }
+        }
+    }
+}
+
+impl FieldOutput {
+    fn format_value(&self, value: &FieldValue, width: usize, out: &mut Vec<u8>) {
+        format_field_value(value, width, self.binary, out);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let class = format!("{}{}Field", self.kind, capitalise(type_name(self.width).unwrap_or("scalar")));
+        let mut out = Vec::new();
+        out.extend_from_slice(BANNER.as_bytes());
+        let mut header = String::from("FoamFile\n{\n    version     2.0;\n");
+        if self.binary {
+            header.push_str("    format      binary;\n    arch        \"LSB;label=32;scalar=64\";\n");
+        } else {
+            header.push_str("    format      ascii;\n");
+        }
+        header.push_str(&format!(
+            "    class       {};\n    location    \"{}\";\n    object      {};\n}}\n{}\n\n",
+            class, self.location, self.object, SEPARATOR
+        ));
+        let dims: Vec<String> = self.dimensions.iter().map(|&d| format_scalar(d)).collect();
+        header.push_str(&format!("dimensions      [{}];\n\ninternalField   ", dims.join(" ")));
+        out.extend_from_slice(header.as_bytes());
+        self.format_value(&self.internal, self.width, &mut out);
+        out.extend_from_slice(b";\n\nboundaryField\n{\n");
+        for (name, patch) in &self.patches {
+            out.extend_from_slice(format!("    {}\n", format_key(name)).as_bytes());
+            match patch {
+                PatchOutput::Empty => out.extend_from_slice(b"    {\n        type            empty;\n    }\n"),
+                PatchOutput::Calculated(value) => {
+                    out.extend_from_slice(b"    {\n        type            calculated;\n        value           ");
+                    self.format_value(value, self.width, &mut out);
+                    out.extend_from_slice(b";\n    }\n");
+                }
+                PatchOutput::Entries(entries) => {
+                    out.extend_from_slice(b"    {\n");
+                    for entry in entries {
+                        match entry {
+                            PatchEntry::Text(text) => out.extend_from_slice(text.as_bytes()),
+                            PatchEntry::Values(key, width, values) => {
+                                out.extend_from_slice(format!("        {:<15} ", format_key(key)).as_bytes());
+                                self.format_value(&FieldValue::NonUniform(values.clone()), *width, &mut out);
+                                out.push(b';');
+                            }
+                        }
+                        out.push(b'\n');
+                    }
+                    out.extend_from_slice(b"    }\n");
+                }
+            }
+        }
+        out.extend_from_slice(format!("}}\n\n\n{}\n", FOOTER).as_bytes());
+        out
+    }
+}
+
+fn capitalise(s: &str) -> String {
+    let mut chars = s.chars();
+    chars.next().map_or(String::new(), |c| c.to_ascii_uppercase().to_string() + chars.as_str())
+}
+
+// `calculated` patches for a volume field from the patches' face values
+// concatenated in patch order, or `empty` for empty patches
+pub fn calculated_patches(patches: &[Patch], boundary: &[f64], width: usize) -> Vec<(String, PatchOutput)> {
+    let mut start = 0;
+    patches
+        .iter()
+        .map(|p| {
+            let end = (start + p.n_faces * width).min(boundary.len());
+            let values = boundary[start.min(end)..end].to_vec();
+            start = end;
+            let output = if p.patch_type == "empty" { PatchOutput::Empty } else { PatchOutput::Calculated(FieldValue::NonUniform(values)) };
+            (p.name.clone(), output)
+        })
+        .collect()
+}
+
+// Cell values of a volume field followed by every patch's face values, the
+// layout calculated_patches() splits again
+pub fn vol_field_values(mesh: &PolyMesh, patches: &[Patch], file: &FieldFile) -> Result<Vec<f64>, String> {
+    let mut values = cell_values(mesh, file)?;
+    for p in patches {
+        values.extend(file.patch_face_values(mesh, p)?);
+    }
+    Ok(values)
+}
+
+// boundaryField entries from a Python spec: patch name (or regex key) to a
+// dict of entries, or to a bare type name such as "zeroGradient"
+fn boundary_spec_patches(spec: &Bound<PyDict>) -> PyResult<Vec<(String, PatchOutput)>> {
+    let mut patches = Vec::new();
+    for (k, v) in spec.iter() {
+        let name = k.str()?.to_string();
+        let entries = match v.cast::<PyDict>() {
+            Ok(entries) => patch_entries(entries)?,
+            Err(_) => {
+                let kind: String = v
+                    .extract()
+                    .map_err(|_| PyValueError::new_err(format!("'{}': expected a dict of entries or a patch type", name)))?;
+                vec![PatchEntry::Text(format!("        {:<15} {};", "type", kind))]
+            }
+        };
+        patches.push((name, PatchOutput::Entries(entries)));
+    }
+    Ok(patches)
+}
+
+// Writes a volume field holding `values` to `path`. When the case's mesh is
+// found (path is <case>/<time>/<name>) the value count is checked against
+// it and empty patches missing from `patches` are added.
+fn write_vol_field(
+    path: &Path,
+    width: usize,
+    values: Vec<f64>,
+    dimensions: Dimensions,
+    mut patches: Vec<(String, PatchOutput)>,
+    binary: bool,
+) -> PyResult<()> {
+    let (Some(object), Some(location)) = (path.file_name(), path.parent().and_then(|p| p.file_name())) else {
+        return Err(PyValueError::new_err(format!("{}: expected <case>/<time>/<field>", path.display())));
+    };
+    if let Some(root) = path.parent().and_then(Path::parent) {
+        let dir = mesh_dir(root, None);
+        if let (Some(owner), Some(neighbour)) = (load_labels(&dir, "owner")?, load_labels(&dir, "neighbour")?) {
+            let n_cells = cell_count(&owner, &neighbour);
+            if values.len() != n_cells * width {
+                return Err(PyValueError::new_err(format!(
+                    "{}: {} values for a mesh of {} cells",
+                    path.display(),
+                    values.len() / width,
+                    n_cells
+                )));
+            }
+        }
+        for p in load_boundary(&dir)?.unwrap_or_default() {
+            if p.patch_type == "empty" && !patches.iter().any(|(name, _)| *name == p.name) {
+                patches.push((p.name, PatchOutput::Empty));
+            }
+        }
+    }
+    let output = FieldOutput {
+        kind: "vol",
+        object: object.to_string_lossy().into_owned(),
+        location: location.to_string_lossy().into_owned(),
+        dimensions,
+        width,
+        internal: FieldValue::NonUniform(values),
+        patches,
+        binary,
+    };
+    write_atomic(path, output.to_bytes())?;
+    Ok(())
+}
+
+/// Write a volScalarField to `path` (<case>/<time>/<name>) with one value
+/// per cell, e.g. an initial condition mapped to the cells. `dimensions` is
+/// the 7-entry dimension set; `boundary_spec` maps each patch (or regex
+/// key) to its entries as for set_boundary_condition, e.g. {"inlet":
+/// {"type": "fixedValue", "value": 1.0}, "outlet": "zeroGradient"}; numpy
+/// arrays become nonuniform lists. If the case's mesh is found the value
+/// count is checked and empty patches are filled in. Written as ASCII, or
+/// binary with `binary`; the file is replaced atomically.
+#[pyfunction]
+#[pyo3(signature = (path, values, dimensions, boundary_spec, binary = false))]
+pub fn write_scalar_field(
+    py: Python,
+    path: String,
+    values: PyArrayLike1<f64, AllowTypeChange>,
+    dimensions: Dimensions,
+    boundary_spec: &Bound<PyDict>,
+    binary: bool,
+) -> PyResult<()> {
+    let values: Vec<f64> = values.as_array().iter().copied().collect();
+    let patches = boundary_spec_patches(boundary_spec)?;
+    py.detach(|| write_vol_field(Path::new(&path), 1, values, dimensions, patches, binary))
+}
+
+/// Write a volVectorField to `path` from an (n_cells, 3) array, e.g. a
+/// parabolic inlet profile mapped to the cells. Otherwise as
+/// write_scalar_field.
+#[pyfunction]
+#[pyo3(signature = (path, values, dimensions, boundary_spec, binary = false))]
+pub fn write_vector_field(
+    py: Python,
+    path: String,
+    values: PyArrayLike2<f64, AllowTypeChange>,
+    dimensions: Dimensions,
+    boundary_spec: &Bound<PyDict>,
+    binary: bool,
+) -> PyResult<()> {
+    let array = values.as_array();
+    if array.ncols() != 3 {
+        return Err(PyValueError::new_err(format!("expected an (n, 3) array, got {} columns", array.ncols())));
+    }
+    let values: Vec<f64> = array.iter().copied().collect();
+    let patches = boundary_spec_patches(boundary_spec)?;
+    py.detach(|| write_vol_field(Path::new(&path), 3, values, dimensions, patches, binary))
+}
@@ -0,0 +1,233 @@
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyArrayMethods};
+use pyo3::prelude::*;
+use rayon::prelude::*;
+use std::path::Path;
+
+use crate::mesh::{mesh_dir, PolyMesh};
+use crate::options::{self, Options};
+use crate::source::open_field_file;
+use crate::time::time_dir;
+use crate::{find_internal_field, internal_field_components, InternalField};
+
+pub type Vec3 = [f64; 3];
+
+pub fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn scale(a: Vec3, s: f64) -> Vec3 {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub fn dot(a: Vec3, b: Vec3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+pub fn mag(a: Vec3) -> f64 {
+    dot(a, a).sqrt()
+}
+
+// Face geometry computed the way OpenFOAM's primitiveMesh does
+pub struct FaceGeometry {
+    pub centres: Vec<Vec3>,
+    // Area-weighted face normals (Sf), pointing out of the owner cell
+    pub areas: Vec<Vec3>,
+}
+
+pub struct CellGeometry {
+    pub centres: Vec<Vec3>,
+    pub volumes: Vec<f64>,
+}
+
+// Centre and area vector of one face, by fanning triangles around the
+// point average
+pub fn face_centre_area(points: &[Vec3], face: &[usize]) -> (Vec3, Vec3) {
+    let n = face.len();
+    if n == 3 {
+        let (a, b, c) = (points[face[0]], points[face[1]], points[face[2]]);
+        return (scale(add(add(a, b), c), 1.0 / 3.0), scale(cross(sub(b, a), sub(c, a)), 0.5));
+    }
+
+    let mut estimate = [0.0; 3];
+    for &p in face {
+        estimate = add(estimate, points[p]);
+    }
+    let estimate = scale(estimate, 1.0 / n as f64);
+
+    let mut sum_n = [0.0; 3];
+    let mut sum_a = 0.0;
+    let mut sum_ac = [0.0; 3];
+    for i in 0..n {
+        let this = points[face[i]];
+        let next = points[face[(i + 1) % n]];
+        let c = add(add(this, next), estimate);
+        let tri_n = cross(sub(next, this), sub(estimate, this));
+        let a = mag(tri_n);
+        sum_n = add(sum_n, tri_n);
+        sum_a += a;
+        sum_ac = add(sum_ac, scale(c, a));
+    }
+
+    let centre = if sum_a < 1e-300 { estimate } else { scale(sum_ac, 1.0 / (3.0 * sum_a)) };
+    (centre, scale(sum_n, 0.5))
+}
+
+// Faces of each cell in CSR form, built from owner/neighbour
+pub fn cell_faces(mesh: &PolyMesh) -> (Vec<usize>, Vec<usize>) {
+    let mut counts = vec![0usize; mesh.n_cells + 1];
+    for &c in mesh.owner.iter().chain(&mesh.neighbour) {
+        counts[c + 1] += 1;
+    }
+    for i in 0..mesh.n_cells {
+        counts[i + 1] += counts[i];
+    }
+    let offsets = counts.clone();
+    let mut fill = counts;
+    let mut faces = vec![0usize; offsets[mesh.n_cells]];
+    for (f, &c) in mesh.owner.iter().enumerate() {
+        faces[fill[c]] = f;
+        fill[c] += 1;
+    }
+    for (f, &c) in mesh.neighbour.iter().enumerate() {
+        faces[fill[c]] = f;
+        fill[c] += 1;
+    }
+    (offsets, faces)
+}
+
+impl FaceGeometry {
+    pub fn compute(mesh: &PolyMesh) -> FaceGeometry {
+        let (centres, areas) = (0..mesh.n_faces())
+            .into_par_iter()
+            .map(|f| face_centre_area(&mesh.points, mesh.face(f)))
+            .unzip();
+        FaceGeometry { centres, areas }
+    }
+}
+
+impl CellGeometry {
+    pub fn compute(mesh: &PolyMesh, face_geom: &FaceGeometry) -> CellGeometry {
+        let face_centres = &face_geom.centres;
+        let face_areas = &face_geom.areas;
+        let (offsets, faces) = cell_faces(mesh);
+        let (centres, volumes) = (0..mesh.n_cells)
+            .into_par_iter()
+            .map(|c| {
+                let my_faces = &faces[offsets[c]..offsets[c + 1]];
+                if my_faces.is_empty() {
+                    return ([0.0; 3], 0.0);
+                }
+
+                let mut estimate = [0.0; 3];
+                for &f in my_faces {
+                    estimate = add(estimate, face_centres[f]);
+                }
+                let estimate = scale(estimate, 1.0 / my_faces.len() as f64);
+
+                // Decompose into pyramids with the face as base and the
+                // estimated centre as apex
+                let mut vol3 = 0.0;
+                let mut centre = [0.0; 3];
+                for &f in my_faces {
+                    let mut pyr3 = dot(face_areas[f], sub(face_centres[f], estimate));
+                    if mesh.owner[f] != c {
+                        pyr3 = -pyr3;
+                    }
+                    let pc = add(scale(face_centres[f], 0.75), scale(estimate, 0.25));
+                    centre = add(centre, scale(pc, pyr3));
+                    vol3 += pyr3;
+                }
+
+                if vol3.abs() > 1e-300 {
+                    (scale(centre, 1.0 / vol3), vol3 / 3.0)
+                } else {
+                    (estimate, 0.0)
+                }
+            })
+            .unzip();
+
+        CellGeometry { centres, volumes }
+    }
+}
+
+// Everything derived from the mesh that cell-walking and interpolation need
+pub struct MeshGeometry {
+    pub face_centres: Vec<Vec3>,
+    pub face_areas: Vec<Vec3>,
+    pub cell_centres: Vec<Vec3>,
+    pub cell_volumes: Vec<f64>,
+    cell_offsets: Vec<usize>,
+    cell_faces: Vec<usize>,
+}
+
+impl MeshGeometry {
+    pub fn compute(mesh: &PolyMesh) -> MeshGeometry {
+        let faces = FaceGeometry::compute(mesh);
+        let cells = CellGeometry::compute(mesh, &faces);
+        let (cell_offsets, cell_faces) = cell_faces(mesh);
+        MeshGeometry {
+            face_centres: faces.centres,
+            face_areas: faces.areas,
+            cell_centres: cells.centres,
+            cell_volumes: cells.volumes,
+            cell_offsets,
+            cell_faces,
+        }
+    }
+
+    pub fn faces_of(&self, cell: usize) -> &[usize] {
+        &self.cell_faces[self.cell_offsets[cell]..self.cell_offsets[cell + 1]]
+    }
+}
+
+// Cell volumes for a time step: a `V` field written by writeCellVolumes if the
+// time directory has one, otherwise computed from the mesh
+pub fn load_cell_volumes(case_root: &Path, time: &str, region: Option<&str>) -> PyResult<Option<Vec<f64>>> {
+    if let Some(data) = open_field_file(&time_dir(case_root, time, region).join("V").to_string_lossy())? {
+        if let Some(field @ InternalField::NonUniform(_)) = find_internal_field(&data) {
+            let (values, width) = internal_field_components(field);
+            if width == 1 && !values.is_empty() {
+                return Ok(Some(values));
+            }
+        }
+    }
+    Ok(PolyMesh::load(&mesh_dir(case_root, region))?.map(|m| CellGeometry::compute(&m, &FaceGeometry::compute(&m)).volumes))
+}
+
+// (volumes, centres) as handed back to Python
+type CellGeometryArrays<'py> = (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray2<f64>>);
+
+/// Cell volumes and centres of constant/polyMesh, computed with OpenFOAM's
+/// face-decomposition algorithm. Returns (volumes, centres) with shapes (N,)
+/// and (N, 3), or None if the mesh is missing. With `region`, the mesh in
+/// constant/<region>/polyMesh is used.
+#[pyfunction]
+#[pyo3(signature = (case_root, region = None, options = None))]
+pub fn compute_cell_geometry<'py>(
+    py: Python<'py>,
+    case_root: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<CellGeometryArrays<'py>>> {
+    let geometry = options::detach(py, &options, || -> PyResult<Option<CellGeometry>> {
+        Ok(PolyMesh::load(&mesh_dir(Path::new(&case_root), region.as_deref()))?
+            .map(|m| CellGeometry::compute(&m, &FaceGeometry::compute(&m))))
+    })?;
+
+    match geometry {
+        Some(g) => {
+            let n = g.volumes.len();
+            let centres: Vec<f64> = g.centres.into_iter().flatten().collect();
+            Ok(Some((g.volumes.into_pyarray(py), centres.into_pyarray(py).reshape([n, 3])?)))
+        }
+        None => Ok(None),
+    }
+}
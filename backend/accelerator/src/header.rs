@@ -0,0 +1,272 @@
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use regex::bytes::Regex;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::options::{self, Options};
+use crate::source::{open_field_file, open_field_head, resolve_field_path};
+use crate::{field, internal_field_layout, scan, InternalLayout};
+
+// Entries of the `FoamFile { ... }` banner, in file order with quotes removed
+pub struct FoamHeader {
+    pub entries: Vec<(String, String)>,
+}
+
+impl FoamHeader {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn is_binary(&self) -> bool {
+        self.get("format") == Some("binary")
+    }
+
+    // Byte width of a label or scalar from the arch entry, e.g.
+    // "LSB;label=32;scalar=64". OpenFOAM's defaults apply when absent.
+    pub fn arch_width(&self, kind: &str) -> usize {
+        let default = if kind == "label" { 4 } else { 8 };
+        self.get("arch")
+            .and_then(|arch| {
+                arch.split(';')
+                    .find_map(|part| part.strip_prefix(kind)?.strip_prefix('='))
+                    .and_then(|bits| bits.parse::<usize>().ok())
+            })
+            .map_or(default, |bits| bits / 8)
+    }
+}
+
+// Byte offset just past the closing brace of the FoamFile block, or 0 for
+// headerless files
+pub fn header_end(data: &[u8]) -> usize {
+    let head = &data[..data.len().min(4096)];
+    let start = match memchr::memmem::find(head, b"FoamFile") {
+        Some(i) => i,
+        None => return 0,
+    };
+    memchr::memchr(b'}', &head[start..]).map_or(0, |i| start + i + 1)
+}
+
+// Drop `//` and `/* */` comments from a small block of text
+pub fn strip_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("//") {
+            rest = after.find('\n').map_or("", |i| &after[i..]);
+        } else if let Some(after) = rest.strip_prefix("/*") {
+            rest = after.find("*/").map_or("", |i| &after[i + 2..]);
+        } else {
+            let c = rest.chars().next().unwrap();
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+// Split on `;` outside of double quotes (arch is "LSB;label=32;scalar=64")
+fn split_entries(body: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (i, c) in body.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => {
+                out.push(&body[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(&body[start..]);
+    out
+}
+
+pub fn parse_header(data: &[u8]) -> Option<FoamHeader> {
+    // The header sits right after the banner comment, so only look at the start
+    let head = &data[..data.len().min(4096)];
+    let text = String::from_utf8_lossy(head);
+    let text = strip_comments(&text);
+
+    let start = text.find("FoamFile")?;
+    let open = start + text[start..].find('{')?;
+    let close = open + text[open..].find('}')?;
+
+    let mut entries = Vec::new();
+    for entry in split_entries(&text[open + 1..close]) {
+        let entry = entry.trim();
+        let mut parts = entry.splitn(2, char::is_whitespace);
+        let key = match parts.next() {
+            Some(k) if !k.is_empty() => k,
+            _ => continue,
+        };
+        let value = parts.next().unwrap_or("").trim().trim_matches('"');
+        entries.push((key.to_string(), value.to_string()));
+    }
+
+    Some(FoamHeader { entries })
+}
+
+/// Parse the FoamFile header of an OpenFOAM file into a dict (version, format,
+/// class, arch, location, object, ...). Returns None if the file is missing or
+/// has no header.
+#[pyfunction]
+#[pyo3(signature = (path, options = None))]
+pub fn parse_foamfile_header(py: Python, path: String, options: Option<Options>) -> PyResult<Option<HashMap<String, String>>> {
+    options::detach(py, &options, || {
+        let data = match open_field_file(&path)? {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+
+        Ok(parse_header(&data).map(|h| h.entries.into_iter().collect()))
+    })
+}
+
+// Exponents of [mass length time temperature moles current luminous-intensity]
+pub type Dimensions = [f64; 7];
+
+// Python-facing form of a dimension set
+pub type DimensionTuple = (f64, f64, f64, f64, f64, f64, f64);
+
+// SI base unit symbols in dimensionSet order
+const SI_UNITS: [&str; 7] = ["kg", "m", "s", "K", "mol", "A", "cd"];
+
+static RE_DIMENSIONS: OnceLock<Regex> = OnceLock::new();
+
+fn get_re_dimensions() -> &'static Regex {
+    RE_DIMENSIONS.get_or_init(|| Regex::new(r"(?m)^\s*dimensions\s+\[([^\]]*)\]").unwrap())
+}
+
+// Parse the body of a dimension set. Both the numeric form `0 2 -2 0 0 0 0`
+// (older files may give only the first five) and the named form `m^2 s^-2`
+// are accepted.
+pub fn parse_dimension_set(body: &str) -> Option<Dimensions> {
+    let mut dims = [0.0; 7];
+    let tokens: Vec<&str> = body.split_whitespace().collect();
+
+    if tokens.iter().all(|t| t.parse::<f64>().is_ok()) {
+        if tokens.len() != 5 && tokens.len() != 7 {
+            return None;
+        }
+        for (d, t) in dims.iter_mut().zip(&tokens) {
+            *d = t.parse().ok()?;
+        }
+        return Some(dims);
+    }
+
+    for token in tokens {
+        let (unit, exp) = match token.split_once('^') {
+            Some((u, e)) => (u, e.parse::<f64>().ok()?),
+            None => (token, 1.0),
+        };
+        let idx = SI_UNITS.iter().position(|u| *u == unit)?;
+        dims[idx] += exp;
+    }
+    Some(dims)
+}
+
+pub fn parse_dimensions(data: &[u8]) -> Option<Dimensions> {
+    // dimensions comes before internalField, so stop there to avoid scanning
+    // the whole value list of a large field
+    let limit = crate::scan::find_keyword(data, 0, b"internalField").unwrap_or(data.len());
+    let head = crate::scan::uncommented(&data[..limit]);
+    let caps = get_re_dimensions().captures(&head)?;
+    let body = std::str::from_utf8(caps.get(1)?.as_bytes()).ok()?;
+    parse_dimension_set(body)
+}
+
+fn format_exponent(e: f64) -> String {
+    if e.fract() == 0.0 {
+        format!("{}", e as i64)
+    } else {
+        format!("{}", e)
+    }
+}
+
+/// Read the `dimensions` entry of a field file as a 7-tuple of SI exponents
+/// (kg, m, s, K, mol, A, cd). Returns None if the file or entry is missing.
+#[pyfunction]
+#[pyo3(signature = (path, options = None))]
+pub fn parse_field_dimensions(py: Python, path: String, options: Option<Options>) -> PyResult<Option<DimensionTuple>> {
+    options::detach(py, &options, || {
+        let data = match open_field_file(&path)? {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+
+        Ok(parse_dimensions(&data).map(|d| (d[0], d[1], d[2], d[3], d[4], d[5], d[6])))
+    })
+}
+
+// Bytes field_info() reads before falling back to the whole file, enough
+// for the header and the internalField tokens of any ordinary field file.
+// The fallback looks no further than this past the internalField keyword.
+const INFO_HEAD_BYTES: u64 = 64 << 10;
+
+/// Metadata of a field file from its header and the tokens before the
+/// internalField values, which aren't read: a dict with `class`, `format`,
+/// `dimensions` (a 7-tuple or None), `components` per value, `uniform`,
+/// `count`, the number of values declared for a nonuniform list (None for a
+/// uniform field or a list written without its count) and the file's `size`
+/// in bytes (compressed size for .gz files). Returns None if the file is
+/// missing or empty.
+#[pyfunction]
+#[pyo3(signature = (path, options = None))]
+pub fn field_info<'py>(py: Python<'py>, path: String, options: Option<Options>) -> PyResult<Option<Bound<'py, PyDict>>> {
+    type Info = (Option<FoamHeader>, Option<Dimensions>, Option<InternalLayout>, u64);
+    let info = options::detach(py, &options, || -> PyResult<Option<Info>> {
+        let Some(resolved) = resolve_field_path(std::path::Path::new(&path)) else {
+            return Ok(None);
+        };
+        let size = std::fs::metadata(&resolved)?.len();
+        let Some((head, whole)) = open_field_head(&path, INFO_HEAD_BYTES)? else {
+            return Ok(None);
+        };
+        let layout = internal_field_layout(&head);
+        if layout.is_some() || whole {
+            return Ok(Some((parse_header(&head), parse_dimensions(&head), layout, size)));
+        }
+        // A header or banner too long for the first read: the file up to the
+        // list head, leaving its values alone
+        let Some(all) = open_field_file(&path)? else {
+            return Ok(None);
+        };
+        let start = scan::find_keyword(&all, 0, b"internalField").unwrap_or_else(|| header_end(&all));
+        let data = &all[..(start + INFO_HEAD_BYTES as usize).min(all.len())];
+        Ok(Some((parse_header(data), parse_dimensions(data), internal_field_layout(data), size)))
+    })?;
+
+    let Some((header, dimensions, layout, size)) = info else {
+        return Ok(None);
+    };
+    let get = |key: &str| header.as_ref().and_then(|h| h.get(key).map(str::to_string));
+    let class = get("class");
+    let out = PyDict::new(py);
+    out.set_item("components", class.as_deref().and_then(field::type_width))?;
+    out.set_item("class", class)?;
+    out.set_item("format", get("format"))?;
+    out.set_item("dimensions", dimensions.map(|d| (d[0], d[1], d[2], d[3], d[4], d[5], d[6])))?;
+    out.set_item("uniform", layout.as_ref().map(|l| matches!(l, InternalLayout::Uniform)))?;
+    out.set_item("count", match layout {
+        Some(InternalLayout::NonUniform(count)) => count,
+        _ => None,
+    })?;
+    out.set_item("size", size)?;
+    Ok(Some(out))
+}
+
+/// Format a dimension set as an SI unit string, e.g. (0, 2, -2, 0, 0, 0, 0)
+/// gives "m^2 s^-2". Dimensionless sets give an empty string.
+#[pyfunction]
+pub fn dimensions_to_units(dimensions: Dimensions) -> String {
+    SI_UNITS
+        .iter()
+        .zip(dimensions)
+        .filter(|(_, e)| *e != 0.0)
+        .map(|(u, e)| if e == 1.0 { u.to_string() } else { format!("{}^{}", u, format_exponent(e)) })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,262 @@
+// Sidecar index of per-time field summaries, kept in <case>/.foamflask/, so
+// reopening a finished case draws its history plots from one small file
+// instead of reparsing every time directory. Each entry carries the field
+// file's mtime and size and is recomputed when they change; entries for
+// files still being written aren't stored.
+
+use numpy::{IntoPyArray, PyArrayMethods};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::cache::{stamp, Stamp};
+use crate::dict::write_atomic;
+use crate::field::mag_sqr;
+use crate::options::{self, Options, Progress};
+use crate::source::{open_field_file, resolve_field_path};
+use crate::stats::RunningStats;
+use crate::time::{field_file_complete, time_dirs};
+use crate::{check_internal_count, find_internal_field, header_width, internal_field_components, is_empty_list};
+
+const INDEX_DIR: &str = ".foamflask";
+const INDEX_FILE: &str = "summary.idx";
+// Bumped whenever the record layout changes; older files are ignored
+const MAGIC: &[u8; 8] = b"FFSIDX01";
+
+// Statistics of one field file: per-component means, and count, min, max
+// and std of the values (scalars) or of their magnitudes
+#[derive(Clone)]
+pub struct Summary {
+    pub means: Vec<f64>,
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+    pub std: f64,
+}
+
+pub fn summarize(path: &Path) -> Option<Summary> {
+    let data = open_field_file(&path.to_string_lossy()).ok()??;
+    let field = find_internal_field(&data)?;
+    check_internal_count(&data, &field).ok()?;
+    if is_empty_list(&field) {
+        let width = header_width(&data).unwrap_or(1);
+        return Some(Summary { means: vec![f64::NAN; width], count: 0, min: f64::NAN, max: f64::NAN, std: f64::NAN });
+    }
+    let (values, width) = internal_field_components(field);
+    if values.is_empty() {
+        return None;
+    }
+    let mut sums = vec![0.0; width];
+    let mut stats = RunningStats::default();
+    for v in values.chunks_exact(width) {
+        for (s, x) in sums.iter_mut().zip(v) {
+            *s += x;
+        }
+        stats.push(if width == 1 { v[0] } else { mag_sqr(v).sqrt() });
+    }
+    let n = stats.count as f64;
+    Some(Summary {
+        means: sums.into_iter().map(|s| s / n).collect(),
+        count: stats.count as u64,
+        min: stats.min,
+        max: stats.max,
+        std: stats.std(),
+    })
+}
+
+// (time directory, field) -> the file's stamp when summarized, and its summary
+type Entries = HashMap<(String, String), (Stamp, Summary)>;
+
+fn index_path(case_root: &Path) -> PathBuf {
+    case_root.join(INDEX_DIR).join(INDEX_FILE)
+}
+
+// Little-endian reader over the index file; None once it runs short
+struct Cursor<'a>(&'a [u8]);
+
+impl Cursor<'_> {
+    fn take(&mut self, n: usize) -> Option<&[u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        Some(f64::from_bits(self.u64()?))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u64()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+}
+
+// Entries of a case's index; an unreadable, truncated or outdated file reads
+// as empty and is rebuilt
+fn load(case_root: &Path) -> Entries {
+    let mut entries = Entries::new();
+    let Ok(data) = std::fs::read(index_path(case_root)) else {
+        return entries;
+    };
+    let Some(body) = data.strip_prefix(MAGIC) else {
+        return entries;
+    };
+    let mut cur = Cursor(body);
+    let mut record = || -> Option<((String, String), (Stamp, Summary))> {
+        let time = cur.string()?;
+        let field = cur.string()?;
+        let mtime = UNIX_EPOCH + Duration::from_nanos(cur.u64()?);
+        let size = cur.u64()?;
+        let count = cur.u64()?;
+        let width = cur.u64()? as usize;
+        let means = (0..width).map(|_| cur.f64()).collect::<Option<Vec<f64>>>()?;
+        let (min, max, std) = (cur.f64()?, cur.f64()?, cur.f64()?);
+        Some(((time, field), ((mtime, size), Summary { means, count, min, max, std })))
+    };
+    let mut parsed = Entries::new();
+    while let Some((key, value)) = record() {
+        parsed.insert(key, value);
+    }
+    if cur.0.is_empty() {
+        entries = parsed;
+    }
+    entries
+}
+
+fn save(case_root: &Path, entries: &Entries) -> std::io::Result<()> {
+    let mut out = MAGIC.to_vec();
+    let put_str = |out: &mut Vec<u8>, s: &str| {
+        out.extend((s.len() as u64).to_le_bytes());
+        out.extend(s.as_bytes());
+    };
+    for ((time, field), ((mtime, size), s)) in entries {
+        let nanos = mtime.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+        put_str(&mut out, time);
+        put_str(&mut out, field);
+        for v in [nanos, *size, s.count, s.means.len() as u64] {
+            out.extend(v.to_le_bytes());
+        }
+        for v in s.means.iter().chain([&s.min, &s.max, &s.std]) {
+            out.extend(v.to_le_bytes());
+        }
+    }
+    std::fs::create_dir_all(case_root.join(INDEX_DIR))?;
+    write_atomic(&index_path(case_root), out)
+}
+
+// Summaries of `field` in every time directory of a case that has it, as
+// (time value, summary), taken from the index where the file is unchanged.
+// The index is updated when anything was recomputed; a case that can't be
+// written to (read-only, say) is summarized all the same.
+pub fn field_summaries(case_root: &Path, field: &str) -> PyResult<Vec<(f64, Summary)>> {
+    let dirs = time_dirs(case_root)?;
+    let mut entries = load(case_root);
+    let use_index = options::current().cache;
+
+    type Looked = (Option<Summary>, Option<(Stamp, Summary)>);
+    let progress = Progress::start(dirs.len());
+    let looked: Vec<Looked> = dirs
+        .par_iter()
+        .map(|(_, name)| {
+            // A cancelled scan skips the remaining files
+            if options::cancelled() {
+                return (None, None);
+            }
+            let path = case_root.join(name).join(field);
+            let Some(before) = resolve_field_path(&path).and_then(|p| stamp(&p)) else {
+                return (None, None);
+            };
+            if let Some((stored, summary)) = entries.get(&(name.clone(), field.to_string())) {
+                if use_index && *stored == before {
+                    return (Some(summary.clone()), None);
+                }
+            }
+            let summary = summarize(&path);
+            let after = resolve_field_path(&path).and_then(|p| stamp(&p));
+            let fresh = match &summary {
+                Some(s) if after == Some(before) && field_file_complete(&path) => Some((before, s.clone())),
+                _ => None,
+            };
+            (summary, fresh)
+        })
+        .inspect(|_| progress.step())
+        .collect();
+    options::check_cancelled()?;
+
+    let mut changed = false;
+    let mut out = Vec::new();
+    for ((t, name), (summary, fresh)) in dirs.iter().zip(looked) {
+        if let Some(fresh) = fresh {
+            entries.insert((name.clone(), field.to_string()), fresh);
+            changed = true;
+        }
+        if let Some(s) = summary {
+            out.push((*t, s));
+        }
+    }
+    // Forget time directories that were deleted
+    let before = entries.len();
+    entries.retain(|(time, _), _| dirs.iter().any(|(_, name)| name == time));
+    changed |= entries.len() != before;
+
+    if changed {
+        let _ = save(case_root, &entries);
+    }
+    Ok(out)
+}
+
+/// Per-time statistics of `field_name` over a case's time directories, for
+/// history plots. Returns a dict of arrays: `times`, `mean` ((N,) for
+/// scalars, (N, components) otherwise), and `count`, `min`, `max` and `std`
+/// of the values, or of their magnitudes for vectors and tensors. Summaries
+/// are kept in an index under <case>/.foamflask/ and only files that are
+/// new or changed since are parsed, so reopening a finished case is quick;
+/// `Options(cache=False)` reparses every file. Time directories without the
+/// field are skipped. With `region`, <time>/<region> is read.
+#[pyfunction]
+#[pyo3(signature = (case_root, field_name, region = None, options = None))]
+pub fn field_history<'py>(
+    py: Python<'py>,
+    case_root: String,
+    field_name: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyDict>> {
+    // A region's fields are indexed under "<region>/<field>"
+    let field = match region {
+        Some(region) => format!("{}/{}", region, field_name),
+        None => field_name,
+    };
+    let summaries = options::detach(py, &options, || field_summaries(Path::new(&case_root), &field))?;
+
+    // The first time step fixes the field rank
+    let width = summaries.first().map_or(1, |(_, s)| s.means.len());
+    let summaries: Vec<(f64, Summary)> = summaries.into_iter().filter(|(_, s)| s.means.len() == width).collect();
+    let column = |f: fn(&Summary) -> f64| summaries.iter().map(|(_, s)| f(s)).collect::<Vec<f64>>();
+    let times: Vec<f64> = summaries.iter().map(|(t, _)| *t).collect();
+    let means: Vec<f64> = summaries.iter().flat_map(|(_, s)| s.means.iter().copied()).collect();
+    let counts: Vec<u64> = summaries.iter().map(|(_, s)| s.count).collect();
+
+    let out = PyDict::new(py);
+    out.set_item("times", times.into_pyarray(py))?;
+    if width == 1 {
+        out.set_item("mean", means.into_pyarray(py))?;
+    } else {
+        out.set_item("mean", means.into_pyarray(py).reshape([summaries.len(), width])?)?;
+    }
+    out.set_item("count", counts.into_pyarray(py))?;
+    out.set_item("min", column(|s| s.min).into_pyarray(py))?;
+    out.set_item("max", column(|s| s.max).into_pyarray(py))?;
+    out.set_item("std", column(|s| s.std).into_pyarray(py))?;
+    Ok(out)
+}
@@ -0,0 +1,337 @@
+use numpy::{IntoPyArray, PyArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rayon::prelude::*;
+use std::path::Path;
+
+use crate::for_each_number;
+use crate::geometry::{add, scale, CellGeometry, FaceGeometry, Vec3};
+use crate::header::{header_end, parse_header};
+use crate::list::{decode_labels, decode_scalars, read_label_list, read_scalar_list, reserve_bounded, skip_ws_comments, ListFormat};
+use crate::mesh::{mesh_dir, PolyMesh};
+use crate::options::{self, Options};
+use crate::source::open_field_file;
+
+// Particle locations as stored in a cloud's positions file
+enum Positions {
+    // Pre-v1706 layout, also written by newer ESI versions alongside
+    // `coordinates`: "(x y z) celli ..."
+    Cartesian { points: Vec<Vec3>, cells: Vec<i64> },
+    // Barycentric tet coordinates: "(a b c d) celli tetFacei tetPti"
+    Barycentric { coordinates: Vec<[f64; 4]>, cells: Vec<i64>, tet_faces: Vec<i64>, tet_points: Vec<i64> },
+}
+
+impl Positions {
+    fn cells(&self) -> &[i64] {
+        match self {
+            Positions::Cartesian { cells, .. } | Positions::Barycentric { cells, .. } => cells,
+        }
+    }
+}
+
+// One particle record of an ASCII positions list: the parenthesised
+// coordinates and the labels after them
+fn ascii_records(body: &[u8]) -> Vec<(Vec<f64>, Vec<f64>)> {
+    let mut records = Vec::new();
+    let mut pos = 0;
+    while let Some(open) = body[pos..].iter().position(|b| *b == b'(').map(|i| pos + i) {
+        let close = body[open..].iter().position(|b| *b == b')').map_or(body.len(), |i| open + i);
+        let next = body[close..].iter().position(|b| *b == b'(').map_or(body.len(), |i| close + i);
+        let mut coords = Vec::with_capacity(4);
+        for_each_number(&body[open + 1..close], |v| coords.push(v));
+        let mut labels = Vec::new();
+        for_each_number(&body[(close + 1).min(next)..next], |v| labels.push(v));
+        records.push((coords, labels));
+        pos = next;
+    }
+    records
+}
+
+fn parse_ascii_positions(body: &[u8], count: usize) -> Result<Positions, String> {
+    let records = ascii_records(body);
+    if records.len() != count {
+        return Err(format!("expected {} particles, found {}", count, records.len()));
+    }
+    let label = |r: &(Vec<f64>, Vec<f64>), i: usize| r.1.get(i).map_or(-1, |&v| v as i64);
+    match records.first().map_or(3, |r| r.0.len()) {
+        3 => Ok(Positions::Cartesian {
+            points: records.iter().map(|r| [r.0[0], r.0[1], r.0[2]]).collect(),
+            cells: records.iter().map(|r| label(r, 0)).collect(),
+        }),
+        4 => Ok(Positions::Barycentric {
+            coordinates: records.iter().map(|r| [r.0[0], r.0[1], r.0[2], r.0[3]]).collect(),
+            cells: records.iter().map(|r| label(r, 0)).collect(),
+            tet_faces: records.iter().map(|r| label(r, 1)).collect(),
+            tet_points: records.iter().map(|r| label(r, 2)).collect(),
+        }),
+        n => Err(format!("particle positions with {} coordinates", n)),
+    }
+}
+
+// Binary records are written one per particle as "(" raw bytes ")". The
+// record size tells the layouts apart: position + celli (old), the
+// positionsCompat1706 struct (ESI), or barycentric coordinates + celli +
+// tetFacei + tetPti.
+fn parse_binary_positions(data: &[u8], start: usize, count: usize, fmt: ListFormat) -> Result<Positions, String> {
+    let (s, l) = (fmt.scalar_bytes, fmt.label_bytes);
+    let layouts = [(3 * s + l, 3), (4 * s + 6 * l, 3), (4 * s + 3 * l, 4)];
+    let records_of = |size: usize| -> Option<Vec<&[u8]>> {
+        let mut records = Vec::with_capacity(reserve_bounded(count, data.len().saturating_sub(start)));
+        let mut pos = start;
+        for _ in 0..count {
+            while data.get(pos).is_some_and(|b| b.is_ascii_whitespace()) {
+                pos += 1;
+            }
+            if data.get(pos) != Some(&b'(') || data.get(pos + 1 + size) != Some(&b')') {
+                return None;
+            }
+            records.push(&data[pos + 1..pos + 1 + size]);
+            pos += size + 2;
+        }
+        Some(records)
+    };
+    for (size, n_coords) in layouts {
+        let Some(records) = records_of(size) else {
+            continue;
+        };
+        let coords: Vec<Vec<f64>> = records.iter().map(|r| decode_scalars(&r[..n_coords * s], s)).collect();
+        let labels: Vec<Vec<i64>> = records.iter().map(|r| decode_labels(&r[n_coords * s..], l)).collect();
+        return Ok(if n_coords == 3 {
+            Positions::Cartesian {
+                points: coords.iter().map(|c| [c[0], c[1], c[2]]).collect(),
+                cells: labels.iter().map(|l| l[0]).collect(),
+            }
+        } else {
+            Positions::Barycentric {
+                coordinates: coords.iter().map(|c| [c[0], c[1], c[2], c[3]]).collect(),
+                cells: labels.iter().map(|l| l[0]).collect(),
+                tet_faces: labels.iter().map(|l| l[1]).collect(),
+                tet_points: labels.iter().map(|l| l[2]).collect(),
+            }
+        });
+    }
+    Err("unrecognised binary particle record size".into())
+}
+
+fn parse_positions(data: &[u8]) -> Result<Positions, String> {
+    let fmt = ListFormat::of(data);
+    let pos = skip_ws_comments(data, header_end(data));
+    let digits = data[pos..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let count: usize = std::str::from_utf8(&data[pos..pos + digits])
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .ok_or("missing particle count")?;
+    let open = skip_ws_comments(data, pos + digits);
+    if data.get(open) != Some(&b'(') {
+        return Err("expected '(' after the particle count".into());
+    }
+    if fmt.binary {
+        parse_binary_positions(data, open + 1, count, fmt)
+    } else {
+        let close = data.iter().rposition(|b| *b == b')').filter(|&c| c > open).ok_or("unterminated particle list")?;
+        parse_ascii_positions(&data[open + 1..close], count)
+    }
+}
+
+// Cartesian locations of barycentric particles. The tet is the cell centre,
+// the face's base point and two consecutive face points, as in OpenFOAM's
+// tetIndices. The base point is taken to be the face's first point, which
+// is what OpenFOAM chooses unless that gives a degenerate tet.
+fn barycentric_to_cartesian(
+    mesh: &PolyMesh,
+    centres: &[Vec3],
+    coordinates: &[[f64; 4]],
+    cells: &[i64],
+    tet_faces: &[i64],
+    tet_points: &[i64],
+) -> Result<Vec<Vec3>, String> {
+    (0..coordinates.len())
+        .into_par_iter()
+        .map(|i| {
+            let (cell, face) = (cells[i], tet_faces[i]);
+            if cell < 0 || cell as usize >= centres.len() || face < 0 || face as usize >= mesh.n_faces() {
+                return Err(format!("particle {} refers to a cell or face outside the mesh", i));
+            }
+            let f = mesh.face(face as usize);
+            let n = f.len();
+            let mut a = (tet_points[i].max(0) as usize) % n;
+            let mut b = (a + 1) % n;
+            if mesh.owner[face as usize] != cell as usize {
+                std::mem::swap(&mut a, &mut b);
+            }
+            let [w0, w1, w2, w3] = coordinates[i];
+            let p = &mesh.points;
+            Ok(add(
+                add(scale(centres[cell as usize], w0), scale(p[f[0]], w1)),
+                add(scale(p[f[a]], w2), scale(p[f[b]], w3)),
+            ))
+        })
+        .collect()
+}
+
+// Width of a cloud field's values from its class, and whether they are labels
+fn field_layout(class: &str) -> Option<(usize, bool)> {
+    let kind = class.trim_start_matches("IOField<").trim_start_matches("Field<").trim_end_matches('>');
+    Some(match kind.trim_end_matches("Field") {
+        "label" => (1, true),
+        "scalar" | "sphericalTensor" => (1, false),
+        "vector" => (3, false),
+        "symmTensor" => (6, false),
+        "tensor" => (9, false),
+        _ => return None,
+    })
+}
+
+enum CloudField {
+    Labels(Vec<i64>),
+    Scalars(Vec<f64>, usize),
+}
+
+// A per-particle field file; None for files that aren't numeric fields
+// (positions, coordinates, string fields, ...)
+fn read_cloud_field(path: &Path) -> Result<Option<CloudField>, String> {
+    let Some(data) = open_field_file(&path.to_string_lossy()).map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    let Some((width, labels)) = parse_header(&data).and_then(|h| h.get("class").and_then(field_layout)) else {
+        return Ok(None);
+    };
+    let field = if labels {
+        read_label_list(&data).map(CloudField::Labels)
+    } else {
+        read_scalar_list(&data, width).map(|v| CloudField::Scalars(v, width))
+    };
+    field.map(Some).ok_or_else(|| format!("{}: malformed or truncated field", path.display()))
+}
+
+/// Names of the clouds written at a time step (the directories under
+/// <time>/lagrangian), sorted.
+#[pyfunction]
+pub fn list_clouds(py: Python, case_root: String, time: String) -> PyResult<Vec<String>> {
+    py.detach(|| {
+        let dir = Path::new(&case_root).join(&time).join("lagrangian");
+        let mut clouds = Vec::new();
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(e) => e,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(clouds),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                clouds.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        clouds.sort();
+        Ok(clouds)
+    })
+}
+
+struct Cloud {
+    positions: Option<Vec<Vec3>>,
+    barycentric: Option<Vec<[f64; 4]>>,
+    cells: Vec<i64>,
+    fields: Vec<(String, CloudField)>,
+}
+
+/// Read a Lagrangian cloud from <time>/lagrangian/<cloud>. Returns a dict
+/// with `positions` (N, 3), `cell` (N,) and `fields`, a dict of the
+/// per-particle fields (d, U, T, origId, ...) as (N,) or (N, components)
+/// arrays; pass `fields` to read only some. ASCII and binary files are
+/// supported, in both the old Cartesian positions format and the
+/// barycentric format of newer versions. Barycentric positions are
+/// converted using constant/polyMesh; without a mesh `positions` is None
+/// and the raw (N, 4) coordinates are under `barycentric`. Returns None if
+/// the cloud has no positions file.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, cloud, fields=None, options = None))]
+pub fn read_lagrangian<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    cloud: String,
+    fields: Option<Vec<String>>,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let root = Path::new(&case_root);
+    let dir = root.join(&time).join("lagrangian").join(&cloud);
+    let cloud = options::detach(py, &options, || -> PyResult<Option<Cloud>> {
+        // ESI writes barycentric "coordinates" and a Cartesian "positions"
+        // copy; the Foundation's "positions" is barycentric
+        let data = match open_field_file(&dir.join("positions").to_string_lossy())? {
+            Some(d) => d,
+            None => match open_field_file(&dir.join("coordinates").to_string_lossy())? {
+                Some(d) => d,
+                None => return Ok(None),
+            },
+        };
+        let positions = parse_positions(&data).map_err(|e| PyValueError::new_err(format!("{}: {}", dir.display(), e)))?;
+        let cells = positions.cells().to_vec();
+        let (points, barycentric) = match positions {
+            Positions::Cartesian { points, .. } => (Some(points), None),
+            Positions::Barycentric { coordinates, cells, tet_faces, tet_points } => {
+                let points = match PolyMesh::load(&mesh_dir(root, None))? {
+                    Some(mesh) => {
+                        let centres = CellGeometry::compute(&mesh, &FaceGeometry::compute(&mesh)).centres;
+                        let points = barycentric_to_cartesian(&mesh, &centres, &coordinates, &cells, &tet_faces, &tet_points)
+                            .map_err(|e| PyValueError::new_err(format!("{}: {}", dir.display(), e)))?;
+                        Some(points)
+                    }
+                    None => None,
+                };
+                (points, Some(coordinates))
+            }
+        };
+
+        let mut names: Vec<String> = match fields {
+            Some(f) => f,
+            None => std::fs::read_dir(&dir)?
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+                .map(|e| e.file_name().to_string_lossy().trim_end_matches(".gz").to_string())
+                .filter(|n| n != "positions" && n != "coordinates")
+                .collect(),
+        };
+        names.sort();
+        names.dedup();
+        let read: Vec<Result<Option<CloudField>, String>> =
+            names.par_iter().map(|n| read_cloud_field(&dir.join(n))).collect();
+        let mut cloud_fields = Vec::new();
+        for (name, field) in names.into_iter().zip(read) {
+            if let Some(f) = field.map_err(PyValueError::new_err)? {
+                cloud_fields.push((name, f));
+            }
+        }
+        Ok(Some(Cloud { positions: points, barycentric, cells, fields: cloud_fields }))
+    })?;
+    let Some(cloud) = cloud else {
+        return Ok(None);
+    };
+
+    let n = cloud.cells.len();
+    let out = PyDict::new(py);
+    let positions = cloud.positions.map(|p| {
+        let flat: Vec<f64> = p.into_iter().flatten().collect();
+        flat.into_pyarray(py).reshape([n, 3])
+    });
+    out.set_item("positions", positions.transpose()?)?;
+    if let Some(b) = cloud.barycentric {
+        let flat: Vec<f64> = b.into_iter().flatten().collect();
+        out.set_item("barycentric", flat.into_pyarray(py).reshape([n, 4])?)?;
+    }
+    out.set_item("cell", cloud.cells.into_pyarray(py))?;
+    let fields = PyDict::new(py);
+    for (name, field) in cloud.fields {
+        match field {
+            CloudField::Labels(v) => fields.set_item(name, v.into_pyarray(py))?,
+            CloudField::Scalars(v, 1) => fields.set_item(name, v.into_pyarray(py))?,
+            CloudField::Scalars(v, w) => {
+                let rows = v.len() / w;
+                fields.set_item(name, v.into_pyarray(py).reshape([rows, w])?)?
+            }
+        }
+    }
+    out.set_item("fields", fields)?;
+    Ok(Some(out))
+}
@@ -1,261 +1,822 @@
+mod aio;
+mod algebra;
+mod batch;
+mod blockmesh;
+mod boundary;
+mod cache;
+mod calc;
+mod case;
+mod chunked;
+mod colormap;
+mod columnar;
+mod convergence;
+mod decimate;
+mod decomposed;
+mod derived;
+mod dict;
+mod downsample;
+mod errors;
+mod export;
+mod field;
+mod geometry;
+mod header;
+mod index;
+mod lagrangian;
+mod list;
+mod log;
+mod mesh;
+mod monitor;
+mod options;
+mod postprocess;
+mod probe;
+mod regions;
+mod render;
+mod runner;
+mod scan;
+mod shutdown;
+mod smooth;
+mod snappy;
+mod source;
+mod spectrum;
+mod stats;
+mod surface;
+mod time;
+mod validate;
+mod vtk;
+mod watch;
+mod zones;
 
 use pyo3::prelude::*;
-use std::fs::File;
-use std::path::Path;
-use memmap2::MmapOptions;
+use numpy::{PyArray1, PyArray2, IntoPyArray, PyArrayMethods};
 use regex::bytes::Regex;
 use std::sync::OnceLock;
-use std::io::Read;
+
+use list::skip_ws_comments;
+use options::{CancelToken, Options};
+use source::open_field_file;
 
 // Pre-compiled regexes
-static RE_INTERNAL_FIELD: OnceLock<Regex> = OnceLock::new();
-static RE_NONUNIFORM: OnceLock<Regex> = OnceLock::new();
-static RE_UNIFORM: OnceLock<Regex> = OnceLock::new();
+static RE_BOUNDARY_FIELD: OnceLock<Regex> = OnceLock::new();
 
-fn get_re_internal_field() -> &'static Regex {
-    RE_INTERNAL_FIELD.get_or_init(|| Regex::new(r"internalField").unwrap())
+fn get_re_boundary_field() -> &'static Regex {
+    RE_BOUNDARY_FIELD.get_or_init(|| Regex::new(r"boundaryField").unwrap())
 }
 
-fn get_re_nonuniform() -> &'static Regex {
-    RE_NONUNIFORM.get_or_init(|| Regex::new(r"nonuniform").unwrap())
+// The internalField entry of a field file, borrowed from the mapped bytes
+pub(crate) enum InternalField<'a> {
+    // The captured value after `uniform`, e.g. `1.5` or `(0 0 1)`
+    Uniform(&'a [u8]),
+    // Everything between the outer parens of the nonuniform list
+    NonUniform(&'a [u8]),
 }
 
-fn get_re_uniform() -> &'static Regex {
-    // uniform <value>; or uniform (<value>);
-    RE_UNIFORM.get_or_init(|| Regex::new(r"uniform\s+([^\s;]+|[^\s;]+\s+[^\s;]+\s+[^\s;]+|\([^\)]+\));").unwrap())
+// The word starting at `pos`
+fn word_at(data: &[u8], pos: usize) -> &[u8] {
+    let len = data[pos..].iter().take_while(|b| b.is_ascii_alphanumeric() || **b == b'_').count();
+    &data[pos..pos + len]
 }
 
-#[pyfunction]
-fn parse_scalar_field(py: Python, path: String) -> PyResult<Option<f64>> {
-    py.allow_threads(|| {
-        let path = Path::new(&path);
-        if !path.exists() {
-            return Ok(None);
+pub(crate) fn find_internal_field(data: &[u8]) -> Option<InternalField<'_>> {
+    let pos = scan::find_keyword(data, 0, b"internalField")?;
+    internal_field_value(data, pos + "internalField".len())
+}
+
+// Tokenise the value of an internalField entry whose keyword ends at `pos`:
+// `uniform <value>;` or `nonuniform List<type> N ( ... )`, with any amount of
+// whitespace and comments between the tokens
+fn internal_field_value(data: &[u8], pos: usize) -> Option<InternalField<'_>> {
+    let pos = skip_ws_comments(data, pos);
+    let kind = word_at(data, pos);
+    let pos = skip_ws_comments(data, pos + kind.len());
+    match kind {
+        b"uniform" => {
+            let len = match data.get(pos)? {
+                b'(' => list::matching_paren(data, pos)? + 1 - pos,
+                _ => data[pos..].iter().take_while(|b| !b.is_ascii_whitespace() && !matches!(b, b';' | b'/')).count(),
+            };
+            (len > 0).then(|| InternalField::Uniform(&data[pos..pos + len]))
         }
+        b"nonuniform" => {
+            let (count, start) = nonuniform_head(data, pos)?;
+
+            // `N{value}`, N copies of one value, as OpenFOAM writes a list
+            // whose values are all equal (`0{0}` when it's empty)
+            if data[start] == b'{' {
+                let close = start + memchr::memchr(b'}', &data[start..])?;
+                return match count? {
+                    0 => Some(InternalField::NonUniform(&data[start + 1..start + 1])),
+                    _ if list::ListFormat::of(data).binary => None,
+                    _ => Some(InternalField::Uniform(data[start + 1..close].trim_ascii())),
+                };
+            }
+
+            // Binary bodies can contain any byte, and a truncated ASCII list
+            // has no matching paren; both end at the last ')' before
+            // boundaryField
+            let last_paren = || {
+                let end_limit = match get_re_boundary_field().find_at(data, start) {
+                    Some(b_mat) => b_mat.start(),
+                    None => data.len(),
+                };
+                memchr::memrchr(b')', &data[start..end_limit]).map(|i| start + i)
+            };
+            let end = if list::ListFormat::of(data).binary {
+                last_paren()?
+            } else {
+                list::matching_paren(data, start).or_else(last_paren)?
+            };
 
-        let file = File::open(path)?;
-        // Check if file is empty
-        if file.metadata()?.len() == 0 {
-            return Ok(None);
+            Some(InternalField::NonUniform(&data[start + 1..end]))
         }
+        _ => None,
+    }
+}
 
-        let mmap = unsafe { MmapOptions::new().map(&file)? };
-
-        // 1. Search for internalField
-        let re_int = get_re_internal_field();
-        if let Some(mat) = re_int.find(&mmap) {
-            let start_search = mat.end();
-            let search_window = &mmap[start_search..std::cmp::min(start_search + 500, mmap.len())];
-
-            // 2. Check for nonuniform
-            let re_non = get_re_nonuniform();
-            if let Some(non_mat) = re_non.find(search_window) {
-                // Find list start '('
-                // We search from where nonuniform ended in the window
-                let offset = start_search + non_mat.end();
-
-                // Search for '('
-                let mut paren_start = None;
-                for i in offset..mmap.len() {
-                    if mmap[i] == b'(' {
-                        paren_start = Some(i);
-                        break;
-                    }
-                }
+// Check a nonuniform internalField against its declared count; the values
+// are counted without parsing them. Uniform fields and lists without a
+// count pass.
+pub(crate) fn check_internal_count(data: &[u8], field: &InternalField<'_>) -> PyResult<()> {
+    let InternalField::NonUniform(list) = field else {
+        return Ok(());
+    };
+    // The count ends just before the '(' that precedes the list body
+    let start = (list.as_ptr() as usize - data.as_ptr() as usize).saturating_sub(1);
+    let head = scan::uncommented(&data[..start]);
+    let before = head.trim_ascii_end();
+    let digits = before.iter().rev().take_while(|b| b.is_ascii_digit()).count();
+    let Some(expected) = std::str::from_utf8(&before[before.len() - digits..]).ok().and_then(|s| s.parse::<usize>().ok()) else {
+        return Ok(());
+    };
+
+    let fmt = list::ListFormat::of(data);
+    let found = if fmt.binary {
+        list.len() / (fmt.scalar_bytes * header_width(data).unwrap_or(1))
+    } else {
+        let width = tuple_width(list).max(1);
+        let count_tokens = |chunk: &[u8]| {
+            chunk
+                .split(|b| matches!(*b, b' ' | b'\n' | b'\t' | b'\r' | b'(' | b')'))
+                .filter(|t| t.first().is_some_and(|b| b.is_ascii_digit() || matches!(b, b'-' | b'+' | b'.' | b'n' | b'N' | b'i' | b'I')))
+                .count()
+        };
+        chunked::fold_chunks(list, width, count_tokens, |a, b| a + b) / width
+    };
+    if found == expected {
+        return Ok(());
+    }
+    let message = format!(
+        "internalField declares {} values but {} were read{}",
+        expected,
+        found,
+        if found < expected { " (truncated or still being written?)" } else { "" }
+    );
+    Err(errors::count_error(message, expected, found))
+}
+
+// `[List<type>] [N] (` following `nonuniform` at `pos`: the count, when one
+// is written, and the offset of the '(', or of the '{' of `N{value}`
+fn nonuniform_head(data: &[u8], mut pos: usize) -> Option<(Option<usize>, usize)> {
+    if data[pos..].starts_with(b"List<") {
+        pos = skip_ws_comments(data, pos + data[pos..].iter().position(|b| *b == b'>')? + 1);
+    }
+    // The count is optional here; check_internal_count validates it
+    let digits = data[pos..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let count = std::str::from_utf8(&data[pos..pos + digits]).ok().and_then(|d| d.parse().ok());
+    let start = skip_ws_comments(data, pos + digits);
+    matches!(data.get(start), Some(b'(' | b'{')).then_some((count, start))
+}
+
+// How an internalField is stored, as far as the tokens before its value tell
+pub(crate) enum InternalLayout {
+    Uniform,
+    // With the count written before the list, if any
+    NonUniform(Option<usize>),
+}
+
+// The internalField's layout, found without touching a list body
+pub(crate) fn internal_field_layout(data: &[u8]) -> Option<InternalLayout> {
+    let pos = scan::find_keyword(data, 0, b"internalField")?;
+    let pos = skip_ws_comments(data, pos + "internalField".len());
+    let kind = word_at(data, pos);
+    match kind {
+        b"uniform" => Some(InternalLayout::Uniform),
+        b"nonuniform" => {
+            let (count, _) = nonuniform_head(data, skip_ws_comments(data, pos + kind.len()))?;
+            Some(InternalLayout::NonUniform(count))
+        }
+        _ => None,
+    }
+}
+
+// An empty nonuniform list, `List<scalar> 0()` or the compact `0()`, as
+// written for processors or zones without cells. Readers return NaN means
+// and empty arrays for it rather than the None of a missing file.
+pub(crate) fn is_empty_list(field: &InternalField<'_>) -> bool {
+    matches!(field, InternalField::NonUniform(content) if content.trim_ascii().is_empty())
+}
+
+// Components per value from the header class, for lists with no values to
+// tell from
+pub(crate) fn header_width(data: &[u8]) -> Option<usize> {
+    header::parse_header(data).and_then(|h| h.get("class").and_then(field::type_width))
+}
+
+// Fast ASCII float parsing over a list body. Parens are treated as separators so
+// vector lists come out as a flat x, y, z, x, y, z ... sequence.
+pub(crate) fn for_each_number<F: FnMut(f64)>(list_content: &[u8], mut f: F) {
+    for chunk in scan::uncommented(list_content).split(|b| matches!(*b, b' ' | b'\n' | b'\t' | b'\r' | b'(' | b')')) {
+        if chunk.is_empty() {
+            continue;
+        }
+        // Check if it looks like a number
+        // nan and inf are kept too so a diverged field doesn't shift the
+        // components of the values after it
+        if chunk[0].is_ascii_digit() || matches!(chunk[0], b'-' | b'+' | b'.' | b'n' | b'N' | b'i' | b'I') {
+            if let Ok(val) = fast_float2::parse::<f64, _>(chunk) {
+                f(val);
+            }
+        }
+    }
+}
+
+pub(crate) fn parse_uniform_scalar(value: &[u8]) -> Option<f64> {
+    fast_float2::parse(value).ok()
+}
+
+// Visit every scalar value of an internalField; a uniform value is visited once
+pub(crate) fn for_each_scalar<F: FnMut(f64)>(field: &InternalField<'_>, mut f: F) {
+    match field {
+        InternalField::NonUniform(list_content) => for_each_number(list_content, f),
+        InternalField::Uniform(value) => {
+            if let Some(val) = parse_uniform_scalar(value) {
+                f(val);
+            }
+        }
+    }
+}
+
+pub(crate) fn parse_uniform_vector(value: &[u8]) -> Option<(f64, f64, f64)> {
+    let s = std::str::from_utf8(value).unwrap_or("");
+    // remove parens
+    let clean = s.replace(['(', ')'], "");
+    let parts: Vec<&str> = clean.split_whitespace().collect();
+    if parts.len() == 3 {
+        let x = parts[0].parse::<f64>().unwrap_or(0.0);
+        let y = parts[1].parse::<f64>().unwrap_or(0.0);
+        let z = parts[2].parse::<f64>().unwrap_or(0.0);
+        return Some((x, y, z));
+    }
+    None
+}
+
+// Number of components per value, taken from the first `( ... )` group of a
+// nonuniform list or from a uniform value. Scalars have no parens and give 1.
+pub(crate) fn tuple_width(content: &[u8]) -> usize {
+    let content = &*scan::uncommented(content);
+    let open = match content.iter().position(|b| *b == b'(') {
+        Some(i) => i,
+        None => return 1,
+    };
+    let close = content[open..].iter().position(|b| *b == b')').map_or(content.len(), |i| open + i);
+    let mut n = 0;
+    for_each_number(&content[open + 1..close], |_| n += 1);
+    n
+}
+
+// Flat component values of the internalField plus the detected tuple width.
+// Partial trailing tuples from a truncated file are dropped.
+pub(crate) fn internal_field_components(field: InternalField<'_>) -> (Vec<f64>, usize) {
+    let content = match field {
+        InternalField::NonUniform(c) | InternalField::Uniform(c) => c,
+    };
+    let width = tuple_width(content).max(1);
+    let mut values = chunked::parse_values(content, width);
+    values.truncate(values.len() - values.len() % width);
+    (values, width)
+}
+
+// Rows a uniform value stands for: N for `N{value}`, else one, since a
+// `uniform` entry doesn't record the cell count
+fn uniform_repeats(data: &[u8], field: Option<&InternalField<'_>>) -> usize {
+    match (field, internal_field_layout(data)) {
+        (Some(InternalField::Uniform(_)), Some(InternalLayout::NonUniform(Some(n)))) => n,
+        _ => 1,
+    }
+}
+
+// `values` repeated `n` times; the count comes from the file, so an
+// impossible one is a parse error rather than an abort
+fn repeat_rows(values: Vec<f64>, n: usize) -> PyResult<Vec<f64>> {
+    if n == 1 {
+        return Ok(values);
+    }
+    let mut out = Vec::new();
+    out.try_reserve_exact(values.len().saturating_mul(n))
+        .map_err(|_| errors::parse_error(format!("internalField declares {} values, too many to hold", n)))?;
+    for _ in 0..n {
+        out.extend_from_slice(&values);
+    }
+    Ok(out)
+}
+
+// Per-component means of an internalField of any rank, streamed without
+// collecting the values
+pub(crate) fn internal_field_means(field: &InternalField<'_>) -> Option<Vec<f64>> {
+    let content = match field {
+        InternalField::NonUniform(c) | InternalField::Uniform(c) => c,
+    };
+    let width = tuple_width(content).max(1);
+    // Chunks start at component 0, so each one can keep its own index
+    let chunk_sums = |chunk: &[u8]| {
+        let mut sums = vec![0.0; width];
+        let mut idx = 0;
+        let mut count = 0;
+        for_each_number(chunk, |val| {
+            sums[idx] += val;
+            idx += 1;
+            if idx == width {
+                idx = 0;
+                count += 1;
+            }
+        });
+        (sums, count)
+    };
+    let merge = |(mut a, n): (Vec<f64>, usize), (b, m): (Vec<f64>, usize)| {
+        a.iter_mut().zip(&b).for_each(|(x, y)| *x += y);
+        (a, n + m)
+    };
+    let (sums, count) = chunked::fold_chunks(content, width, chunk_sums, merge);
+
+    if count == 0 {
+        return None;
+    }
+    Some(sums.into_iter().map(|s| s / count as f64).collect())
+}
+
+// Components per value of an internalField: the header's class, else the
+// shape of the value itself
+pub(crate) fn field_width(data: &[u8], field: &InternalField<'_>) -> usize {
+    header_width(data).unwrap_or_else(|| match field {
+        InternalField::NonUniform(c) | InternalField::Uniform(c) => tuple_width(c).max(1),
+    })
+}
+
+// Per-value magnitudes of a vector or tensor internalField
+fn internal_field_magnitudes(field: InternalField<'_>) -> Vec<f64> {
+    let (values, width) = internal_field_components(field);
+    values.chunks_exact(width).map(|v| field::mag_sqr(v).sqrt()).collect()
+}
+
+pub(crate) fn check_width(width: usize, expected: &[usize], kind: &str) -> PyResult<()> {
+    if expected.contains(&width) {
+        Ok(())
+    } else {
+        Err(errors::parse_error(format!(
+            "expected a {} field, found {} components per value", kind, width
+        )))
+    }
+}
+
+fn component_means(values: &[f64], width: usize) -> Vec<f64> {
+    let mut sums = vec![0.0; width];
+    for tuple in values.chunks_exact(width) {
+        for (s, v) in sums.iter_mut().zip(tuple) {
+            *s += v;
+        }
+    }
+    let n = (values.len() / width) as f64;
+    sums.into_iter().map(|s| s / n).collect()
+}
 
-                if let Some(start) = paren_start {
-                    // Find matching ')'
-                    // Usually before boundaryField
-                    // For speed, let's just search for the last ')' before EOF or before "boundaryField"
-                    // But robustly, we should scan forward.
-                    // Assuming well-formed file.
-
-                    // Let's find "boundaryField"
-                    let boundary_re = Regex::new(r"boundaryField").unwrap();
-                    let end_limit = if let Some(b_mat) = boundary_re.find_at(&mmap, start) {
-                        b_mat.start()
-                    } else {
-                        mmap.len()
-                    };
-
-                    // Find last ')' in range
-                    let mut paren_end = None;
-                    for i in (start..end_limit).rev() {
-                        if mmap[i] == b')' {
-                            paren_end = Some(i);
-                            break;
-                        }
-                    }
-
-                    if let Some(end) = paren_end {
-                        let list_content = &mmap[start+1..end];
-                        // Parse numbers (simulating np.mean)
-                        // We can iterate and parse.
-                        // This is potentially faster than allocating a string and calling split
-
-                        let mut sum = 0.0;
-                        let mut count = 0;
-
-                        // Fast ASCII float parsing
-                        for chunk in list_content.split(|b| *b == b' ' || *b == b'\n' || *b == b'\t' || *b == b'\r') {
-                            if !chunk.is_empty() {
-                                // Check if it looks like a number
-                                if chunk[0].is_ascii_digit() || chunk[0] == b'-' || chunk[0] == b'+' || chunk[0] == b'.' {
-                                    // unsafe from_utf8_unchecked is fine if we trust split
-                                    if let Ok(s) = std::str::from_utf8(chunk) {
-                                         if let Ok(val) = s.parse::<f64>() {
-                                             sum += val;
-                                             count += 1;
-                                         }
-                                    }
-                                }
-                            }
-                        }
-
-                        if count > 0 {
-                            return Ok(Some(sum / count as f64));
-                        }
-                    }
+/// Mean of a scalar field's internalField, NaN for an empty list (`0()`).
+/// Raises FieldNotFound for a missing file, UnsupportedFormat for a binary
+/// one and FieldParseError when no value can be read; with `lenient=True`
+/// those return None instead. A vector or tensor field (by its header class)
+/// raises TypeError, or None when lenient, unless `allow_magnitude=True`,
+/// which gives the mean of the values' magnitudes. See Options for
+/// `options`.
+#[pyfunction]
+#[pyo3(signature = (path, lenient = false, allow_magnitude = false, options = None))]
+fn parse_scalar_field(py: Python, path: String, lenient: bool, allow_magnitude: bool, options: Option<Options>) -> PyResult<Option<f64>> {
+    let lenient = options::lenient(&options, lenient);
+    options::detach(py, &options, || cache::cached("parse_scalar_field", &path, (lenient, allow_magnitude), || {
+        let data = match open_field_file(&path)? {
+            Some(m) => m,
+            None if lenient => return Ok(None),
+            None => return Err(errors::missing_field(&path)),
+        };
+        if !lenient {
+            errors::check_ascii(&path, &data)?;
+        }
+
+        let field = find_internal_field(&data);
+        if let Some(f) = &field {
+            check_internal_count(&data, f)?;
+        }
+        let width = field.as_ref().map_or(1, |f| field_width(&data, f));
+        if width > 1 && !allow_magnitude {
+            return if lenient { Ok(None) } else { Err(errors::not_scalar(&path, width)) };
+        }
+        match field {
+            Some(f) if is_empty_list(&f) => return Ok(Some(f64::NAN)),
+            Some(f) if width > 1 => {
+                let mags = internal_field_magnitudes(f);
+                if !mags.is_empty() {
+                    return Ok(Some(mags.iter().sum::<f64>() / mags.len() as f64));
                 }
-            } else {
-                // Check for uniform
-                let re_uni = get_re_uniform();
-                if let Some(caps) = re_uni.captures(search_window) {
-                     if let Some(val_match) = caps.get(1) {
-                         if let Ok(s) = std::str::from_utf8(val_match.as_bytes()) {
-                             if let Ok(val) = s.parse::<f64>() {
-                                 return Ok(Some(val));
-                             }
-                         }
-                     }
+            }
+            Some(InternalField::NonUniform(list_content)) => {
+                // Parse numbers (simulating np.mean)
+                let chunk_sum = |chunk: &[u8]| {
+                    let mut sum = 0.0;
+                    let mut count = 0;
+                    for_each_number(chunk, |val| {
+                        sum += val;
+                        count += 1;
+                    });
+                    (sum, count)
+                };
+                let (sum, count) = chunked::fold_chunks(list_content, 1, chunk_sum, |a, b| (a.0 + b.0, a.1 + b.1));
+
+                if count > 0 {
+                    return Ok(Some(sum / count as f64));
+                }
+            }
+            Some(InternalField::Uniform(value)) => {
+                if let Some(val) = parse_uniform_scalar(value) {
+                    return Ok(Some(val));
                 }
             }
+            None => {}
         }
 
-        Ok(None)
-    })
+        errors::unreadable_field(&path, lenient, None)
+    }))
 }
 
+/// Read the full internalField of a scalar field as a 1-D float64 array.
+/// A uniform field yields a single-element array since the file does not
+/// record the cell count, `N{value}` N copies of the value, and an empty
+/// list (`0()`) an empty array. Errors
+/// and `options` as parse_scalar_field; `lenient=True` returns None
+/// instead. With `allow_magnitude=True` a vector or tensor field gives its
+/// magnitudes.
 #[pyfunction]
-fn parse_vector_field(py: Python, path: String) -> PyResult<(f64, f64, f64)> {
-    py.allow_threads(|| {
-        let path = Path::new(&path);
-        if !path.exists() {
-            return Ok((0.0, 0.0, 0.0));
+#[pyo3(signature = (path, lenient = false, allow_magnitude = false, options = None))]
+fn read_scalar_field<'py>(py: Python<'py>, path: String, lenient: bool, allow_magnitude: bool, options: Option<Options>) -> PyResult<Option<Bound<'py, PyArray1<f64>>>> {
+    let lenient = options::lenient(&options, lenient);
+    let values = options::detach(py, &options, || cache::cached("read_scalar_field", &path, (lenient, allow_magnitude), || -> PyResult<Option<Vec<f64>>> {
+        let data = match open_field_file(&path)? {
+            Some(m) => m,
+            None if lenient => return Ok(None),
+            None => return Err(errors::missing_field(&path)),
+        };
+        if !lenient {
+            errors::check_ascii(&path, &data)?;
         }
 
-        let file = File::open(path)?;
-        if file.metadata()?.len() == 0 {
-            return Ok((0.0, 0.0, 0.0));
+        let field = find_internal_field(&data);
+        if let Some(f) = &field {
+            check_internal_count(&data, f)?;
+        }
+        let width = field.as_ref().map_or(1, |f| field_width(&data, f));
+        if width > 1 && !allow_magnitude {
+            return if lenient { Ok(None) } else { Err(errors::not_scalar(&path, width)) };
         }
+        let repeats = uniform_repeats(&data, field.as_ref());
+        let values = match field {
+            Some(f) if width > 1 => internal_field_magnitudes(f),
+            Some(InternalField::NonUniform(list_content)) => chunked::parse_values(list_content, 1),
+            Some(InternalField::Uniform(value)) => match parse_uniform_scalar(value) {
+                Some(v) => vec![v],
+                None => return errors::unreadable_field(&path, lenient, None),
+            },
+            None => return errors::unreadable_field(&path, lenient, None),
+        };
+        repeat_rows(values, repeats).map(Some)
+    }))?;
+
+    // The Vec is handed over to NumPy without copying
+    Ok(values.map(|v| v.into_pyarray(py)))
+}
 
-        let mmap = unsafe { MmapOptions::new().map(&file)? };
-
-        let re_int = get_re_internal_field();
-        if let Some(mat) = re_int.find(&mmap) {
-            let start_search = mat.end();
-            let search_window = &mmap[start_search..std::cmp::min(start_search + 500, mmap.len())];
-
-            let re_non = get_re_nonuniform();
-            if let Some(non_mat) = re_non.find(search_window) {
-                 let offset = start_search + non_mat.end();
-                 let mut paren_start = None;
-                 for i in offset..mmap.len() {
-                    if mmap[i] == b'(' {
-                        paren_start = Some(i);
-                        break;
-                    }
+/// Mean of a vector field's internalField, NaNs for an empty list (`0()`).
+/// Errors and `options` as parse_scalar_field; `lenient=True` returns
+/// (0, 0, 0) instead, as this function did before it raised.
+#[pyfunction]
+#[pyo3(signature = (path, lenient = false, options = None))]
+fn parse_vector_field(py: Python, path: String, lenient: bool, options: Option<Options>) -> PyResult<(f64, f64, f64)> {
+    let lenient = options::lenient(&options, lenient);
+    options::detach(py, &options, || cache::cached("parse_vector_field", &path, lenient, || {
+        let data = match open_field_file(&path)? {
+            Some(m) => m,
+            None if lenient => return Ok((0.0, 0.0, 0.0)),
+            None => return Err(errors::missing_field(&path)),
+        };
+        if !lenient {
+            errors::check_ascii(&path, &data)?;
+        }
+
+        let field = find_internal_field(&data);
+        if let Some(f) = &field {
+            check_internal_count(&data, f)?;
+        }
+        match field {
+            Some(f) if is_empty_list(&f) => return Ok((f64::NAN, f64::NAN, f64::NAN)),
+            Some(field @ InternalField::NonUniform(list_content)) => {
+                // A tensor file would otherwise be silently regrouped into triples
+                if let Err(e) = check_width(tuple_width(list_content), &[3], "vector") {
+                    return if lenient { Ok((0.0, 0.0, 0.0)) } else { Err(e) };
+                }
+                if let Some(m) = internal_field_means(&field) {
+                    return Ok((m[0], m[1], m[2]));
+                }
+            }
+            Some(InternalField::Uniform(value)) => {
+                // uniform (<val> <val> <val>);
+                if let Some(v) = parse_uniform_vector(value) {
+                    return Ok(v);
                 }
+            }
+            None => {}
+        }
+
+        errors::unreadable_field(&path, lenient, (0.0, 0.0, 0.0))
+    }))
+}
 
-                if let Some(start) = paren_start {
-                     // Find boundaryField
-                    let boundary_re = Regex::new(r"boundaryField").unwrap();
-                    let end_limit = if let Some(b_mat) = boundary_re.find_at(&mmap, start) {
-                        b_mat.start()
-                    } else {
-                        mmap.len()
-                    };
-
-                    let mut paren_end = None;
-                    for i in (start..end_limit).rev() {
-                        if mmap[i] == b')' {
-                            paren_end = Some(i);
-                            break;
-                        }
-                    }
-
-                    if let Some(end) = paren_end {
-                        let list_content = &mmap[start+1..end];
-
-                        let mut sum_x = 0.0;
-                        let mut sum_y = 0.0;
-                        let mut sum_z = 0.0;
-                        let mut count = 0;
-
-                        // Vectors are (x y z)
-                        // We can split by ')' to get chunks like "(x y z" (preceding '(' is gone if we split by space)
-                        // Actually, simpler to just parse all numbers and group by 3.
-
-                        // Replace '(' and ')' with space (virtually) and split
-                        // Since we are iterating, we can just skip parens
-
-                        let mut val_idx = 0; // 0=x, 1=y, 2=z
-
-                        for chunk in list_content.split(|b| *b == b' ' || *b == b'\n' || *b == b'\t' || *b == b'\r' || *b == b'(' || *b == b')') {
-                             if !chunk.is_empty() {
-                                if chunk[0].is_ascii_digit() || chunk[0] == b'-' || chunk[0] == b'+' || chunk[0] == b'.' {
-                                    if let Ok(s) = std::str::from_utf8(chunk) {
-                                         if let Ok(val) = s.parse::<f64>() {
-                                             match val_idx {
-                                                 0 => sum_x += val,
-                                                 1 => sum_y += val,
-                                                 2 => {
-                                                     sum_z += val;
-                                                     count += 1;
-                                                 }
-                                                 _ => {}
-                                             }
-                                             val_idx = (val_idx + 1) % 3;
-                                         }
-                                    }
-                                }
-                             }
-                        }
-
-                        if count > 0 {
-                            let n = count as f64;
-                            return Ok((sum_x / n, sum_y / n, sum_z / n));
-                        }
-                    }
+/// Read the full internalField of a vector field as an (N, 3) float64 array.
+/// A uniform field yields a (1, 3) array, `N{value}` an (N, 3) array and an
+/// empty list (`0()`) a (0, 3) array. Errors and `options` as parse_scalar_field; `lenient=True`
+/// returns None instead.
+#[pyfunction]
+#[pyo3(signature = (path, lenient = false, options = None))]
+fn read_vector_field<'py>(py: Python<'py>, path: String, lenient: bool, options: Option<Options>) -> PyResult<Option<Bound<'py, PyArray2<f64>>>> {
+    let lenient = options::lenient(&options, lenient);
+    let values = options::detach(py, &options, || cache::cached("read_vector_field", &path, lenient, || -> PyResult<Option<Vec<f64>>> {
+        let data = match open_field_file(&path)? {
+            Some(m) => m,
+            None if lenient => return Ok(None),
+            None => return Err(errors::missing_field(&path)),
+        };
+        if !lenient {
+            errors::check_ascii(&path, &data)?;
+        }
+
+        let field = find_internal_field(&data);
+        if let Some(f) = &field {
+            check_internal_count(&data, f)?;
+        }
+        let repeats = uniform_repeats(&data, field.as_ref());
+        match field {
+            Some(f) if is_empty_list(&f) => Ok(Some(Vec::new())),
+            Some(field) => {
+                let (values, width) = internal_field_components(field);
+                if let Err(e) = check_width(width, &[3], "vector") {
+                    return if lenient { Ok(None) } else { Err(e) };
                 }
+                repeat_rows(values, repeats).map(Some)
+            }
+            None => errors::unreadable_field(&path, lenient, None),
+        }
+    }))?;
 
-            } else {
-                 // uniform (<val> <val> <val>);
-                 let re_uni = get_re_uniform();
-                 if let Some(caps) = re_uni.captures(search_window) {
-                     if let Some(val_match) = caps.get(1) {
-                         let s = std::str::from_utf8(val_match.as_bytes()).unwrap_or("");
-                         // remove parens
-                         let clean = s.replace("(", "").replace(")", "");
-                         let parts: Vec<&str> = clean.split_whitespace().collect();
-                         if parts.len() == 3 {
-                             let x = parts[0].parse::<f64>().unwrap_or(0.0);
-                             let y = parts[1].parse::<f64>().unwrap_or(0.0);
-                             let z = parts[2].parse::<f64>().unwrap_or(0.0);
-                             return Ok((x, y, z));
-                         }
-                     }
-                 }
+    match values {
+        Some(v) => {
+            let rows = v.len() / 3;
+            // Reshaping the contiguous buffer is a view, not a copy
+            Ok(Some(v.into_pyarray(py).reshape([rows, 3])?))
+        }
+        None => Ok(None),
+    }
+}
+
+// volTensorField values carry 9 components, volSymmTensorField values 6
+const TENSOR_WIDTHS: [usize; 2] = [9, 6];
+
+// Width of an empty tensor list: the header's class, else a full tensor
+fn empty_tensor_width(data: &[u8]) -> usize {
+    header_width(data).filter(|w| TENSOR_WIDTHS.contains(w)).unwrap_or(9)
+}
+
+/// Per-component means of a tensor or symmTensor field (9 or 6 values, in
+/// OpenFOAM component order), NaNs for an empty list (`0()`). Errors and
+/// `options` as parse_scalar_field; `lenient=True` returns None instead.
+#[pyfunction]
+#[pyo3(signature = (path, lenient = false, options = None))]
+fn parse_tensor_field(py: Python, path: String, lenient: bool, options: Option<Options>) -> PyResult<Option<Vec<f64>>> {
+    let lenient = options::lenient(&options, lenient);
+    options::detach(py, &options, || cache::cached("parse_tensor_field", &path, lenient, || {
+        let data = match open_field_file(&path)? {
+            Some(m) => m,
+            None if lenient => return Ok(None),
+            None => return Err(errors::missing_field(&path)),
+        };
+        if !lenient {
+            errors::check_ascii(&path, &data)?;
+        }
+
+        let field = find_internal_field(&data);
+        if let Some(f) = &field {
+            check_internal_count(&data, f)?;
+        }
+        match field {
+            Some(f) if is_empty_list(&f) => Ok(Some(vec![f64::NAN; empty_tensor_width(&data)])),
+            Some(field) => {
+                let (values, width) = internal_field_components(field);
+                check_width(width, &TENSOR_WIDTHS, "tensor or symmTensor")?;
+                if values.is_empty() {
+                    return errors::unreadable_field(&path, lenient, None);
+                }
+                Ok(Some(component_means(&values, width)))
             }
+            None => errors::unreadable_field(&path, lenient, None),
         }
+    }))
+}
 
-        Ok((0.0, 0.0, 0.0))
-    })
+/// Read the full internalField of a tensor or symmTensor field as an (N, 9)
+/// or (N, 6) float64 array; an empty list (`0()`) yields a (0, 9) or (0, 6)
+/// array. Errors and `options` as parse_scalar_field; `lenient=True`
+/// returns None instead.
+#[pyfunction]
+#[pyo3(signature = (path, lenient = false, options = None))]
+fn read_tensor_field<'py>(py: Python<'py>, path: String, lenient: bool, options: Option<Options>) -> PyResult<Option<Bound<'py, PyArray2<f64>>>> {
+    let lenient = options::lenient(&options, lenient);
+    let values = options::detach(py, &options, || cache::cached("read_tensor_field", &path, lenient, || -> PyResult<Option<(Vec<f64>, usize)>> {
+        let data = match open_field_file(&path)? {
+            Some(m) => m,
+            None if lenient => return Ok(None),
+            None => return Err(errors::missing_field(&path)),
+        };
+        if !lenient {
+            errors::check_ascii(&path, &data)?;
+        }
+
+        let field = find_internal_field(&data);
+        if let Some(f) = &field {
+            check_internal_count(&data, f)?;
+        }
+        match field {
+            Some(f) if is_empty_list(&f) => Ok(Some((Vec::new(), empty_tensor_width(&data)))),
+            Some(field) => {
+                let (values, width) = internal_field_components(field);
+                check_width(width, &TENSOR_WIDTHS, "tensor or symmTensor")?;
+                Ok(Some((values, width)))
+            }
+            None => errors::unreadable_field(&path, lenient, None),
+        }
+    }))?;
+
+    match values {
+        Some((v, width)) => {
+            let rows = v.len() / width;
+            Ok(Some(v.into_pyarray(py).reshape([rows, width])?))
+        }
+        None => Ok(None),
+    }
 }
 
 #[pymodule]
 fn accelerator(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Options>()?;
+    m.add_class::<CancelToken>()?;
+    m.add("ParseOptions", m.py().get_type::<Options>())?;
+    m.add("FieldNotFound", m.py().get_type::<errors::FieldNotFound>())?;
+    m.add("Cancelled", m.py().get_type::<errors::Cancelled>())?;
+    m.add_class::<errors::FieldParseError>()?;
+    m.add_class::<errors::UnsupportedFormat>()?;
+    m.add_class::<errors::FieldCountError>()?;
+    m.add_class::<case::Case>()?;
+    m.add_class::<case::Field>()?;
+    m.add_function(wrap_pyfunction!(cache::cache_clear, m)?)?;
+    m.add_function(wrap_pyfunction!(cache::cache_info, m)?)?;
     m.add_function(wrap_pyfunction!(parse_scalar_field, m)?)?;
+    m.add_function(wrap_pyfunction!(read_scalar_field, m)?)?;
     m.add_function(wrap_pyfunction!(parse_vector_field, m)?)?;
+    m.add_function(wrap_pyfunction!(read_vector_field, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_tensor_field, m)?)?;
+    m.add_function(wrap_pyfunction!(read_tensor_field, m)?)?;
+    m.add_function(wrap_pyfunction!(aio::parse_scalar_field_async, m)?)?;
+    m.add_function(wrap_pyfunction!(aio::read_scalar_field_async, m)?)?;
+    m.add_function(wrap_pyfunction!(aio::parse_vector_field_async, m)?)?;
+    m.add_function(wrap_pyfunction!(aio::read_vector_field_async, m)?)?;
+    m.add_function(wrap_pyfunction!(aio::field_stats_async, m)?)?;
+    m.add_function(wrap_pyfunction!(header::parse_foamfile_header, m)?)?;
+    m.add_function(wrap_pyfunction!(header::parse_field_dimensions, m)?)?;
+    m.add_function(wrap_pyfunction!(header::field_info, m)?)?;
+    m.add_function(wrap_pyfunction!(header::dimensions_to_units, m)?)?;
+    m.add_class::<stats::FieldStats>()?;
+    m.add_function(wrap_pyfunction!(stats::field_stats, m)?)?;
+    m.add_class::<stats::FiniteCheck>()?;
+    m.add_function(wrap_pyfunction!(stats::check_finite, m)?)?;
+    m.add_class::<stats::VectorFieldStats>()?;
+    m.add_function(wrap_pyfunction!(stats::vector_field_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(stats::field_histogram, m)?)?;
+    m.add_function(wrap_pyfunction!(stats::field_quantiles, m)?)?;
+    m.add_function(wrap_pyfunction!(stats::field_volume_average, m)?)?;
+    m.add_function(wrap_pyfunction!(batch::parse_fields, m)?)?;
+    m.add_function(wrap_pyfunction!(time::list_time_dirs, m)?)?;
+    m.add_function(wrap_pyfunction!(time::list_fields, m)?)?;
+    m.add_function(wrap_pyfunction!(time::field_time_series, m)?)?;
+    m.add_function(wrap_pyfunction!(time::latest_time, m)?)?;
+    m.add_function(wrap_pyfunction!(index::field_history, m)?)?;
+    m.add_function(wrap_pyfunction!(decomposed::parse_scalar_field_parallel_case, m)?)?;
+    m.add_function(wrap_pyfunction!(decomposed::reconstruct_field, m)?)?;
+    m.add_function(wrap_pyfunction!(mesh::read_points, m)?)?;
+    m.add_function(wrap_pyfunction!(mesh::read_faces, m)?)?;
+    m.add_function(wrap_pyfunction!(mesh::read_owner, m)?)?;
+    m.add_function(wrap_pyfunction!(mesh::read_neighbour, m)?)?;
+    m.add_function(wrap_pyfunction!(mesh::read_mesh_topology, m)?)?;
+    m.add_function(wrap_pyfunction!(mesh::read_boundary, m)?)?;
+    m.add_function(wrap_pyfunction!(mesh::mesh_summary, m)?)?;
+    m.add_function(wrap_pyfunction!(mesh::list_regions, m)?)?;
+    m.add_function(wrap_pyfunction!(zones::read_cell_zones, m)?)?;
+    m.add_function(wrap_pyfunction!(zones::read_face_zones, m)?)?;
+    m.add_function(wrap_pyfunction!(zones::read_point_zones, m)?)?;
+    m.add_function(wrap_pyfunction!(zones::list_sets, m)?)?;
+    m.add_function(wrap_pyfunction!(zones::read_set, m)?)?;
+    m.add_function(wrap_pyfunction!(geometry::compute_cell_geometry, m)?)?;
+    m.add_class::<log::Residual>()?;
+    m.add_class::<log::LogStep>()?;
+    m.add_function(wrap_pyfunction!(log::parse_solver_log, m)?)?;
+    m.add_function(wrap_pyfunction!(log::parse_solver_log_incremental, m)?)?;
+    m.add_class::<log::LogFollower>()?;
+    m.add_function(wrap_pyfunction!(log::parse_continuity_errors, m)?)?;
+    m.add_function(wrap_pyfunction!(log::estimate_eta, m)?)?;
+    m.add_class::<log::LogError>()?;
+    m.add_function(wrap_pyfunction!(log::detect_log_errors, m)?)?;
+    m.add_class::<log::LogWarning>()?;
+    m.add_function(wrap_pyfunction!(log::collect_log_warnings, m)?)?;
+    m.add_function(wrap_pyfunction!(postprocess::read_force_coeffs, m)?)?;
+    m.add_function(wrap_pyfunction!(postprocess::read_forces, m)?)?;
+    m.add_function(wrap_pyfunction!(postprocess::read_probes, m)?)?;
+    m.add_function(wrap_pyfunction!(postprocess::read_sampled_set, m)?)?;
+    m.add_function(wrap_pyfunction!(postprocess::read_field_min_max, m)?)?;
+    m.add_function(wrap_pyfunction!(postprocess::read_vol_field_value, m)?)?;
+    m.add_function(wrap_pyfunction!(postprocess::read_solver_info, m)?)?;
+    m.add_class::<dict::Dimensioned>()?;
+    m.add_function(wrap_pyfunction!(dict::parse_dict, m)?)?;
+    m.add_function(wrap_pyfunction!(dict::set_dict_entry, m)?)?;
+    m.add_function(wrap_pyfunction!(dict::write_dict, m)?)?;
+    m.add_function(wrap_pyfunction!(dict::request_write_now, m)?)?;
+    m.add_function(wrap_pyfunction!(dict::request_stop, m)?)?;
+    m.add_function(wrap_pyfunction!(blockmesh::parse_blockmesh, m)?)?;
+    m.add_function(wrap_pyfunction!(snappy::check_snappy_dict, m)?)?;
+    m.add_function(wrap_pyfunction!(validate::validate_case, m)?)?;
+    m.add_function(wrap_pyfunction!(boundary::read_boundary_conditions, m)?)?;
+    m.add_function(wrap_pyfunction!(boundary::set_boundary_condition, m)?)?;
+    m.add_function(wrap_pyfunction!(lagrangian::list_clouds, m)?)?;
+    m.add_function(wrap_pyfunction!(lagrangian::read_lagrangian, m)?)?;
+    m.add_function(wrap_pyfunction!(field::read_surface_field, m)?)?;
+    m.add_function(wrap_pyfunction!(field::patch_flux_sum, m)?)?;
+    m.add_function(wrap_pyfunction!(field::patch_average, m)?)?;
+    m.add_function(wrap_pyfunction!(field::write_scalar_field, m)?)?;
+    m.add_function(wrap_pyfunction!(field::write_vector_field, m)?)?;
+    m.add_function(wrap_pyfunction!(regions::set_field_regions, m)?)?;
+    m.add_function(wrap_pyfunction!(surface::extract_patch_surface, m)?)?;
+    m.add_function(wrap_pyfunction!(surface::slice_plane, m)?)?;
+    m.add_function(wrap_pyfunction!(surface::isosurface, m)?)?;
+    m.add_function(wrap_pyfunction!(probe::probe_points, m)?)?;
+    m.add_function(wrap_pyfunction!(probe::sample_line, m)?)?;
+    m.add_function(wrap_pyfunction!(derived::compute_gradient, m)?)?;
+    m.add_function(wrap_pyfunction!(derived::compute_vorticity, m)?)?;
+    m.add_function(wrap_pyfunction!(derived::compute_q_criterion, m)?)?;
+    m.add_function(wrap_pyfunction!(derived::wall_metrics, m)?)?;
+    m.add_function(wrap_pyfunction!(stats::field_integral, m)?)?;
+    m.add_function(wrap_pyfunction!(stats::conditional_stats, m)?)?;
+    m.add_function(wrap_pyfunction!(time::time_average_field, m)?)?;
+    m.add_function(wrap_pyfunction!(algebra::write_field_expression, m)?)?;
+    m.add_function(wrap_pyfunction!(vtk::read_vtk_surface, m)?)?;
+    m.add_function(wrap_pyfunction!(export::export_vtk, m)?)?;
+    m.add_function(wrap_pyfunction!(export::export_vtu, m)?)?;
+    m.add_function(wrap_pyfunction!(export::export_gltf, m)?)?;
+    m.add_function(wrap_pyfunction!(colormap::apply_colormap, m)?)?;
+    m.add_function(wrap_pyfunction!(render::render_slice_png, m)?)?;
+    m.add_function(wrap_pyfunction!(downsample::downsample_lttb, m)?)?;
+    m.add_function(wrap_pyfunction!(smooth::moving_average, m)?)?;
+    m.add_function(wrap_pyfunction!(smooth::exponential_moving_average, m)?)?;
+    m.add_function(wrap_pyfunction!(spectrum::compute_psd, m)?)?;
+    m.add_function(wrap_pyfunction!(convergence::check_convergence, m)?)?;
+    m.add_function(wrap_pyfunction!(export::export_ensight, m)?)?;
+    m.add_function(wrap_pyfunction!(columnar::residuals_table, m)?)?;
+    m.add_function(wrap_pyfunction!(columnar::probes_table, m)?)?;
+    m.add_function(wrap_pyfunction!(columnar::field_stats_table, m)?)?;
+    m.add_function(wrap_pyfunction!(columnar::export_case_summary, m)?)?;
+    m.add_function(wrap_pyfunction!(decimate::decimate_surface, m)?)?;
+    m.add_function(wrap_pyfunction!(watch::watch_case, m)?)?;
+    m.add_function(wrap_pyfunction!(runner::run_solver, m)?)?;
+    m.add_class::<runner::SolverRun>()?;
+    m.add_class::<runner::RankReport>()?;
+    m.add_function(wrap_pyfunction!(runner::set_decomposition, m)?)?;
+    m.add_function(wrap_pyfunction!(runner::run_parallel, m)?)?;
+    m.add_function(wrap_pyfunction!(monitor::monitor_pid, m)?)?;
+    m.add_class::<monitor::ProcessMonitor>()?;
+    m.add_class::<watch::Watcher>()?;
+    m.add_class::<columnar::Table>()?;
+    m.add_class::<convergence::Plateau>()?;
+    m.add_class::<convergence::ConvergenceCriteria>()?;
+    m.add_class::<convergence::ConvergenceCheck>()?;
+    m.add_class::<convergence::ConvergenceVerdict>()?;
     Ok(())
 }
@@ -0,0 +1,181 @@
+use memchr::memchr3;
+
+use crate::for_each_number;
+use crate::header::{header_end, parse_header};
+use crate::scan::{comment_len, uncommented};
+
+// Layout of the list data that follows a file's header
+#[derive(Clone, Copy)]
+pub struct ListFormat {
+    pub binary: bool,
+    pub label_bytes: usize,
+    pub scalar_bytes: usize,
+}
+
+impl ListFormat {
+    // The header's format unless the call's Options override it
+    pub fn of(data: &[u8]) -> ListFormat {
+        let mut format = match parse_header(data) {
+            Some(h) => ListFormat {
+                binary: h.is_binary(),
+                label_bytes: h.arch_width("label"),
+                scalar_bytes: h.arch_width("scalar"),
+            },
+            None => ListFormat { binary: false, label_bytes: 4, scalar_bytes: 8 },
+        };
+        if let Some(binary) = crate::options::current().binary {
+            format.binary = binary;
+        }
+        format
+    }
+}
+
+// Skip whitespace and `//`, `/* */` comments starting at `pos`
+pub fn skip_ws_comments(data: &[u8], mut pos: usize) -> usize {
+    while pos < data.len() {
+        match data[pos] {
+            b' ' | b'\t' | b'\n' | b'\r' => pos += 1,
+            b'/' if comment_len(data, pos) > 0 => pos += comment_len(data, pos),
+            _ => break,
+        }
+    }
+    pos
+}
+
+// The body of a sized list `N ( ... )` or `N { value }` starting at `pos`
+pub enum ListBody<'a> {
+    // Bytes between the parens, plus the declared count
+    Items(usize, &'a [u8]),
+    // `N{value}`: every entry is the same value
+    Uniform(usize, &'a [u8]),
+}
+
+// Capacity to reserve for `count` items declared in a file, of which
+// `remaining` bytes are left to hold them. Every item takes at least a byte,
+// so a corrupt or huge count can't reserve more than the file could fill.
+pub fn reserve_bounded(count: usize, remaining: usize) -> usize {
+    count.min(remaining)
+}
+
+// Parse the count and delimit the body of the list starting at `pos`. Binary
+// bodies are exactly `count * item_bytes` long; ASCII bodies end at the
+// matching close paren.
+pub fn list_body(data: &[u8], pos: usize, fmt: ListFormat, item_bytes: usize) -> Option<(ListBody<'_>, usize)> {
+    let pos = skip_ws_comments(data, pos);
+    let digits = data[pos..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let count: usize = std::str::from_utf8(&data[pos..pos + digits]).ok()?.parse().ok()?;
+    let pos = skip_ws_comments(data, pos + digits);
+
+    match *data.get(pos)? {
+        b'(' if fmt.binary => {
+            let start = pos + 1;
+            let end = start.checked_add(count.checked_mul(item_bytes)?)?;
+            if end > data.len() {
+                return None;
+            }
+            Some((ListBody::Items(count, &data[start..end]), end + 1))
+        }
+        b'(' => {
+            let end = matching_paren(data, pos)?;
+            Some((ListBody::Items(count, &data[pos + 1..end]), end + 1))
+        }
+        b'{' => {
+            let end = pos + data[pos..].iter().position(|b| *b == b'}')?;
+            Some((ListBody::Uniform(count, &data[pos + 1..end]), end + 1))
+        }
+        _ => None,
+    }
+}
+
+// Index of the paren closing the one at `open`; parens in comments don't count
+pub fn matching_paren(data: &[u8], open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut pos = open;
+    while let Some(i) = memchr3(b'(', b')', b'/', &data[pos..]) {
+        pos += i;
+        match data[pos] {
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos);
+                }
+            }
+            _ => pos += comment_len(data, pos).saturating_sub(1),
+        }
+        pos += 1;
+    }
+    None
+}
+
+pub fn decode_labels(bytes: &[u8], label_bytes: usize) -> Vec<i64> {
+    if label_bytes == 8 {
+        bytes.chunks_exact(8).map(|c| i64::from_le_bytes(c.try_into().unwrap())).collect()
+    } else {
+        bytes.chunks_exact(4).map(|c| i32::from_le_bytes(c.try_into().unwrap()) as i64).collect()
+    }
+}
+
+pub fn decode_scalars(bytes: &[u8], scalar_bytes: usize) -> Vec<f64> {
+    if scalar_bytes == 4 {
+        bytes.chunks_exact(4).map(|c| f32::from_le_bytes(c.try_into().unwrap()) as f64).collect()
+    } else {
+        bytes.chunks_exact(8).map(|c| f64::from_le_bytes(c.try_into().unwrap())).collect()
+    }
+}
+
+fn parse_ascii_labels(body: &[u8]) -> Vec<i64> {
+    uncommented(body)
+        .split(|b| b.is_ascii_whitespace())
+        .filter(|t| !t.is_empty())
+        .filter_map(|t| std::str::from_utf8(t).ok()?.parse().ok())
+        .collect()
+}
+
+// The label list starting at `pos`, plus the position after it
+pub fn label_list_at(data: &[u8], pos: usize, fmt: ListFormat) -> Option<(Vec<i64>, usize)> {
+    let (body, next) = list_body(data, pos, fmt, fmt.label_bytes)?;
+    let labels = match body {
+        ListBody::Items(_, bytes) if fmt.binary => decode_labels(bytes, fmt.label_bytes),
+        ListBody::Items(count, bytes) => {
+            // A short list means the file is still being written
+            let labels = parse_ascii_labels(bytes);
+            if labels.len() != count {
+                return None;
+            }
+            labels
+        }
+        ListBody::Uniform(count, value) => {
+            let v: i64 = std::str::from_utf8(value).ok()?.trim().parse().ok()?;
+            vec![v; count]
+        }
+    };
+    Some((labels, next))
+}
+
+// A labelList file (owner, neighbour, cellProcAddressing, ...) in ASCII or
+// binary format
+pub fn read_label_list(data: &[u8]) -> Option<Vec<i64>> {
+    label_list_at(data, header_end(data), ListFormat::of(data)).map(|(labels, _)| labels)
+}
+
+// A list of fixed-width scalar tuples (pointField, vectorField, ...) flattened
+// to `count * width` values, in ASCII or binary format. A count mismatch means
+// the file is truncated and yields None.
+pub fn read_scalar_list(data: &[u8], width: usize) -> Option<Vec<f64>> {
+    let fmt = ListFormat::of(data);
+    let (body, _) = list_body(data, header_end(data), fmt, width * fmt.scalar_bytes)?;
+    match body {
+        ListBody::Items(_, bytes) if fmt.binary => Some(decode_scalars(bytes, fmt.scalar_bytes)),
+        ListBody::Items(count, bytes) => {
+            let mut values = Vec::with_capacity(reserve_bounded(count.saturating_mul(width), bytes.len()));
+            for_each_number(bytes, |val| values.push(val));
+            (values.len() == count.saturating_mul(width)).then_some(values)
+        }
+        ListBody::Uniform(count, value) => {
+            let mut item = Vec::with_capacity(width);
+            for_each_number(value, |val| item.push(val));
+            (item.len() == width).then(|| item.repeat(count))
+        }
+    }
+}
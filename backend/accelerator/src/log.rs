@@ -0,0 +1,770 @@
+use memmap2::Mmap;
+use numpy::IntoPyArray;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::downsample::{check_points, log10_series, lttb_indices, pick};
+
+// One linear solve reported on a "Solving for" line
+#[pyclass(frozen, get_all)]
+#[derive(Clone)]
+pub struct Residual {
+    pub solver: String,
+    pub initial_residual: f64,
+    pub final_residual: f64,
+    pub iterations: u64,
+}
+
+#[pymethods]
+impl Residual {
+    fn __repr__(&self) -> String {
+        format!(
+            "Residual(solver={:?}, initial_residual={}, final_residual={}, iterations={})",
+            self.solver, self.initial_residual, self.final_residual, self.iterations
+        )
+    }
+}
+
+// Everything the solver printed between one "Time = " line and the next
+#[pyclass(frozen, get_all)]
+#[derive(Clone)]
+pub struct LogStep {
+    pub time: f64,
+    pub residuals: BTreeMap<String, Residual>,
+    // (sum local, global, cumulative) from the step's last continuity line
+    pub continuity: Option<(f64, f64, f64)>,
+    // Cumulative CPU and wall-clock seconds at the end of the step
+    pub execution_time: Option<f64>,
+    pub clock_time: Option<f64>,
+}
+
+#[pymethods]
+impl LogStep {
+    fn __repr__(&self) -> String {
+        let fields: Vec<&str> = self.residuals.keys().map(String::as_str).collect();
+        format!("LogStep(time={}, fields={:?})", self.time, fields)
+    }
+}
+
+// Leading number of a value such as "0.005", "1e-05," or "0.01s" (newer
+// OpenFOAM releases print a unit after the time)
+fn leading_number(s: &str) -> Option<f64> {
+    let s = s.trim_start();
+    let end = s
+        .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-')))
+        .unwrap_or(s.len());
+    s[..end].parse().ok()
+}
+
+// Value following `key` on a line, e.g. "Initial residual = "
+fn value_after(line: &str, key: &str) -> Option<f64> {
+    let rest = &line[line.find(key)? + key.len()..];
+    leading_number(rest.trim_start().strip_prefix('=').unwrap_or(rest))
+}
+
+// "smoothSolver:  Solving for Ux, Initial residual = 0.1, Final residual = 1e-06, No Iterations 4"
+fn parse_solving_line(line: &str) -> Option<(String, Residual)> {
+    let at = line.find("Solving for")?;
+    let solver = line[..at].trim().trim_end_matches(':').trim().to_string();
+    let rest = &line[at + "Solving for".len()..];
+    let field = rest[..rest.find(',')?].trim().to_string();
+    let residual = Residual {
+        solver,
+        initial_residual: value_after(rest, "Initial residual")?,
+        final_residual: value_after(rest, "Final residual")?,
+        iterations: value_after(rest, "No Iterations").map_or(0, |n| n as u64),
+    };
+    Some((field, residual))
+}
+
+// "time step continuity errors : sum local = 1e-09, global = 2e-20, cumulative = 3e-19"
+fn parse_continuity_line(line: &str) -> Option<(f64, f64, f64)> {
+    let rest = line.strip_prefix("time step continuity errors")?;
+    Some((
+        value_after(rest, "sum local")?,
+        value_after(rest, "global")?,
+        value_after(rest, "cumulative")?,
+    ))
+}
+
+// Line-oriented state machine shared by the full and incremental parsers.
+// A step is only complete once the next "Time = " line (or the final "End")
+// has been seen, since a running solver may still be printing into it.
+#[derive(Default)]
+pub struct LogParser {
+    pub done: Vec<LogStep>,
+    current: Option<LogStep>,
+    // Byte offset of the first line not belonging to a completed step
+    resume: usize,
+    // An "End" line was seen: the solver finished
+    pub ended: bool,
+}
+
+impl LogParser {
+    fn finish_step(&mut self, line_start: usize) {
+        if let Some(step) = self.current.take() {
+            self.done.push(step);
+        }
+        self.resume = line_start;
+    }
+
+    fn feed(&mut self, line: &str, line_start: usize, line_end: usize) {
+        if let Some(rest) = line.strip_prefix("Time =") {
+            self.finish_step(line_start);
+            if let Some(time) = leading_number(rest) {
+                self.current = Some(LogStep {
+                    time,
+                    residuals: BTreeMap::new(),
+                    continuity: None,
+                    execution_time: None,
+                    clock_time: None,
+                });
+            }
+            return;
+        }
+        if line.trim_end() == "End" {
+            self.finish_step(line_end);
+            self.ended = true;
+            return;
+        }
+        let Some(step) = self.current.as_mut() else {
+            // Output outside a time step (mesh checks and initialisation
+            // before the first one, finalisation after End) is skipped
+            self.resume = line_end;
+            return;
+        };
+        if let Some((field, residual)) = parse_solving_line(line) {
+            // Keep the first solve of each field: its initial residual is the
+            // convergence measure, later correctors start from a solved state
+            step.residuals.entry(field).or_insert(residual);
+        } else if let Some(errors) = parse_continuity_line(line) {
+            // Every pressure corrector prints one; the last is what the
+            // cumulative error carries forward
+            step.continuity = Some(errors);
+        } else if line.starts_with("ExecutionTime") {
+            // "ExecutionTime = 0.52 s  ClockTime = 1 s"
+            step.execution_time = value_after(line, "ExecutionTime");
+            step.clock_time = value_after(line, "ClockTime");
+        }
+    }
+
+    // Feed every complete line of `data`, which starts at file offset `base`.
+    // Returns the offset just past the last newline.
+    pub fn feed_lines(&mut self, data: &[u8], base: usize) -> usize {
+        let mut pos = 0;
+        while let Some(nl) = data[pos..].iter().position(|&b| b == b'\n') {
+            let end = pos + nl + 1;
+            let line = String::from_utf8_lossy(&data[pos..pos + nl]);
+            self.feed(line.trim_end_matches('\r'), base + pos, base + end);
+            pos = end;
+        }
+        base + pos
+    }
+}
+
+// Memory-map a log file; missing and empty logs yield None
+fn map_log(path: &str) -> std::io::Result<Option<Mmap>> {
+    let file = match File::open(Path::new(path)) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if file.metadata()?.len() == 0 {
+        return Ok(None);
+    }
+    Ok(Some(unsafe { Mmap::map(&file)? }))
+}
+
+// Every time step of a log, including one still being written
+pub fn read_log_steps(path: &str) -> std::io::Result<Option<Vec<LogStep>>> {
+    let Some(data) = map_log(path)? else {
+        return Ok(None);
+    };
+    let mut parser = LogParser::default();
+    let end = parser.feed_lines(&data, 0);
+    // A trailing line without a newline is still part of the last step
+    parser.feed(&String::from_utf8_lossy(&data[end..]), end, data.len());
+    parser.finish_step(data.len());
+    Ok(Some(parser.done))
+}
+
+// Thin steps to `max_points` with LTTB over each field's initial residual,
+// on the log scale residuals are plotted with
+pub fn downsample_steps(steps: Vec<LogStep>, max_points: usize) -> Vec<LogStep> {
+    let fields: BTreeSet<&String> = steps.iter().flat_map(|s| s.residuals.keys()).collect();
+    let series: Vec<Vec<f64>> = fields
+        .iter()
+        .map(|&f| {
+            let initial: Vec<f64> = steps.iter().map(|s| s.residuals.get(f).map_or(f64::NAN, |r| r.initial_residual)).collect();
+            log10_series(&initial)
+        })
+        .collect();
+    let series: Vec<&[f64]> = series.iter().map(Vec::as_slice).collect();
+    let times: Vec<f64> = steps.iter().map(|s| s.time).collect();
+    let kept = lttb_indices(&times, &series, max_points);
+    if kept.len() == steps.len() {
+        return steps;
+    }
+    pick(&steps, &kept)
+}
+
+/// Parse a solver log into a list of LogStep records, one per time step,
+/// each holding the residuals of the fields solved in that step. With
+/// `max_points` the steps are thinned to that many with downsample_lttb(),
+/// keeping those that shape the initial residual curves on a log scale.
+/// Returns None if the log is missing or empty.
+#[pyfunction]
+#[pyo3(signature = (path, max_points=None))]
+pub fn parse_solver_log(py: Python, path: String, max_points: Option<usize>) -> PyResult<Option<Vec<LogStep>>> {
+    max_points.map(check_points).transpose()?;
+    let steps = py.detach(|| read_log_steps(&path))?;
+    Ok(match max_points {
+        Some(max_points) => steps.map(|steps| py.detach(|| downsample_steps(steps, max_points))),
+        None => steps,
+    })
+}
+
+/// Time step continuity errors from a solver log as a dict of arrays: time,
+/// sum_local, global and cumulative. Steps without a continuity line (e.g.
+/// solvers without a pressure equation) are skipped. With `max_points` the
+/// history is thinned to that many steps with downsample_lttb(). Returns
+/// None if the log is missing or empty.
+#[pyfunction]
+#[pyo3(signature = (path, max_points=None))]
+pub fn parse_continuity_errors<'py>(
+    py: Python<'py>,
+    path: String,
+    max_points: Option<usize>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    max_points.map(check_points).transpose()?;
+    let Some(steps) = py.detach(|| read_log_steps(&path))? else {
+        return Ok(None);
+    };
+    let (mut time, mut local, mut global, mut cumulative) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for step in &steps {
+        if let Some((l, g, c)) = step.continuity {
+            time.push(step.time);
+            local.push(l);
+            global.push(g);
+            cumulative.push(c);
+        }
+    }
+    if let Some(max_points) = max_points {
+        let kept = py.detach(|| lttb_indices(&time, &[&local, &global, &cumulative], max_points));
+        if kept.len() < time.len() {
+            (time, local, global, cumulative) = (pick(&time, &kept), pick(&local, &kept), pick(&global, &kept), pick(&cumulative, &kept));
+        }
+    }
+    let out = PyDict::new(py);
+    out.set_item("time", time.into_pyarray(py))?;
+    out.set_item("sum_local", local.into_pyarray(py))?;
+    out.set_item("global", global.into_pyarray(py))?;
+    out.set_item("cumulative", cumulative.into_pyarray(py))?;
+    Ok(Some(out))
+}
+
+// Identity of a file, telling a log that was rotated or replaced by a rerun
+// from one that grew
+#[cfg(unix)]
+fn file_id(meta: &std::fs::Metadata) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (meta.dev(), meta.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_: &std::fs::Metadata) -> (u64, u64) {
+    (0, 0)
+}
+
+// Where a LogFollower is in the log it follows
+#[derive(Default)]
+struct Follow {
+    file: Option<(File, (u64, u64))>,
+    // Bytes read from the open file, and those of them after its last newline
+    offset: u64,
+    partial: Vec<u8>,
+    parser: LogParser,
+    ready: VecDeque<LogStep>,
+}
+
+impl Follow {
+    // Parse whatever was written to `path` since the last call. A log that
+    // shrank was truncated and is read again from its start; one replaced by
+    // a new file is read to its end first. Steps carry over either way, so
+    // a rotation mid-step loses nothing, but an unfinished last line of the
+    // old contents is dropped rather than joined to the new first line.
+    fn poll(&mut self, path: &Path) -> std::io::Result<()> {
+        loop {
+            if self.file.is_none() {
+                let file = match File::open(path) {
+                    Ok(f) => f,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+                    Err(e) => return Err(e),
+                };
+                let id = file_id(&file.metadata()?);
+                self.file = Some((file, id));
+                self.offset = 0;
+                self.partial.clear();
+            }
+            let Some((file, id)) = self.file.as_mut() else {
+                return Ok(());
+            };
+            if file.metadata()?.len() < self.offset {
+                file.seek(SeekFrom::Start(0))?;
+                self.offset = 0;
+                self.partial.clear();
+            }
+            let before = self.partial.len();
+            file.read_to_end(&mut self.partial)?;
+            self.offset += (self.partial.len() - before) as u64;
+            let used = self.parser.feed_lines(&self.partial, 0);
+            self.partial.drain(..used);
+            self.ready.extend(self.parser.done.drain(..));
+
+            match std::fs::metadata(path) {
+                Ok(meta) if file_id(&meta) != *id => self.file = None,
+                _ => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Follow a solver log as the solver writes it, like `tail -F`: iterating
+/// yields each LogStep once the solver has moved on to the next time step,
+/// waiting (without the GIL) for more output in between, and stops after
+/// the solver's "End". A log that doesn't exist yet is waited for; one that
+/// is truncated, rotated or replaced is followed from its new start. With
+/// `from_start=False` steps already in the log are skipped. With `timeout`,
+/// iteration also stops when no step completes within that many seconds
+/// and can be resumed by iterating again; close() ends it for good.
+#[pyclass(frozen, module = "accelerator")]
+pub struct LogFollower {
+    path: PathBuf,
+    poll_interval: Duration,
+    timeout: Option<Duration>,
+    follow: Mutex<Follow>,
+    closed: AtomicBool,
+}
+
+#[pymethods]
+impl LogFollower {
+    #[new]
+    #[pyo3(signature = (path, poll_interval = 0.25, timeout = None, from_start = true))]
+    fn new(py: Python<'_>, path: PathBuf, poll_interval: f64, timeout: Option<f64>, from_start: bool) -> PyResult<Self> {
+        let seconds = |name: &str, s: f64| {
+            Duration::try_from_secs_f64(s).map_err(|_| PyValueError::new_err(format!("{} must be a non-negative number of seconds", name)))
+        };
+        let poll_interval = seconds("poll_interval", poll_interval)?;
+        let timeout = timeout.map(|t| seconds("timeout", t)).transpose()?;
+        let mut follow = Follow::default();
+        if !from_start {
+            py.detach(|| follow.poll(&path))?;
+            follow.ready.clear();
+        }
+        Ok(LogFollower { path, poll_interval, timeout, follow: Mutex::new(follow), closed: AtomicBool::new(false) })
+    }
+
+    fn __iter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python<'_>) -> PyResult<Option<LogStep>> {
+        let started = Instant::now();
+        loop {
+            if self.closed.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+            let (step, ended) = py.detach(|| -> std::io::Result<_> {
+                let mut follow = self.follow.lock().unwrap();
+                if follow.ready.is_empty() {
+                    follow.poll(&self.path)?;
+                }
+                Ok((follow.ready.pop_front(), follow.parser.ended))
+            })?;
+            if step.is_some() || ended {
+                return Ok(step);
+            }
+            let wait = match self.timeout {
+                Some(timeout) if started.elapsed() >= timeout => return Ok(None),
+                Some(timeout) => self.poll_interval.min(timeout - started.elapsed()),
+                None => self.poll_interval,
+            };
+            py.detach(|| std::thread::sleep(wait));
+            // Let Ctrl-C through while waiting
+            py.check_signals()?;
+        }
+    }
+
+    /// Stop iterating, also from another thread waiting in the loop.
+    fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+
+    #[getter]
+    fn closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("LogFollower({:?})", self.path.display().to_string())
+    }
+}
+
+/// Parse only the part of a solver log after `offset` and return
+/// (steps, new_offset). Pass new_offset back on the next call to pick up where
+/// this one stopped. Only completed time steps are returned; the step the
+/// solver is still writing is re-read next time. If the log is shorter than
+/// `offset` it was truncated or replaced, so parsing restarts from 0.
+#[pyfunction]
+#[pyo3(signature = (path, offset=0))]
+pub fn parse_solver_log_incremental(py: Python, path: String, offset: usize) -> PyResult<(Vec<LogStep>, usize)> {
+    py.detach(|| {
+        let Some(data) = map_log(&path)? else {
+            return Ok((Vec::new(), 0));
+        };
+        let start = if offset > data.len() { 0 } else { offset };
+        let mut parser = LogParser { resume: start, ..Default::default() };
+        parser.feed_lines(&data[start..], start);
+        Ok((parser.done, parser.resume))
+    })
+}
+
+// Wall-clock seconds per unit of simulated time over the last `window` timed
+// steps. ClockTime only has whole-second resolution, so fast steps fall back
+// to ExecutionTime, which is CPU time but close enough for a serial solver.
+fn seconds_per_sim_time(steps: &[LogStep], window: usize) -> Option<f64> {
+    let timed: Vec<&LogStep> = steps.iter().filter(|s| s.execution_time.is_some()).collect();
+    let recent = &timed[timed.len().saturating_sub(window.max(2))..];
+    let (first, last) = (recent.first()?, recent.last()?);
+    let sim = last.time - first.time;
+    if sim <= 0.0 {
+        return None;
+    }
+    let clock = match (first.clock_time, last.clock_time) {
+        (Some(a), Some(b)) if b > a => b - a,
+        _ => last.execution_time? - first.execution_time?,
+    };
+    Some(clock / sim)
+}
+
+/// Estimated wall-clock seconds until the solver reaches `end_time`,
+/// extrapolated from the cost of the last `window` time steps in the log.
+/// Returns 0.0 once the run is past end_time and None if the log has too few
+/// timed steps to tell.
+#[pyfunction]
+#[pyo3(signature = (path, end_time, window=20))]
+pub fn estimate_eta(py: Python, path: String, end_time: f64, window: usize) -> PyResult<Option<f64>> {
+    py.detach(|| {
+        let Some(steps) = read_log_steps(&path)? else {
+            return Ok(None);
+        };
+        let Some(last) = steps.iter().rev().find(|s| s.execution_time.is_some()) else {
+            return Ok(None);
+        };
+        if last.time >= end_time {
+            return Ok(Some(0.0));
+        }
+        Ok(seconds_per_sim_time(&steps, window).map(|rate| (end_time - last.time) * rate))
+    })
+}
+
+// A FOAM FATAL ERROR / FATAL IO ERROR block
+#[pyclass(frozen, get_all)]
+#[derive(Clone)]
+pub struct LogError {
+    // "FATAL ERROR" or "FATAL IO ERROR"
+    pub kind: String,
+    pub message: String,
+    // The "From ..." function that raised it
+    pub function: Option<String>,
+    // OpenFOAM source location ("in file ... at line ...")
+    pub source_file: Option<String>,
+    pub source_line: Option<u64>,
+    // Dictionary the IO error is about ("file: ... at line ...")
+    pub io_file: Option<String>,
+    pub io_line: Option<u64>,
+    // Last time step started before the error, if any
+    pub time: Option<f64>,
+    // 1-based line number of the "--> FOAM FATAL" header in the log
+    pub log_line: usize,
+    // Non-blank log lines leading up to the error
+    pub context: Vec<String>,
+}
+
+#[pymethods]
+impl LogError {
+    fn __repr__(&self) -> String {
+        format!("LogError(kind={:?}, message={:?}, log_line={})", self.kind, self.message, self.log_line)
+    }
+}
+
+// Lines of a log without their terminators
+fn log_lines(data: &[u8]) -> impl Iterator<Item = Cow<'_, str>> {
+    data.split(|&b| b == b'\n').map(|l| String::from_utf8_lossy(l.strip_suffix(b"\r").unwrap_or(l)))
+}
+
+// Parallel runs prefix every line with the rank: "[3] --> FOAM FATAL ERROR:".
+// Returns the rank and the rest of the line.
+pub fn split_rank(line: &str) -> Option<(usize, &str)> {
+    let rest = line.strip_prefix('[')?;
+    let close = rest.find(']')?;
+    let rank = rest[..close].parse().ok().filter(|_| rest[..close].bytes().all(|b| b.is_ascii_digit()))?;
+    Some((rank, rest[close + 1..].strip_prefix(' ').unwrap_or(&rest[close + 1..])))
+}
+
+fn strip_rank(line: &str) -> &str {
+    split_rank(line).map_or(line, |(_, rest)| rest)
+}
+
+// Leading digits of a line number such as "542." or "18 to 48."
+fn leading_label(s: &str) -> Option<u64> {
+    let s = s.trim_start();
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s[..end].parse().ok()
+}
+
+// "path at line 18 to 48." (newer) or "path from line 17 to line 48." (older)
+fn file_and_line(s: &str) -> (String, Option<u64>) {
+    for sep in [" at line ", " from line "] {
+        if let Some(at) = s.find(sep) {
+            return (s[..at].trim().to_string(), leading_label(&s[at + sep.len()..]));
+        }
+    }
+    (s.trim().trim_end_matches('.').to_string(), None)
+}
+
+impl LogError {
+    fn new(kind: &str, header_rest: &str, time: Option<f64>, log_line: usize, context: Vec<String>) -> Self {
+        // The header carries the build tag on newer releases,
+        // "(openfoam-2306)", and sometimes the start of the message
+        let rest = header_rest.trim();
+        let message = if rest.starts_with('(') && rest.ends_with(')') { "" } else { rest };
+        LogError {
+            kind: kind.to_string(),
+            message: message.to_string(),
+            function: None,
+            source_file: None,
+            source_line: None,
+            io_file: None,
+            io_line: None,
+            time,
+            log_line,
+            context,
+        }
+    }
+
+    fn absorb(&mut self, line: &str) {
+        let line = line.trim();
+        if let Some(from) = line.strip_prefix("From ") {
+            let from = from.strip_prefix("function ").unwrap_or(from);
+            self.function = Some(from.to_string());
+        } else if let Some(rest) = line.strip_prefix("in file ") {
+            let (file, n) = file_and_line(rest);
+            self.source_file = Some(file);
+            self.source_line = n;
+        } else if let Some(rest) = line.strip_prefix("file: ") {
+            let (file, n) = file_and_line(rest);
+            self.io_file = Some(file);
+            self.io_line = n;
+        } else if !line.is_empty() && self.function.is_none() {
+            // Message text runs until the "From" location
+            if !self.message.is_empty() {
+                self.message.push('\n');
+            }
+            self.message.push_str(line);
+        }
+    }
+}
+
+pub fn scan_errors(data: &[u8], context: usize) -> Vec<LogError> {
+    let mut errors: Vec<LogError> = Vec::new();
+    let mut current: Option<LogError> = None;
+    let mut recent: VecDeque<String> = VecDeque::with_capacity(context);
+    let mut time = None;
+
+    let mut finish = |current: &mut Option<LogError>| {
+        if let Some(e) = current.take() {
+            // Every rank of a parallel run reports the same error
+            if !errors.iter().any(|o| o.kind == e.kind && o.message == e.message) {
+                errors.push(e);
+            }
+        }
+    };
+
+    for (idx, raw) in log_lines(data).enumerate() {
+        let line = strip_rank(&raw);
+        if let Some(rest) = line.strip_prefix("Time =") {
+            time = leading_number(rest).or(time);
+        }
+        let header = line.trim_start().strip_prefix("--> FOAM FATAL ");
+        if let Some(header) = header {
+            finish(&mut current);
+            let (kind, rest) = match header.strip_prefix("IO ERROR:") {
+                Some(rest) => ("FATAL IO ERROR", rest),
+                None => ("FATAL ERROR", header.strip_prefix("ERROR:").unwrap_or(header)),
+            };
+            current = Some(LogError::new(kind, rest, time, idx + 1, recent.iter().cloned().collect()));
+            continue;
+        }
+        if let Some(e) = current.as_mut() {
+            let trimmed = line.trim();
+            // "FOAM exiting", "FOAM aborting", "FOAM parallel run exiting"
+            if trimmed.starts_with("FOAM ") && (trimmed.ends_with("exiting") || trimmed.ends_with("aborting")) {
+                finish(&mut current);
+            } else {
+                e.absorb(line);
+            }
+            continue;
+        }
+        if context > 0 && !line.trim().is_empty() {
+            if recent.len() == context {
+                recent.pop_front();
+            }
+            recent.push_back(line.to_string());
+        }
+    }
+    finish(&mut current);
+    errors
+}
+
+/// Scan a solver log for FOAM FATAL ERROR and FATAL IO ERROR blocks. Returns
+/// a list of LogError records with the message, the raising function, the
+/// source and dictionary locations it references and up to `context` log
+/// lines leading up to it; errors repeated by every rank of a parallel run
+/// are reported once. Returns None if the log is missing or empty.
+#[pyfunction]
+#[pyo3(signature = (path, context=10))]
+pub fn detect_log_errors(py: Python, path: String, context: usize) -> PyResult<Option<Vec<LogError>>> {
+    py.detach(|| {
+        let Some(data) = map_log(&path)? else {
+            return Ok(None);
+        };
+        Ok(Some(scan_errors(&data, context)))
+    })
+}
+
+// Distinct FOAM Warning / IOWarning, with how often it was repeated
+#[pyclass(frozen, get_all)]
+#[derive(Clone)]
+pub struct LogWarning {
+    // "Warning" or "IOWarning"
+    pub kind: String,
+    pub message: String,
+    pub function: Option<String>,
+    pub source_file: Option<String>,
+    pub source_line: Option<u64>,
+    pub count: usize,
+    // Time step and 1-based log line of the first occurrence
+    pub first_time: Option<f64>,
+    pub first_log_line: usize,
+}
+
+#[pymethods]
+impl LogWarning {
+    fn __repr__(&self) -> String {
+        let first_time = self.first_time.map_or("None".to_string(), |t| t.to_string());
+        format!(
+            "LogWarning(kind={:?}, message={:?}, count={}, first_time={})",
+            self.kind, self.message, self.count, first_time
+        )
+    }
+}
+
+impl LogWarning {
+    fn absorb(&mut self, line: &str) {
+        let line = line.trim();
+        if let Some(from) = line.strip_prefix("From ") {
+            let from = from.strip_prefix("function ").unwrap_or(from);
+            self.function = Some(from.to_string());
+        } else if let Some(rest) = line.strip_prefix("in file ") {
+            let (file, n) = file_and_line(rest);
+            self.source_file = Some(file);
+            self.source_line = n;
+        } else {
+            // Unlike errors, warnings print the message after the location
+            if !self.message.is_empty() {
+                self.message.push('\n');
+            }
+            self.message.push_str(line);
+        }
+    }
+}
+
+fn scan_warnings(data: &[u8]) -> Vec<LogWarning> {
+    let mut warnings: Vec<LogWarning> = Vec::new();
+    let mut seen: HashMap<(String, Option<String>, String), usize> = HashMap::new();
+    let mut current: Option<LogWarning> = None;
+    let mut time = None;
+
+    let mut finish = |current: &mut Option<LogWarning>| {
+        if let Some(w) = current.take() {
+            let key = (w.kind.clone(), w.function.clone(), w.message.clone());
+            match seen.get(&key) {
+                Some(&i) => warnings[i].count += 1,
+                None => {
+                    seen.insert(key, warnings.len());
+                    warnings.push(w);
+                }
+            }
+        }
+    };
+
+    for (idx, raw) in log_lines(data).enumerate() {
+        let line = strip_rank(&raw);
+        if let Some(rest) = line.strip_prefix("Time =") {
+            time = leading_number(rest).or(time);
+        }
+        let header = line.trim_start().strip_prefix("--> FOAM ");
+        if let Some(kind) = header.and_then(|h| h.split(':').next()).map(str::trim) {
+            if kind == "Warning" || kind == "IOWarning" {
+                finish(&mut current);
+                current = Some(LogWarning {
+                    kind: kind.to_string(),
+                    message: String::new(),
+                    function: None,
+                    source_file: None,
+                    source_line: None,
+                    count: 1,
+                    first_time: time,
+                    first_log_line: idx + 1,
+                });
+                continue;
+            }
+        }
+        if let Some(w) = current.as_mut() {
+            // The block is indented; a blank line or the solver's own
+            // column-0 output ends it
+            if line.trim().is_empty() || !line.starts_with(char::is_whitespace) {
+                finish(&mut current);
+            } else {
+                w.absorb(line);
+            }
+        }
+    }
+    finish(&mut current);
+    warnings
+}
+
+/// Collect the FOAM Warning and IOWarning blocks of a solver log,
+/// deduplicated by function and message. Returns a list of LogWarning
+/// records in order of first appearance, each with its repeat count and the
+/// time step it first occurred in. Returns None if the log is missing or
+/// empty.
+#[pyfunction]
+pub fn collect_log_warnings(py: Python, path: String) -> PyResult<Option<Vec<LogWarning>>> {
+    py.detach(|| {
+        let Some(data) = map_log(&path)? else {
+            return Ok(None);
+        };
+        Ok(Some(scan_warnings(&data)))
+    })
+}
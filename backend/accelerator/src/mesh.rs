@@ -0,0 +1,556 @@
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::{Path, PathBuf};
+
+use crate::dict::{load_dict, Value};
+use crate::header::{header_end, parse_header, strip_comments};
+use crate::list::{
+    decode_scalars, label_list_at, list_body, read_label_list, read_scalar_list, reserve_bounded, ListBody, ListFormat,
+};
+use crate::for_each_number;
+use crate::geometry::Vec3;
+use crate::options::{self, Options};
+use crate::source::open_field_file;
+
+// constant/polyMesh of a case, or constant/<region>/polyMesh for one region
+// of a multi-region case
+pub fn mesh_dir(case_root: &Path, region: Option<&str>) -> PathBuf {
+    let constant = case_root.join("constant");
+    match region {
+        Some(region) => constant.join(region).join("polyMesh"),
+        None => constant.join("polyMesh"),
+    }
+}
+
+// Flat x, y, z coordinates of every mesh point. Missing files give None;
+// a malformed or truncated file is an error.
+pub fn load_points(mesh_dir: &Path) -> PyResult<Option<Vec<f64>>> {
+    let path = mesh_dir.join("points");
+    let data = match open_field_file(&path.to_string_lossy())? {
+        Some(d) => d,
+        None => return Ok(None),
+    };
+    match read_scalar_list(&data, 3) {
+        Some(points) => Ok(Some(points)),
+        None => Err(PyValueError::new_err(format!("{}: malformed or truncated point list", path.display()))),
+    }
+}
+
+// points, faces, owner and neighbour of a polyMesh
+pub struct PolyMesh {
+    pub points: Vec<[f64; 3]>,
+    pub face_offsets: Vec<usize>,
+    pub face_labels: Vec<usize>,
+    pub owner: Vec<usize>,
+    pub neighbour: Vec<usize>,
+    pub n_cells: usize,
+}
+
+impl PolyMesh {
+    pub fn load(mesh_dir: &Path) -> PyResult<Option<PolyMesh>> {
+        // Each file can take a while on a large mesh, so a cancelled call
+        // stops between them
+        let points = load_points(mesh_dir)?;
+        options::check_cancelled()?;
+        let faces = load_faces(mesh_dir)?;
+        options::check_cancelled()?;
+        // The owner note carries the real nCells; without it the cells are
+        // numbered by the owner/neighbour labels themselves
+        let owner_path = mesh_dir.join("owner");
+        let owner_data = open_field_file(&owner_path.to_string_lossy())?;
+        let declared_cells = owner_data.as_deref().and_then(|o| owner_note_count(o, "nCells"));
+        let owner = match owner_data {
+            Some(d) => Some(read_label_list(&d).ok_or_else(|| malformed(&owner_path, "label list"))?),
+            None => None,
+        };
+        options::check_cancelled()?;
+        let neighbour = load_labels(mesh_dir, "neighbour")?;
+        let (points, faces, owner, neighbour) = match (points, faces, owner, neighbour) {
+            (Some(p), Some(f), Some(o), Some(n)) => (p, f, o, n),
+            _ => return Ok(None),
+        };
+        let n_cells = declared_cells.unwrap_or_else(|| cell_count(&owner, &neighbour));
+        let n_points = points.len() / 3;
+        let (offsets, labels) = faces;
+
+        let invalid = |msg: String| PyValueError::new_err(format!("{}: {}", mesh_dir.display(), msg));
+        let as_index = |v: Vec<i64>, end: usize, what: &str| -> PyResult<Vec<usize>> {
+            if v.iter().any(|&x| x < 0 || x as usize >= end) {
+                return Err(invalid(format!("{} out of range", what)));
+            }
+            Ok(v.into_iter().map(|x| x as usize).collect())
+        };
+        let mesh = PolyMesh {
+            points: points.chunks_exact(3).map(|p| [p[0], p[1], p[2]]).collect(),
+            face_offsets: as_index(offsets, labels.len() + 1, "face offsets")?,
+            face_labels: as_index(labels, n_points, "face point labels")?,
+            owner: as_index(owner, n_cells, "owner labels")?,
+            neighbour: as_index(neighbour, n_cells, "neighbour labels")?,
+            n_cells,
+        };
+        if mesh.face_offsets.windows(2).any(|w| w[0] > w[1]) {
+            return Err(invalid("face offsets decrease".to_string()));
+        }
+        if mesh.owner.len() + 1 != mesh.face_offsets.len() {
+            return Err(invalid(format!(
+                "owner has {} entries for {} faces",
+                mesh.owner.len(),
+                mesh.face_offsets.len().saturating_sub(1)
+            )));
+        }
+        if mesh.neighbour.len() > mesh.owner.len() {
+            return Err(invalid(format!(
+                "neighbour has {} entries for {} faces",
+                mesh.neighbour.len(),
+                mesh.owner.len()
+            )));
+        }
+        Ok(Some(mesh))
+    }
+
+    pub fn n_faces(&self) -> usize {
+        self.owner.len()
+    }
+
+    pub fn face(&self, i: usize) -> &[usize] {
+        &self.face_labels[self.face_offsets[i]..self.face_offsets[i + 1]]
+    }
+}
+
+/// Read constant/polyMesh/points, or constant/<region>/polyMesh/points for
+/// one region, as an (N, 3) float64 array. ASCII, binary and
+/// gzip-compressed files are supported. Returns None if there is no points
+/// file.
+#[pyfunction]
+#[pyo3(signature = (case_root, region = None, options = None))]
+pub fn read_points<'py>(
+    py: Python<'py>,
+    case_root: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyArray2<f64>>>> {
+    let points = options::detach(py, &options, || load_points(&mesh_dir(Path::new(&case_root), region.as_deref())))?;
+    match points {
+        Some(p) => {
+            let rows = p.len() / 3;
+            Ok(Some(p.into_pyarray(py).reshape([rows, 3])?))
+        }
+        None => Ok(None),
+    }
+}
+
+fn malformed(path: &Path, what: &str) -> PyErr {
+    PyValueError::new_err(format!("{}: malformed or truncated {}", path.display(), what))
+}
+
+// ASCII faceList body: `n(l0 l1 ... )` per face
+fn parse_face_list(body: &[u8], count: usize) -> Option<(Vec<i64>, Vec<i64>)> {
+    let mut offsets = Vec::with_capacity(reserve_bounded(count, body.len()) + 1);
+    let mut labels = Vec::with_capacity(reserve_bounded(count.saturating_mul(4), body.len()));
+    offsets.push(0);
+    let mut tokens = body
+        .split(|b| matches!(*b, b' ' | b'\n' | b'\t' | b'\r' | b'(' | b')'))
+        .filter(|t| !t.is_empty());
+    while let Some(tok) = tokens.next() {
+        let n: usize = std::str::from_utf8(tok).ok()?.parse().ok()?;
+        for _ in 0..n {
+            labels.push(std::str::from_utf8(tokens.next()?).ok()?.parse().ok()?);
+        }
+        offsets.push(labels.len() as i64);
+    }
+    (offsets.len() - 1 == count).then_some((offsets, labels))
+}
+
+// Faces in compact form: face i uses labels[offsets[i]..offsets[i + 1]].
+// Handles faceList (ASCII) as well as faceCompactList (ASCII or binary),
+// which stores the offsets and the labels as two consecutive label lists.
+pub fn load_faces(mesh_dir: &Path) -> PyResult<Option<(Vec<i64>, Vec<i64>)>> {
+    let path = mesh_dir.join("faces");
+    let data = match open_field_file(&path.to_string_lossy())? {
+        Some(d) => d,
+        None => return Ok(None),
+    };
+    let fmt = ListFormat::of(&data);
+    let compact = parse_header(&data).is_some_and(|h| h.get("class") == Some("faceCompactList"));
+
+    let faces = if compact {
+        label_list_at(&data, header_end(&data), fmt)
+            .and_then(|(offsets, next)| label_list_at(&data, next, fmt).map(|(labels, _)| (offsets, labels)))
+    } else {
+        match list_body(&data, header_end(&data), fmt, fmt.label_bytes) {
+            Some((ListBody::Items(count, body), _)) if !fmt.binary => parse_face_list(body, count),
+            _ => None,
+        }
+    };
+    faces.map(Some).ok_or_else(|| malformed(&path, "face list"))
+}
+
+// owner, neighbour and other plain label lists of the mesh
+pub fn load_labels(mesh_dir: &Path, name: &str) -> PyResult<Option<Vec<i64>>> {
+    let path = mesh_dir.join(name);
+    let data = match open_field_file(&path.to_string_lossy())? {
+        Some(d) => d,
+        None => return Ok(None),
+    };
+    read_label_list(&data).map(Some).ok_or_else(|| malformed(&path, "label list"))
+}
+
+// Cells are numbered 0..nCells, and every cell owns at least one face
+pub fn cell_count(owner: &[i64], neighbour: &[i64]) -> usize {
+    owner.iter().chain(neighbour).max().map_or(0, |m| *m as usize + 1)
+}
+
+// (offsets, labels) as handed back to Python
+type CompactFaces<'py> = (Bound<'py, PyArray1<i64>>, Bound<'py, PyArray1<i64>>);
+
+/// Read constant/polyMesh/faces in compact form as (offsets, labels) int64
+/// arrays: face i uses labels[offsets[i]:offsets[i + 1]]. `region` reads a
+/// region's faces from constant/<region>/polyMesh. Returns None if there is
+/// no faces file.
+#[pyfunction]
+#[pyo3(signature = (case_root, region = None, options = None))]
+pub fn read_faces<'py>(
+    py: Python<'py>,
+    case_root: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<CompactFaces<'py>>> {
+    let faces = options::detach(py, &options, || load_faces(&mesh_dir(Path::new(&case_root), region.as_deref())))?;
+    Ok(faces.map(|(offsets, labels)| (offsets.into_pyarray(py), labels.into_pyarray(py))))
+}
+
+/// Read the owner file of constant/polyMesh, or of `region`'s polyMesh, as
+/// an int64 array (one owner cell per face).
+#[pyfunction]
+#[pyo3(signature = (case_root, region = None, options = None))]
+pub fn read_owner<'py>(
+    py: Python<'py>,
+    case_root: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyArray1<i64>>>> {
+    let owner = options::detach(py, &options, || load_labels(&mesh_dir(Path::new(&case_root), region.as_deref()), "owner"))?;
+    Ok(owner.map(|o| o.into_pyarray(py)))
+}
+
+/// Read constant/polyMesh/neighbour (constant/<region>/polyMesh/neighbour
+/// with `region`) as an int64 array, one neighbour cell per internal face.
+#[pyfunction]
+#[pyo3(signature = (case_root, region = None, options = None))]
+pub fn read_neighbour<'py>(
+    py: Python<'py>,
+    case_root: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyArray1<i64>>>> {
+    let neighbour = options::detach(py, &options, || load_labels(&mesh_dir(Path::new(&case_root), region.as_deref()), "neighbour"))?;
+    Ok(neighbour.map(|n| n.into_pyarray(py)))
+}
+
+/// Read faces, owner and neighbour of the mesh (or of `region`'s mesh)
+/// together. Returns a dict with the arrays `face_offsets`, `face_labels`,
+/// `owner` and `neighbour` plus the counts `n_faces`, `n_internal_faces` and
+/// `n_cells`, or None if any file is missing.
+#[pyfunction]
+#[pyo3(signature = (case_root, region = None, options = None))]
+pub fn read_mesh_topology<'py>(
+    py: Python<'py>,
+    case_root: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let topology = options::detach(py, &options, || -> PyResult<Option<_>> {
+        let dir = mesh_dir(Path::new(&case_root), region.as_deref());
+        let (faces, owner, neighbour) = match (load_faces(&dir)?, load_labels(&dir, "owner")?, load_labels(&dir, "neighbour")?) {
+            (Some(f), Some(o), Some(n)) => (f, o, n),
+            _ => return Ok(None),
+        };
+        Ok(Some((faces, owner, neighbour)))
+    })?;
+
+    let ((offsets, labels), owner, neighbour) = match topology {
+        Some(t) => t,
+        None => return Ok(None),
+    };
+    let out = PyDict::new(py);
+    out.set_item("n_faces", offsets.len().saturating_sub(1))?;
+    out.set_item("n_internal_faces", neighbour.len())?;
+    out.set_item("n_cells", cell_count(&owner, &neighbour))?;
+    out.set_item("face_offsets", offsets.into_pyarray(py))?;
+    out.set_item("face_labels", labels.into_pyarray(py))?;
+    out.set_item("owner", owner.into_pyarray(py))?;
+    out.set_item("neighbour", neighbour.into_pyarray(py))?;
+    Ok(Some(out))
+}
+
+// One entry of constant/polyMesh/boundary
+#[derive(Clone)]
+pub struct Patch {
+    pub name: String,
+    pub patch_type: String,
+    pub n_faces: usize,
+    pub start_face: usize,
+    pub in_groups: Vec<String>,
+    // Remaining entries (neighbourPatch, transform, ...) as raw text
+    pub entries: Vec<(String, String)>,
+}
+
+// Words inside the parens of `List<word> 2(wall viscous)` or `(wall)`
+fn parse_word_list(value: &str) -> Vec<String> {
+    let inner = match (value.find('('), value.rfind(')')) {
+        (Some(a), Some(b)) if a < b => &value[a + 1..b],
+        _ => return Vec::new(),
+    };
+    inner.split_whitespace().map(|w| w.trim_matches('"').to_string()).collect()
+}
+
+fn parse_patch(name: &str, body: &str) -> Option<Patch> {
+    let mut patch = Patch {
+        name: name.to_string(),
+        patch_type: String::new(),
+        n_faces: 0,
+        start_face: 0,
+        in_groups: Vec::new(),
+        entries: Vec::new(),
+    };
+    for entry in body.split(';') {
+        let entry = entry.trim();
+        let (key, value) = match entry.split_once(char::is_whitespace) {
+            Some((k, v)) => (k, v.trim()),
+            None => continue,
+        };
+        match key {
+            "type" => patch.patch_type = value.to_string(),
+            "nFaces" => patch.n_faces = value.parse().ok()?,
+            "startFace" => patch.start_face = value.parse().ok()?,
+            "inGroups" => patch.in_groups = parse_word_list(value),
+            _ => patch.entries.push((key.to_string(), value.to_string())),
+        }
+    }
+    Some(patch)
+}
+
+// Patches of a polyBoundaryMesh file: `N ( name { ... } ... )`
+pub fn parse_boundary(data: &[u8]) -> Option<Vec<Patch>> {
+    let text = String::from_utf8_lossy(&data[header_end(data)..]);
+    let text = strip_comments(&text);
+    let open = text.find('(')?;
+    let close = text.rfind(')')?;
+    let mut rest = text.get(open + 1..close)?;
+
+    let mut patches = Vec::new();
+    while let Some(brace) = rest.find('{') {
+        let name = rest[..brace].trim();
+        // Patch dictionaries don't nest, so the next '}' closes this one
+        let end = brace + rest[brace..].find('}')?;
+        patches.push(parse_patch(name, &rest[brace + 1..end])?);
+        rest = &rest[end + 1..];
+    }
+    Some(patches)
+}
+
+pub fn load_boundary(mesh_dir: &Path) -> PyResult<Option<Vec<Patch>>> {
+    let path = mesh_dir.join("boundary");
+    let data = match open_field_file(&path.to_string_lossy())? {
+        Some(d) => d,
+        None => return Ok(None),
+    };
+    parse_boundary(&data).map(Some).ok_or_else(|| malformed(&path, "boundary file"))
+}
+
+pub fn patch_to_dict(py: Python<'_>, patch: Patch) -> PyResult<Bound<'_, PyDict>> {
+    let d = PyDict::new(py);
+    for (key, value) in patch.entries {
+        d.set_item(key, value)?;
+    }
+    d.set_item("name", patch.name)?;
+    d.set_item("type", patch.patch_type)?;
+    d.set_item("n_faces", patch.n_faces)?;
+    d.set_item("start_face", patch.start_face)?;
+    d.set_item("in_groups", patch.in_groups)?;
+    Ok(d)
+}
+
+/// Parse constant/polyMesh/boundary into a list of dicts with `name`, `type`,
+/// `n_faces`, `start_face` and `in_groups`, plus any other patch entries as raw
+/// strings. A region's patches come from constant/<region>/polyMesh/boundary.
+/// Returns None if there is no boundary file.
+#[pyfunction]
+#[pyo3(signature = (case_root, region = None, options = None))]
+pub fn read_boundary<'py>(
+    py: Python<'py>,
+    case_root: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<Vec<Bound<'py, PyDict>>>> {
+    let patches = options::detach(py, &options, || load_boundary(&mesh_dir(Path::new(&case_root), region.as_deref())))?;
+    let patches = match patches {
+        Some(p) => p,
+        None => return Ok(None),
+    };
+
+    patches.into_iter().map(|p| patch_to_dict(py, p)).collect::<PyResult<_>>().map(Some)
+}
+
+// A count from the `note "nPoints:.. nCells:.. nFaces:.. nInternalFaces:.."`
+// entry OpenFOAM writes into the owner header
+pub fn owner_note_count(owner: &[u8], key: &str) -> Option<usize> {
+    let header = parse_header(owner)?;
+    let note = header.get("note")?;
+    let rest = &note[note.find(&format!("{}:", key))? + key.len() + 1..];
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+// Declared length of the list following a file's header, without reading it
+fn list_count(data: &[u8]) -> Option<usize> {
+    let fmt = ListFormat::of(data);
+    let (body, _) = list_body(data, header_end(data), fmt, 0)?;
+    match body {
+        ListBody::Items(n, _) | ListBody::Uniform(n, _) => Some(n),
+    }
+}
+
+// Streamed bounding box of a points file: no point array is built
+fn points_bounds(data: &[u8]) -> Option<(Vec3, Vec3)> {
+    let fmt = ListFormat::of(data);
+    let (body, _) = list_body(data, header_end(data), fmt, 3 * fmt.scalar_bytes)?;
+    let bytes = match body {
+        ListBody::Items(_, b) => b,
+        ListBody::Uniform(_, b) => b,
+    };
+
+    let mut lo = [f64::INFINITY; 3];
+    let mut hi = [f64::NEG_INFINITY; 3];
+    let mut idx = 0;
+    let mut visit = |val: f64| {
+        lo[idx] = lo[idx].min(val);
+        hi[idx] = hi[idx].max(val);
+        idx = (idx + 1) % 3;
+    };
+    if fmt.binary {
+        // Decode one point at a time rather than the whole list
+        for chunk in bytes.chunks_exact(3 * fmt.scalar_bytes) {
+            decode_scalars(chunk, fmt.scalar_bytes).into_iter().for_each(&mut visit);
+        }
+    } else {
+        for_each_number(bytes, visit);
+    }
+    lo[0].is_finite().then_some((lo, hi))
+}
+
+/// Overview of constant/polyMesh, or of one region's mesh, read from
+/// headers, list counts and one streamed pass over the points: a dict with
+/// `n_points`, `n_faces`, `n_internal_faces`, `n_cells`, `bounds` ((xmin,
+/// ymin, zmin), (xmax, ymax, zmax)) and `patches`. Returns None if the mesh
+/// is missing.
+#[pyfunction]
+#[pyo3(signature = (case_root, region = None, options = None))]
+pub fn mesh_summary<'py>(
+    py: Python<'py>,
+    case_root: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let summary = options::detach(py, &options, || -> PyResult<Option<_>> {
+        let dir = mesh_dir(Path::new(&case_root), region.as_deref());
+        let points = match open_field_file(&dir.join("points").to_string_lossy())? {
+            Some(d) => d,
+            None => return Ok(None),
+        };
+        let owner = open_field_file(&dir.join("owner").to_string_lossy())?;
+        let neighbour = open_field_file(&dir.join("neighbour").to_string_lossy())?;
+
+        // The owner note has every count; the list lengths are the fallback.
+        // Without either, nCells needs the owner/neighbour labels themselves.
+        let note = |key| owner.as_deref().and_then(|o| owner_note_count(o, key));
+        let n_points = note("nPoints").or_else(|| list_count(&points));
+        let n_faces = note("nFaces").or_else(|| owner.as_deref().and_then(list_count));
+        let n_internal_faces = note("nInternalFaces").or_else(|| neighbour.as_deref().and_then(list_count));
+        let n_cells = match note("nCells") {
+            Some(n) => Some(n),
+            None => match (owner.as_deref().and_then(read_label_list), neighbour.as_deref().and_then(read_label_list)) {
+                (Some(o), Some(n)) => Some(cell_count(&o, &n)),
+                _ => None,
+            },
+        };
+
+        let bounds = points_bounds(&points);
+        let patches = load_boundary(&dir)?.unwrap_or_default();
+        Ok(Some((n_points, n_faces, n_internal_faces, n_cells, bounds, patches)))
+    })?;
+
+    let (n_points, n_faces, n_internal_faces, n_cells, bounds, patches) = match summary {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+    let out = PyDict::new(py);
+    out.set_item("n_points", n_points)?;
+    out.set_item("n_faces", n_faces)?;
+    out.set_item("n_internal_faces", n_internal_faces)?;
+    out.set_item("n_cells", n_cells)?;
+    let as_tuple = |v: Vec3| (v[0], v[1], v[2]);
+    out.set_item("bounds", bounds.map(|(lo, hi)| (as_tuple(lo), as_tuple(hi))))?;
+    let patches: Vec<_> = patches.into_iter().map(|p| patch_to_dict(py, p)).collect::<PyResult<_>>()?;
+    out.set_item("patches", patches)?;
+    Ok(Some(out))
+}
+
+// Region names in constant/regionProperties, `regions (fluid (air) solid
+// (heater fins));`, in the order listed
+fn region_properties(case_root: &Path) -> PyResult<Option<Vec<String>>> {
+    let path = case_root.join("constant").join("regionProperties");
+    let Some(dict) = load_dict(&path.to_string_lossy())? else {
+        return Ok(None);
+    };
+    let Some([Value::List(groups)]) = dict.stream("regions") else {
+        return Ok(None);
+    };
+    let names = groups
+        .iter()
+        .filter_map(|v| match v {
+            Value::List(names) => Some(names),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|v| match v {
+            Value::Word(w) | Value::Str(w) => Some(w.clone()),
+            _ => None,
+        })
+        .collect();
+    Ok(Some(names))
+}
+
+// Regions of a case as list_regions gives them
+pub fn region_names(case_root: &Path) -> PyResult<Vec<String>> {
+    if let Some(names) = region_properties(case_root)? {
+        return Ok(names);
+    }
+    let entries = match std::fs::read_dir(case_root.join("constant")) {
+        Ok(e) => e,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut regions = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name != "polyMesh" && entry.file_type()?.is_dir() && mesh_dir(case_root, Some(&name)).is_dir() {
+            regions.push(name);
+        }
+    }
+    regions.sort();
+    Ok(regions)
+}
+
+/// Regions of a multi-region case (chtMultiRegionFoam and the like): the
+/// names listed in constant/regionProperties, or without one the
+/// directories under constant/ holding their own polyMesh, sorted. Empty for
+/// a single-region case. Pass a name as `region` to the mesh, field and
+/// stats readers.
+#[pyfunction]
+#[pyo3(signature = (case_root, options = None))]
+pub fn list_regions(py: Python, case_root: String, options: Option<Options>) -> PyResult<Vec<String>> {
+    options::detach(py, &options, || region_names(Path::new(&case_root)))
+}
@@ -0,0 +1,258 @@
+// Resource usage of a running solver, sampled from /proc on a Rust thread.
+// A sample covers the process and all its descendants, so an mpirun launch
+// counts every rank. CPU and I/O are rates over the interval since the
+// previous sample, worked out per process so that ranks starting or exiting
+// between samples don't distort the totals.
+
+use numpy::IntoPyArray;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Clone, Copy)]
+struct Sample {
+    // Seconds since monitoring started
+    time: f64,
+    cpu_percent: f64,
+    rss: u64,
+    read_rate: f64,
+    write_rate: f64,
+    processes: usize,
+}
+
+// Counters of one process: CPU ticks and bytes read from and written to storage
+#[derive(Clone, Copy, Default)]
+struct Counters {
+    ticks: u64,
+    rss: u64,
+    read: u64,
+    write: u64,
+}
+
+#[cfg(target_os = "linux")]
+mod proc_fs {
+    use super::Counters;
+    use std::collections::HashMap;
+
+    // (ppid, utime + stime, rss pages) from /proc/<pid>/stat. The command
+    // name may hold spaces and parentheses, so fields are counted from the
+    // last ')'.
+    fn stat(pid: u32) -> Option<(u32, u64, u64)> {
+        let text = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        let fields: Vec<&str> = text[text.rfind(')')? + 1..].split_whitespace().collect();
+        let field = |i: usize| fields.get(i)?.parse::<u64>().ok();
+        Some((field(1)? as u32, field(11)? + field(12)?, field(21)?))
+    }
+
+    // read_bytes and write_bytes of /proc/<pid>/io; zero where it can't be
+    // read (another user's process)
+    fn io(pid: u32) -> (u64, u64) {
+        let Ok(text) = std::fs::read_to_string(format!("/proc/{}/io", pid)) else {
+            return (0, 0);
+        };
+        let value = |key: &str| {
+            text.lines().find_map(|l| l.strip_prefix(key)).and_then(|v| v.trim().parse().ok()).unwrap_or(0)
+        };
+        (value("read_bytes:"), value("write_bytes:"))
+    }
+
+    pub fn alive(pid: u32) -> bool {
+        // A zombie has exited; only its parent hasn't reaped it yet
+        std::fs::read_to_string(format!("/proc/{}/stat", pid))
+            .is_ok_and(|t| t.rfind(')').is_some_and(|i| !t[i + 1..].trim_start().starts_with('Z')))
+    }
+
+    // Counters of `root` and every process descending from it
+    pub fn tree(root: u32) -> HashMap<u32, Counters> {
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as u64;
+        let mut all = HashMap::new();
+        if let Ok(entries) = std::fs::read_dir("/proc") {
+            for pid in entries.flatten().filter_map(|e| e.file_name().to_str()?.parse::<u32>().ok()) {
+                if let Some(stat) = stat(pid) {
+                    all.insert(pid, stat);
+                }
+            }
+        }
+        let mut members = vec![root];
+        let mut i = 0;
+        while i < members.len() {
+            let parent = members[i];
+            members.extend(all.iter().filter(|(pid, s)| s.0 == parent && **pid != parent).map(|(pid, _)| *pid));
+            i += 1;
+        }
+        members
+            .into_iter()
+            .filter_map(|pid| {
+                let (_, ticks, rss) = *all.get(&pid)?;
+                let (read, write) = io(pid);
+                Some((pid, Counters { ticks, rss: rss * page, read, write }))
+            })
+            .collect()
+    }
+
+    pub fn ticks_per_second() -> f64 {
+        unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as f64
+    }
+}
+
+struct Shared {
+    samples: Mutex<VecDeque<Sample>>,
+    stop: AtomicBool,
+    done: AtomicBool,
+}
+
+#[cfg(target_os = "linux")]
+fn sample_loop(pid: u32, interval: Duration, history: usize, shared: Arc<Shared>) {
+    use std::time::Instant;
+    let started = Instant::now();
+    let tick = proc_fs::ticks_per_second();
+    let mut previous = proc_fs::tree(pid);
+    let mut at = Instant::now();
+    while !shared.stop.load(Ordering::Relaxed) && proc_fs::alive(pid) {
+        // Sleep in short steps so stop() is prompt
+        let due = at + interval;
+        while Instant::now() < due && !shared.stop.load(Ordering::Relaxed) {
+            std::thread::sleep((due - Instant::now()).min(Duration::from_millis(50)));
+        }
+        let current = proc_fs::tree(pid);
+        if current.is_empty() {
+            break;
+        }
+        let now = Instant::now();
+        let elapsed = (now - at).as_secs_f64().max(1e-9);
+        let (mut ticks, mut read, mut write) = (0, 0, 0);
+        for (pid, c) in &current {
+            let p = previous.get(pid).copied().unwrap_or_default();
+            ticks += c.ticks.saturating_sub(p.ticks);
+            read += c.read.saturating_sub(p.read);
+            write += c.write.saturating_sub(p.write);
+        }
+        let sample = Sample {
+            time: (now - started).as_secs_f64(),
+            cpu_percent: 100.0 * ticks as f64 / tick / elapsed,
+            rss: current.values().map(|c| c.rss).sum(),
+            read_rate: read as f64 / elapsed,
+            write_rate: write as f64 / elapsed,
+            processes: current.len(),
+        };
+        let mut samples = shared.samples.lock().unwrap();
+        if samples.len() == history {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+        drop(samples);
+        (previous, at) = (current, now);
+    }
+    shared.done.store(true, Ordering::Relaxed);
+}
+
+/// Resource usage of a process tree sampled in the background; see
+/// monitor_pid().
+#[pyclass(frozen, module = "accelerator")]
+pub struct ProcessMonitor {
+    #[pyo3(get)]
+    pid: u32,
+    shared: Arc<Shared>,
+}
+
+#[pymethods]
+impl ProcessMonitor {
+    /// The samples so far as a dict of arrays: time (seconds since
+    /// monitoring started), cpu_percent (100 per busy core), rss (bytes),
+    /// read_rate and write_rate (bytes per second to and from storage) and
+    /// processes (how many were in the tree).
+    fn series<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let samples: Vec<Sample> = self.shared.samples.lock().unwrap().iter().copied().collect();
+        let out = PyDict::new(py);
+        let column = |f: fn(&Sample) -> f64| samples.iter().map(f).collect::<Vec<f64>>().into_pyarray(py);
+        out.set_item("time", column(|s| s.time))?;
+        out.set_item("cpu_percent", column(|s| s.cpu_percent))?;
+        out.set_item("rss", samples.iter().map(|s| s.rss).collect::<Vec<u64>>().into_pyarray(py))?;
+        out.set_item("read_rate", column(|s| s.read_rate))?;
+        out.set_item("write_rate", column(|s| s.write_rate))?;
+        out.set_item("processes", samples.iter().map(|s| s.processes as u64).collect::<Vec<u64>>().into_pyarray(py))?;
+        Ok(out)
+    }
+
+    /// The newest sample as a dict of numbers, with the keys of series();
+    /// None before the first.
+    fn latest<'py>(&self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyDict>>> {
+        let Some(s) = self.shared.samples.lock().unwrap().back().copied() else {
+            return Ok(None);
+        };
+        let out = PyDict::new(py);
+        out.set_item("time", s.time)?;
+        out.set_item("cpu_percent", s.cpu_percent)?;
+        out.set_item("rss", s.rss)?;
+        out.set_item("read_rate", s.read_rate)?;
+        out.set_item("write_rate", s.write_rate)?;
+        out.set_item("processes", s.processes)?;
+        Ok(Some(out))
+    }
+
+    /// Stop sampling. Sampling also stops by itself when the process exits.
+    fn stop(&self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+    }
+
+    #[getter]
+    fn running(&self) -> bool {
+        !self.shared.done.load(Ordering::Relaxed)
+    }
+
+    fn __len__(&self) -> usize {
+        self.shared.samples.lock().unwrap().len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ProcessMonitor(pid={}, samples={}, running={})", self.pid, self.__len__(), if self.running() { "True" } else { "False" })
+    }
+}
+
+impl Drop for ProcessMonitor {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+pub fn start(pid: u32, interval: f64, history: usize) -> PyResult<ProcessMonitor> {
+    let interval = Duration::try_from_secs_f64(interval)
+        .ok()
+        .filter(|i| !i.is_zero())
+        .ok_or_else(|| PyValueError::new_err("interval must be a positive number of seconds"))?;
+    if history == 0 {
+        return Err(PyValueError::new_err("history must be at least 1"));
+    }
+    let shared = Arc::new(Shared { samples: Mutex::new(VecDeque::new()), stop: AtomicBool::new(false), done: AtomicBool::new(false) });
+    #[cfg(target_os = "linux")]
+    {
+        if !proc_fs::alive(pid) {
+            return Err(PyValueError::new_err(format!("no running process {}", pid)));
+        }
+        let sampler = shared.clone();
+        std::thread::Builder::new()
+            .name(format!("accelerator-monitor-{}", pid))
+            .spawn(move || sample_loop(pid, interval, history, sampler))?;
+        Ok(ProcessMonitor { pid, shared })
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (interval, shared);
+        Err(pyo3::exceptions::PyNotImplementedError::new_err("process monitoring reads /proc and needs Linux"))
+    }
+}
+
+/// Sample the CPU, memory and storage I/O of process `pid` and all its
+/// descendants (the ranks of an mpirun, say) every `interval` seconds on a
+/// Rust thread, keeping the last `history` samples. Returns a
+/// ProcessMonitor; sampling stops when the process exits or on stop().
+/// Linux only.
+#[pyfunction]
+#[pyo3(signature = (pid, interval = 1.0, history = 3600))]
+pub fn monitor_pid(pid: u32, interval: f64, history: usize) -> PyResult<ProcessMonitor> {
+    start(pid, interval, history)
+}
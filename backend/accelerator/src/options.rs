@@ -0,0 +1,367 @@
+// Reader settings passed to the parsing functions as one object, so new
+// knobs don't have to be threaded through every signature. A call's
+// settings live in a thread-local for the duration of the call, on the
+// calling thread and on every rayon worker it uses, so the helpers deep in
+// the readers (open_field_file, ListFormat::of) pick them up directly.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::errors;
+use crate::source::{ReadOptions, ReadStrategy};
+
+/// A flag for calling off a long read from another thread, e.g. when the
+/// user navigates away: pass it as `Options(cancel=token)` and call
+/// `token.cancel()`. Mesh loads, time-series scans and reconstructions check
+/// it between files, time directories and ranks, and raise Cancelled. A
+/// token stays cancelled, so use a fresh one for the next read.
+#[pyclass(frozen, module = "accelerator")]
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+}
+
+#[pymethods]
+impl CancelToken {
+    #[new]
+    fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Ask reads using this token to stop at their next check.
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    #[getter]
+    fn cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("CancelToken(cancelled={})", if self.cancelled() { "True" } else { "False" })
+    }
+}
+
+// Oversubscribing the cores further only adds scheduling and memory cost
+const THREADS_PER_CORE: usize = 4;
+
+/// Settings accepted by every parsing function as `options=`.
+///
+/// `strict` (default True) makes the field readers raise for missing or
+/// unreadable files; False is the same as their `lenient=True`. `format`
+/// is "auto" (from the FoamFile header), "ascii" or "binary", for files
+/// whose header is missing or wrong. `read` is "auto" (memory-map, but read
+/// files on network filesystems such as NFS or SMB into memory when they fit
+/// under `max_memory`, or 1 GiB without a cap), "mmap", or "stream" (plain
+/// sequential reads, for filesystems where mmap page faults stall).
+/// `max_memory` caps the bytes held for a streamed or decompressed file;
+/// larger files raise MemoryError. `advise` sends MADV_SEQUENTIAL and
+/// MADV_WILLNEED for mapped files. `threads` sizes the worker pool for the
+/// call (default: one per core, at most four per core). `cache=False`
+/// reads the file again even when an unchanged copy's result is cached (see
+/// cache_clear). `cancel` takes a CancelToken for stopping the call from
+/// another thread.
+/// `progress` is a callable receiving `(done, total)` as a time-series scan,
+/// reconstruction or export works through its time directories, ranks or
+/// pieces: once at the start, at most every 0.2 s while running, and once
+/// at the end. It is called from worker threads; exceptions it raises are
+/// reported and otherwise ignored.
+#[pyclass(frozen, module = "accelerator")]
+#[derive(Clone)]
+pub struct Options {
+    #[pyo3(get)]
+    strict: bool,
+    #[pyo3(get)]
+    format: String,
+    #[pyo3(get)]
+    read: String,
+    #[pyo3(get)]
+    max_memory: Option<u64>,
+    #[pyo3(get)]
+    advise: bool,
+    #[pyo3(get)]
+    threads: Option<usize>,
+    #[pyo3(get)]
+    cache: bool,
+    #[pyo3(get)]
+    cancel: Option<CancelToken>,
+    progress: Option<Arc<Py<PyAny>>>,
+}
+
+#[pymethods]
+impl Options {
+    #[new]
+    #[pyo3(signature = (*, strict = true, format = "auto".to_string(), read = "auto".to_string(), max_memory = None, advise = true, threads = None, cache = true, cancel = None, progress = None))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        strict: bool,
+        format: String,
+        read: String,
+        max_memory: Option<u64>,
+        advise: bool,
+        threads: Option<usize>,
+        cache: bool,
+        cancel: Option<CancelToken>,
+        progress: Option<Bound<'_, PyAny>>,
+    ) -> PyResult<Self> {
+        if !matches!(format.as_str(), "auto" | "ascii" | "binary") {
+            return Err(PyValueError::new_err(format!("format must be 'auto', 'ascii' or 'binary', not '{}'", format)));
+        }
+        if !matches!(read.as_str(), "auto" | "mmap" | "stream") {
+            return Err(PyValueError::new_err(format!("read must be 'auto', 'mmap' or 'stream', not '{}'", read)));
+        }
+        if threads == Some(0) {
+            return Err(PyValueError::new_err("threads must be positive"));
+        }
+        let max_threads = std::thread::available_parallelism().map_or(1, |n| n.get()) * THREADS_PER_CORE;
+        if let Some(t) = threads.filter(|t| *t > max_threads) {
+            return Err(PyValueError::new_err(format!(
+                "threads must be at most {} ({} per core), not {}",
+                max_threads, THREADS_PER_CORE, t
+            )));
+        }
+        if let Some(p) = &progress {
+            if !p.is_callable() {
+                return Err(PyValueError::new_err("progress must be callable"));
+            }
+        }
+        let progress = progress.map(|p| Arc::new(p.unbind()));
+        Ok(Options { strict, format, read, max_memory, advise, threads, cache, cancel, progress })
+    }
+
+    #[getter]
+    fn progress(&self, py: Python<'_>) -> Option<Py<PyAny>> {
+        self.progress.as_ref().map(|p| p.clone_ref(py))
+    }
+
+    fn __repr__(&self) -> String {
+        let py_bool = |b: bool| if b { "True" } else { "False" };
+        let py_opt = |v: Option<String>| v.unwrap_or_else(|| "None".to_string());
+        format!(
+            "Options(strict={}, format='{}', read='{}', max_memory={}, advise={}, threads={}, cache={}, cancel={}, progress={})",
+            py_bool(self.strict),
+            self.format,
+            self.read,
+            py_opt(self.max_memory.map(|m| m.to_string())),
+            py_bool(self.advise),
+            py_opt(self.threads.map(|t| t.to_string())),
+            py_bool(self.cache),
+            py_opt(self.cancel.as_ref().map(|c| c.__repr__())),
+            if self.progress.is_some() { "<callable>" } else { "None" }
+        )
+    }
+}
+
+// The parts of Options the Rust side reads while parsing
+#[derive(Clone, Copy)]
+pub struct Settings {
+    pub read: ReadOptions,
+    // Some(binary) overrides the header's format entry
+    pub binary: Option<bool>,
+    pub cache: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { read: ReadOptions::default(), binary: None, cache: true }
+    }
+}
+
+impl From<&Options> for Settings {
+    fn from(o: &Options) -> Self {
+        let strategy = match o.read.as_str() {
+            "mmap" => ReadStrategy::Mmap,
+            "stream" => ReadStrategy::Stream,
+            _ => ReadStrategy::Auto,
+        };
+        let binary = match o.format.as_str() {
+            "ascii" => Some(false),
+            "binary" => Some(true),
+            _ => None,
+        };
+        Settings { read: ReadOptions { strategy, max_memory: o.max_memory, advise: o.advise }, binary, cache: o.cache }
+    }
+}
+
+// The running call's cancellation flag and progress callback, kept apart
+// from the Copy settings
+#[derive(Clone, Default)]
+struct Hooks {
+    cancel: Option<Arc<AtomicBool>>,
+    progress: Option<Arc<Py<PyAny>>>,
+}
+
+thread_local! {
+    static CURRENT: Cell<Settings> = Cell::new(Settings::default());
+    static HOOKS: RefCell<Hooks> = RefCell::new(Hooks::default());
+}
+
+// `lenient` unless the options ask for non-strict reading
+pub fn lenient(options: &Option<Options>, lenient: bool) -> bool {
+    lenient || options.as_ref().is_some_and(|o| !o.strict)
+}
+
+// Settings of the call running on this thread
+pub fn current() -> Settings {
+    CURRENT.with(|c| c.get())
+}
+
+// Whether the call running on this thread has been cancelled
+pub fn cancelled() -> bool {
+    HOOKS.with(|h| h.borrow().cancel.as_ref().is_some_and(|f| f.load(Ordering::Relaxed)))
+}
+
+// Raise Cancelled if the running call has been cancelled; long reads call
+// this between units of work
+pub fn check_cancelled() -> PyResult<()> {
+    if cancelled() {
+        return Err(errors::cancelled());
+    }
+    Ok(())
+}
+
+// Shortest time between two progress reports
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+// Reports (done, total) to the running call's progress callback as the items
+// of a loop complete; shared by the workers of a parallel loop. Does
+// nothing when the call has no callback.
+pub struct Progress {
+    callback: Option<Arc<Py<PyAny>>>,
+    total: usize,
+    done: AtomicUsize,
+    // When the last report was made; held while reporting so reports arrive
+    // in order
+    last: Mutex<Instant>,
+}
+
+impl Progress {
+    // Start tracking `total` items, reporting (0, total)
+    pub fn start(total: usize) -> Progress {
+        let callback = HOOKS.with(|h| h.borrow().progress.clone());
+        let progress = Progress { callback, total, done: AtomicUsize::new(0), last: Mutex::new(Instant::now()) };
+        progress.report(0);
+        progress
+    }
+
+    // One more item done
+    pub fn step(&self) {
+        if self.callback.is_none() {
+            return;
+        }
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if done >= self.total {
+            let _last = self.last.lock();
+            self.report(self.total);
+            return;
+        }
+        // Workers finding a report under way or a recent one skip theirs
+        let Ok(mut last) = self.last.try_lock() else {
+            return;
+        };
+        if last.elapsed() >= PROGRESS_INTERVAL {
+            self.report(self.done.load(Ordering::Relaxed).min(self.total - 1));
+            *last = Instant::now();
+        }
+    }
+
+    // The work ended before `total` steps, say by converging early
+    pub fn finish(&self) {
+        if self.callback.is_some() && self.done.swap(self.total, Ordering::Relaxed) < self.total {
+            let _last = self.last.lock();
+            self.report(self.total);
+        }
+    }
+
+    fn report(&self, done: usize) {
+        let Some(callback) = &self.callback else {
+            return;
+        };
+        Python::attach(|py| {
+            if let Err(e) = callback.call1(py, (done, self.total)) {
+                e.write_unraisable(py, Some(callback.bind(py)));
+            }
+        });
+    }
+}
+
+// The CancelToken passed in `options`, if any
+pub fn cancel_token(options: &Option<Options>) -> Option<CancelToken> {
+    options.as_ref()?.cancel.clone()
+}
+
+// Idle worker pools by thread count (0 for rayon's default). Workers carry
+// the settings of the call they serve, so a pool is taken out for one call
+// and put back for the next to reuse rather than shared between calls.
+// Concurrent calls each take a pool, but only a few per size are kept idle
+// afterwards; the sizes themselves are bounded by THREADS_PER_CORE.
+static POOLS: OnceLock<Mutex<HashMap<usize, Vec<ThreadPool>>>> = OnceLock::new();
+
+const IDLE_POOLS: usize = 2;
+
+fn take_pool(threads: usize) -> Result<ThreadPool, ThreadPoolBuildError> {
+    let idle = POOLS.get_or_init(Default::default).lock().unwrap().get_mut(&threads).and_then(Vec::pop);
+    match idle {
+        Some(pool) => Ok(pool),
+        None => ThreadPoolBuilder::new().num_threads(threads).build(),
+    }
+}
+
+fn put_pool(threads: usize, pool: ThreadPool) {
+    let mut pools = POOLS.get_or_init(Default::default).lock().unwrap();
+    let idle = pools.entry(threads).or_default();
+    if idle.len() < IDLE_POOLS {
+        idle.push(pool);
+    }
+}
+
+// Set `settings` and `hooks` on every worker of `pool`
+fn carry(pool: &ThreadPool, settings: Settings, hooks: &Hooks) {
+    pool.broadcast(|_| {
+        CURRENT.with(|c| c.set(settings));
+        HOOKS.with(|h| h.replace(hooks.clone()));
+    });
+}
+
+// Run `f` without the GIL and with `options` in effect. With options the
+// work runs on a pool sized by `threads`, reused from earlier calls, whose
+// workers all carry the settings; without, it runs as py.detach() would.
+pub fn detach<T, F>(py: Python, options: &Option<Options>, f: F) -> T
+where
+    T: Send,
+    F: FnOnce() -> T + Send,
+{
+    let Some(o) = options else {
+        return py.detach(f);
+    };
+    let settings = Settings::from(o);
+    let threads = o.threads.unwrap_or(0);
+    let hooks = Hooks { cancel: o.cancel.as_ref().map(|t| t.flag.clone()), progress: o.progress.clone() };
+    py.detach(move || match take_pool(threads) {
+        Ok(pool) => {
+            carry(&pool, settings, &hooks);
+            let out = pool.install(f);
+            // Idle workers shouldn't hold on to the progress callback
+            carry(&pool, Settings::default(), &Hooks::default());
+            put_pool(threads, pool);
+            out
+        }
+        // Nested parallel work then runs with the defaults, the calling
+        // thread's own work doesn't
+        Err(_) => {
+            let saved = CURRENT.with(|c| c.replace(settings));
+            let saved_hooks = HOOKS.with(|h| h.replace(hooks));
+            let out = f();
+            CURRENT.with(|c| c.set(saved));
+            HOOKS.with(|h| h.replace(saved_hooks));
+            out
+        }
+    })
+}
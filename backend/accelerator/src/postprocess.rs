@@ -0,0 +1,933 @@
+use numpy::{IntoPyArray, PyArrayMethods};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::downsample::{check_points, log10_series, lttb_indices, pick};
+use crate::time::time_dirs;
+
+// Whitespace-separated tokens of a line, keeping parenthesised groups such as
+// "(1 2 3)" or "Cl(f)" together
+pub fn top_level_tokens(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(s) = start.take() {
+                    tokens.push(&line[s..i]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(s) = start {
+        tokens.push(&line[s..]);
+    }
+    tokens
+}
+
+// Contents of a "(...)" token, or None for a plain value
+fn group_inner(token: &str) -> Option<&str> {
+    token.strip_prefix('(')?.strip_suffix(')')
+}
+
+// Column names for one data token. Plain values keep the header name;
+// groups are split into components, using the names a header like
+// "forces(pressure viscous porous)" lists when they line up, x/y/z for
+// 3-vectors and indices otherwise.
+fn expand_names(name: &str, token: &str, out: &mut Vec<String>) {
+    let Some(inner) = group_inner(token) else {
+        out.push(name.to_string());
+        return;
+    };
+    let parts = top_level_tokens(inner);
+    let listed: Option<(&str, Vec<&str>)> = name
+        .find('(')
+        .and_then(|open| Some((&name[..open], group_inner(&name[open..])?.split_whitespace().collect())));
+    for (i, part) in parts.iter().enumerate() {
+        let sub = match &listed {
+            Some((base, subs)) if subs.len() == parts.len() => format!("{}_{}", base, subs[i]),
+            _ if parts.len() == 3 => format!("{}_{}", name, ["x", "y", "z"][i]),
+            _ => format!("{}_{}", name, i),
+        };
+        expand_names(&sub, part, out);
+    }
+}
+
+// Flatten a row's tokens into numbers; anything unparsable becomes NaN so
+// columns stay aligned
+fn flatten_row(tokens: &[&str], out: &mut Vec<f64>) {
+    for token in tokens {
+        for leaf in token.split(|c: char| c == '(' || c == ')' || c.is_whitespace()) {
+            if !leaf.is_empty() {
+                out.push(leaf.parse().unwrap_or(f64::NAN));
+            }
+        }
+    }
+}
+
+// Column-oriented table from a function-object .dat file
+#[derive(Default)]
+pub struct Table {
+    pub names: Vec<String>,
+    pub columns: Vec<Vec<f64>>,
+}
+
+impl Table {
+    fn push_row(&mut self, row: &[f64]) {
+        for (col, v) in self.columns.iter_mut().zip(row) {
+            col.push(*v);
+        }
+    }
+
+    // Drop rows at or after `time`, which a restart segment overwrites
+    fn truncate_from(&mut self, time: f64) {
+        let keep = self.columns.first().map_or(0, |t| t.iter().take_while(|&&v| v < time).count());
+        for col in &mut self.columns {
+            col.truncate(keep);
+        }
+    }
+
+    // Append a later restart segment with the same layout
+    pub fn append(&mut self, other: Table) {
+        if self.names.is_empty() {
+            *self = other;
+            return;
+        }
+        if other.names != self.names {
+            return;
+        }
+        if let Some(&start) = other.columns.first().and_then(|t| t.first()) {
+            self.truncate_from(start);
+        }
+        for (col, more) in self.columns.iter_mut().zip(other.columns) {
+            col.extend(more);
+        }
+    }
+
+    // Thin to `max_points` rows that plot the same against time, the first
+    // column
+    pub fn downsample(&mut self, max_points: usize) {
+        let Some((time, rest)) = self.columns.split_first() else {
+            return;
+        };
+        let series: Vec<&[f64]> = rest.iter().map(Vec::as_slice).collect();
+        let kept = lttb_indices(time, &series, max_points);
+        if kept.len() < time.len() {
+            self.columns.iter_mut().for_each(|col| *col = pick(col, &kept));
+        }
+    }
+
+    pub fn into_dict<'py>(self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let out = PyDict::new(py);
+        for (name, col) in self.names.into_iter().zip(self.columns) {
+            out.set_item(name, col.into_pyarray(py))?;
+        }
+        Ok(out)
+    }
+}
+
+// Parse a .dat file: '#' comment lines, the last of which before the data
+// names the columns, then whitespace or tab separated rows. The first data row
+// fixes the layout; rows that don't match it (including a partial line a
+// running solver is still writing) are skipped. Columns without a usable
+// header are named col0, col1, ...
+pub fn parse_columns(text: &str) -> Table {
+    let mut header: Option<&str> = None;
+    let mut table = Table::default();
+    let mut row = Vec::new();
+    // Only newline-terminated lines are complete
+    let complete = &text[..text.rfind('\n').map_or(0, |i| i + 1)];
+    for line in complete.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            if table.names.is_empty() {
+                header = Some(comment);
+            }
+            continue;
+        }
+        let tokens = top_level_tokens(line);
+        row.clear();
+        flatten_row(&tokens, &mut row);
+        if table.names.is_empty() {
+            let header_tokens = header.map(top_level_tokens).unwrap_or_default();
+            let mut names = Vec::new();
+            for (i, token) in tokens.iter().enumerate() {
+                let name = match header_tokens.get(i) {
+                    Some(h) if header_tokens.len() == tokens.len() => h.to_string(),
+                    _ => format!("col{}", i),
+                };
+                expand_names(&name, token, &mut names);
+            }
+            if names.len() != row.len() {
+                continue;
+            }
+            table.columns = vec![Vec::new(); names.len()];
+            table.names = names;
+        }
+        if row.len() == table.names.len() {
+            table.push_row(&row);
+        }
+    }
+    table
+}
+
+// Function-object time history; the first column is always named "time"
+pub fn parse_table(text: &str) -> Table {
+    let mut table = parse_columns(text);
+    if let Some(first) = table.names.first_mut() {
+        *first = "time".to_string();
+    }
+    table
+}
+
+// postProcessing/<function>/<start time>/ directories, oldest first. Each
+// restart of the solver starts a new one.
+pub fn segment_dirs(case_root: &Path, function: &str) -> std::io::Result<Vec<PathBuf>> {
+    let root = case_root.join("postProcessing").join(function);
+    Ok(time_dirs(&root)?.into_iter().map(|(_, name)| root.join(name)).collect())
+}
+
+// Tables of one .dat file keyed by field. Most files hold a single table,
+// kept under "".
+pub type KeyedTables = BTreeMap<String, Table>;
+
+fn single_table(text: &str) -> KeyedTables {
+    BTreeMap::from([(String::new(), parse_table(text))])
+}
+
+// Read and merge a file from every segment of a function object. The first of
+// `file_names` present in a segment is used, since names changed between
+// OpenFOAM releases, falling back to "<function>.dat". Returns None if no
+// segment has the file.
+pub fn read_segments(
+    case_root: &Path,
+    function: &str,
+    file_names: &[&str],
+    parse: fn(&str) -> KeyedTables,
+) -> std::io::Result<Option<KeyedTables>> {
+    let mut merged: Option<KeyedTables> = None;
+    let fallback = format!("{}.dat", function);
+    for dir in segment_dirs(case_root, function)? {
+        let candidates = file_names.iter().copied().chain([fallback.as_str()]);
+        let Some(path) = candidates.map(|f| dir.join(f)).find(|p| p.is_file()) else {
+            continue;
+        };
+        let text = std::fs::read(&path)?;
+        let merged = merged.get_or_insert_with(BTreeMap::new);
+        for (key, table) in parse(&String::from_utf8_lossy(&text)) {
+            merged.entry(key).or_default().append(table);
+        }
+    }
+    Ok(merged)
+}
+
+// Function object directories under postProcessing whose name starts with
+// `prefix`, sorted by name
+fn function_dirs(case_root: &Path, prefix: &str) -> std::io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    let entries = match std::fs::read_dir(case_root.join("postProcessing")) {
+        Ok(e) => e,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(names),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        if let Ok(name) = entry.file_name().into_string() {
+            if name.starts_with(prefix) && entry.file_type()?.is_dir() {
+                names.push(name);
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+// Tables from the named function object, or from the first one starting with
+// `prefix` that has any of `file_names`
+fn read_named_or_first(
+    case_root: &Path,
+    name: Option<&str>,
+    prefix: &str,
+    file_names: &[&str],
+    parse: fn(&str) -> KeyedTables,
+) -> std::io::Result<Option<KeyedTables>> {
+    if let Some(name) = name {
+        return read_segments(case_root, name, file_names, parse);
+    }
+    for function in function_dirs(case_root, prefix)? {
+        if let Some(tables) = read_segments(case_root, &function, file_names, parse)? {
+            return Ok(Some(tables));
+        }
+    }
+    Ok(None)
+}
+
+// Column `column` of a function object's output with its times, merged over
+// restart segments, from whichever of its .dat files has that column
+pub fn read_function_column(case_root: &Path, function: &str, column: &str) -> std::io::Result<Option<(Vec<f64>, Vec<f64>)>> {
+    let mut files = BTreeSet::new();
+    for dir in segment_dirs(case_root, function)? {
+        for entry in std::fs::read_dir(dir)?.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".dat") {
+                files.insert(name);
+            }
+        }
+    }
+    for file in &files {
+        let Some(mut table) = read_segments(case_root, function, &[file], single_table)?.and_then(|mut t| t.remove("")) else {
+            continue;
+        };
+        if let Some(i) = table.names.iter().skip(1).position(|n| n == column) {
+            let values = table.columns.swap_remove(i + 1);
+            return Ok(Some((table.columns.swap_remove(0), values)));
+        }
+    }
+    Ok(None)
+}
+
+// Single-table function object output as a dict of columns
+fn read_single<'py>(
+    py: Python<'py>,
+    case_root: &str,
+    name: Option<&str>,
+    prefix: &str,
+    file_names: &[&str],
+    max_points: Option<usize>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    max_points.map(check_points).transpose()?;
+    let table = py.detach(|| -> std::io::Result<Option<Table>> {
+        let tables = read_named_or_first(Path::new(case_root), name, prefix, file_names, single_table)?;
+        let mut table = tables.and_then(|mut t| t.remove(""));
+        if let (Some(table), Some(max_points)) = (&mut table, max_points) {
+            table.downsample(max_points);
+        }
+        Ok(table)
+    })?;
+    table.map(|t| t.into_dict(py)).transpose()
+}
+
+/// Force coefficient history of a case from
+/// postProcessing/<name>/<time>/coefficient.dat (forceCoeffs.dat on older
+/// releases), with restart segments merged. Returns a dict of column name to
+/// array: time, Cd, Cl, Cm and whatever else the header lists. Without
+/// `name` the first postProcessing directory starting with "force" that has
+/// coefficients is used. With `max_points` the history is thinned to that
+/// many rows with downsample_lttb(), all columns kept at the same times.
+/// Returns None if there is no such output.
+#[pyfunction]
+#[pyo3(signature = (case_root, name=None, max_points=None))]
+pub fn read_force_coeffs<'py>(
+    py: Python<'py>,
+    case_root: String,
+    name: Option<String>,
+    max_points: Option<usize>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    read_single(py, &case_root, name.as_deref(), "force", &["coefficient.dat", "forceCoeffs.dat"], max_points)
+}
+
+/// Force and moment history of a case from postProcessing/<name>/<time>/
+/// force.dat (forces.dat on older releases), with restart segments merged.
+/// Vector columns are split into _x/_y/_z arrays, e.g. total_x or
+/// forces_pressure_x. Without `name` the first postProcessing directory
+/// starting with "force" that has forces is used. `max_points` thins the
+/// history as in read_force_coeffs(). Returns None if there is no such
+/// output.
+#[pyfunction]
+#[pyo3(signature = (case_root, name=None, max_points=None))]
+pub fn read_forces<'py>(
+    py: Python<'py>,
+    case_root: String,
+    name: Option<String>,
+    max_points: Option<usize>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    read_single(py, &case_root, name.as_deref(), "force", &["force.dat", "forces.dat"], max_points)
+}
+
+// Split "stat(field)" column names, e.g. "min(p)" or "max(U)_x", into the
+// field and the per-field column name ("min", "max_x")
+fn split_stat_column(name: &str) -> Option<(&str, String)> {
+    let open = name.find('(')?;
+    let close = open + name[open..].rfind(')')?;
+    Some((&name[open + 1..close], format!("{}{}", &name[..open], &name[close + 1..])))
+}
+
+// Regroup a wide table with min(p) max(p) min(U) ... columns per field
+fn split_wide_table(table: Table) -> KeyedTables {
+    let mut out = KeyedTables::new();
+    let Some(time) = table.columns.first().cloned() else {
+        return out;
+    };
+    for (name, col) in table.names.into_iter().zip(table.columns).skip(1) {
+        let Some((field, stat)) = split_stat_column(&name) else {
+            continue;
+        };
+        let t = out.entry(field.to_string()).or_insert_with(|| Table {
+            names: vec!["time".to_string()],
+            columns: vec![time.clone()],
+        });
+        t.names.push(stat);
+        t.columns.push(col);
+    }
+    out
+}
+
+// fieldMinMax output. With `location yes` every row names its field in the
+// second column and carries the min/max locations (and processor in parallel
+// runs); without it there is one row per time with min(field) and
+// max(field) columns.
+fn parse_min_max(text: &str) -> KeyedTables {
+    let mut header = "";
+    let mut wide = String::new();
+    let mut per_field: BTreeMap<String, String> = BTreeMap::new();
+    let complete = &text[..text.rfind('\n').map_or(0, |i| i + 1)];
+    for line in complete.lines() {
+        let trimmed = line.trim();
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            header = comment;
+            continue;
+        }
+        let tokens = top_level_tokens(trimmed);
+        let named = tokens.get(1).filter(|t| !t.starts_with('(') && t.parse::<f64>().is_err());
+        match named {
+            Some(field) => {
+                let rows = per_field.entry(field.to_string()).or_default();
+                for (i, token) in tokens.iter().enumerate() {
+                    if i != 1 {
+                        rows.push_str(token);
+                        rows.push(' ');
+                    }
+                }
+                rows.push('\n');
+            }
+            None => {
+                wide.push_str(trimmed);
+                wide.push('\n');
+            }
+        }
+    }
+
+    if per_field.is_empty() {
+        return split_wide_table(parse_table(&format!("#{}\n{}", header, wide)));
+    }
+    // Drop the "field" column from the header to match the rows
+    let header_tokens = top_level_tokens(header);
+    let header: Vec<&str> = header_tokens.iter().enumerate().filter(|(i, _)| *i != 1).map(|(_, t)| *t).collect();
+    let header = header.join(" ");
+    per_field
+        .into_iter()
+        .map(|(field, rows)| (field, parse_table(&format!("#{}\n{}", header, rows))))
+        .collect()
+}
+
+/// Min/max history from a fieldMinMax function object, with restart
+/// segments merged. Returns a dict mapping each field name to a dict of
+/// arrays: time, min and max, plus location(min)_x/_y/_z,
+/// location(max)_x/_y/_z and processor columns when the function object
+/// writes locations. Without `name` the first postProcessing directory
+/// starting with "fieldMinMax" is used. `max_points` thins each field's
+/// history as in read_force_coeffs(). Returns None if there is no such
+/// output.
+#[pyfunction]
+#[pyo3(signature = (case_root, name=None, max_points=None))]
+pub fn read_field_min_max<'py>(
+    py: Python<'py>,
+    case_root: String,
+    name: Option<String>,
+    max_points: Option<usize>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    max_points.map(check_points).transpose()?;
+    let tables = py.detach(|| -> std::io::Result<Option<KeyedTables>> {
+        let mut tables =
+            read_named_or_first(Path::new(&case_root), name.as_deref(), "fieldMinMax", &["fieldMinMax.dat"], parse_min_max)?;
+        if let (Some(tables), Some(max_points)) = (&mut tables, max_points) {
+            tables.values_mut().for_each(|t| t.downsample(max_points));
+        }
+        Ok(tables)
+    })?;
+    let Some(tables) = tables else {
+        return Ok(None);
+    };
+    let out = PyDict::new(py);
+    for (field, table) in tables {
+        out.set_item(field, table.into_dict(py)?)?;
+    }
+    Ok(Some(out))
+}
+
+/// History of a volFieldValue function object (volAverage, volIntegrate,
+/// min, max, ...) with restart segments merged. Returns a dict of column
+/// name to array, e.g. time, "volAverage(p)" and "volIntegrate(U)_x".
+/// Without `name` the first postProcessing directory starting with
+/// "volFieldValue" is used. `max_points` thins the history as in
+/// read_force_coeffs(). Returns None if there is no such output.
+#[pyfunction]
+#[pyo3(signature = (case_root, name=None, max_points=None))]
+pub fn read_vol_field_value<'py>(
+    py: Python<'py>,
+    case_root: String,
+    name: Option<String>,
+    max_points: Option<usize>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    read_single(py, &case_root, name.as_deref(), "volFieldValue", &["volFieldValue.dat"], max_points)
+}
+
+// Probe locations and samples of one probes output file
+pub struct Probes {
+    pub locations: Vec<f64>,
+    pub times: Vec<f64>,
+    pub values: Vec<f64>,
+    pub n_probes: usize,
+    pub width: usize,
+}
+
+impl Probes {
+    // Thin to `max_points` times, each probe value a series of its own
+    pub fn downsample(&mut self, max_points: usize) {
+        let row = self.values.len() / self.times.len().max(1);
+        let series: Vec<Vec<f64>> = (0..row).map(|k| self.values.iter().skip(k).step_by(row).copied().collect()).collect();
+        let series: Vec<&[f64]> = series.iter().map(Vec::as_slice).collect();
+        let kept = lttb_indices(&self.times, &series, max_points);
+        if kept.len() < self.times.len() {
+            self.times = pick(&self.times, &kept);
+            self.values = kept.iter().flat_map(|&i| self.values[i * row..(i + 1) * row].iter().copied()).collect();
+        }
+    }
+}
+
+// "# Probe 0 (0.0254 0.0253 0)" headers give the locations, then each row is
+// the time followed by one value or "(x y z)" tuple per probe
+pub fn parse_probes(text: &str) -> Probes {
+    let mut probes = Probes {
+        locations: Vec::new(),
+        times: Vec::new(),
+        values: Vec::new(),
+        n_probes: 0,
+        width: 0,
+    };
+    let mut row = Vec::new();
+    let complete = &text[..text.rfind('\n').map_or(0, |i| i + 1)];
+    for line in complete.lines() {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            let tokens = top_level_tokens(comment);
+            if let ["Probe", index, location] = tokens[..] {
+                if index.parse::<usize>().is_ok() && location.starts_with('(') {
+                    let before = probes.locations.len();
+                    flatten_row(&[location], &mut probes.locations);
+                    probes.locations.resize(before + 3, f64::NAN);
+                }
+            }
+            continue;
+        }
+        let tokens = top_level_tokens(line);
+        if tokens.len() < 2 {
+            continue;
+        }
+        if probes.width == 0 {
+            probes.n_probes = tokens.len() - 1;
+            let mut first = Vec::new();
+            flatten_row(&tokens[1..2], &mut first);
+            probes.width = first.len();
+        }
+        row.clear();
+        flatten_row(&tokens, &mut row);
+        // Skip rows with a different layout, e.g. a partial final line
+        if tokens.len() != probes.n_probes + 1 || row.len() != 1 + probes.n_probes * probes.width {
+            continue;
+        }
+        probes.times.push(row[0]);
+        probes.values.extend_from_slice(&row[1..]);
+    }
+    probes
+}
+
+/// Read a probes function-object file, e.g. postProcessing/probes/0/U.
+/// Returns a dict with "time" (N,), "locations" (P, 3) and "values", which is
+/// (N, P) for scalar fields and (N, P, 3) for vectors. With `max_points`
+/// the samples are thinned to that many times with downsample_lttb(), every
+/// probe kept at the same times. Returns None if the file is missing.
+#[pyfunction]
+#[pyo3(signature = (path, max_points=None))]
+pub fn read_probes<'py>(py: Python<'py>, path: String, max_points: Option<usize>) -> PyResult<Option<Bound<'py, PyDict>>> {
+    max_points.map(check_points).transpose()?;
+    let probes = py.detach(|| match std::fs::read(&path) {
+        Ok(text) => {
+            let mut probes = parse_probes(&String::from_utf8_lossy(&text));
+            if let Some(max_points) = max_points {
+                probes.downsample(max_points);
+            }
+            Ok(Some(probes))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    })?;
+    let Some(p) = probes else {
+        return Ok(None);
+    };
+
+    let rows = p.times.len();
+    let n_locations = p.locations.len() / 3;
+    let values = if p.width <= 1 {
+        p.values.into_pyarray(py).reshape([rows, p.n_probes])?.into_any()
+    } else {
+        p.values.into_pyarray(py).reshape([rows, p.n_probes, p.width])?.into_any()
+    };
+    let out = PyDict::new(py);
+    out.set_item("time", p.times.into_pyarray(py))?;
+    out.set_item("locations", p.locations.into_pyarray(py).reshape([n_locations, 3])?)?;
+    out.set_item("values", values)?;
+    Ok(Some(out))
+}
+
+const SET_EXTENSIONS: [&str; 4] = ["xy", "csv", "raw", "dat"];
+
+// Name columns of a headerless .xy/.raw sample from the field names in its
+// file name: one ("distance" or a single axis) or three (xyz) coordinate
+// columns, then equal-width blocks per field
+fn name_set_columns(table: &mut Table, fields: &[&str]) {
+    let n = table.names.len();
+    let layout = [1usize, 3].into_iter().find_map(|coords| {
+        let rest = n.checked_sub(coords)?;
+        (!fields.is_empty() && rest > 0 && rest % fields.len() == 0).then_some((coords, rest / fields.len()))
+    });
+    let Some((coords, width)) = layout else {
+        return;
+    };
+    let mut names: Vec<String> = if coords == 1 {
+        vec!["coord".to_string()]
+    } else {
+        ["x", "y", "z"].map(String::from).to_vec()
+    };
+    for field in fields {
+        match width {
+            1 => names.push(field.to_string()),
+            3 => names.extend(["x", "y", "z"].map(|c| format!("{}_{}", field, c))),
+            _ => names.extend((0..width).map(|i| format!("{}_{}", field, i))),
+        }
+    }
+    table.names = names;
+}
+
+// One sample file of a set, e.g. lineX1_p_T.xy or lineX1_U.csv
+fn read_set_file(path: &Path, set_name: &str) -> std::io::Result<Table> {
+    let text = std::fs::read(path)?;
+    let text = String::from_utf8_lossy(&text);
+    let is_csv = path.extension().is_some_and(|e| e == "csv");
+    if is_csv {
+        // The csv writer's first line names the columns
+        let mut lines = text.splitn(2, '\n');
+        let header = lines.next().unwrap_or("").replace(',', " ");
+        let body = lines.next().unwrap_or("").replace(',', " ");
+        return Ok(parse_columns(&format!("#{}\n{}", header, body)));
+    }
+    let mut table = parse_columns(&text);
+    if table.names.first().is_some_and(|n| n == "col0") {
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let fields: Vec<&str> = stem
+            .strip_prefix(set_name)
+            .and_then(|s| s.strip_prefix('_'))
+            .map(|s| s.split('_').collect())
+            .unwrap_or_default();
+        name_set_columns(&mut table, &fields);
+    }
+    Ok(table)
+}
+
+// Columns of every sample file of `set_name` in one time directory. Scalar
+// and vector fields go to separate files that repeat the coordinates, so
+// columns already present are only taken once.
+fn read_set_time(dir: &Path, set_name: &str) -> std::io::Result<Option<Table>> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let matches_set = name.strip_prefix(set_name).is_some_and(|r| r.starts_with('_') || r.starts_with('.'));
+        let known_ext = path.extension().and_then(|e| e.to_str()).is_some_and(|e| SET_EXTENSIONS.contains(&e));
+        if matches_set && known_ext {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    let mut merged: Option<Table> = None;
+    for path in files {
+        let table = read_set_file(&path, set_name)?;
+        let Some(m) = merged.as_mut() else {
+            merged = Some(table);
+            continue;
+        };
+        let rows = m.columns.first().map_or(0, Vec::len);
+        for (name, col) in table.names.into_iter().zip(table.columns) {
+            if col.len() == rows && !m.names.contains(&name) {
+                m.names.push(name);
+                m.columns.push(col);
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// Line samples of `set_name` from postProcessing/<function>/<time>/ for
+/// every written time (.xy, .csv and raw formats). Returns a dict with
+/// "time", an array of the sampled times, and "samples", a list with one dict
+/// per time mapping column name to array: the coordinate column(s) ("x",
+/// "distance", ... from a header, or "coord" / x, y, z without one) followed
+/// by the fields, vectors split into _x/_y/_z. Returns None if the set was
+/// never written.
+#[pyfunction]
+#[pyo3(signature = (case_root, set_name, function="sets"))]
+pub fn read_sampled_set<'py>(
+    py: Python<'py>,
+    case_root: String,
+    set_name: String,
+    function: &str,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let frames = py.detach(|| -> std::io::Result<Vec<(f64, Table)>> {
+        let root = Path::new(&case_root).join("postProcessing").join(function);
+        let mut frames = Vec::new();
+        for (t, name) in time_dirs(&root)? {
+            if let Some(table) = read_set_time(&root.join(name), &set_name)? {
+                frames.push((t, table));
+            }
+        }
+        Ok(frames)
+    })?;
+    if frames.is_empty() {
+        return Ok(None);
+    }
+
+    let (times, tables): (Vec<f64>, Vec<Table>) = frames.into_iter().unzip();
+    let samples = PyList::empty(py);
+    for table in tables {
+        samples.append(table.into_dict(py)?)?;
+    }
+    let out = PyDict::new(py);
+    out.set_item("time", times.into_pyarray(py))?;
+    out.set_item("samples", samples)?;
+    Ok(Some(out))
+}
+
+// Typed column of solverInfo output
+enum InfoColumn {
+    Float(Vec<f64>),
+    Bool(Vec<bool>),
+    Text(Vec<String>),
+}
+
+impl InfoColumn {
+    // Column type from the header name: "p_solver" is the solver's name,
+    // "p_converged" a flag and everything else a number
+    fn for_name(name: &str) -> Self {
+        if name.ends_with("_solver") {
+            InfoColumn::Text(Vec::new())
+        } else if name.ends_with("_converged") {
+            InfoColumn::Bool(Vec::new())
+        } else {
+            InfoColumn::Float(Vec::new())
+        }
+    }
+
+    fn push(&mut self, token: &str) {
+        match self {
+            InfoColumn::Float(v) => v.push(token.parse().unwrap_or(f64::NAN)),
+            InfoColumn::Bool(v) => v.push(matches!(token, "true" | "yes" | "on" | "1")),
+            InfoColumn::Text(v) => v.push(token.to_string()),
+        }
+    }
+
+    fn truncate(&mut self, len: usize) {
+        match self {
+            InfoColumn::Float(v) => v.truncate(len),
+            InfoColumn::Bool(v) => v.truncate(len),
+            InfoColumn::Text(v) => v.truncate(len),
+        }
+    }
+
+    fn extend(&mut self, other: InfoColumn) {
+        match (self, other) {
+            (InfoColumn::Float(a), InfoColumn::Float(b)) => a.extend(b),
+            (InfoColumn::Bool(a), InfoColumn::Bool(b)) => a.extend(b),
+            (InfoColumn::Text(a), InfoColumn::Text(b)) => a.extend(b),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Default)]
+struct SolverInfo {
+    names: Vec<String>,
+    columns: Vec<InfoColumn>,
+}
+
+impl SolverInfo {
+    fn times(&self) -> &[f64] {
+        match self.columns.first() {
+            Some(InfoColumn::Float(t)) => t,
+            _ => &[],
+        }
+    }
+
+    // Thin to `max_points` rows as Table::downsample does, residual columns
+    // (all but the iteration counts) by their logarithm
+    fn downsample(&mut self, max_points: usize) {
+        let series: Vec<Vec<f64>> = self
+            .names
+            .iter()
+            .zip(&self.columns)
+            .skip(1)
+            .filter_map(|(name, col)| match col {
+                InfoColumn::Float(v) if name.ends_with("_iters") => Some(v.clone()),
+                InfoColumn::Float(v) => Some(log10_series(v)),
+                _ => None,
+            })
+            .collect();
+        let series: Vec<&[f64]> = series.iter().map(Vec::as_slice).collect();
+        let kept = lttb_indices(self.times(), &series, max_points);
+        if kept.len() == self.times().len() {
+            return;
+        }
+        for col in &mut self.columns {
+            match col {
+                InfoColumn::Float(v) => *v = pick(v, &kept),
+                InfoColumn::Bool(v) => *v = pick(v, &kept),
+                InfoColumn::Text(v) => *v = pick(v, &kept),
+            }
+        }
+    }
+
+    // Same restart handling as Table::append
+    fn append(&mut self, other: SolverInfo) {
+        if self.names.is_empty() {
+            *self = other;
+            return;
+        }
+        if other.names != self.names {
+            return;
+        }
+        if let Some(&start) = other.times().first() {
+            let keep = self.times().iter().take_while(|&&t| t < start).count();
+            self.columns.iter_mut().for_each(|c| c.truncate(keep));
+        }
+        for (col, more) in self.columns.iter_mut().zip(other.columns) {
+            col.extend(more);
+        }
+    }
+}
+
+// solverInfo.dat: columns are discovered from the "# Time U_solver
+// Ux_initial Ux_final Ux_iters ... U_converged p_solver ..." header. The
+// older residuals function object (residuals.dat, "# Time p Ux Uy") parses
+// the same way with one initial-residual column per field.
+fn parse_solver_info(text: &str) -> SolverInfo {
+    let mut info = SolverInfo::default();
+    let mut header = "";
+    let complete = &text[..text.rfind('\n').map_or(0, |i| i + 1)];
+    for line in complete.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            if info.names.is_empty() {
+                header = comment;
+            }
+            continue;
+        }
+        let tokens = top_level_tokens(line);
+        if info.names.is_empty() {
+            let mut names: Vec<String> = top_level_tokens(header).iter().map(|t| t.to_string()).collect();
+            if names.len() != tokens.len() {
+                continue;
+            }
+            names[0] = "time".to_string();
+            info.columns = names.iter().map(|n| InfoColumn::for_name(n)).collect();
+            info.names = names;
+        }
+        if tokens.len() == info.names.len() {
+            for (col, token) in info.columns.iter_mut().zip(&tokens) {
+                col.push(token);
+            }
+        }
+    }
+    info
+}
+
+fn read_solver_info_segments(case_root: &Path, function: &str) -> std::io::Result<Option<SolverInfo>> {
+    let mut merged: Option<SolverInfo> = None;
+    for dir in segment_dirs(case_root, function)? {
+        let Some(path) = ["solverInfo.dat", "residuals.dat"].map(|f| dir.join(f)).into_iter().find(|p| p.is_file())
+        else {
+            continue;
+        };
+        let text = std::fs::read(&path)?;
+        merged.get_or_insert_with(SolverInfo::default).append(parse_solver_info(&String::from_utf8_lossy(&text)));
+    }
+    Ok(merged)
+}
+
+/// Residual history from a solverInfo (or residuals) function object, with
+/// restart segments merged, as an alternative to parsing the solver log.
+/// Returns a dict keyed by the header's column names: "time", numeric
+/// columns such as "Ux_initial", "Ux_final" and "Ux_iters" as float arrays,
+/// "<field>_converged" as bool arrays and "<field>_solver" as lists of solver
+/// names. Without `name` the first postProcessing directory starting with
+/// "solverInfo" or "residuals" is used. With `max_points` the history is
+/// thinned to that many rows with downsample_lttb(), residuals compared on a
+/// log scale as they are plotted. Returns None if there is no such output.
+#[pyfunction]
+#[pyo3(signature = (case_root, name=None, max_points=None))]
+pub fn read_solver_info<'py>(
+    py: Python<'py>,
+    case_root: String,
+    name: Option<String>,
+    max_points: Option<usize>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    max_points.map(check_points).transpose()?;
+    let info = py.detach(|| -> std::io::Result<Option<SolverInfo>> {
+        let root = Path::new(&case_root);
+        let mut info = None;
+        if let Some(name) = &name {
+            info = read_solver_info_segments(root, name)?;
+        } else {
+            'search: for prefix in ["solverInfo", "residuals"] {
+                for function in function_dirs(root, prefix)? {
+                    info = read_solver_info_segments(root, &function)?;
+                    if info.is_some() {
+                        break 'search;
+                    }
+                }
+            }
+        }
+        if let (Some(info), Some(max_points)) = (&mut info, max_points) {
+            info.downsample(max_points);
+        }
+        Ok(info)
+    })?;
+    let Some(info) = info else {
+        return Ok(None);
+    };
+
+    let out = PyDict::new(py);
+    for (name, col) in info.names.into_iter().zip(info.columns) {
+        match col {
+            InfoColumn::Float(v) => out.set_item(name, v.into_pyarray(py))?,
+            InfoColumn::Bool(v) => out.set_item(name, v.into_pyarray(py))?,
+            InfoColumn::Text(v) => out.set_item(name, v)?,
+        }
+    }
+    Ok(Some(out))
+}
@@ -0,0 +1,422 @@
+// Point location in the mesh: a KD-tree over cell centres to start from,
+// then a walk across faces to the cell that actually contains the point.
+// Locators are cached per mesh so repeated probes (clicks in the viewer)
+// don't rebuild the tree.
+
+use numpy::{IntoPyArray, PyArrayMethods};
+use pyo3::exceptions::{PyFileNotFoundError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use crate::field::{load_field, load_mesh, load_patches};
+use crate::geometry::{add, cross, dot, mag, scale, sub, MeshGeometry, Vec3};
+use crate::mesh::{mesh_dir, PolyMesh};
+use crate::options::{self, Options};
+use crate::source::resolve_field_path;
+use crate::surface::{cell_values, point_values};
+use crate::time::time_dir;
+
+// Balanced KD-tree stored implicitly: each slice of `order` has its median
+// at the middle, split on axis depth % 3
+pub struct KdTree {
+    points: Vec<Vec3>,
+    order: Vec<usize>,
+}
+
+impl KdTree {
+    pub fn build(points: Vec<Vec3>) -> KdTree {
+        fn split(points: &[Vec3], order: &mut [usize], depth: usize) {
+            if order.len() <= 1 {
+                return;
+            }
+            let axis = depth % 3;
+            let mid = order.len() / 2;
+            order.select_nth_unstable_by(mid, |&a, &b| points[a][axis].total_cmp(&points[b][axis]));
+            let (left, right) = order.split_at_mut(mid);
+            split(points, left, depth + 1);
+            split(points, &mut right[1..], depth + 1);
+        }
+        let mut order: Vec<usize> = (0..points.len()).collect();
+        split(&points, &mut order, 0);
+        KdTree { points, order }
+    }
+
+    // Index of the point nearest `p`
+    pub fn nearest(&self, p: Vec3) -> Option<usize> {
+        fn search(tree: &KdTree, lo: usize, hi: usize, depth: usize, p: Vec3, best: &mut (f64, usize)) {
+            if lo >= hi {
+                return;
+            }
+            let mid = lo + (hi - lo) / 2;
+            let i = tree.order[mid];
+            let r = sub(p, tree.points[i]);
+            let d2 = dot(r, r);
+            if d2 < best.0 {
+                *best = (d2, i);
+            }
+            let diff = r[depth % 3];
+            let (near, far) = if diff < 0.0 { ((lo, mid), (mid + 1, hi)) } else { ((mid + 1, hi), (lo, mid)) };
+            search(tree, near.0, near.1, depth + 1, p, best);
+            if diff * diff < best.0 {
+                search(tree, far.0, far.1, depth + 1, p, best);
+            }
+        }
+        let mut best = (f64::INFINITY, usize::MAX);
+        search(self, 0, self.order.len(), 0, p, &mut best);
+        (best.1 != usize::MAX).then_some(best.1)
+    }
+}
+
+pub struct CellLocator {
+    pub mesh: PolyMesh,
+    pub geom: MeshGeometry,
+    tree: KdTree,
+}
+
+// Barycentric coordinates of `p` in the tet `x`, if it is not degenerate
+fn barycentric(x: [Vec3; 4], p: Vec3) -> Option<[f64; 4]> {
+    let (e1, e2, e3, r) = (sub(x[1], x[0]), sub(x[2], x[0]), sub(x[3], x[0]), sub(p, x[0]));
+    let det = dot(e1, cross(e2, e3));
+    if det.abs() < 1e-300 {
+        return None;
+    }
+    let l1 = dot(r, cross(e2, e3)) / det;
+    let l2 = dot(e1, cross(r, e3)) / det;
+    let l3 = dot(e1, cross(e2, r)) / det;
+    Some([1.0 - l1 - l2 - l3, l1, l2, l3])
+}
+
+impl CellLocator {
+    pub fn build(mesh: PolyMesh) -> CellLocator {
+        let geom = MeshGeometry::compute(&mesh);
+        let tree = KdTree::build(geom.cell_centres.clone());
+        CellLocator { mesh, geom, tree }
+    }
+
+    // The cell containing `p`: start at the nearest cell centre and keep
+    // stepping through the face `p` lies furthest outside of. None if that
+    // face is on the boundary, i.e. `p` is outside the mesh.
+    pub fn find_cell(&self, p: Vec3) -> Option<usize> {
+        self.find_cell_from(p, self.tree.nearest(p)?)
+    }
+
+    // As find_cell, but walking from `cell`, e.g. the previous point's cell
+    // when stepping along a line
+    pub fn find_cell_from(&self, p: Vec3, mut cell: usize) -> Option<usize> {
+        let n_internal = self.mesh.neighbour.len();
+        for _ in 0..self.mesh.n_cells.max(1) {
+            let mut exit = None;
+            let mut furthest = 0.0;
+            for &f in self.geom.faces_of(cell) {
+                let area = self.geom.face_areas[f];
+                let length = mag(area);
+                if length == 0.0 {
+                    continue;
+                }
+                let outward = if self.mesh.owner[f] == cell { area } else { scale(area, -1.0) };
+                // Distance outside the face, with a tolerance on the cell size
+                let d = dot(sub(p, self.geom.face_centres[f]), outward) / length - 1e-9 * length.sqrt();
+                if d > furthest {
+                    furthest = d;
+                    exit = Some(f);
+                }
+            }
+            match exit {
+                None => return Some(cell),
+                Some(f) if f >= n_internal => return None,
+                Some(f) => {
+                    cell = if self.mesh.owner[f] == cell { self.mesh.neighbour[f] } else { self.mesh.owner[f] };
+                }
+            }
+        }
+        None
+    }
+
+    // Linear interpolation inside `cell` over its tets (cell centre, face
+    // centre and a face edge), with the cell value at the centre, point
+    // values at the corners and their mean at face centres
+    pub fn interpolate(&self, cell: usize, p: Vec3, cells: &[f64], points: &[f64], width: usize) -> Vec<f64> {
+        let own = cells[cell * width..(cell + 1) * width].to_vec();
+        let at = |i: usize| &points[i * width..(i + 1) * width];
+        for &f in self.geom.faces_of(cell) {
+            let face = self.mesh.face(f);
+            for (i, &a) in face.iter().enumerate() {
+                let b = face[(i + 1) % face.len()];
+                let tet = [self.geom.cell_centres[cell], self.geom.face_centres[f], self.mesh.points[a], self.mesh.points[b]];
+                let Some(l) = barycentric(tet, p) else {
+                    continue;
+                };
+                if l.iter().any(|w| *w < -1e-9) {
+                    continue;
+                }
+                let mut face_value = vec![0.0; width];
+                for &q in face {
+                    for (s, v) in face_value.iter_mut().zip(at(q)) {
+                        *s += v / face.len() as f64;
+                    }
+                }
+                return (0..width).map(|k| l[0] * own[k] + l[1] * face_value[k] + l[2] * at(a)[k] + l[3] * at(b)[k]).collect();
+            }
+        }
+        own
+    }
+
+    // Where the ray from `origin` along `dir` leaves `cell` after
+    // parameter `t`: the parameter and the exit face
+    fn exit(&self, cell: usize, origin: Vec3, dir: Vec3, t: f64) -> Option<(f64, usize)> {
+        let mut best: Option<(f64, usize)> = None;
+        for &f in self.geom.faces_of(cell) {
+            let area = self.geom.face_areas[f];
+            let outward = if self.mesh.owner[f] == cell { area } else { scale(area, -1.0) };
+            let rate = dot(dir, outward);
+            if rate <= 0.0 {
+                continue;
+            }
+            let tf = dot(sub(self.geom.face_centres[f], origin), outward) / rate;
+            // Allowing a touch behind `t` steps through cells the line only
+            // grazes at an edge or corner
+            if tf > t - 1e-12 && best.is_none_or(|(b, _)| tf < b) {
+                best = Some((tf, f));
+            }
+        }
+        best
+    }
+
+    // Cells crossed by the segment from `start` to `end`, with the
+    // parameter (0..1) range of each crossing
+    pub fn crossings(&self, start: Vec3, end: Vec3) -> Vec<(usize, f64, f64)> {
+        let dir = sub(end, start);
+        let mut out = Vec::new();
+        let Some(mut cell) = self.find_cell(start) else {
+            return out;
+        };
+        let n_internal = self.mesh.neighbour.len();
+        let mut t = 0.0;
+        for _ in 0..2 * self.mesh.n_cells {
+            if t >= 1.0 {
+                break;
+            }
+            let Some((exit, f)) = self.exit(cell, start, dir, t) else {
+                break;
+            };
+            let exit = exit.max(t);
+            if exit > t {
+                out.push((cell, t, exit.min(1.0)));
+            }
+            if f >= n_internal {
+                break;
+            }
+            cell = if self.mesh.owner[f] == cell { self.mesh.neighbour[f] } else { self.mesh.owner[f] };
+            t = exit;
+        }
+        out
+    }
+}
+
+type CachedLocator = (Option<SystemTime>, Arc<CellLocator>);
+
+static LOCATORS: OnceLock<Mutex<HashMap<PathBuf, CachedLocator>>> = OnceLock::new();
+
+// Newest modification time of the mesh files, to spot a re-meshed case
+pub fn mesh_stamp(dir: &Path) -> Option<SystemTime> {
+    ["points", "faces", "owner", "neighbour"]
+        .iter()
+        .filter_map(|name| resolve_field_path(&dir.join(name)))
+        .filter_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+// The locator for a case's mesh, built on first use and rebuilt when the
+// mesh files change. Only the last few meshes are kept.
+pub fn locator(case_root: &Path, region: Option<&str>) -> PyResult<Arc<CellLocator>> {
+    let dir = mesh_dir(case_root, region);
+    let stamp = mesh_stamp(&dir);
+    let cache = LOCATORS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some((cached, locator)) = cache.lock().unwrap().get(&dir) {
+        if *cached == stamp {
+            return Ok(locator.clone());
+        }
+    }
+    let locator = Arc::new(CellLocator::build(load_mesh(case_root, region)?));
+    let mut cache = cache.lock().unwrap();
+    if cache.len() >= 4 {
+        cache.clear();
+    }
+    cache.insert(dir, (stamp, locator.clone()));
+    Ok(locator)
+}
+
+/// Field values at arbitrary points, e.g. clicked in the viewer. Each point
+/// is located in its cell with a cached KD-tree of cell centres; `method`
+/// "linear" interpolates within the cell from the cell and point values,
+/// "nearest" returns the containing cell's value. Returns a dict with
+/// `values`, (n,) or (n, components) with NaN for points outside the mesh,
+/// and `cell`, the containing cell or -1. With `region`, the field and mesh
+/// of that region are used.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field, points, method = "linear".to_string(), region = None, options = None))]
+#[allow(clippy::too_many_arguments)]
+pub fn probe_points<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    field: String,
+    points: Vec<[f64; 3]>,
+    method: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let linear = probe_method(&method)?;
+    let (values, cells, width) = options::detach(py, &options, || {
+        let root = Path::new(&case_root);
+        let region = region.as_deref();
+        let path = time_dir(root, &time, region).join(&field);
+        probe_field(&*locator(root, region)?, root, region, &path, &points, linear)
+    })?;
+    probe_dict(py, values, cells, width)
+}
+
+// Whether `method` asks for linear interpolation
+pub fn probe_method(method: &str) -> PyResult<bool> {
+    match method {
+        "linear" => Ok(true),
+        "nearest" => Ok(false),
+        _ => Err(PyValueError::new_err(format!("unknown method '{}', expected 'linear' or 'nearest'", method))),
+    }
+}
+
+// Values at each point, `width` components each, the cells the points are
+// in (-1 outside the mesh) and `width`
+pub type Probed = (Vec<f64>, Vec<i64>, usize);
+
+// Probe the field file at `path`
+pub fn probe_field(locator: &CellLocator, root: &Path, region: Option<&str>, path: &Path, points: &[Vec3], linear: bool) -> PyResult<Probed> {
+    let file = load_field(path)?
+        .ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no such field file", path.display())))?;
+    let error = |e: String| PyValueError::new_err(format!("{}: {}", path.display(), e));
+    let width = file.width;
+    let cell_vals = cell_values(&locator.mesh, &file).map_err(error)?;
+    let point_vals = if linear {
+        point_values(&locator.mesh, &locator.geom, &load_patches(root, region)?, &file).map_err(error)?
+    } else {
+        Vec::new()
+    };
+    let mut values = Vec::with_capacity(points.len() * width);
+    let mut cells = Vec::with_capacity(points.len());
+    for &p in points {
+        let cell = locator.find_cell(p);
+        match cell {
+            Some(c) if linear => values.extend(locator.interpolate(c, p, &cell_vals, &point_vals, width)),
+            Some(c) => values.extend_from_slice(&cell_vals[c * width..(c + 1) * width]),
+            None => values.extend(std::iter::repeat_n(f64::NAN, width)),
+        }
+        cells.push(cell.map_or(-1, |c| c as i64));
+    }
+    Ok((values, cells, width))
+}
+
+pub fn probe_dict(py: Python<'_>, values: Vec<f64>, cells: Vec<i64>, width: usize) -> PyResult<Bound<'_, PyDict>> {
+    let out = PyDict::new(py);
+    let n = cells.len();
+    let values = values.into_pyarray(py);
+    if width == 1 {
+        out.set_item("values", values)?;
+    } else {
+        out.set_item("values", values.reshape([n, width])?)?;
+    }
+    out.set_item("cell", cells.into_pyarray(py))?;
+    Ok(out)
+}
+
+/// Field values along the straight line from `start` to `end`, e.g. a
+/// boundary-layer or wake profile. With `n` the line is sampled at n evenly
+/// spaced points (NaN where it is outside the mesh); with n=None it is
+/// sampled once per cell it crosses, at the midpoint of the crossing, which
+/// needs `start` inside the mesh. Values are interpolated linearly within
+/// cells. Returns a dict with `distance` from start (n,), `points` (n, 3),
+/// `values` (n,) or (n, components) and `cell` (n,), -1 outside the mesh.
+/// With `region`, the field and mesh of that region are used.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field, start, end, n = Some(100), region = None, options = None))]
+#[allow(clippy::too_many_arguments)]
+pub fn sample_line<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    field: String,
+    start: [f64; 3],
+    end: [f64; 3],
+    n: Option<usize>,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyDict>> {
+    type Samples = (Vec<f64>, Vec<f64>, Vec<f64>, Vec<i64>, usize);
+    let (distance, points, values, cells, width) = options::detach(py, &options, || -> PyResult<Samples> {
+        let root = Path::new(&case_root);
+        let region = region.as_deref();
+        let path = time_dir(root, &time, region).join(&field);
+        let file = load_field(&path)?
+            .ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no such field file", path.display())))?;
+        let locator = locator(root, region)?;
+        let error = |e: String| PyValueError::new_err(format!("{}: {}", path.display(), e));
+        let width = file.width;
+        let cell_vals = cell_values(&locator.mesh, &file).map_err(error)?;
+        let point_vals = point_values(&locator.mesh, &locator.geom, &load_patches(root, region)?, &file).map_err(error)?;
+
+        let dir = sub(end, start);
+        let length = mag(dir);
+        let samples: Vec<(f64, Option<usize>)> = match n {
+            Some(n) => {
+                let mut hint = None;
+                (0..n)
+                    .map(|i| {
+                        let t = if n > 1 { i as f64 / (n - 1) as f64 } else { 0.0 };
+                        let p = add(start, scale(dir, t));
+                        let cell = hint.and_then(|h| locator.find_cell_from(p, h)).or_else(|| locator.find_cell(p));
+                        hint = cell.or(hint);
+                        (t, cell)
+                    })
+                    .collect()
+            }
+            None => {
+                let crossed = locator.crossings(start, end);
+                if crossed.is_empty() {
+                    return Err(PyValueError::new_err("the line's start is outside the mesh"));
+                }
+                crossed.into_iter().map(|(c, t0, t1)| ((t0 + t1) / 2.0, Some(c))).collect()
+            }
+        };
+
+        let mut distance = Vec::with_capacity(samples.len());
+        let mut points = Vec::with_capacity(samples.len() * 3);
+        let mut values = Vec::with_capacity(samples.len() * width);
+        let mut cells = Vec::with_capacity(samples.len());
+        for (t, cell) in samples {
+            let p = add(start, scale(dir, t));
+            distance.push(t * length);
+            points.extend_from_slice(&p);
+            match cell {
+                Some(c) => values.extend(locator.interpolate(c, p, &cell_vals, &point_vals, width)),
+                None => values.extend(std::iter::repeat_n(f64::NAN, width)),
+            }
+            cells.push(cell.map_or(-1, |c| c as i64));
+        }
+        Ok((distance, points, values, cells, width))
+    })?;
+    let out = PyDict::new(py);
+    let n = cells.len();
+    out.set_item("distance", distance.into_pyarray(py))?;
+    out.set_item("points", points.into_pyarray(py).reshape([n, 3])?)?;
+    let values = values.into_pyarray(py);
+    if width == 1 {
+        out.set_item("values", values)?;
+    } else {
+        out.set_item("values", values.reshape([n, width])?)?;
+    }
+    out.set_item("cell", cells.into_pyarray(py))?;
+    Ok(out)
+}
@@ -0,0 +1,148 @@
+// setFields-style initialisation: cells whose centres fall inside a box,
+// sphere or cylinder get a fixed value, as boxToCell, sphereToCell and
+// cylinderToCell select them.
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use pyo3::exceptions::{PyFileNotFoundError, PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::io::Write;
+use std::path::Path;
+
+use crate::dict::write_atomic;
+use crate::field::{format_field_value, load_mesh, parse_field_file, FieldValue};
+use crate::geometry::{dot, mag, sub, CellGeometry, FaceGeometry, Vec3};
+use crate::list::ListFormat;
+use crate::source::{open_field_file, resolve_field_path, FieldData};
+
+enum Shape {
+    Box { min: Vec3, max: Vec3 },
+    Sphere { centre: Vec3, radius: f64 },
+    Cylinder { p1: Vec3, p2: Vec3, radius: f64 },
+}
+
+impl Shape {
+    fn contains(&self, p: Vec3) -> bool {
+        match self {
+            Shape::Box { min, max } => (0..3).all(|k| p[k] >= min[k] && p[k] <= max[k]),
+            Shape::Sphere { centre, radius } => mag(sub(p, *centre)) <= *radius,
+            Shape::Cylinder { p1, p2, radius } => {
+                let axis = sub(*p2, *p1);
+                let length2 = dot(axis, axis);
+                let d = sub(p, *p1);
+                let along = dot(d, axis);
+                along >= 0.0 && along <= length2 && dot(d, d) - along * along / length2 <= radius * radius
+            }
+        }
+    }
+}
+
+struct Region {
+    shape: Shape,
+    value: Vec<f64>,
+}
+
+fn region_from_py(spec: &Bound<PyDict>) -> PyResult<Region> {
+    let get = |key: &str| -> PyResult<Bound<PyAny>> {
+        spec.get_item(key)?.ok_or_else(|| PyKeyError::new_err(format!("region has no '{}'", key)))
+    };
+    let kind: String = get("type")?.extract()?;
+    let shape = match kind.as_str() {
+        "box" | "boxToCell" => Shape::Box { min: get("min")?.extract()?, max: get("max")?.extract()? },
+        "sphere" | "sphereToCell" => Shape::Sphere { centre: get("centre")?.extract()?, radius: get("radius")?.extract()? },
+        "cylinder" | "cylinderToCell" => {
+            let (p1, p2): (Vec3, Vec3) = (get("p1")?.extract()?, get("p2")?.extract()?);
+            if p1 == p2 {
+                return Err(PyValueError::new_err("cylinder p1 and p2 coincide"));
+            }
+            Shape::Cylinder { p1, p2, radius: get("radius")?.extract()? }
+        }
+        _ => return Err(PyValueError::new_err(format!("unknown region type '{}' (box, sphere or cylinder)", kind))),
+    };
+    let value = get("value")?;
+    let value = match value.extract::<f64>() {
+        Ok(v) => vec![v],
+        Err(_) => value.extract()?,
+    };
+    Ok(Region { shape, value })
+}
+
+// Sets the regions' values in the internalField of <time>/<field>, leaving
+// the rest of the file byte-identical. Returns the cells set per region.
+fn set_regions(root: &Path, time: &str, field: &str, regions: &[Region]) -> PyResult<Vec<usize>> {
+    let path = resolve_field_path(&root.join(time).join(field))
+        .ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no such field file", root.join(time).join(field).display())))?;
+    let error = |msg: String| PyValueError::new_err(format!("{}: {}", path.display(), msg));
+    let Some(data) = open_field_file(&path.to_string_lossy())? else {
+        return Err(error("empty field file".into()));
+    };
+    let file = parse_field_file(&data).map_err(error)?;
+    if !file.class.starts_with("vol") {
+        return Err(error(format!("{} is not a volume field", file.class)));
+    }
+    let fmt = ListFormat::of(&data);
+    if fmt.binary && fmt.scalar_bytes != 8 {
+        return Err(error(format!("{}-byte binary scalars are not supported", fmt.scalar_bytes)));
+    }
+    let width = file.width;
+    if let Some(r) = regions.iter().find(|r| r.value.len() != width) {
+        return Err(error(format!("region value has {} components, the field has {}", r.value.len(), width)));
+    }
+    let mesh = load_mesh(root, None)?;
+    let (Some(internal), Some(span)) = (&file.internal, file.internal_span.clone()) else {
+        return Err(error("no readable internalField".into()));
+    };
+    let mut values = internal
+        .expand(mesh.n_cells, width)
+        .ok_or_else(|| error(format!("internalField does not have {} values", mesh.n_cells)))?;
+
+    let centres = CellGeometry::compute(&mesh, &FaceGeometry::compute(&mesh)).centres;
+    let mut counts = Vec::with_capacity(regions.len());
+    for region in regions {
+        let mut count = 0;
+        for (cell, &c) in centres.iter().enumerate() {
+            if region.shape.contains(c) {
+                values[cell * width..(cell + 1) * width].copy_from_slice(&region.value);
+                count += 1;
+            }
+        }
+        counts.push(count);
+    }
+
+    let mut out = Vec::with_capacity(data.len() + values.len() * 8);
+    out.extend_from_slice(&data[..span.start]);
+    format_field_value(&FieldValue::NonUniform(values), width, fmt.binary, &mut out);
+    out.extend_from_slice(&data[span.end..]);
+    if matches!(data, FieldData::Decompressed(_)) {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&out)?;
+        out = encoder.finish()?;
+    }
+    write_atomic(&path, out)?;
+    Ok(counts)
+}
+
+/// Set the internalField of an existing volume field inside regions, like
+/// setFields for the common shapes. `regions` is a list of dicts applied
+/// in order (later regions win), each with a `type` and a `value` (a
+/// number, or a sequence matching the field's components):
+/// {"type": "box", "min": (x, y, z), "max": (x, y, z)},
+/// {"type": "sphere", "centre": (x, y, z), "radius": r} or
+/// {"type": "cylinder", "p1": (x, y, z), "p2": (x, y, z), "radius": r}.
+/// A cell is inside when its centre is. The field in `time` (default "0")
+/// is rewritten atomically with a nonuniform internalField in its own
+/// format; everything else in the file stays as written. Returns the
+/// number of cells set by each region.
+#[pyfunction]
+#[pyo3(signature = (case_root, field, regions, time = "0".to_string()))]
+pub fn set_field_regions(
+    py: Python,
+    case_root: String,
+    field: String,
+    regions: Vec<Bound<PyDict>>,
+    time: String,
+) -> PyResult<Vec<usize>> {
+    let regions = regions.iter().map(region_from_py).collect::<PyResult<Vec<_>>>()?;
+    py.detach(|| set_regions(Path::new(&case_root), &time, &field, &regions))
+}
@@ -0,0 +1,215 @@
+// Preview images rendered on the CPU, for thumbnails where there is no GPU
+// or VTK on the server. A plane section is laid flat in the image, its
+// triangles filled scanline by scanline with the field interpolated across
+// them, and the pixels written out as an RGBA PNG with a transparent
+// background.
+
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rayon::prelude::*;
+use std::io::Write;
+use std::path::Path;
+
+use crate::colormap::{finite_range, Colormap};
+use crate::geometry::{cross, dot, mag, scale, sub, Vec3};
+use crate::mesh::mesh_dir;
+use crate::options::{self, Options};
+use crate::surface::{plane_cut, TriSurface};
+use crate::zones::selected_cells;
+
+// Image axes for a plane seen from the side its normal points to: up is
+// the global z axis (y for planes facing up or down) laid in the plane
+fn image_axes(normal: Vec3) -> (Vec3, Vec3) {
+    let n = scale(normal, 1.0 / mag(normal));
+    let global = if n[2].abs() > 0.9 { [0.0, 1.0, 0.0] } else { [0.0, 0.0, 1.0] };
+    let up = sub(global, scale(n, dot(global, n)));
+    let up = scale(up, 1.0 / mag(up));
+    (cross(up, n), up)
+}
+
+// An RGBA8 image as a PNG: unfiltered scanlines, zlib-compressed
+pub fn png(width: usize, height: usize, rgba: &[u8]) -> Vec<u8> {
+    let chunk = |out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]| {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let mut crc = Crc::new();
+        crc.update(kind);
+        crc.update(data);
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        out.extend_from_slice(&crc.sum().to_be_bytes());
+    };
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, no filtering, not interlaced
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in rgba.chunks_exact(4 * width) {
+        let _ = encoder.write_all(&[0]);
+        let _ = encoder.write_all(row);
+    }
+    let data = encoder.finish().unwrap_or_default();
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &data);
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+// Fill the section's triangles into a `width` x `height` image. The section
+// is scaled to fit with its aspect ratio kept and centred.
+fn rasterize(surface: &TriSurface, normal: Vec3, width: usize, height: usize, colormap: &Colormap, range: (f64, f64)) -> Vec<u8> {
+    let mut rgba = vec![0u8; 4 * width * height];
+    let Some(values) = &surface.values else {
+        return rgba;
+    };
+    if surface.triangles.is_empty() {
+        return rgba;
+    }
+    let (right, up) = image_axes(normal);
+    let flat: Vec<[f64; 2]> = surface.points.iter().map(|&p| [dot(p, right), dot(p, up)]).collect();
+    let (lo, hi) = flat.iter().fold(([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]), |(lo, hi), p| {
+        ([lo[0].min(p[0]), lo[1].min(p[1])], [hi[0].max(p[0]), hi[1].max(p[1])])
+    });
+    let size = [(hi[0] - lo[0]).max(f64::MIN_POSITIVE), (hi[1] - lo[1]).max(f64::MIN_POSITIVE)];
+    let pixels = (width as f64 / size[0]).min(height as f64 / size[1]);
+    let margin = [(width as f64 - size[0] * pixels) / 2.0, (height as f64 - size[1] * pixels) / 2.0];
+    // Pixel coordinates, rows counted down from the top
+    let xy: Vec<[f64; 2]> = flat.iter().map(|p| [margin[0] + (p[0] - lo[0]) * pixels, height as f64 - margin[1] - (p[1] - lo[1]) * pixels]).collect();
+
+    // Each band of rows is filled by one thread from the triangles that reach it
+    const BAND: usize = 16;
+    rgba.par_chunks_mut(4 * width * BAND).enumerate().for_each(|(band, pixels)| {
+        let (top, bottom) = ((band * BAND) as f64, ((band * BAND) + pixels.len() / (4 * width)) as f64);
+        for tri in &surface.triangles {
+            let [a, b, c] = tri.map(|i| xy[i as usize]);
+            let (ymin, ymax) = (a[1].min(b[1]).min(c[1]), a[1].max(b[1]).max(c[1]));
+            if ymax < top || ymin >= bottom {
+                continue;
+            }
+            let area = (b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1]);
+            if area == 0.0 {
+                continue;
+            }
+            let [va, vb, vc] = tri.map(|i| values[i as usize]);
+            let x0 = a[0].min(b[0]).min(c[0]).floor().max(0.0) as usize;
+            let x1 = (a[0].max(b[0]).max(c[0]).ceil() as usize).min(width);
+            let y0 = ymin.floor().max(top) as usize;
+            let y1 = (ymax.ceil().min(bottom)) as usize;
+            for y in y0..y1 {
+                let py = y as f64 + 0.5;
+                for x in x0..x1 {
+                    let px = x as f64 + 0.5;
+                    // Barycentric weights of the pixel centre; shared edges
+                    // are filled by both triangles, which is harmless
+                    let wa = ((b[0] - px) * (c[1] - py) - (c[0] - px) * (b[1] - py)) / area;
+                    let wb = ((c[0] - px) * (a[1] - py) - (a[0] - px) * (c[1] - py)) / area;
+                    let wc = 1.0 - wa - wb;
+                    if wa < -1e-9 || wb < -1e-9 || wc < -1e-9 {
+                        continue;
+                    }
+                    let i = 4 * ((y - band * BAND) * width + x);
+                    pixels[i..i + 4].copy_from_slice(&colormap.rgba(wa * va + wb * vb + wc * vc, range.0, range.1));
+                }
+            }
+        }
+    });
+    rgba
+}
+
+// A plane given as "x", "y" or "z" (normal to that axis through the middle
+// of the mesh) or as (origin, normal)
+#[derive(FromPyObject)]
+pub enum PlaneSpec {
+    Axis(String),
+    Plane(Vec3, Vec3),
+}
+
+/// Render the section of a case by `plane` as a PNG thumbnail, on the CPU
+/// with no GPU or VTK. `plane` is "x", "y" or "z" for the plane normal to
+/// that axis through the middle of the mesh, or (origin, normal). The field
+/// is interpolated onto the section as in slice_plane() (magnitudes for
+/// vectors and tensors) and coloured through `cmap` (see apply_colormap()),
+/// spanning `vmin` to `vmax`, by default the range on the section. The
+/// section is fitted into `resolution` pixels, an int for the longer side
+/// with the other following the section's shape, or (width, height);
+/// pixels off the section are transparent. The plane is seen from the side
+/// its normal points to, with z up (y for planes normal to z). With
+/// `region`, the mesh and field of that region are used. Returns the PNG as
+/// bytes.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field, plane, resolution = None, cmap = "viridis", vmin = None, vmax = None, cell_zone = None, region = None, options = None))]
+#[allow(clippy::too_many_arguments)]
+pub fn render_slice_png<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    field: String,
+    plane: PlaneSpec,
+    resolution: Option<Bound<'py, PyAny>>,
+    cmap: &str,
+    vmin: Option<f64>,
+    vmax: Option<f64>,
+    cell_zone: Option<String>,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let colormap = Colormap::find(cmap)?;
+    let (origin, normal) = match plane {
+        PlaneSpec::Axis(axis) => match axis.as_str() {
+            "x" => (None, [1.0, 0.0, 0.0]),
+            "y" => (None, [0.0, 1.0, 0.0]),
+            "z" => (None, [0.0, 0.0, 1.0]),
+            _ => return Err(PyValueError::new_err(format!("plane must be 'x', 'y', 'z' or (origin, normal), not '{}'", axis))),
+        },
+        PlaneSpec::Plane(origin, normal) => (Some(origin), normal),
+    };
+    if mag(normal) == 0.0 {
+        return Err(PyValueError::new_err("the plane normal is zero"));
+    }
+    // The longer side, or both
+    let (side, fixed) = match resolution {
+        None => (512, None),
+        Some(r) => match r.extract::<usize>() {
+            Ok(side) => (side, None),
+            Err(_) => {
+                let (w, h): (usize, usize) = r.extract()?;
+                (w.max(h), Some((w, h)))
+            }
+        },
+    };
+    if side == 0 || fixed.is_some_and(|(w, h)| w == 0 || h == 0) || side > 16384 {
+        return Err(PyValueError::new_err("resolution must be between 1 and 16384 pixels"));
+    }
+
+    let root = Path::new(&case_root);
+    let region = region.as_deref();
+    let image = options::detach(py, &options, || -> PyResult<Vec<u8>> {
+        let cells = selected_cells(&mesh_dir(root, region), cell_zone.as_deref(), None)?;
+        let section = plane_cut(root, region, &time, &field, origin, normal, cells)?;
+        let (width, height) = fixed.unwrap_or_else(|| {
+            let (right, up) = image_axes(normal);
+            let extent = |axis: Vec3| {
+                let d = section.points.iter().map(|&p| dot(p, axis));
+                d.clone().fold(f64::NEG_INFINITY, f64::max) - d.fold(f64::INFINITY, f64::min)
+            };
+            let (w, h) = (extent(right), extent(up));
+            if !(w > 0.0 && h > 0.0) {
+                (side, side)
+            } else if w >= h {
+                (side, ((side as f64 * h / w).round() as usize).max(1))
+            } else {
+                (((side as f64 * w / h).round() as usize).max(1), side)
+            }
+        });
+        let found = section.values.as_deref().and_then(finite_range).unwrap_or((0.0, 0.0));
+        let range = (vmin.unwrap_or(found.0), vmax.unwrap_or(found.1));
+        Ok(png(width, height, &rasterize(&section, normal, width, height, &colormap, range)))
+    })?;
+    Ok(PyBytes::new(py, &image))
+}
@@ -0,0 +1,527 @@
+// Running a solver from the web app. The process is started directly, without
+// a shell, in a process group of its own so that signals reach everything it
+// starts (mpirun and its ranks). Two Rust threads read its stdout and stderr,
+// write every line to the log file as it arrives and feed stdout through the
+// log parser, so completed time steps are available while the run goes on.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::decomposed::{collated_dirs, processor_dirs};
+use crate::dict::{set_entry_in_file, write_atomic};
+use crate::log::{scan_errors, split_rank, LogError, LogParser, LogStep};
+use crate::monitor::{self, ProcessMonitor};
+use crate::shutdown;
+
+// Output lines kept for tail(), and per rank for rank_report()
+const TAIL_LINES: usize = 1000;
+const RANK_LINES: usize = 200;
+
+#[derive(Default)]
+struct RunState {
+    parser: LogParser,
+    steps: Vec<LogStep>,
+    tail: VecDeque<String>,
+    // Return code, set once the process has exited and both streams are
+    // closed
+    exit: Option<i32>,
+    paused: bool,
+    killed: bool,
+    // Lines a parallel run printed with a "[rank]" prefix, and the ranks
+    // MPI reported as having aborted
+    ranks: BTreeMap<usize, VecDeque<String>>,
+    aborted: BTreeSet<usize>,
+}
+
+// The rank named by an MPI abort notice: Open MPI's "MPI_ABORT was invoked on
+// rank 3 in communicator MPI_COMM_WORLD" or MPICH's "application called
+// MPI_Abort(MPI_COMM_WORLD, 1) - process 3"
+fn aborted_rank(line: &str) -> Option<usize> {
+    let rest = match line.find("MPI_ABORT was invoked on rank ") {
+        Some(at) => &line[at + "MPI_ABORT was invoked on rank ".len()..],
+        None => line[line.find("called MPI_Abort(")?..].split_once(") - process ")?.1,
+    };
+    rest.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
+}
+
+struct Shared {
+    child: Mutex<Child>,
+    log: Mutex<File>,
+    state: Mutex<RunState>,
+    on_step: Option<Py<PyAny>>,
+}
+
+impl Shared {
+    // Write a line to the log and keep it for tail(); stdout lines are also
+    // parsed, and steps they complete passed to on_step
+    fn line(&self, line: &[u8], parse: bool) {
+        {
+            let mut log = self.log.lock().unwrap();
+            // A full disk must not stop the solver; the log just goes short
+            let _ = log.write_all(line).and_then(|_| log.flush());
+        }
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.tail.len() == TAIL_LINES {
+                state.tail.pop_front();
+            }
+            let text = String::from_utf8_lossy(line).trim_end().to_string();
+            if let Some((rank, rest)) = split_rank(&text) {
+                let lines = state.ranks.entry(rank).or_default();
+                if lines.len() == RANK_LINES {
+                    lines.pop_front();
+                }
+                lines.push_back(rest.to_string());
+            }
+            if let Some(rank) = aborted_rank(&text) {
+                state.aborted.insert(rank);
+            }
+            state.tail.push_back(text);
+        }
+        if parse {
+            self.parse(line);
+        }
+    }
+
+    fn parse(&self, line: &[u8]) {
+        let completed = {
+            let mut state = self.state.lock().unwrap();
+            state.parser.feed_lines(line, 0);
+            let done: Vec<LogStep> = state.parser.done.drain(..).collect();
+            state.steps.extend(done.iter().cloned());
+            done
+        };
+        if let (Some(callback), false) = (&self.on_step, completed.is_empty()) {
+            shutdown::attach(|py| {
+                for step in completed {
+                    if let Err(e) = callback.call1(py, (step,)) {
+                        e.write_unraisable(py, Some(callback.bind(py)));
+                    }
+                }
+            });
+        }
+    }
+}
+
+fn pump(shared: &Shared, stream: impl Read, parse: bool) {
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                // The last line of a run may lack its newline
+                if !line.ends_with(b"\n") {
+                    line.push(b'\n');
+                }
+                shared.line(&line, parse);
+            }
+        }
+    }
+}
+
+// Send `signal` to the solver's process group
+#[cfg(unix)]
+fn signal_group(pid: u32, signal: libc::c_int) -> PyResult<()> {
+    if unsafe { libc::kill(-(pid as libc::pid_t), signal) } != 0 {
+        let e = std::io::Error::last_os_error();
+        // The group is gone already: the run ended
+        if e.raw_os_error() != Some(libc::ESRCH) {
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+// subprocess's convention: the exit code, or minus the signal that ended
+// the process
+fn return_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return -signal;
+        }
+    }
+    status.code().unwrap_or(-1)
+}
+
+/// A solver started by run_solver(). Dropping it leaves the solver running.
+#[pyclass(frozen, module = "accelerator")]
+pub struct SolverRun {
+    shared: Arc<Shared>,
+    log_path: PathBuf,
+    #[pyo3(get)]
+    pid: u32,
+}
+
+#[pymethods]
+impl SolverRun {
+    /// "running", "paused", "finished" (exit code 0), "failed" or "killed".
+    #[getter]
+    fn status(&self) -> &'static str {
+        let state = self.shared.state.lock().unwrap();
+        match state.exit {
+            None if state.paused => "paused",
+            None => "running",
+            Some(_) if state.killed => "killed",
+            Some(0) => "finished",
+            Some(_) => "failed",
+        }
+    }
+
+    /// Exit code once the run has ended, negative if a signal ended it;
+    /// None while it runs.
+    #[getter]
+    fn returncode(&self) -> Option<i32> {
+        self.shared.state.lock().unwrap().exit
+    }
+
+    #[getter]
+    fn log_path(&self) -> PathBuf {
+        self.log_path.clone()
+    }
+
+    /// Time steps the solver has completed, from the `start`-th on. Pass
+    /// the number already seen to get only the new ones.
+    #[pyo3(signature = (start = 0))]
+    fn steps(&self, start: usize) -> Vec<LogStep> {
+        let state = self.shared.state.lock().unwrap();
+        state.steps.get(start..).unwrap_or_default().to_vec()
+    }
+
+    /// Time of the last completed step; None before the first.
+    #[getter]
+    fn latest_time(&self) -> Option<f64> {
+        self.shared.state.lock().unwrap().steps.last().map(|s| s.time)
+    }
+
+    /// The last `lines` lines of output, stdout and stderr interleaved as
+    /// they arrived (at most 1000 are kept).
+    #[pyo3(signature = (lines = 50))]
+    fn tail(&self, lines: usize) -> Vec<String> {
+        let state = self.shared.state.lock().unwrap();
+        state.tail.iter().skip(state.tail.len().saturating_sub(lines)).cloned().collect()
+    }
+
+    /// Wait for the run to end and return its exit code, or None if
+    /// `timeout` seconds pass first. The GIL is released while waiting.
+    #[pyo3(signature = (timeout = None))]
+    fn wait(&self, py: Python<'_>, timeout: Option<f64>) -> PyResult<Option<i32>> {
+        let deadline = timeout.map(|t| std::time::Instant::now() + Duration::from_secs_f64(t.max(0.0)));
+        loop {
+            if let Some(code) = self.returncode() {
+                return Ok(Some(code));
+            }
+            if deadline.is_some_and(|d| std::time::Instant::now() >= d) {
+                return Ok(None);
+            }
+            py.detach(|| std::thread::sleep(Duration::from_millis(20)));
+            py.check_signals()?;
+        }
+    }
+
+    /// Stop the run: SIGTERM to the solver and everything it started, or
+    /// SIGKILL with `force`. OpenFOAM does not write a time directory on
+    /// SIGTERM; use request_stop() for a clean stop.
+    #[pyo3(signature = (force = false))]
+    fn kill(&self, force: bool) -> PyResult<()> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.exit.is_some() {
+            return Ok(());
+        }
+        // Only a delivered signal makes the run's end a kill
+        #[cfg(unix)]
+        {
+            signal_group(self.pid, if force { libc::SIGKILL } else { libc::SIGTERM })?;
+            state.killed = true;
+            if state.paused {
+                // A stopped process only acts on SIGTERM once continued
+                signal_group(self.pid, libc::SIGCONT)?;
+            }
+            Ok(())
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = force;
+            self.shared.child.lock().unwrap().kill()?;
+            state.killed = true;
+            Ok(())
+        }
+    }
+
+    /// Suspend the run (SIGSTOP) until resume(). Unix only.
+    fn pause(&self) -> PyResult<()> {
+        self.set_paused(true)
+    }
+
+    /// Continue a paused run (SIGCONT). Unix only.
+    fn resume(&self) -> PyResult<()> {
+        self.set_paused(false)
+    }
+
+    /// monitor_pid() on the run's processes.
+    #[pyo3(signature = (interval = 1.0, history = 3600))]
+    fn monitor(&self, interval: f64, history: usize) -> PyResult<ProcessMonitor> {
+        monitor::start(self.pid, interval, history)
+    }
+
+    /// What each MPI rank of a parallel run printed under its "[rank]"
+    /// prefix, as RankReport records in rank order, with the FOAM FATAL
+    /// errors found in it and whether MPI reported the rank as aborted. Empty
+    /// for a serial run or one whose ranks printed nothing of their own.
+    fn rank_report(&self) -> Vec<RankReport> {
+        let state = self.shared.state.lock().unwrap();
+        let ranks: BTreeSet<usize> = state.ranks.keys().chain(&state.aborted).copied().collect();
+        ranks
+            .into_iter()
+            .map(|rank| {
+                let lines: Vec<String> = state.ranks.get(&rank).map(|l| l.iter().cloned().collect()).unwrap_or_default();
+                RankReport { rank, aborted: state.aborted.contains(&rank), errors: scan_errors(lines.join("\n").as_bytes(), 5), lines }
+            })
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("SolverRun(pid={}, status={:?})", self.pid, self.status())
+    }
+}
+
+impl SolverRun {
+    fn set_paused(&self, paused: bool) -> PyResult<()> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.exit.is_some() {
+            return Err(PyValueError::new_err("the run has ended"));
+        }
+        #[cfg(unix)]
+        {
+            signal_group(self.pid, if paused { libc::SIGSTOP } else { libc::SIGCONT })?;
+            state.paused = paused;
+            Ok(())
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = (&mut state, paused);
+            Err(pyo3::exceptions::PyNotImplementedError::new_err("pausing a run needs POSIX signals"))
+        }
+    }
+}
+
+// What one rank of a parallel run printed
+#[pyclass(frozen, get_all)]
+#[derive(Clone)]
+pub struct RankReport {
+    pub rank: usize,
+    // MPI reported the rank as having called MPI_Abort
+    pub aborted: bool,
+    pub errors: Vec<LogError>,
+    // The rank's last lines, without their prefix
+    pub lines: Vec<String>,
+}
+
+#[pymethods]
+impl RankReport {
+    fn __repr__(&self) -> String {
+        format!("RankReport(rank={}, aborted={}, errors={})", self.rank, if self.aborted { "True" } else { "False" }, self.errors.len())
+    }
+}
+
+/// Start a solver in `case_root` and return a SolverRun to follow it.
+/// `cmd` is the program and its arguments, e.g. ["foamRun"] or ["mpirun",
+/// "-np", "4", "foamRun", "-parallel"]; no shell is involved. `env` adds to
+/// (or overrides) the inherited environment. stdout and stderr are written
+/// line by line to `log` (default <case_root>/log.<program>) and stdout is
+/// parsed as it arrives: completed time steps are kept for steps() and
+/// passed to `on_step(step)`, if given, from a Rust thread. Raises OSError
+/// if the program cannot be started.
+#[pyfunction]
+#[pyo3(signature = (case_root, cmd, env = None, log = None, on_step = None))]
+pub fn run_solver(
+    py: Python<'_>,
+    case_root: PathBuf,
+    cmd: Vec<String>,
+    env: Option<HashMap<String, String>>,
+    log: Option<PathBuf>,
+    on_step: Option<Py<PyAny>>,
+) -> PyResult<SolverRun> {
+    let Some(program) = cmd.first() else {
+        return Err(PyValueError::new_err("cmd must name a program"));
+    };
+    if on_step.as_ref().is_some_and(|f| !f.bind(py).is_callable()) {
+        return Err(PyValueError::new_err("on_step must be callable"));
+    }
+    let log = log.unwrap_or_else(|| default_log(&case_root, program));
+    start(py, case_root, cmd, env, log, on_step)
+}
+
+// <case_root>/log.<program>, as OpenFOAM's runApplication names it
+fn default_log(case_root: &Path, program: &str) -> PathBuf {
+    let name = Path::new(program).file_name().map_or_else(|| program.to_string(), |n| n.to_string_lossy().into_owned());
+    case_root.join(format!("log.{}", name))
+}
+
+fn start(
+    py: Python<'_>,
+    case_root: PathBuf,
+    cmd: Vec<String>,
+    env: Option<HashMap<String, String>>,
+    log_path: PathBuf,
+    on_step: Option<Py<PyAny>>,
+) -> PyResult<SolverRun> {
+    shutdown::drain_at_exit(py)?;
+    let (shared, stdout, stderr) = py.detach(|| -> std::io::Result<_> {
+        let log_file = File::create(&log_path)?;
+        let mut command = Command::new(&cmd[0]);
+        command.args(&cmd[1..]).current_dir(&case_root).envs(env.unwrap_or_default());
+        command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        let mut child = command.spawn()?;
+        let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+        let shared = Arc::new(Shared {
+            child: Mutex::new(child),
+            log: Mutex::new(log_file),
+            state: Mutex::new(RunState::default()),
+            on_step,
+        });
+        Ok((shared, stdout, stderr))
+    })?;
+    let pid = shared.child.lock().unwrap().id();
+
+    let errors = stderr.map(|stream| {
+        let shared = shared.clone();
+        std::thread::spawn(move || pump(&shared, stream, false))
+    });
+    let reader = shared.clone();
+    std::thread::Builder::new()
+        .name(format!("accelerator-run-{}", pid))
+        .spawn(move || {
+            if let Some(stream) = stdout {
+                pump(&reader, stream, true);
+            }
+            if let Some(errors) = errors {
+                let _ = errors.join();
+            }
+            // The step the solver was in when it stopped counts as done
+            reader.parse(b"End\n");
+            // Polled rather than waited for, so kill() can still take the child
+            let code = loop {
+                match reader.child.lock().unwrap().try_wait() {
+                    Ok(Some(status)) => break return_code(status),
+                    Ok(None) => {}
+                    Err(_) => break -1,
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            };
+            let mut state = reader.state.lock().unwrap();
+            state.paused = false;
+            state.exit = Some(code);
+        })?;
+    Ok(SolverRun { shared, log_path, pid })
+}
+
+// How many ranks the case is decomposed into: processor* directories, or N
+// of a collated processorsN directory
+fn decomposed_ranks(case_root: &Path) -> std::io::Result<usize> {
+    let processors = processor_dirs(case_root)?;
+    if !processors.is_empty() {
+        return Ok(processors.len());
+    }
+    Ok(collated_dirs(case_root)?
+        .first()
+        .and_then(|dir| dir.file_name()?.to_str()?.strip_prefix("processors")?.split('_').next()?.parse().ok())
+        .unwrap_or(0))
+}
+
+/// Point system/decomposeParDict at `ranks` subdomains, and at `method`
+/// (scotch, simple, hierarchical, ...) if given. An existing dictionary
+/// keeps its other entries and formatting; a missing one is written with
+/// `method`, scotch by default.
+#[pyfunction]
+#[pyo3(signature = (case_root, ranks, method = None))]
+pub fn set_decomposition(py: Python<'_>, case_root: PathBuf, ranks: usize, method: Option<String>) -> PyResult<()> {
+    if ranks == 0 {
+        return Err(PyValueError::new_err("ranks must be at least 1"));
+    }
+    let path = case_root.join("system").join("decomposeParDict");
+    if !path.exists() {
+        let text = format!(
+            "FoamFile\n{{\n    version     2.0;\n    format      ascii;\n    class       dictionary;\n    object      decomposeParDict;\n}}\n\nnumberOfSubdomains {};\n\nmethod          {};\n",
+            ranks,
+            method.as_deref().unwrap_or("scotch")
+        );
+        write_atomic(&path, text)?;
+        return Ok(());
+    }
+    let path = path.to_string_lossy();
+    set_entry_in_file(&path, &["numberOfSubdomains".to_string()], &ranks.into_pyobject(py)?.into_any())?;
+    if let Some(method) = method {
+        set_entry_in_file(&path, &["method".to_string()], &method.into_pyobject(py)?.into_any())?;
+    }
+    Ok(())
+}
+
+/// Run a solver on `ranks` MPI ranks: `mpirun -np <ranks> <cmd> -parallel`
+/// in `case_root`, otherwise as run_solver() (the log defaults to
+/// log.<solver>). The case must already be decomposed into `ranks`
+/// processor directories; a ValueError says how many there are otherwise,
+/// since mpirun would fail less clearly, and the case is left untouched.
+/// This call doesn't decompose, so an existing system/decomposeParDict is
+/// left as the decomposition was made with; a missing one is written with
+/// `ranks` subdomains and `method` (see set_decomposition()) so that
+/// reconstructPar has one. `mpirun` replaces the launcher and its options,
+/// e.g. ["mpirun", "--oversubscribe"]. If a rank aborts, rank_report() on the
+/// returned SolverRun tells which and why.
+#[pyfunction]
+#[pyo3(signature = (case_root, cmd, ranks, env = None, log = None, on_step = None, method = None, mpirun = None))]
+#[allow(clippy::too_many_arguments)]
+pub fn run_parallel(
+    py: Python<'_>,
+    case_root: PathBuf,
+    cmd: Vec<String>,
+    ranks: usize,
+    env: Option<HashMap<String, String>>,
+    log: Option<PathBuf>,
+    on_step: Option<Py<PyAny>>,
+    method: Option<String>,
+    mpirun: Option<Vec<String>>,
+) -> PyResult<SolverRun> {
+    let Some(solver) = cmd.first() else {
+        return Err(PyValueError::new_err("cmd must name a program"));
+    };
+    if on_step.as_ref().is_some_and(|f| !f.bind(py).is_callable()) {
+        return Err(PyValueError::new_err("on_step must be callable"));
+    }
+    let decomposed = py.detach(|| decomposed_ranks(&case_root))?;
+    if decomposed != ranks {
+        return Err(PyValueError::new_err(format!(
+            "{} is decomposed into {} ranks, not {}; run decomposePar first",
+            case_root.display(),
+            decomposed,
+            ranks
+        )));
+    }
+    if !case_root.join("system").join("decomposeParDict").exists() {
+        set_decomposition(py, case_root.clone(), ranks, method)?;
+    }
+
+    let log = log.unwrap_or_else(|| default_log(&case_root, solver));
+    let mut launch = mpirun.filter(|m| !m.is_empty()).unwrap_or_else(|| vec!["mpirun".to_string()]);
+    launch.extend(["-np".to_string(), ranks.to_string()]);
+    launch.extend(cmd.iter().cloned());
+    if !cmd.iter().any(|a| a == "-parallel") {
+        launch.push("-parallel".to_string());
+    }
+    start(py, case_root, launch, env, log, on_step)
+}
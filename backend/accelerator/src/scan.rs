@@ -0,0 +1,60 @@
+// Comment-aware scanning shared by the parsers. OpenFOAM allows `//` and
+// `/* */` comments anywhere whitespace may go, list bodies included, and a
+// commented-out entry (an alternative initial condition, say) must not be
+// taken for the real one.
+
+use memchr::{memchr, memchr3, memmem};
+use std::borrow::Cow;
+
+// Length of the comment starting at `pos`, 0 if none does. An unterminated
+// comment runs to the end of the data.
+pub fn comment_len(data: &[u8], pos: usize) -> usize {
+    match data.get(pos..pos + 2) {
+        Some(b"//") => memchr(b'\n', &data[pos..]).unwrap_or(data.len() - pos),
+        Some(b"/*") => memmem::find(&data[pos + 2..], b"*/").map_or(data.len() - pos, |i| i + 4),
+        _ => 0,
+    }
+}
+
+fn is_word_byte(b: u8) -> bool {
+    !b.is_ascii_whitespace() && !matches!(b, b';' | b'{' | b'}' | b'(' | b')' | b'"' | b'/')
+}
+
+// Offset of the first `word` at or after `from` that stands as a whole word
+// outside comments and quoted strings. Only comment and string openers and
+// the word's first byte need looking at, so memchr jumps between them.
+pub fn find_keyword(data: &[u8], from: usize, word: &[u8]) -> Option<usize> {
+    let mut pos = from;
+    loop {
+        let i = pos + memchr3(b'/', b'"', word[0], &data[pos..])?;
+        match data[i] {
+            b'/' => pos = i + comment_len(data, i).max(1),
+            b'"' => pos = memchr(b'"', &data[i + 1..]).map_or(data.len(), |j| i + j + 2),
+            _ => {
+                let end = i + word.len();
+                let whole = (i == 0 || !is_word_byte(data[i - 1])) && !data.get(end).is_some_and(|b| is_word_byte(*b));
+                if whole && data[i..].starts_with(word) {
+                    return Some(i);
+                }
+                pos = i + 1;
+            }
+        }
+    }
+}
+
+// `data` with its comments blanked to spaces, so offsets still line up.
+// Borrowed when there are none, the usual case for list bodies.
+pub fn uncommented(data: &[u8]) -> Cow<'_, [u8]> {
+    let Some(first) = memchr(b'/', data) else {
+        return Cow::Borrowed(data);
+    };
+    let mut out = data.to_vec();
+    let mut pos = first;
+    while let Some(i) = memchr(b'/', &out[pos..]) {
+        let start = pos + i;
+        let len = comment_len(data, start);
+        out[start..start + len].fill(b' ');
+        pos = start + len.max(1);
+    }
+    Cow::Owned(out)
+}
@@ -0,0 +1,42 @@
+// Interpreter exit with Rust threads still calling into Python: the async
+// readers' workers, the solver runner's step callbacks and the case
+// watcher. A thread that takes the GIL while Python finalizes brings the
+// process down, so exit waits for the calls under way and later ones are
+// dropped.
+
+use pyo3::prelude::*;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+
+// Calls into Python under way from Rust threads, and whether the
+// interpreter is shutting down
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+static CLOSING: AtomicBool = AtomicBool::new(false);
+static AT_EXIT: AtomicBool = AtomicBool::new(false);
+
+#[pyfunction]
+fn drain(py: Python<'_>) {
+    CLOSING.store(true, Ordering::SeqCst);
+    py.detach(|| {
+        while ACTIVE.load(Ordering::SeqCst) > 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    });
+}
+
+// Register drain() with atexit, once
+pub fn drain_at_exit(py: Python<'_>) -> PyResult<()> {
+    if !AT_EXIT.swap(true, Ordering::SeqCst) {
+        py.import("atexit")?.call_method1("register", (wrap_pyfunction!(drain, py)?,))?;
+    }
+    Ok(())
+}
+
+// Run `f` with the GIL from a Rust thread, unless the interpreter is shutting
+// down; exit waits for it. None if it didn't run.
+pub fn attach<T, F: FnOnce(Python<'_>) -> T>(f: F) -> Option<T> {
+    ACTIVE.fetch_add(1, Ordering::SeqCst);
+    let out = (!CLOSING.load(Ordering::SeqCst)).then(|| Python::attach(f));
+    ACTIVE.fetch_sub(1, Ordering::SeqCst);
+    out
+}
@@ -0,0 +1,137 @@
+// Smoothing of monitored signals (force coefficients, residuals, probes)
+// for the plots' smoothed view. Samples that aren't finite are gaps: they
+// stay gaps in the output and are left out of the averages around them, so
+// a missing or diverged sample neither poisons its neighbours nor is
+// papered over.
+
+use numpy::{AllowTypeChange, IntoPyArray, PyArray1, PyArrayLike1};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+// Neumaier-compensated sum, so a window that has slid past a huge sample
+// still averages the small ones after it accurately
+#[derive(Default)]
+struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl CompensatedSum {
+    fn add(&mut self, v: f64) {
+        let t = self.sum + v;
+        self.compensation += if self.sum.abs() >= v.abs() { (self.sum - t) + v } else { (v - t) + self.sum };
+        self.sum = t;
+    }
+
+    fn value(&self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+// Mean over a sliding window of `window` samples ending at each sample, or
+// centred on it, of the finite samples in it. Samples with fewer than
+// `min_periods` finite samples in their window are NaN.
+pub fn moving_mean(values: &[f64], window: usize, center: bool, min_periods: usize) -> Vec<f64> {
+    let n = values.len();
+    // Window of sample i is [i - behind, i + ahead]
+    let (behind, ahead) = if center { ((window - 1) / 2, window / 2) } else { (window - 1, 0) };
+    // Sum and count of the finite samples in [lo, hi)
+    let (mut sum, mut count) = (CompensatedSum::default(), 0usize);
+    let (mut lo, mut hi) = (0, 0);
+    (0..n)
+        .map(|i| {
+            let (start, end) = (i.saturating_sub(behind), (i + ahead + 1).min(n));
+            for &v in &values[hi..end] {
+                if v.is_finite() {
+                    sum.add(v);
+                    count += 1;
+                }
+            }
+            hi = end;
+            for &v in &values[lo..start] {
+                if v.is_finite() {
+                    sum.add(-v);
+                    count -= 1;
+                }
+            }
+            lo = start;
+            if count == 0 {
+                sum = CompensatedSum::default();
+            }
+            if !values[i].is_finite() || count < min_periods.max(1) {
+                f64::NAN
+            } else {
+                sum.value() / count as f64
+            }
+        })
+        .collect()
+}
+
+// Exponential moving average with smoothing factor `alpha`, started at the
+// first finite sample. Gaps are skipped, the average carrying across them.
+pub fn exponential_mean(values: &[f64], alpha: f64) -> Vec<f64> {
+    let mut average: Option<f64> = None;
+    values
+        .iter()
+        .map(|&v| {
+            if !v.is_finite() {
+                return f64::NAN;
+            }
+            let a = average.map_or(v, |a| a + alpha * (v - a));
+            average = Some(a);
+            a
+        })
+        .collect()
+}
+
+/// Moving average of `values` over a window of `window` samples, ending at
+/// each sample or, with `center`, centred on it (reaching one sample further
+/// ahead than behind for even windows). Samples that aren't finite are gaps:
+/// they are NaN in the result and left out of their neighbours' averages.
+/// Near the ends and gaps the window holds fewer samples; where it holds
+/// fewer than `min_periods` finite samples (default 1) the result is NaN.
+/// Returns an array the length of `values`.
+#[pyfunction]
+#[pyo3(signature = (values, window, center = false, min_periods = None))]
+pub fn moving_average<'py>(
+    py: Python<'py>,
+    values: PyArrayLike1<'py, f64, AllowTypeChange>,
+    window: usize,
+    center: bool,
+    min_periods: Option<usize>,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    if window == 0 {
+        return Err(PyValueError::new_err("window must be at least 1"));
+    }
+    let values: Vec<f64> = values.as_array().iter().copied().collect();
+    let smoothed = py.detach(|| moving_mean(&values, window, center, min_periods.unwrap_or(1)));
+    Ok(smoothed.into_pyarray(py))
+}
+
+/// Exponential moving average of `values`, each sample weighted `alpha`
+/// against the average so far (0 < alpha <= 1), or with `span` in samples,
+/// alpha = 2 / (span + 1), as pandas' ewm(adjust=False). The average starts
+/// at the first finite sample. Samples that aren't finite are gaps: they are
+/// NaN in the result and the average carries across them unchanged. Returns
+/// an array the length of `values`.
+#[pyfunction]
+#[pyo3(signature = (values, alpha = None, span = None))]
+pub fn exponential_moving_average<'py>(
+    py: Python<'py>,
+    values: PyArrayLike1<'py, f64, AllowTypeChange>,
+    alpha: Option<f64>,
+    span: Option<f64>,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let alpha = match (alpha, span) {
+        (Some(alpha), None) => alpha,
+        (None, Some(span)) if span >= 1.0 => 2.0 / (span + 1.0),
+        (None, Some(_)) => return Err(PyValueError::new_err("span must be at least 1")),
+        _ => return Err(PyValueError::new_err("give one of alpha or span")),
+    };
+    if !(alpha > 0.0 && alpha <= 1.0) {
+        return Err(PyValueError::new_err(format!("alpha must be in (0, 1], not {}", alpha)));
+    }
+    let values: Vec<f64> = values.as_array().iter().copied().collect();
+    let smoothed = py.detach(|| exponential_mean(&values, alpha));
+    Ok(smoothed.into_pyarray(py))
+}
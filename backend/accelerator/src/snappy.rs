@@ -0,0 +1,374 @@
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::dict::{dict_to_py, load_dict, stream_to_py, Dict, EntryValue, Value};
+
+// Geometry types backed by a file in constant/triSurface
+const SURFACE_TYPES: [&str; 2] = ["triSurfaceMesh", "distributedTriSurfaceMesh"];
+const SURFACE_EXTENSIONS: [&str; 8] = ["stl", "stlb", "obj", "vtk", "vtp", "ftr", "nas", "ac"];
+
+// Problems found while checking a case, as (severity, message)
+#[derive(Default)]
+pub struct Report {
+    issues: Vec<(&'static str, String)>,
+}
+
+impl Report {
+    pub fn error(&mut self, message: String) {
+        self.issues.push(("error", message));
+    }
+
+    pub fn warning(&mut self, message: String) {
+        self.issues.push(("warning", message));
+    }
+
+    pub fn valid(&self) -> bool {
+        !self.issues.iter().any(|(s, _)| *s == "error")
+    }
+
+    // The issues as a list of {"severity", "message"}
+    pub fn to_py<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        let issues = PyList::empty(py);
+        for (severity, message) in &self.issues {
+            let i = PyDict::new(py);
+            i.set_item("severity", severity)?;
+            i.set_item("message", message)?;
+            issues.append(i)?;
+        }
+        Ok(issues)
+    }
+}
+
+fn is_true(dict: &Dict, key: &str) -> bool {
+    matches!(dict.word(key), Some("true" | "on" | "yes" | "1"))
+}
+
+fn has_surface_extension(name: &str) -> bool {
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| SURFACE_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+}
+
+// `file` in any of `dirs`, compressed or not
+fn find_file(dirs: &[PathBuf], file: &str) -> Option<PathBuf> {
+    dirs.iter()
+        .flat_map(|d| [d.join(file), d.join(format!("{}.gz", file))])
+        .find(|p| p.is_file())
+}
+
+// Only the plain settings of a controls dict, without the sub-dicts and
+// lists reported separately
+fn settings<'py>(py: Python<'py>, dict: Option<&Dict>) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let Some(dict) = dict else {
+        return Ok(None);
+    };
+    let out = PyDict::new(py);
+    for entry in &dict.entries {
+        let EntryValue::Stream(values) = &entry.value else {
+            continue;
+        };
+        // "features ( { file ...; } );" is reported on its own
+        let dict_list = matches!(values.as_slice(), [Value::List(l)] if l.iter().any(|i| matches!(i, Value::Dict(_))));
+        if !entry.key.starts_with('#') && !dict_list {
+            out.set_item(&entry.key, stream_to_py(py, values)?)?;
+        }
+    }
+    Ok(Some(out))
+}
+
+fn integer(dict: &Dict, key: &str) -> Option<i64> {
+    dict.number(key).map(|n| n as i64)
+}
+
+// (min, max) refinement level from "level (2 3);"
+fn level_pair(dict: &Dict) -> Option<(i64, i64)> {
+    let pair = match dict.stream("level")? {
+        [Value::List(l)] if l.len() == 2 => (l[0].number()?, l[1].number()?),
+        [v] => (v.number()?, v.number()?),
+        _ => return None,
+    };
+    Some((pair.0 as i64, pair.1 as i64))
+}
+
+fn geometry_report<'py>(
+    py: Python<'py>,
+    geometry: &Dict,
+    surface_dirs: &[PathBuf],
+    names: &mut HashSet<String>,
+    report: &mut Report,
+) -> PyResult<Bound<'py, PyList>> {
+    let out = PyList::empty(py);
+    for entry in &geometry.entries {
+        let EntryValue::Dict(d) = &entry.value else {
+            continue;
+        };
+        let kind = d
+            .word("type")
+            .map(str::to_string)
+            .or_else(|| has_surface_extension(&entry.key).then(|| "triSurfaceMesh".to_string()));
+        let name = d.word("name").unwrap_or(&entry.key).to_string();
+        // Surfaces can be referred to by name, key or key without extension
+        names.insert(name.clone());
+        names.insert(entry.key.clone());
+        if let Some(stem) = Path::new(&entry.key).file_stem() {
+            names.insert(stem.to_string_lossy().into_owned());
+        }
+
+        let file_based = kind.as_deref().is_some_and(|k| SURFACE_TYPES.contains(&k));
+        let file = file_based.then(|| d.word("file").unwrap_or(&entry.key).to_string());
+        let found = file.as_deref().and_then(|f| find_file(surface_dirs, f));
+        if let (Some(f), None) = (&file, &found) {
+            report.error(format!("geometry '{}': constant/triSurface/{} not found", entry.key, f));
+        }
+        if kind.is_none() {
+            report.error(format!("geometry '{}' has no type", entry.key));
+        }
+
+        let regions = PyList::empty(py);
+        if let Some(r) = d.sub("regions") {
+            for region in &r.entries {
+                let region_name = match &region.value {
+                    EntryValue::Dict(rd) => rd.word("name").unwrap_or(&region.key),
+                    EntryValue::Stream(_) => &region.key,
+                };
+                regions.append(region_name)?;
+            }
+        }
+
+        let g = PyDict::new(py);
+        g.set_item("name", &name)?;
+        g.set_item("key", &entry.key)?;
+        g.set_item("type", kind)?;
+        g.set_item("file", file)?;
+        g.set_item("path", found.as_ref().map(|p| p.to_string_lossy().into_owned()))?;
+        g.set_item("exists", file_based.then_some(found.is_some()))?;
+        g.set_item("regions", regions)?;
+        g.set_item("params", dict_to_py(py, d)?)?;
+        out.append(g)?;
+    }
+    Ok(out)
+}
+
+fn features_report<'py>(
+    py: Python<'py>,
+    controls: &Dict,
+    feature_dirs: &[PathBuf],
+    report: &mut Report,
+) -> PyResult<Bound<'py, PyList>> {
+    let out = PyList::empty(py);
+    let items = match controls.stream("features") {
+        Some([Value::List(items)]) => items.as_slice(),
+        _ => &[],
+    };
+    for item in items {
+        let Value::Dict(d) = item else {
+            continue;
+        };
+        let file = d.word("file").map(str::to_string);
+        let found = file.as_deref().and_then(|f| find_file(feature_dirs, f));
+        match (&file, &found) {
+            (Some(f), None) => report.error(format!("feature edge file {} not found (run surfaceFeatureExtract)", f)),
+            (None, _) => report.error("features entry without a file".into()),
+            _ => {}
+        }
+        let f = PyDict::new(py);
+        f.set_item("file", file)?;
+        f.set_item("exists", found.is_some())?;
+        f.set_item("level", integer(d, "level"))?;
+        f.set_item("levels", d.stream("levels").map(|v| stream_to_py(py, v)).transpose()?)?;
+        out.append(f)?;
+    }
+    Ok(out)
+}
+
+fn check_level(report: &mut Report, what: &str, level: Option<(i64, i64)>) {
+    if let Some((min, max)) = level {
+        if min > max {
+            report.error(format!("{}: minimum level {} exceeds maximum {}", what, min, max));
+        }
+    }
+}
+
+fn refinement_surfaces_report<'py>(
+    py: Python<'py>,
+    controls: &Dict,
+    names: &HashSet<String>,
+    report: &mut Report,
+) -> PyResult<Bound<'py, PyDict>> {
+    let out = PyDict::new(py);
+    let Some(surfaces) = controls.sub("refinementSurfaces") else {
+        return Ok(out);
+    };
+    for entry in &surfaces.entries {
+        let EntryValue::Dict(d) = &entry.value else {
+            continue;
+        };
+        if !names.contains(&entry.key) {
+            report.error(format!("refinementSurfaces '{}' is not a geometry entry", entry.key));
+        }
+        let level = level_pair(d);
+        check_level(report, &format!("refinementSurfaces '{}'", entry.key), level);
+        let regions = PyDict::new(py);
+        if let Some(r) = d.sub("regions") {
+            for region in &r.entries {
+                if let EntryValue::Dict(rd) = &region.value {
+                    let region_level = level_pair(rd);
+                    check_level(report, &format!("refinementSurfaces '{}' region '{}'", entry.key, region.key), region_level);
+                    regions.set_item(&region.key, region_level)?;
+                }
+            }
+        }
+        let s = PyDict::new(py);
+        s.set_item("level", level)?;
+        s.set_item("regions", regions)?;
+        s.set_item("patch_type", d.sub("patchInfo").and_then(|p| p.word("type")))?;
+        s.set_item("face_zone", d.word("faceZone"))?;
+        s.set_item("cell_zone", d.word("cellZone"))?;
+        out.set_item(&entry.key, s)?;
+    }
+    Ok(out)
+}
+
+fn refinement_regions_report<'py>(
+    py: Python<'py>,
+    controls: &Dict,
+    names: &HashSet<String>,
+    report: &mut Report,
+) -> PyResult<Bound<'py, PyDict>> {
+    let out = PyDict::new(py);
+    let Some(regions) = controls.sub("refinementRegions") else {
+        return Ok(out);
+    };
+    for entry in &regions.entries {
+        let EntryValue::Dict(d) = &entry.value else {
+            continue;
+        };
+        if !names.contains(&entry.key) {
+            report.error(format!("refinementRegions '{}' is not a geometry entry", entry.key));
+        }
+        let r = PyDict::new(py);
+        r.set_item("mode", d.word("mode"))?;
+        r.set_item("levels", d.stream("levels").map(|v| stream_to_py(py, v)).transpose()?)?;
+        out.set_item(&entry.key, r)?;
+    }
+    Ok(out)
+}
+
+fn layers_report<'py>(py: Python<'py>, controls: Option<&Dict>, report: &mut Report, enabled: bool) -> PyResult<Bound<'py, PyDict>> {
+    let out = PyDict::new(py);
+    let mut total = 0;
+    if let Some(layers) = controls.and_then(|c| c.sub("layers")) {
+        for entry in &layers.entries {
+            if let EntryValue::Dict(d) = &entry.value {
+                let n = integer(d, "nSurfaceLayers");
+                total += n.unwrap_or(0);
+                out.set_item(&entry.key, n)?;
+            }
+        }
+    }
+    if enabled && total == 0 {
+        report.warning("addLayers is on but no patch has nSurfaceLayers".into());
+    }
+    if let Some(c) = controls {
+        if c.number("expansionRatio").is_some_and(|r| r < 1.0) {
+            report.warning("addLayersControls expansionRatio is below 1".into());
+        }
+        if let (Some(min), Some(last)) = (c.number("minThickness"), c.number("finalLayerThickness")) {
+            if min > last {
+                report.warning("addLayersControls minThickness exceeds finalLayerThickness".into());
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Summarise system/snappyHexMeshDict (or `path`) before running
+/// snappyHexMesh. Returns a dict with the enabled `steps`, `geometry`
+/// entries (with whether their surface file exists under
+/// constant/triSurface), `features`, `refinement_surfaces` and
+/// `refinement_regions` levels, `location_in_mesh`, `layers` (nSurfaceLayers
+/// per patch), the plain `controls` of each section, and `issues`: a list of
+/// {"severity", "message"} for missing files, references to undefined
+/// geometry, inverted levels and missing sections. `valid` is False when any
+/// issue is an error. Returns None if the dictionary is missing.
+#[pyfunction]
+#[pyo3(signature = (case_root, path=None))]
+pub fn check_snappy_dict<'py>(py: Python<'py>, case_root: String, path: Option<String>) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let root = Path::new(&case_root);
+    let path = path.unwrap_or_else(|| root.join("system").join("snappyHexMeshDict").to_string_lossy().into_owned());
+    let Some(dict) = py.detach(|| load_dict(&path))? else {
+        return Ok(None);
+    };
+    let tri_surface = root.join("constant").join("triSurface");
+    let feature_dirs = [tri_surface.clone(), root.join("constant").join("extendedFeatureEdgeMesh")];
+    let mut report = Report::default();
+
+    let castellated = is_true(&dict, "castellatedMesh");
+    let snap = is_true(&dict, "snap");
+    let add_layers = is_true(&dict, "addLayers");
+    let steps = PyDict::new(py);
+    steps.set_item("castellated_mesh", castellated)?;
+    steps.set_item("snap", snap)?;
+    steps.set_item("add_layers", add_layers)?;
+
+    let cmc = dict.sub("castellatedMeshControls");
+    let snap_controls = dict.sub("snapControls");
+    let alc = dict.sub("addLayersControls");
+    let quality = dict.sub("meshQualityControls");
+    for (section, present, needed) in [
+        ("castellatedMeshControls", cmc.is_some(), castellated),
+        ("snapControls", snap_controls.is_some(), snap),
+        ("addLayersControls", alc.is_some(), add_layers),
+        ("meshQualityControls", quality.is_some(), true),
+    ] {
+        if needed && !present {
+            report.error(format!("{} is missing", section));
+        }
+    }
+
+    let mut names = HashSet::new();
+    let geometry = match dict.sub("geometry") {
+        Some(g) => geometry_report(py, g, std::slice::from_ref(&tri_surface), &mut names, &mut report)?,
+        None => PyList::empty(py),
+    };
+    if geometry.is_empty() {
+        report.warning("no geometry entries".into());
+    }
+
+    let empty = Dict::default();
+    let controls = cmc.unwrap_or(&empty);
+    let features = features_report(py, controls, &feature_dirs, &mut report)?;
+    let surfaces = refinement_surfaces_report(py, controls, &names, &mut report)?;
+    let regions = refinement_regions_report(py, controls, &names, &mut report)?;
+    let location = match controls.stream("locationInMesh") {
+        Some(v @ [Value::List(_)]) => Some(stream_to_py(py, v)?),
+        _ => controls.stream("locationsInMesh").map(|v| stream_to_py(py, v)).transpose()?,
+    };
+    if castellated && cmc.is_some() && location.is_none() {
+        report.error("castellatedMeshControls has no locationInMesh".into());
+    }
+    let layers = layers_report(py, alc, &mut report, add_layers)?;
+
+    let section_settings = PyDict::new(py);
+    section_settings.set_item("castellated", settings(py, cmc)?)?;
+    section_settings.set_item("snap", settings(py, snap_controls)?)?;
+    section_settings.set_item("layers", settings(py, alc)?)?;
+    section_settings.set_item("quality", quality.map(|q| dict_to_py(py, q)).transpose()?)?;
+
+    let out = PyDict::new(py);
+    out.set_item("steps", steps)?;
+    out.set_item("geometry", geometry)?;
+    out.set_item("features", features)?;
+    out.set_item("refinement_surfaces", surfaces)?;
+    out.set_item("refinement_regions", regions)?;
+    out.set_item("location_in_mesh", location)?;
+    out.set_item("layers", layers)?;
+    out.set_item("controls", section_settings)?;
+    out.set_item("issues", report.to_py(py)?)?;
+    out.set_item("valid", report.valid())?;
+    Ok(Some(out))
+}
@@ -0,0 +1,192 @@
+use flate2::read::MultiGzDecoder;
+use memmap2::{Mmap, MmapOptions};
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// Without an explicit cap, the largest network-filesystem file `Auto` reads
+// into memory rather than mapping
+const AUTO_STREAM_LIMIT: u64 = 1 << 30;
+
+// Raw bytes of a field file. Plain files are memory-mapped or, when mapping
+// is a bad idea, read into an owned buffer; gzip files (written with
+// `writeCompression on;`) are inflated into one.
+pub enum FieldData {
+    Mapped(Mmap),
+    Read(Vec<u8>),
+    Decompressed(Vec<u8>),
+}
+
+// How a field file's bytes are brought into memory
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReadStrategy {
+    // mmap, except on network filesystems where page faults can stall for
+    // seconds; files there are read sequentially when they fit
+    Auto,
+    Mmap,
+    Stream,
+}
+
+#[derive(Clone, Copy)]
+pub struct ReadOptions {
+    pub strategy: ReadStrategy,
+    // Most bytes held in an owned buffer (streamed or decompressed); None
+    // for no cap
+    pub max_memory: Option<u64>,
+    // MADV_SEQUENTIAL/MADV_WILLNEED on mappings, since the parsers read
+    // front to back
+    pub advise: bool,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions { strategy: ReadStrategy::Auto, max_memory: None, advise: true }
+    }
+}
+
+// statfs magic numbers of filesystems where mmap page faults go over the
+// network: NFS, SMB/CIFS, SMB2, Ceph, Lustre, GPFS, AFS, 9P and FUSE
+// (sshfs, s3fs, ...)
+#[cfg(target_os = "linux")]
+const NETWORK_FS_MAGIC: [u32; 10] =
+    [0x6969, 0x517b, 0xff53_4d42, 0xfe53_4d42, 0x00c3_6400, 0x0bd0_0bd0, 0x4750_4653, 0x5346_414f, 0x0102_1997, 0x6573_5546];
+
+#[cfg(target_os = "linux")]
+fn on_network_fs(file: &File) -> bool {
+    use std::os::fd::AsRawFd;
+    let mut st: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstatfs(file.as_raw_fd(), &mut st) } != 0 {
+        return false;
+    }
+    NETWORK_FS_MAGIC.contains(&(st.f_type as u32))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn on_network_fs(_file: &File) -> bool {
+    false
+}
+
+fn over_cap(path: &Path, len: u64, cap: u64) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::OutOfMemory,
+        format!("{}: {} bytes exceeds the max_memory cap of {} bytes", path.display(), len, cap),
+    )
+}
+
+impl Deref for FieldData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FieldData::Mapped(m) => m,
+            FieldData::Read(v) | FieldData::Decompressed(v) => v,
+        }
+    }
+}
+
+// Resolve the on-disk file for a field path. OpenFOAM only writes `U.gz` when
+// compression is on, so callers asking for `U` fall back to the .gz sibling.
+pub fn resolve_field_path(path: &Path) -> Option<PathBuf> {
+    if path.exists() {
+        return Some(path.to_path_buf());
+    }
+    let mut gz = path.as_os_str().to_owned();
+    gz.push(".gz");
+    let gz = PathBuf::from(gz);
+    if gz.exists() {
+        return Some(gz);
+    }
+    None
+}
+
+// Open a field file, transparently decompressing gzip content.
+// Missing and empty files yield None.
+// Read with the current call's Options.
+pub fn open_field_file(path: &str) -> std::io::Result<Option<FieldData>> {
+    open_field_file_with(path, &crate::options::current().read)
+}
+
+// Up to `limit` bytes from the start of a field file, decompressing gzip
+// content, and whether that was the whole file. Reads only what it returns,
+// for header queries on files too large to map or inflate in full.
+pub fn open_field_head(path: &str, limit: u64) -> std::io::Result<Option<(Vec<u8>, bool)>> {
+    let Some(path) = resolve_field_path(Path::new(path)) else {
+        return Ok(None);
+    };
+    let mut file = File::open(&path)?;
+    let mut magic = [0u8; 2];
+    let gzip = file.read(&mut magic)? == 2 && magic == GZIP_MAGIC;
+    file.rewind()?;
+
+    let mut buf = Vec::new();
+    if gzip {
+        MultiGzDecoder::new(file).take(limit + 1).read_to_end(&mut buf)?;
+    } else {
+        file.take(limit + 1).read_to_end(&mut buf)?;
+    }
+    if buf.is_empty() {
+        return Ok(None);
+    }
+    let whole = buf.len() as u64 <= limit;
+    buf.truncate(limit as usize);
+    Ok(Some((buf, whole)))
+}
+
+// open_field_file() with an explicit read strategy
+pub fn open_field_file_with(path: &str, options: &ReadOptions) -> std::io::Result<Option<FieldData>> {
+    let path = match resolve_field_path(Path::new(path)) {
+        Some(p) => p,
+        None => return Ok(None),
+    };
+
+    let mut file = File::open(&path)?;
+    let len = file.metadata()?.len();
+    // Check if file is empty
+    if len == 0 {
+        return Ok(None);
+    }
+
+    let stream = match options.strategy {
+        ReadStrategy::Mmap => false,
+        ReadStrategy::Stream => true,
+        ReadStrategy::Auto => len <= options.max_memory.unwrap_or(AUTO_STREAM_LIMIT) && on_network_fs(&file),
+    };
+    let data = if stream {
+        if let Some(cap) = options.max_memory.filter(|cap| len > *cap) {
+            return Err(over_cap(&path, len, cap));
+        }
+        let mut buf = Vec::with_capacity(len as usize);
+        file.read_to_end(&mut buf)?;
+        FieldData::Read(buf)
+    } else {
+        let mmap = unsafe { MmapOptions::new().map(&file)? };
+        #[cfg(unix)]
+        if options.advise {
+            // Only hints; a kernel that ignores them costs nothing
+            let _ = mmap.advise(memmap2::Advice::Sequential);
+            let _ = mmap.advise(memmap2::Advice::WillNeed);
+        }
+        FieldData::Mapped(mmap)
+    };
+
+    // Detect by magic bytes rather than extension so renamed files still work
+    if data.starts_with(&GZIP_MAGIC) {
+        let cap = options.max_memory.unwrap_or(u64::MAX);
+        // Guess the inflated size, but never reserve past the cap
+        let guess = data.len().saturating_mul(4).min(usize::try_from(cap).unwrap_or(usize::MAX));
+        let mut buf = Vec::with_capacity(guess);
+        MultiGzDecoder::new(&data[..]).take(cap.saturating_add(1)).read_to_end(&mut buf)?;
+        if buf.len() as u64 > cap {
+            return Err(over_cap(&path, buf.len() as u64, cap));
+        }
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        return Ok(Some(FieldData::Decompressed(buf)));
+    }
+
+    Ok(Some(data))
+}
@@ -0,0 +1,190 @@
+// Power spectra of monitored signals, for shedding frequencies and Strouhal
+// numbers from probe and force histories. Solver output is rarely evenly
+// spaced (adjustable time steps, restarts), so the history is first
+// interpolated onto a uniform grid over the same span with as many
+// samples, then split into half-overlapping windowed segments whose
+// periodograms are averaged (Welch's method; a single segment is the plain
+// periodogram).
+
+use numpy::{AllowTypeChange, IntoPyArray, PyArrayLike1};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use std::f64::consts::PI;
+
+// Fewest samples per segment worth transforming
+const MIN_SEGMENT: usize = 4;
+
+#[derive(Clone, Copy)]
+enum Window {
+    Rectangular,
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+impl Window {
+    fn find(name: &str) -> PyResult<Window> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" | "boxcar" | "rectangular" => Ok(Window::Rectangular),
+            "hann" | "hanning" => Ok(Window::Hann),
+            "hamming" => Ok(Window::Hamming),
+            "blackman" => Ok(Window::Blackman),
+            _ => Err(PyValueError::new_err(format!(
+                "unknown window '{}': use hann, hamming, blackman or none",
+                name
+            ))),
+        }
+    }
+
+    // Periodic window of `n` samples, as scipy's get_window
+    fn weights(self, n: usize) -> Vec<f64> {
+        (0..n)
+            .map(|i| {
+                let x = 2.0 * PI * i as f64 / n as f64;
+                match self {
+                    Window::Rectangular => 1.0,
+                    Window::Hann => 0.5 - 0.5 * x.cos(),
+                    Window::Hamming => 0.54 - 0.46 * x.cos(),
+                    Window::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos(),
+                }
+            })
+            .collect()
+    }
+}
+
+// Finite samples in time order, the last of any repeated time kept (a
+// restart overwrites the end of the previous run), linearly interpolated
+// onto an even grid. Returns the samples and their spacing.
+fn resample(times: &[f64], values: &[f64]) -> (Vec<f64>, f64) {
+    let mut samples: Vec<(f64, f64)> =
+        times.iter().zip(values).filter(|(t, v)| t.is_finite() && v.is_finite()).map(|(&t, &v)| (t, v)).collect();
+    samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    samples.reverse();
+    samples.dedup_by(|later, kept| later.0 == kept.0);
+    samples.reverse();
+    let n = samples.len();
+    if n < 2 {
+        return (samples.into_iter().map(|(_, v)| v).collect(), 0.0);
+    }
+    let (start, end) = (samples[0].0, samples[n - 1].0);
+    let dt = (end - start) / (n - 1) as f64;
+    let mut j = 0;
+    let uniform = (0..n)
+        .map(|i| {
+            let t = if i == n - 1 { end } else { start + i as f64 * dt };
+            while j + 2 < n && samples[j + 1].0 <= t {
+                j += 1;
+            }
+            let ((t0, v0), (t1, v1)) = (samples[j], samples[j + 1]);
+            v0 + (v1 - v0) * ((t - t0) / (t1 - t0)).clamp(0.0, 1.0)
+        })
+        .collect();
+    (uniform, dt)
+}
+
+// One-sided power spectral density of evenly spaced `samples`, in value^2
+// per unit frequency, averaged over `segments` half-overlapping segments
+// with each segment's mean removed. Returns the frequencies and densities.
+fn welch(samples: &[f64], dt: f64, window: Window, segments: usize) -> (Vec<f64>, Vec<f64>) {
+    let n = samples.len();
+    let length = (2 * n / (segments + 1)).min(n);
+    let step = (length / 2).max(1);
+    let weights = window.weights(length);
+    let power: f64 = weights.iter().map(|w| w * w).sum();
+    let fft = FftPlanner::new().plan_fft_forward(length);
+    let bins = length / 2 + 1;
+
+    let mut psd = vec![0.0; bins];
+    let mut buffer = vec![Complex::new(0.0, 0.0); length];
+    for s in 0..segments {
+        let segment = &samples[s * step..s * step + length];
+        let mean = segment.iter().sum::<f64>() / length as f64;
+        for ((b, v), w) in buffer.iter_mut().zip(segment).zip(&weights) {
+            *b = Complex::new((v - mean) * w, 0.0);
+        }
+        fft.process(&mut buffer);
+        for (p, b) in psd.iter_mut().zip(&buffer) {
+            *p += b.norm_sqr();
+        }
+    }
+    // Density scaling, doubled for the negative frequencies folded in except
+    // at zero and at the Nyquist frequency of an even segment
+    let scale = dt / (power * segments as f64);
+    for (k, p) in psd.iter_mut().enumerate() {
+        let folded = k > 0 && !(length.is_multiple_of(2) && k == length / 2);
+        *p *= if folded { 2.0 * scale } else { scale };
+    }
+    let frequencies = (0..bins).map(|k| k as f64 / (length as f64 * dt)).collect();
+    (frequencies, psd)
+}
+
+// Frequency of the strongest non-zero bin, refined between bins by the
+// vertex of a parabola through it and its neighbours
+fn peak_frequency(frequencies: &[f64], psd: &[f64]) -> Option<f64> {
+    let (k, _) = psd.iter().enumerate().skip(1).max_by(|a, b| a.1.total_cmp(b.1))?;
+    if k + 1 >= psd.len() {
+        return Some(frequencies[k]);
+    }
+    let (a, b, c) = (psd[k - 1], psd[k], psd[k + 1]);
+    let curvature = a - 2.0 * b + c;
+    let offset = if curvature < 0.0 { (0.5 * (a - c) / curvature).clamp(-0.5, 0.5) } else { 0.0 };
+    Some(frequencies[k] + offset * (frequencies[1] - frequencies[0]))
+}
+
+/// Power spectral density of a probe or force history, e.g. read_probes()
+/// or read_force_coeffs() columns against their times. The samples (those
+/// with finite time and value, the last of any repeated time) are
+/// interpolated onto an even grid over the same span with as many points,
+/// split into `segments` half-overlapping segments, each with its mean
+/// removed and weighted by `window` (hann, hamming, blackman or none), and
+/// their periodograms averaged (Welch's method). Returns a dict with
+/// "frequency" and "psd" arrays (one-sided, value squared per unit
+/// frequency), "dt" of the grid and "peak_frequency", the strongest
+/// non-zero frequency refined between bins. With `length` and `velocity`
+/// the peak is also reported as a "strouhal" number, f L / U.
+#[pyfunction]
+#[pyo3(signature = (times, values, window = "hann", segments = 1, length = None, velocity = None))]
+pub fn compute_psd<'py>(
+    py: Python<'py>,
+    times: PyArrayLike1<'py, f64, AllowTypeChange>,
+    values: PyArrayLike1<'py, f64, AllowTypeChange>,
+    window: &str,
+    segments: usize,
+    length: Option<f64>,
+    velocity: Option<f64>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let window = Window::find(window)?;
+    if segments == 0 {
+        return Err(PyValueError::new_err("segments must be at least 1"));
+    }
+    let times: Vec<f64> = times.as_array().iter().copied().collect();
+    let values: Vec<f64> = values.as_array().iter().copied().collect();
+    if times.len() != values.len() {
+        return Err(PyValueError::new_err(format!("{} times but {} values", times.len(), values.len())));
+    }
+    let (samples, dt) = py.detach(|| resample(&times, &values));
+    let needed = MIN_SEGMENT * (segments + 1) / 2;
+    if samples.len() < needed || dt <= 0.0 {
+        return Err(PyValueError::new_err(format!(
+            "{} distinct samples are too few for {} segment(s); at least {} are needed",
+            samples.len(),
+            segments,
+            needed
+        )));
+    }
+    let (frequencies, psd) = py.detach(|| welch(&samples, dt, window, segments));
+    let peak = peak_frequency(&frequencies, &psd);
+
+    let out = PyDict::new(py);
+    out.set_item("frequency", frequencies.into_pyarray(py))?;
+    out.set_item("psd", psd.into_pyarray(py))?;
+    out.set_item("dt", dt)?;
+    out.set_item("peak_frequency", peak)?;
+    if let (Some(length), Some(velocity)) = (length, velocity) {
+        out.set_item("strouhal", peak.map(|f| f * length / velocity))?;
+    }
+    Ok(out)
+}
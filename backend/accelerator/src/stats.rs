@@ -0,0 +1,874 @@
+use numpy::{IntoPyArray, PyArray1};
+use std::path::Path;
+use wide::f64x4;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::batch::means_to_py;
+use crate::cache;
+use crate::chunked::fold_chunks;
+use crate::field::{load_field, type_width};
+use crate::header::parse_header;
+use crate::list::ListFormat;
+use crate::geometry::load_cell_volumes;
+use crate::options::{self, Options};
+use crate::source::open_field_file;
+use crate::time::time_dir;
+use crate::zones::{field_mesh_dir, selected_cells, zone_values};
+use crate::errors;
+use crate::{
+    check_internal_count, check_width, field_width, find_internal_field, is_empty_list, for_each_number, for_each_scalar, internal_field_components,
+    parse_uniform_vector, tuple_width, InternalField,
+};
+
+// Single-pass accumulator (Welford) so the value list is never materialized
+#[derive(Clone, Copy)]
+pub struct RunningStats {
+    pub count: usize,
+    pub mean: f64,
+    m2: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Default for RunningStats {
+    fn default() -> Self {
+        RunningStats { count: 0, mean: 0.0, m2: 0.0, min: f64::INFINITY, max: f64::NEG_INFINITY }
+    }
+}
+
+impl RunningStats {
+    pub fn push(&mut self, val: f64) {
+        self.count += 1;
+        let delta = val - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (val - self.mean);
+        self.min = self.min.min(val);
+        self.max = self.max.max(val);
+    }
+
+    // Combine two accumulators over disjoint values (Chan et al.)
+    pub fn merge(self, other: Self) -> Self {
+        if self.count == 0 {
+            return other;
+        }
+        if other.count == 0 {
+            return self;
+        }
+        let (na, nb) = (self.count as f64, other.count as f64);
+        let n = na + nb;
+        let delta = other.mean - self.mean;
+        RunningStats {
+            count: self.count + other.count,
+            mean: self.mean + delta * nb / n,
+            m2: self.m2 + other.m2 + delta * delta * na * nb / n,
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    // Population standard deviation, matching np.std
+    pub fn std(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        (self.m2 / self.count as f64).sqrt()
+    }
+}
+
+/// Summary statistics of a field's internalField values.
+#[pyclass(frozen, get_all)]
+#[derive(Clone)]
+pub struct FieldStats {
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std: f64,
+    pub count: usize,
+}
+
+#[pymethods]
+impl FieldStats {
+    fn __repr__(&self) -> String {
+        format!(
+            "FieldStats(mean={}, min={}, max={}, std={}, count={})",
+            self.mean, self.min, self.max, self.std, self.count
+        )
+    }
+}
+
+impl FieldStats {
+    // Statistics of no values at all, e.g. an empty `0()` list
+    fn empty() -> Self {
+        FieldStats { mean: f64::NAN, min: f64::NAN, max: f64::NAN, std: f64::NAN, count: 0 }
+    }
+}
+
+// Kahan-compensated sums, one per SIMD lane
+#[derive(Clone, Copy)]
+struct Compensated {
+    sum: f64x4,
+    c: f64x4,
+}
+
+impl Compensated {
+    fn add(&mut self, x: f64x4) {
+        let y = x - self.c;
+        let t = self.sum + y;
+        self.c = (t - self.sum) - y;
+        self.sum = t;
+    }
+
+    // The lanes folded into one sum, still compensated
+    fn total(&self) -> f64 {
+        let (mut sum, mut c) = (0.0, 0.0);
+        for x in self.sum.to_array().into_iter().chain(self.c.to_array().map(|c| -c)) {
+            let y = x - c;
+            let t = sum + y;
+            c = (t - sum) - y;
+            sum = t;
+        }
+        sum
+    }
+}
+
+// Sum, sum of squares, min and max of a buffer of parsed values, four lanes
+// at a time. The sums are compensated so means of 10^8 values keep their
+// precision, and taken about the first value so the variance doesn't cancel
+// away when the mean dwarfs the spread (pressure around 1e5, say).
+pub struct SimdStats {
+    count: usize,
+    shift: Option<f64>,
+    sum: Compensated,
+    sum_sq: Compensated,
+    min: f64x4,
+    max: f64x4,
+}
+
+impl Default for SimdStats {
+    fn default() -> Self {
+        let zero = Compensated { sum: f64x4::ZERO, c: f64x4::ZERO };
+        SimdStats {
+            count: 0,
+            shift: None,
+            sum: zero,
+            sum_sq: zero,
+            min: f64x4::splat(f64::INFINITY),
+            max: f64x4::splat(f64::NEG_INFINITY),
+        }
+    }
+}
+
+impl SimdStats {
+    pub fn push_slice(&mut self, values: &[f64]) {
+        let Some(&first) = values.first() else {
+            return;
+        };
+        let shift = *self.shift.get_or_insert(first);
+        let k = f64x4::splat(shift);
+        let mut lanes = values.chunks_exact(4);
+        for chunk in &mut lanes {
+            let v = f64x4::from([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            self.min = self.min.min(v);
+            self.max = self.max.max(v);
+            let d = v - k;
+            self.sum.add(d);
+            self.sum_sq.add(d * d);
+        }
+        // Pad the tail with the shift, which adds nothing to the sums
+        let rest = lanes.remainder();
+        if !rest.is_empty() {
+            let mut v = [shift; 4];
+            v[..rest.len()].copy_from_slice(rest);
+            let mut bounds = [rest[0]; 4];
+            bounds[..rest.len()].copy_from_slice(rest);
+            self.min = self.min.min(f64x4::from(bounds));
+            self.max = self.max.max(f64x4::from(bounds));
+            let d = f64x4::from(v) - k;
+            self.sum.add(d);
+            self.sum_sq.add(d * d);
+        }
+        self.count += values.len();
+    }
+}
+
+impl From<SimdStats> for RunningStats {
+    fn from(s: SimdStats) -> Self {
+        let (Some(shift), n) = (s.shift, s.count as f64) else {
+            return RunningStats::default();
+        };
+        let (sum, sum_sq) = (s.sum.total(), s.sum_sq.total());
+        // Rounding can take the spread just below zero; NaN stays NaN
+        let m2 = sum_sq - sum * sum / n;
+        let fold = |v: f64x4, f: fn(f64, f64) -> f64, init: f64| v.to_array().into_iter().fold(init, f);
+        RunningStats {
+            count: s.count,
+            mean: shift + sum / n,
+            m2: if m2 < 0.0 { 0.0 } else { m2 },
+            min: fold(s.min, f64::min, f64::INFINITY),
+            max: fold(s.max, f64::max, f64::NEG_INFINITY),
+        }
+    }
+}
+
+// Statistics of one chunk of a list body, parsed into a small buffer that
+// is handed to the SIMD kernel whenever it fills
+fn chunk_stats(chunk: &[u8]) -> RunningStats {
+    const BUFFER: usize = 1024;
+    let mut stats = SimdStats::default();
+    let mut buffer = Vec::with_capacity(BUFFER);
+    for_each_number(chunk, |val| {
+        buffer.push(val);
+        if buffer.len() == BUFFER {
+            stats.push_slice(&buffer);
+            buffer.clear();
+        }
+    });
+    stats.push_slice(&buffer);
+    stats.into()
+}
+
+impl From<RunningStats> for FieldStats {
+    fn from(s: RunningStats) -> Self {
+        FieldStats { mean: s.mean, min: s.min, max: s.max, std: s.std(), count: s.count }
+    }
+}
+
+/// Mean, min, max, std and count of a scalar field, computed in one pass over
+/// the internalField. A uniform field counts as a single value and an empty
+/// list (`0()`) gives count 0 with NaN statistics. Returns None for a missing
+/// or unparsable file. With `cell_zone` or `cell_set`, only the cells of that
+/// cellZone or topoSet cellSet of the field's mesh are counted.
+#[pyfunction]
+#[pyo3(signature = (path, cell_zone = None, cell_set = None, options = None))]
+pub fn field_stats(
+    py: Python,
+    path: String,
+    cell_zone: Option<String>,
+    cell_set: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<FieldStats>> {
+    options::detach(py, &options, || {
+        let cells = selected_cells(&field_mesh_dir(Path::new(&path)), cell_zone.as_deref(), cell_set.as_deref())?;
+        cache::cached("field_stats", &path, &cells, || {
+            let data = match open_field_file(&path)? {
+                Some(m) => m,
+                None => return Ok(None),
+            };
+            if let Some(cells) = &cells {
+                let Some((values, width)) = zone_components(&data, cells)? else {
+                    return Ok(None);
+                };
+                check_width(width, &[1], "scalar")?;
+                let mut stats = RunningStats::default();
+                values.iter().for_each(|v| stats.push(*v));
+                return Ok(Some(if values.is_empty() { FieldStats::empty() } else { stats.into() }));
+            }
+
+            let mut stats = RunningStats::default();
+            if let Some(field) = find_internal_field(&data) {
+                check_internal_count(&data, &field)?;
+                check_width(field_width(&data, &field), &[1], "scalar")?;
+                match field {
+                    _ if is_empty_list(&field) => return Ok(Some(FieldStats::empty())),
+                    InternalField::NonUniform(list) => stats = fold_chunks(list, 1, chunk_stats, RunningStats::merge),
+                    InternalField::Uniform(_) => for_each_scalar(&field, |val| stats.push(val)),
+                }
+            }
+
+            if stats.count == 0 {
+                return Ok(None);
+            }
+            Ok(Some(stats.into()))
+        })
+    })
+}
+
+// Flat components of the cells of a cellZone or cellSet, plus the tuple
+// width; None without an internalField
+fn zone_components(data: &[u8], cells: &[usize]) -> PyResult<Option<(Vec<f64>, usize)>> {
+    let Some(field) = find_internal_field(data) else {
+        return Ok(None);
+    };
+    check_internal_count(data, &field)?;
+    let (values, width) = internal_field_components(field);
+    let values = zone_values(&values, width, cells).map_err(errors::parse_error)?;
+    Ok(Some((values, width)))
+}
+
+/// Result of a NaN/Inf scan over a field's internalField.
+#[pyclass(frozen, get_all)]
+#[derive(Clone, Default)]
+pub struct FiniteCheck {
+    pub finite: bool,
+    // Values (cells) scanned
+    pub count: usize,
+    // Components that are NaN or +-Inf
+    pub nan: usize,
+    pub inf: usize,
+    // Value index and component of the first non-finite component
+    pub first_index: Option<usize>,
+    pub first_component: Option<usize>,
+}
+
+#[pymethods]
+impl FiniteCheck {
+    fn __repr__(&self) -> String {
+        format!(
+            "FiniteCheck(finite={}, count={}, nan={}, inf={}, first_index={})",
+            self.finite,
+            self.count,
+            self.nan,
+            self.inf,
+            self.first_index.map_or("None".to_string(), |i| i.to_string())
+        )
+    }
+}
+
+impl FiniteCheck {
+    fn record(&mut self, position: usize, width: usize, is_nan: bool) {
+        if is_nan {
+            self.nan += 1;
+        } else {
+            self.inf += 1;
+        }
+        if self.first_index.is_none() {
+            self.first_index = Some(position / width);
+            self.first_component = Some(position % width);
+        }
+    }
+}
+
+// Classify ASCII number tokens by their first character after the sign,
+// without parsing them: nan/NaN and inf/Infinity are what OpenFOAM writes
+fn scan_ascii(content: &[u8], width: usize, check: &mut FiniteCheck) -> usize {
+    let mut n = 0;
+    for chunk in crate::scan::uncommented(content).split(|b| matches!(*b, b' ' | b'\n' | b'\t' | b'\r' | b'(' | b')')) {
+        let digits = chunk.strip_prefix(b"-").or_else(|| chunk.strip_prefix(b"+")).unwrap_or(chunk);
+        match digits.first() {
+            Some(b'0'..=b'9' | b'.') => {}
+            Some(b'n' | b'N') => check.record(n, width, true),
+            Some(b'i' | b'I') => check.record(n, width, false),
+            _ => continue,
+        }
+        n += 1;
+    }
+    n
+}
+
+// Non-finite doubles (or floats) have an all-ones exponent; a non-zero
+// mantissa makes them NaN
+fn scan_binary(content: &[u8], scalar_bytes: usize, width: usize, check: &mut FiniteCheck) -> usize {
+    let classify = |bytes: &[u8]| -> Option<bool> {
+        if scalar_bytes == 4 {
+            let bits = u32::from_le_bytes(bytes.try_into().ok()?);
+            ((bits >> 23) & 0xff == 0xff).then_some(bits & 0x7f_ffff != 0)
+        } else {
+            let bits = u64::from_le_bytes(bytes.try_into().ok()?);
+            ((bits >> 52) & 0x7ff == 0x7ff).then_some(bits & 0xf_ffff_ffff_ffff != 0)
+        }
+    };
+    let mut n = 0;
+    for bytes in content.chunks_exact(scalar_bytes) {
+        if let Some(is_nan) = classify(bytes) {
+            check.record(n, width, is_nan);
+        }
+        n += 1;
+    }
+    n
+}
+
+/// Scan a field's internalField for NaN and Inf, e.g. to flag a diverging
+/// run from its latest time step. ASCII numbers are classified without
+/// being parsed and binary lists by their exponent bits, so the scan is
+/// much faster than reading the field. Returns a FiniteCheck with the
+/// counts and the index (cell) and component of the first non-finite
+/// value, or None for a missing file or one without an internalField.
+#[pyfunction]
+#[pyo3(signature = (path, options = None))]
+pub fn check_finite(py: Python, path: String, options: Option<Options>) -> PyResult<Option<FiniteCheck>> {
+    options::detach(py, &options, || cache::cached("check_finite", &path, (), || {
+        let data = match open_field_file(&path)? {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        let Some(field) = find_internal_field(&data) else {
+            return Ok(None);
+        };
+        let width = parse_header(&data)
+            .and_then(|h| h.get("class").and_then(type_width))
+            .unwrap_or(1);
+        let fmt = ListFormat::of(&data);
+        let mut check = FiniteCheck::default();
+        let components = match field {
+            InternalField::NonUniform(content) if fmt.binary => scan_binary(content, fmt.scalar_bytes, width, &mut check),
+            InternalField::NonUniform(content) | InternalField::Uniform(content) => scan_ascii(content, width, &mut check),
+        };
+        check.count = components / width;
+        check.finite = check.nan == 0 && check.inf == 0;
+        Ok(Some(check))
+    }))
+}
+
+/// Per-component and magnitude statistics of a vector field.
+#[pyclass(frozen, get_all)]
+#[derive(Clone)]
+pub struct VectorFieldStats {
+    pub x: FieldStats,
+    pub y: FieldStats,
+    pub z: FieldStats,
+    pub magnitude: FieldStats,
+}
+
+#[pymethods]
+impl VectorFieldStats {
+    fn __repr__(&self) -> String {
+        format!(
+            "VectorFieldStats(x={}, y={}, z={}, magnitude={})",
+            self.x.__repr__(),
+            self.y.__repr__(),
+            self.z.__repr__(),
+            self.magnitude.__repr__()
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct RunningVectorStats {
+    pub components: [RunningStats; 3],
+    pub magnitude: RunningStats,
+}
+
+impl RunningVectorStats {
+    pub fn push(&mut self, v: [f64; 3]) {
+        for (s, c) in self.components.iter_mut().zip(v) {
+            s.push(c);
+        }
+        self.magnitude.push((v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt());
+    }
+}
+
+impl From<RunningVectorStats> for VectorFieldStats {
+    fn from(s: RunningVectorStats) -> Self {
+        VectorFieldStats {
+            x: s.components[0].into(),
+            y: s.components[1].into(),
+            z: s.components[2].into(),
+            magnitude: s.magnitude.into(),
+        }
+    }
+}
+
+/// Min/max/mean/std of Ux, Uy, Uz and of |U| for a vector field, computed in
+/// one pass over the internalField. Returns None for a missing or unparsable
+/// file; an empty list (`0()`) gives count 0 with NaN statistics. With
+/// `cell_zone` or `cell_set`, only the cells of that cellZone or topoSet
+/// cellSet of the field's mesh are counted.
+#[pyfunction]
+#[pyo3(signature = (path, cell_zone = None, cell_set = None, options = None))]
+pub fn vector_field_stats(
+    py: Python,
+    path: String,
+    cell_zone: Option<String>,
+    cell_set: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<VectorFieldStats>> {
+    options::detach(py, &options, || {
+        let cells = selected_cells(&field_mesh_dir(Path::new(&path)), cell_zone.as_deref(), cell_set.as_deref())?;
+        cache::cached("vector_field_stats", &path, &cells, || {
+            let data = match open_field_file(&path)? {
+                Some(m) => m,
+                None => return Ok(None),
+            };
+
+            let mut stats = RunningVectorStats::default();
+            if let Some(cells) = &cells {
+                let Some((values, width)) = zone_components(&data, cells)? else {
+                    return Ok(None);
+                };
+                check_width(width, &[3], "vector")?;
+                if values.is_empty() {
+                    let empty = FieldStats::empty();
+                    return Ok(Some(VectorFieldStats { x: empty.clone(), y: empty.clone(), z: empty.clone(), magnitude: empty }));
+                }
+                values.chunks_exact(3).for_each(|v| stats.push([v[0], v[1], v[2]]));
+                return Ok(Some(stats.into()));
+            }
+            let field = find_internal_field(&data);
+            if let Some(f) = &field {
+                check_internal_count(&data, f)?;
+            }
+            match field {
+                Some(f) if is_empty_list(&f) => {
+                    let empty = FieldStats::empty();
+                    return Ok(Some(VectorFieldStats { x: empty.clone(), y: empty.clone(), z: empty.clone(), magnitude: empty }));
+                }
+                Some(InternalField::NonUniform(list_content)) => {
+                    check_width(tuple_width(list_content), &[3], "vector")?;
+                    let mut v = [0.0; 3];
+                    let mut idx = 0;
+                    for_each_number(list_content, |val| {
+                        v[idx] = val;
+                        idx += 1;
+                        if idx == 3 {
+                            stats.push(v);
+                            idx = 0;
+                        }
+                    });
+                }
+                Some(InternalField::Uniform(value)) => {
+                    if let Some((x, y, z)) = parse_uniform_vector(value) {
+                        stats.push([x, y, z]);
+                    }
+                }
+                None => {}
+            }
+
+            if stats.magnitude.count == 0 {
+                return Ok(None);
+            }
+            Ok(Some(stats.into()))
+        })
+    })
+}
+
+// (edges, counts) as handed back to Python
+type Histogram<'py> = (Bound<'py, PyArray1<f64>>, Bound<'py, PyArray1<i64>>);
+
+// Bin edges and counts following np.histogram: equal-width bins over
+// [lo, hi], the last bin closed on the right, out-of-range values ignored
+fn histogram_bins<I: FnOnce(&mut dyn FnMut(f64))>(bins: usize, lo: f64, hi: f64, visit: I) -> (Vec<f64>, Vec<i64>) {
+    // np.histogram widens a degenerate range the same way
+    let (lo, hi) = if lo == hi { (lo - 0.5, hi + 0.5) } else { (lo, hi) };
+    let width = (hi - lo) / bins as f64;
+    let edges: Vec<f64> = (0..=bins).map(|i| lo + width * i as f64).collect();
+
+    let mut counts = vec![0i64; bins];
+    visit(&mut |val: f64| {
+        if !(lo..=hi).contains(&val) {
+            return;
+        }
+        let idx = (((val - lo) / width) as usize).min(bins - 1);
+        counts[idx] += 1;
+    });
+    (edges, counts)
+}
+
+/// Histogram of a scalar field's internalField values, binned in Rust.
+/// Returns (edges, counts) arrays like np.histogram, or None for a missing or
+/// unparsable file. Without `range` the data min/max are used, which costs a
+/// second pass over the file instead of holding every value in memory; an
+/// empty list (`0()`) is binned over [0, 1] like np.histogram does. Vector
+/// and tensor fields raise FieldParseError.
+#[pyfunction]
+#[pyo3(signature = (path, bins=10, range=None, options = None))]
+pub fn field_histogram<'py>(
+    py: Python<'py>,
+    path: String,
+    bins: usize,
+    range: Option<(f64, f64)>,
+    options: Option<Options>,
+) -> PyResult<Option<Histogram<'py>>> {
+    if bins == 0 {
+        return Err(PyValueError::new_err("bins must be positive"));
+    }
+    if let Some((lo, hi)) = range {
+        if !lo.is_finite() || !hi.is_finite() || lo > hi {
+            return Err(PyValueError::new_err("range must be finite with min <= max"));
+        }
+    }
+
+    let result = options::detach(py, &options, || -> PyResult<Option<(Vec<f64>, Vec<i64>)>> {
+        let data = match open_field_file(&path)? {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        let field = match find_internal_field(&data) {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+        check_internal_count(&data, &field)?;
+        check_width(field_width(&data, &field), &[1], "scalar")?;
+
+        let (lo, hi) = match range {
+            Some(r) => r,
+            None if is_empty_list(&field) => (0.0, 1.0),
+            None => {
+                let mut stats = RunningStats::default();
+                for_each_scalar(&field, |val| {
+                    if val.is_finite() {
+                        stats.push(val)
+                    }
+                });
+                if stats.count == 0 {
+                    return Ok(None);
+                }
+                (stats.min, stats.max)
+            }
+        };
+
+        Ok(Some(histogram_bins(bins, lo, hi, |f| for_each_scalar(&field, f))))
+    })?;
+
+    Ok(result.map(|(edges, counts)| (edges.into_pyarray(py), counts.into_pyarray(py))))
+}
+
+// Linear-interpolated quantiles (np.quantile's default method) by repeated
+// selection rather than a full sort. `values` is reordered in place.
+pub fn select_quantiles(values: &mut [f64], qs: &[f64]) -> Vec<f64> {
+    let n = values.len();
+    if n == 0 {
+        return vec![f64::NAN; qs.len()];
+    }
+
+    // Visit the requested ranks in ascending order so each selection only
+    // has to partition what lies to the right of the previous one
+    let mut order: Vec<usize> = (0..qs.len()).collect();
+    order.sort_by(|&a, &b| qs[a].total_cmp(&qs[b]));
+
+    let mut out = vec![0.0; qs.len()];
+    let mut floor = 0;
+    for i in order {
+        let pos = qs[i] * (n - 1) as f64;
+        let lo = pos.floor() as usize;
+        let frac = pos - lo as f64;
+
+        let (_, nth, _) = values[floor..].select_nth_unstable_by(lo - floor, f64::total_cmp);
+        let lower = *nth;
+        floor = lo;
+
+        out[i] = if frac > 0.0 && lo + 1 < n {
+            // Everything right of the pivot is >= it, so the next order
+            // statistic is simply the minimum of that tail
+            let upper = values[lo + 1..].iter().copied().fold(f64::INFINITY, f64::min);
+            lower + frac * (upper - lower)
+        } else {
+            lower
+        };
+    }
+    out
+}
+
+/// Quantiles of a scalar field's internalField values, e.g. qs=[0.01, 0.5,
+/// 0.99], using linear interpolation like np.quantile. NaNs are ignored.
+/// Returns None for a missing or unparsable file and NaNs for an empty list
+/// (`0()`). Vector and tensor fields raise FieldParseError.
+#[pyfunction]
+#[pyo3(signature = (path, qs, options = None))]
+pub fn field_quantiles(py: Python, path: String, qs: Vec<f64>, options: Option<Options>) -> PyResult<Option<Vec<f64>>> {
+    if qs.iter().any(|q| !(0.0..=1.0).contains(q)) {
+        return Err(PyValueError::new_err("quantiles must be in the range [0, 1]"));
+    }
+
+    options::detach(py, &options, || {
+        let data = match open_field_file(&path)? {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        let field = match find_internal_field(&data) {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+        check_internal_count(&data, &field)?;
+        check_width(field_width(&data, &field), &[1], "scalar")?;
+
+        let mut values = Vec::new();
+        for_each_scalar(&field, |val| {
+            if !val.is_nan() {
+                values.push(val)
+            }
+        });
+        if values.is_empty() && !is_empty_list(&field) {
+            return Ok(None);
+        }
+
+        Ok(Some(select_quantiles(&mut values, &qs)))
+    })
+}
+
+// Volume-weighted mean of each component. `values` holds `width` components
+// per cell.
+pub fn weighted_means(values: &[f64], width: usize, volumes: &[f64]) -> Vec<f64> {
+    let mut sums = vec![0.0; width];
+    let mut total = 0.0;
+    for (tuple, &v) in values.chunks_exact(width).zip(volumes) {
+        for (s, x) in sums.iter_mut().zip(tuple) {
+            *s += x * v;
+        }
+        total += v;
+    }
+    sums.into_iter().map(|s| s / total).collect()
+}
+
+/// Volume-weighted mean of a field at one time step: a float for scalars, an
+/// (x, y, z) tuple for vectors and a list for tensors. Cell volumes come from
+/// a `V` file in the time directory if present, otherwise from the mesh.
+/// Returns None if the field is missing. With `region`, the field and mesh
+/// of that region are used.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field, region = None, options = None))]
+pub fn field_volume_average<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    field: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let means = options::detach(py, &options, || -> PyResult<Option<Vec<f64>>> {
+        let root = Path::new(&case_root);
+        let data = match open_field_file(&time_dir(root, &time, region.as_deref()).join(&field).to_string_lossy())? {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        let internal = match find_internal_field(&data) {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+        check_internal_count(&data, &internal)?;
+        let uniform = matches!(internal, InternalField::Uniform(_));
+        let (values, width) = internal_field_components(internal);
+        if uniform || values.is_empty() {
+            // Weighting a constant changes nothing
+            return Ok((!values.is_empty()).then_some(values));
+        }
+
+        let volumes = match load_cell_volumes(root, &time, region.as_deref())? {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        if volumes.len() * width != values.len() {
+            return Err(PyValueError::new_err(format!(
+                "{} has {} values but the mesh has {} cells",
+                field,
+                values.len() / width,
+                volumes.len()
+            )));
+        }
+        Ok(Some(weighted_means(&values, width, &volumes)))
+    })?;
+
+    means_to_py(py, means)
+}
+
+// A field's internal values over the mesh cells, `width` components each,
+// with the cell volumes
+type CellValues = (Vec<f64>, usize, Vec<f64>);
+
+fn cell_values_with_volumes(root: &Path, time: &str, region: Option<&str>, field: &str) -> PyResult<Option<CellValues>> {
+    let path = time_dir(root, time, region).join(field);
+    let Some(file) = load_field(&path)? else {
+        return Ok(None);
+    };
+    let Some(volumes) = load_cell_volumes(root, time, region)? else {
+        return Ok(None);
+    };
+    let values = file.internal.as_ref().and_then(|v| v.expand(volumes.len(), file.width)).ok_or_else(|| {
+        PyValueError::new_err(format!("{}: internalField does not have a value for each of {} cells", path.display(), volumes.len()))
+    })?;
+    Ok(Some((values, file.width, volumes)))
+}
+
+/// Volume integral of a field at one time step, sum(value * V) over the
+/// cells: a float for scalars, an (x, y, z) tuple for vectors and a list for
+/// tensors. Cell volumes come from a `V` file in the time directory if
+/// present, otherwise from the mesh. Returns None if the field is missing.
+/// With `region`, the field and mesh of that region are used.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field, region = None, options = None))]
+pub fn field_integral<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    field: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let sums = options::detach(py, &options, || -> PyResult<Option<Vec<f64>>> {
+        let Some((values, width, volumes)) = cell_values_with_volumes(Path::new(&case_root), &time, region.as_deref(), &field)? else {
+            return Ok(None);
+        };
+        let mut sums = vec![0.0; width];
+        for (tuple, v) in values.chunks_exact(width).zip(&volumes) {
+            for (s, x) in sums.iter_mut().zip(tuple) {
+                *s += x * v;
+            }
+        }
+        Ok(Some(sums))
+    })?;
+
+    means_to_py(py, sums)
+}
+
+fn compare(op: &str) -> PyResult<fn(f64, f64) -> bool> {
+    Ok(match op {
+        ">" | "gt" => |a, b| a > b,
+        ">=" | "ge" => |a, b| a >= b,
+        "<" | "lt" => |a, b| a < b,
+        "<=" | "le" => |a, b| a <= b,
+        "==" | "eq" => |a, b| a == b,
+        "!=" | "ne" => |a, b| a != b,
+        _ => return Err(PyValueError::new_err(format!("unknown comparison '{}'", op))),
+    })
+}
+
+/// Statistics of `field` over the cells where `condition_field op
+/// threshold` holds, e.g. the mean T where alpha.water > 0.5, in one pass.
+/// `op` is one of > >= < <= == != (or gt, ge, lt, le, eq, ne). Vector
+/// fields are taken by magnitude. Returns a dict with the volume-weighted
+/// `mean`, `min`, `max`, the number of cells `count`, their `volume` and
+/// `volume_fraction` of the domain. Returns None if either field is
+/// missing. With `region`, the fields and mesh of that region are used.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field, condition_field, op, threshold, region = None, options = None))]
+#[allow(clippy::too_many_arguments)]
+pub fn conditional_stats<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    field: String,
+    condition_field: String,
+    op: String,
+    threshold: f64,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let test = compare(&op)?;
+    let result = options::detach(py, &options, || -> PyResult<Option<(RunningStats, f64, f64, f64)>> {
+        let root = Path::new(&case_root);
+        let Some((values, width, volumes)) = cell_values_with_volumes(root, &time, region.as_deref(), &field)? else {
+            return Ok(None);
+        };
+        let Some((condition, cwidth, _)) = cell_values_with_volumes(root, &time, region.as_deref(), &condition_field)? else {
+            return Ok(None);
+        };
+        let magnitude = |t: &[f64]| if t.len() == 1 { t[0] } else { t.iter().map(|x| x * x).sum::<f64>().sqrt() };
+        let mut stats = RunningStats::default();
+        let (mut weighted, mut volume, mut total) = (0.0, 0.0, 0.0);
+        for ((tuple, c), v) in values.chunks_exact(width).zip(condition.chunks_exact(cwidth)).zip(&volumes) {
+            total += v;
+            if !test(magnitude(c), threshold) {
+                continue;
+            }
+            let x = magnitude(tuple);
+            stats.push(x);
+            weighted += x * v;
+            volume += v;
+        }
+        Ok(Some((stats, weighted, volume, total)))
+    })?;
+
+    let Some((stats, weighted, volume, total)) = result else {
+        return Ok(None);
+    };
+    let out = PyDict::new(py);
+    let empty = stats.count == 0;
+    out.set_item("mean", if empty || volume <= 0.0 { f64::NAN } else { weighted / volume })?;
+    out.set_item("min", if empty { f64::NAN } else { stats.min })?;
+    out.set_item("max", if empty { f64::NAN } else { stats.max })?;
+    out.set_item("count", stats.count)?;
+    out.set_item("volume", volume)?;
+    out.set_item("volume_fraction", if total > 0.0 { volume / total } else { f64::NAN })?;
+    Ok(Some(out))
+}
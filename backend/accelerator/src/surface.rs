@@ -0,0 +1,527 @@
+// Triangle surfaces cut out of the mesh for browser rendering. Everything is
+// returned as flat float32/uint32 arrays so the frontend can hand them
+// straight to WebGL buffers.
+
+use numpy::{AllowTypeChange, IntoPyArray, PyArrayLike1};
+use pyo3::exceptions::{PyFileNotFoundError, PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::field::{load_field, load_mesh, load_patches, select_patches, FieldFile};
+use crate::geometry::{add, cross, dot, face_centre_area, mag, scale, sub, MeshGeometry, Vec3};
+use crate::mesh::{mesh_dir, Patch, PolyMesh};
+use crate::options::{self, Options};
+use crate::time::time_dir;
+use crate::zones::selected_cells;
+
+pub struct TriSurface {
+    pub points: Vec<Vec3>,
+    pub triangles: Vec<[u32; 3]>,
+    // One scalar per point, if a field was sampled
+    pub values: Option<Vec<f64>>,
+}
+
+impl TriSurface {
+    // Area-weighted vertex normals
+    fn normals(&self) -> Vec<Vec3> {
+        let mut normals = vec![[0.0; 3]; self.points.len()];
+        for tri in &self.triangles {
+            let [a, b, c] = tri.map(|i| self.points[i as usize]);
+            let n = cross(sub(b, a), sub(c, a));
+            for &i in tri {
+                normals[i as usize] = add(normals[i as usize], n);
+            }
+        }
+        for n in &mut normals {
+            let m = mag(*n);
+            if m > 0.0 {
+                *n = scale(*n, 1.0 / m);
+            }
+        }
+        normals
+    }
+}
+
+// Scalars of a field for colouring: the values themselves, or the magnitude
+// of each vector/tensor
+pub fn colour_values(values: &[f64], width: usize) -> Vec<f64> {
+    if width == 1 {
+        return values.to_vec();
+    }
+    values.chunks_exact(width).map(|t| t.iter().map(|v| v * v).sum::<f64>().sqrt()).collect()
+}
+
+fn flat_f32(values: impl IntoIterator<Item = f64>) -> Vec<f32> {
+    values.into_iter().map(|v| v as f32).collect()
+}
+
+/// The WebGL layout shared by every surface extractor: `positions` (3V,)
+/// float32, `normals` (3V,) float32, `indices` (3T,) uint32 and, when a
+/// field was sampled, `values` (V,) float32 with their `range` (min, max).
+pub fn surface_to_py<'py>(py: Python<'py>, surface: TriSurface) -> PyResult<Bound<'py, PyDict>> {
+    let out = PyDict::new(py);
+    let normals = surface.normals();
+    out.set_item("n_vertices", surface.points.len())?;
+    out.set_item("n_triangles", surface.triangles.len())?;
+    out.set_item("positions", flat_f32(surface.points.into_iter().flatten()).into_pyarray(py))?;
+    out.set_item("normals", flat_f32(normals.into_iter().flatten()).into_pyarray(py))?;
+    let indices: Vec<u32> = surface.triangles.into_iter().flatten().collect();
+    out.set_item("indices", indices.into_pyarray(py))?;
+    if let Some(values) = surface.values {
+        let finite = values.iter().copied().filter(|v| v.is_finite());
+        let range = finite.fold(None, |r: Option<(f64, f64)>, v| Some(r.map_or((v, v), |(lo, hi)| (lo.min(v), hi.max(v)))));
+        out.set_item("range", range)?;
+        out.set_item("values", flat_f32(values).into_pyarray(py))?;
+    }
+    Ok(out)
+}
+
+// A 1-D array of a surface dict as floats; None if the key is missing
+pub fn surface_array(surface: &Bound<'_, PyDict>, key: &str) -> PyResult<Option<Vec<f64>>> {
+    let Some(item) = surface.get_item(key)? else {
+        return Ok(None);
+    };
+    let array: PyArrayLike1<f64, AllowTypeChange> = item.extract().map_err(|_| PyValueError::new_err(format!("surface['{}'] must be a 1-D array", key)))?;
+    Ok(Some(array.as_array().iter().copied().collect()))
+}
+
+// A surface in the layout of `surface_to_py` read back, with its `values`
+// if it has them; normals are left to be recomputed
+pub fn surface_from_py(surface: &Bound<'_, PyDict>) -> PyResult<TriSurface> {
+    let required = |key: &str| surface_array(surface, key)?.ok_or_else(|| PyKeyError::new_err(format!("surface has no '{}'", key)));
+    let positions = required("positions")?;
+    let indices = required("indices")?;
+    let n = positions.len() / 3;
+    if !positions.len().is_multiple_of(3) {
+        return Err(PyValueError::new_err("surface positions must hold 3 values per vertex"));
+    }
+    if !indices.len().is_multiple_of(3) || indices.iter().any(|&i| !(i >= 0.0 && i < n as f64 && i.fract() == 0.0)) {
+        return Err(PyValueError::new_err(format!("surface indices must be triangles of vertices below {}", n)));
+    }
+    let values = surface_array(surface, "values")?;
+    if values.as_ref().is_some_and(|v| v.len() != n) {
+        return Err(PyValueError::new_err(format!("surface has {} values for {} vertices", values.map_or(0, |v| v.len()), n)));
+    }
+    Ok(TriSurface {
+        points: positions.chunks_exact(3).map(|p| [p[0], p[1], p[2]]).collect(),
+        triangles: indices.chunks_exact(3).map(|t| [t[0] as u32, t[1] as u32, t[2] as u32]).collect(),
+        values,
+    })
+}
+
+fn patch_surface(root: &Path, region: Option<&str>, patch: &str, time: Option<&str>, field: Option<&str>) -> PyResult<TriSurface> {
+    let mesh = load_mesh(root, region)?;
+    let patches = load_patches(root, region)?;
+    let selected = select_patches(&patches, patch)?;
+    let file = match (time, field) {
+        (Some(time), Some(field)) => {
+            let path = time_dir(root, time, region).join(field);
+            let file = load_field(&path)?
+                .ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no such field file", path.display())))?;
+            Some((path, file))
+        }
+        (None, Some(_)) => return Err(PyValueError::new_err("a field needs a time")),
+        _ => None,
+    };
+
+    let mut local = vec![u32::MAX; mesh.points.len()];
+    let mut points = Vec::new();
+    let mut triangles = Vec::new();
+    let mut sums = Vec::new();
+    let mut weights = Vec::new();
+    for p in selected {
+        let face_values = match &file {
+            Some((path, file)) => {
+                let values = file
+                    .patch_face_values(&mesh, p)
+                    .map_err(|e| PyValueError::new_err(format!("{}: {}", path.display(), e)))?;
+                Some(colour_values(&values, file.width))
+            }
+            None => None,
+        };
+        for i in 0..p.n_faces {
+            let face = mesh.face(p.start_face + i);
+            let ids: Vec<u32> = face
+                .iter()
+                .map(|&g| {
+                    if local[g] == u32::MAX {
+                        local[g] = points.len() as u32;
+                        points.push(mesh.points[g]);
+                        sums.push(0.0);
+                        weights.push(0.0);
+                    }
+                    local[g]
+                })
+                .collect();
+            for k in 1..ids.len().saturating_sub(1) {
+                triangles.push([ids[0], ids[k], ids[k + 1]]);
+            }
+            if let Some(values) = &face_values {
+                // Each face contributes to its vertices by area
+                let area = mag(face_centre_area(&mesh.points, face).1);
+                for &id in &ids {
+                    sums[id as usize] += area * values[i];
+                    weights[id as usize] += area;
+                }
+            }
+        }
+    }
+    let values = file.map(|_| sums.iter().zip(&weights).map(|(s, w)| if *w > 0.0 { s / w } else { f64::NAN }).collect());
+    Ok(TriSurface { points, triangles, values })
+}
+
+/// Triangulated geometry of a patch (or every patch in a patch group) for
+/// in-browser rendering, in the layout of `surface_to_py`. Only the patch's
+/// own points are included, renumbered from 0. With `time` and `field` the
+/// patch values (or, for patches without one, the adjacent cell values;
+/// magnitudes for vectors) are averaged onto the vertices for contour
+/// colouring. With `region`, the mesh and field of that region are used.
+#[pyfunction]
+#[pyo3(signature = (case_root, patch, time = None, field = None, region = None, options = None))]
+pub fn extract_patch_surface<'py>(
+    py: Python<'py>,
+    case_root: String,
+    patch: String,
+    time: Option<String>,
+    field: Option<String>,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let surface = options::detach(py, &options, || {
+        patch_surface(Path::new(&case_root), region.as_deref(), &patch, time.as_deref(), field.as_deref())
+    })?;
+    surface_to_py(py, surface)
+}
+
+// A volume field's values per cell, `width` components each
+pub fn cell_values(mesh: &PolyMesh, file: &FieldFile) -> Result<Vec<f64>, String> {
+    if !file.class.starts_with("vol") {
+        return Err(format!("{} is not a volume field", file.class));
+    }
+    file.internal
+        .as_ref()
+        .and_then(|v| v.expand(mesh.n_cells, file.width))
+        .ok_or_else(|| format!("internalField does not have {} values", mesh.n_cells))
+}
+
+// A volume field's values at the mesh points, `width` components each:
+// inverse-distance weighted from the surrounding cell centres, except that
+// points on patches with a value take the area-weighted patch face values
+pub fn point_values(mesh: &PolyMesh, geom: &MeshGeometry, patches: &[Patch], file: &FieldFile) -> Result<Vec<f64>, String> {
+    let width = file.width;
+    let cells = cell_values(mesh, file)?;
+
+    let n = mesh.points.len();
+    let mut sums = vec![0.0; n * width];
+    let mut weights = vec![0.0; n];
+    let mut cell_points = Vec::new();
+    for c in 0..mesh.n_cells {
+        cell_points.clear();
+        for &f in geom.faces_of(c) {
+            cell_points.extend_from_slice(mesh.face(f));
+        }
+        cell_points.sort_unstable();
+        cell_points.dedup();
+        for &p in &cell_points {
+            let w = 1.0 / mag(sub(mesh.points[p], geom.cell_centres[c])).max(1e-300);
+            for k in 0..width {
+                sums[p * width + k] += w * cells[c * width + k];
+            }
+            weights[p] += w;
+        }
+    }
+
+    let mut patch_sums = vec![0.0; n * width];
+    let mut patch_weights = vec![0.0; n];
+    for patch in patches.iter().filter(|p| p.patch_type != "empty") {
+        let Some(values) = file.patch_value(patch).and_then(|v| v.expand(patch.n_faces, width)) else {
+            continue;
+        };
+        for (i, value) in values.chunks_exact(width).enumerate() {
+            let face = mesh.face(patch.start_face + i);
+            let area = mag(geom.face_areas[patch.start_face + i]);
+            for &p in face {
+                for k in 0..width {
+                    patch_sums[p * width + k] += area * value[k];
+                }
+                patch_weights[p] += area;
+            }
+        }
+    }
+
+    Ok((0..n * width)
+        .map(|i| match (patch_weights[i / width], weights[i / width]) {
+            (pw, _) if pw > 0.0 => patch_sums[i] / pw,
+            (_, w) if w > 0.0 => sums[i] / w,
+            _ => f64::NAN,
+        })
+        .collect())
+}
+
+// A point where the plane crosses the edge between two mesh points, keyed
+// by the edge so neighbouring cells share it
+type EdgeCut = ((usize, usize), Vec3, f64);
+
+// The cells to cut: a zone or set's cells, else all of them
+fn cell_list(mesh: &PolyMesh, cells: Option<Vec<usize>>) -> PyResult<Vec<usize>> {
+    let Some(cells) = cells else {
+        return Ok((0..mesh.n_cells).collect());
+    };
+    if let Some(c) = cells.iter().find(|&&c| c >= mesh.n_cells) {
+        return Err(PyValueError::new_err(format!("cell label {} is out of range for {} cells", c, mesh.n_cells)));
+    }
+    Ok(cells)
+}
+
+// The section of the mesh by a plane, through the centre of the mesh's
+// bounding box without an `origin`
+pub fn plane_cut(
+    root: &Path,
+    region: Option<&str>,
+    time: &str,
+    field: &str,
+    origin: Option<Vec3>,
+    normal: Vec3,
+    cells: Option<Vec<usize>>,
+) -> PyResult<TriSurface> {
+    let length = mag(normal);
+    if length == 0.0 {
+        return Err(PyValueError::new_err("the plane normal is zero"));
+    }
+    let normal = scale(normal, 1.0 / length);
+    let mesh = load_mesh(root, region)?;
+    let origin = origin.unwrap_or_else(|| {
+        let (lo, hi) = mesh.points.iter().fold(([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]), |(lo, hi), p| {
+            ([lo[0].min(p[0]), lo[1].min(p[1]), lo[2].min(p[2])], [hi[0].max(p[0]), hi[1].max(p[1]), hi[2].max(p[2])])
+        });
+        scale(add(lo, hi), 0.5)
+    });
+    let patches = load_patches(root, region)?;
+    let path = time_dir(root, time, region).join(field);
+    let file = load_field(&path)?
+        .ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no such field file", path.display())))?;
+    let geom = MeshGeometry::compute(&mesh);
+    let values = point_values(&mesh, &geom, &patches, &file).map_err(|e| PyValueError::new_err(format!("{}: {}", path.display(), e)))?;
+    let values = colour_values(&values, file.width);
+
+    let distance: Vec<f64> = mesh.points.iter().map(|p| dot(sub(*p, origin), normal)).collect();
+    // In-plane axes, so that sorting cut points by angle winds every
+    // polygon anticlockwise about the normal
+    let axis = if normal[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+    let u = cross(normal, axis);
+    let u = scale(u, 1.0 / mag(u));
+    let v = cross(normal, u);
+
+    let polygons: Vec<Vec<EdgeCut>> = cell_list(&mesh, cells)?
+        .into_par_iter()
+        .filter_map(|c| {
+            let mut cuts: Vec<EdgeCut> = Vec::new();
+            for &f in geom.faces_of(c) {
+                let face = mesh.face(f);
+                for (i, &a) in face.iter().enumerate() {
+                    let b = face[(i + 1) % face.len()];
+                    let (da, db) = (distance[a], distance[b]);
+                    // Points on the plane count as above it
+                    if (da >= 0.0) == (db >= 0.0) {
+                        continue;
+                    }
+                    let key = (a.min(b), a.max(b));
+                    if cuts.iter().any(|cut| cut.0 == key) {
+                        continue;
+                    }
+                    let t = da / (da - db);
+                    let point = add(mesh.points[a], scale(sub(mesh.points[b], mesh.points[a]), t));
+                    cuts.push((key, point, values[a] + t * (values[b] - values[a])));
+                }
+            }
+            if cuts.len() < 3 {
+                return None;
+            }
+            let centre = scale(cuts.iter().fold([0.0; 3], |s, cut| add(s, cut.1)), 1.0 / cuts.len() as f64);
+            let angle = |p: Vec3| {
+                let r = sub(p, centre);
+                dot(r, v).atan2(dot(r, u))
+            };
+            cuts.sort_by(|a, b| angle(a.1).total_cmp(&angle(b.1)));
+            Some(cuts)
+        })
+        .collect();
+
+    let mut ids: HashMap<(usize, usize), u32> = HashMap::new();
+    let mut points = Vec::new();
+    let mut point_values = Vec::new();
+    let mut triangles = Vec::new();
+    for polygon in polygons {
+        let local: Vec<u32> = polygon
+            .into_iter()
+            .map(|(key, point, value)| {
+                *ids.entry(key).or_insert_with(|| {
+                    points.push(point);
+                    point_values.push(value);
+                    (points.len() - 1) as u32
+                })
+            })
+            .collect();
+        for k in 1..local.len() - 1 {
+            triangles.push([local[0], local[k], local[k + 1]]);
+        }
+    }
+    Ok(TriSurface { points, triangles, values: Some(point_values) })
+}
+
+/// Cut the mesh with the plane through `origin` with `normal` and return the
+/// section as a triangle surface (layout of `surface_to_py`), with the
+/// field interpolated onto it: cell values are first interpolated to the
+/// mesh points, then linearly along each cut edge. Vector and tensor fields
+/// are coloured by magnitude. The section is empty if the plane misses the
+/// mesh. With `cell_zone` or `cell_set`, only the cells of that cellZone or
+/// topoSet cellSet are cut. With `region`, the mesh and field of that
+/// region are used.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field, origin, normal, cell_zone = None, cell_set = None, region = None, options = None))]
+#[allow(clippy::too_many_arguments)]
+pub fn slice_plane<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    field: String,
+    origin: [f64; 3],
+    normal: [f64; 3],
+    cell_zone: Option<String>,
+    cell_set: Option<String>,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let root = Path::new(&case_root);
+    let region = region.as_deref();
+    let surface = options::detach(py, &options, || {
+        let cells = selected_cells(&mesh_dir(root, region), cell_zone.as_deref(), cell_set.as_deref())?;
+        plane_cut(root, region, &time, &field, Some(origin), normal, cells)
+    })?;
+    surface_to_py(py, surface)
+}
+
+// Points where the isosurface crosses three tet edges. Tet vertices are mesh
+// points, then face centres, then cell centres, numbered in that order so
+// the edge key is shared by every tet on the edge.
+type IsoTriangle = [((usize, usize), Vec3); 3];
+
+fn iso_surface(root: &Path, region: Option<&str>, time: &str, field: &str, iso: f64, subset: Option<Vec<usize>>) -> PyResult<TriSurface> {
+    let mesh = load_mesh(root, region)?;
+    let patches = load_patches(root, region)?;
+    let path = time_dir(root, time, region).join(field);
+    let file = load_field(&path)?
+        .ok_or_else(|| PyFileNotFoundError::new_err(format!("{}: no such field file", path.display())))?;
+    let error = |e: String| PyValueError::new_err(format!("{}: {}", path.display(), e));
+    let geom = MeshGeometry::compute(&mesh);
+    let cells = colour_values(&cell_values(&mesh, &file).map_err(error)?, file.width);
+    let points = colour_values(&point_values(&mesh, &geom, &patches, &file).map_err(error)?, file.width);
+    let (n_points, n_faces) = (mesh.points.len(), mesh.n_faces());
+    // Face centre values: the mean of the face's point values
+    let faces: Vec<f64> = (0..n_faces)
+        .map(|f| {
+            let face = mesh.face(f);
+            face.iter().map(|&p| points[p]).sum::<f64>() / face.len().max(1) as f64
+        })
+        .collect();
+    let vertex = |i: usize| -> (Vec3, f64) {
+        if i < n_points {
+            (mesh.points[i], points[i])
+        } else if i < n_points + n_faces {
+            (geom.face_centres[i - n_points], faces[i - n_points])
+        } else {
+            (geom.cell_centres[i - n_points - n_faces], cells[i - n_points - n_faces])
+        }
+    };
+    let crossing = |a: usize, b: usize| {
+        let ((pa, va), (pb, vb)) = (vertex(a), vertex(b));
+        let t = (iso - va) / (vb - va);
+        ((a.min(b), a.max(b)), add(pa, scale(sub(pb, pa), t)))
+    };
+
+    let triangles: Vec<IsoTriangle> = cell_list(&mesh, subset)?
+        .into_par_iter()
+        .flat_map_iter(|c| {
+            let mut out = Vec::new();
+            let centre = n_points + n_faces + c;
+            for &f in geom.faces_of(c) {
+                let face = mesh.face(f);
+                for (i, &a) in face.iter().enumerate() {
+                    let tet = [centre, n_points + f, a, face[(i + 1) % face.len()]];
+                    let (above, below): (Vec<usize>, Vec<usize>) = tet.iter().partition(|&&v| vertex(v).1 >= iso);
+                    let cuts: Vec<[(usize, usize); 3]> = match (above.len(), below.len()) {
+                        (1, 3) => vec![[(above[0], below[0]), (above[0], below[1]), (above[0], below[2])]],
+                        (3, 1) => vec![[(below[0], above[0]), (below[0], above[1]), (below[0], above[2])]],
+                        (2, 2) => vec![
+                            [(above[0], below[0]), (above[0], below[1]), (above[1], below[1])],
+                            [(above[0], below[0]), (above[1], below[1]), (above[1], below[0])],
+                        ],
+                        _ => continue,
+                    };
+                    // Orient each triangle to face increasing values
+                    let mean = |vs: &[usize]| scale(vs.iter().fold([0.0; 3], |s, &v| add(s, vertex(v).0)), 1.0 / vs.len() as f64);
+                    let uphill = sub(mean(&above), mean(&below));
+                    for edges in cuts {
+                        let mut tri = edges.map(|(a, b)| crossing(a, b));
+                        if dot(cross(sub(tri[1].1, tri[0].1), sub(tri[2].1, tri[0].1)), uphill) < 0.0 {
+                            tri.swap(1, 2);
+                        }
+                        out.push(tri);
+                    }
+                }
+            }
+            out
+        })
+        .collect();
+
+    let mut ids: HashMap<(usize, usize), u32> = HashMap::new();
+    let mut points = Vec::new();
+    let mut indices = Vec::with_capacity(triangles.len());
+    for tri in triangles {
+        let local = tri.map(|(key, point)| {
+            *ids.entry(key).or_insert_with(|| {
+                points.push(point);
+                (points.len() - 1) as u32
+            })
+        });
+        // Skip triangles collapsed by a crossing at a shared vertex
+        if local[0] != local[1] && local[1] != local[2] && local[0] != local[2] {
+            indices.push(local);
+        }
+    }
+    Ok(TriSurface { points, triangles: indices, values: None })
+}
+
+/// Isosurface of a volume field at `value` (e.g. alpha.water = 0.5) as a
+/// triangle surface in the layout of `surface_to_py`, by marching
+/// tetrahedra: each cell is split into tets on its centre, face centres and
+/// face edges, with values interpolated to the mesh points. Triangles face
+/// towards increasing values. Vector and tensor fields use their magnitude.
+/// With `cell_zone` or `cell_set`, only the cells of that cellZone or topoSet
+/// cellSet are searched. With `region`, the mesh and field of that region
+/// are used.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, field, value, cell_zone = None, cell_set = None, region = None, options = None))]
+#[allow(clippy::too_many_arguments)]
+pub fn isosurface<'py>(
+    py: Python<'py>,
+    case_root: String,
+    time: String,
+    field: String,
+    value: f64,
+    cell_zone: Option<String>,
+    cell_set: Option<String>,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let root = Path::new(&case_root);
+    let region = region.as_deref();
+    let surface = options::detach(py, &options, || {
+        let cells = selected_cells(&mesh_dir(root, region), cell_zone.as_deref(), cell_set.as_deref())?;
+        iso_surface(root, region, &time, &field, value, cells)
+    })?;
+    surface_to_py(py, surface)
+}
@@ -0,0 +1,478 @@
+use numpy::{IntoPyArray, PyArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::batch::field_means;
+use crate::dict::write_atomic;
+use crate::field::{calculated_patches, load_field, load_mesh, load_patches, vol_field_values, FieldOutput, FieldValue};
+use crate::decomposed::processor_dirs;
+use crate::downsample::{check_points, lttb_indices, pick};
+use crate::header::parse_header;
+use crate::options::{self, Options, Progress};
+use crate::source::{open_field_head, resolve_field_path};
+
+// Numeric time directories of a case as (time value, directory name), sorted
+// by time. Names that don't parse as numbers (constant, system, postProcessing,
+// 0.orig, ...) are skipped; "0", "0.005" and "1e-05" all parse. A missing case
+// root has no times.
+pub fn time_dirs(case_root: &Path) -> std::io::Result<Vec<(f64, String)>> {
+    let mut times = Vec::new();
+    let entries = match std::fs::read_dir(case_root) {
+        Ok(e) => e,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(times),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = match entry.file_name().into_string() {
+            Ok(n) => n,
+            Err(_) => continue,
+        };
+        if let Ok(t) = name.parse::<f64>() {
+            if t.is_finite() {
+                times.push((t, name));
+            }
+        }
+    }
+    // "1" and "1.0" compare equal, so break ties by name for a stable order
+    times.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    Ok(times)
+}
+
+// <time> of a case, or <time>/<region> for one region of a multi-region case
+pub fn time_dir(case_root: &Path, time: &str, region: Option<&str>) -> PathBuf {
+    let dir = case_root.join(time);
+    match region {
+        Some(region) => dir.join(region),
+        None => dir,
+    }
+}
+
+/// Time directory names of a case, sorted numerically.
+#[pyfunction]
+pub fn list_time_dirs(py: Python, case_root: String) -> PyResult<Vec<String>> {
+    py.detach(|| {
+        let dirs = time_dirs(Path::new(&case_root))?;
+        Ok(dirs.into_iter().map(|(_, name)| name).collect())
+    })
+}
+
+// Enough of a file for its FoamFile header
+const HEADER_BYTES: u64 = 16 << 10;
+
+// Field files in `dir` as (name, class), sorted by name with any .gz suffix
+// dropped. Only headers are read; files without one or whose class isn't a
+// field (dictionaries, hidden files, editor backups) are left out. A
+// missing directory has no fields.
+pub fn field_classes(dir: &Path) -> std::io::Result<Vec<(String, String)>> {
+    classes_ending(dir, "Field")
+}
+
+// field_classes() for any family of classes: files whose class ends with
+// `suffix` ("Field", "Set", ...)
+pub fn classes_ending(dir: &Path, suffix: &str) -> std::io::Result<Vec<(String, String)>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if let Ok(name) = entry.file_name().into_string() {
+            if !name.starts_with('.') && entry.file_type()?.is_file() {
+                files.push(name);
+            }
+        }
+    }
+    let mut fields: Vec<(String, String)> = files
+        .into_par_iter()
+        .filter_map(|name| {
+            let (head, _) = open_field_head(&dir.join(&name).to_string_lossy(), HEADER_BYTES).ok()??;
+            let class = parse_header(&head)?.get("class")?.to_string();
+            let name = name.strip_suffix(".gz").map_or(name.clone(), str::to_string);
+            class.ends_with(suffix).then_some((name, class))
+        })
+        .collect();
+    fields.sort();
+    fields.dedup_by(|a, b| a.0 == b.0);
+    Ok(fields)
+}
+
+/// Fields written at `time` as a dict of name to class (volScalarField,
+/// surfaceScalarField, pointVectorField, ...), sorted by name. Files are
+/// recognised by their FoamFile header, so dictionaries and other files in
+/// the directory are left out. Fields only present in processor*/<time>
+/// (a decomposed run) are included. An unknown time gives an empty dict.
+/// With `region`, the fields of that region (<time>/<region>) are listed.
+#[pyfunction]
+#[pyo3(signature = (case_root, time, region = None, options = None))]
+pub fn list_fields(
+    py: Python,
+    case_root: String,
+    time: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<BTreeMap<String, String>> {
+    options::detach(py, &options, || {
+        let root = Path::new(&case_root);
+        let mut fields = BTreeMap::new();
+        let dirs = std::iter::once(root.to_path_buf()).chain(processor_dirs(root)?);
+        for dir in dirs {
+            for (name, class) in field_classes(&time_dir(&dir, &time, region.as_deref()))? {
+                fields.entry(name).or_insert(class);
+            }
+        }
+        Ok(fields)
+    })
+}
+
+/// Mean of `field_name` in every time directory of a case, parsed in
+/// parallel. Returns (times, values) arrays; values is 1-D for scalar fields
+/// and (N, components) otherwise. Time directories without the field are
+/// skipped so both arrays stay aligned. With `region`, <time>/<region> is
+/// read. With `max_points` the series is thinned to that many time steps
+/// with downsample_lttb(), every component kept at the same times.
+#[pyfunction]
+#[pyo3(signature = (case_root, field_name, region = None, max_points = None, options = None))]
+pub fn field_time_series<'py>(
+    py: Python<'py>,
+    case_root: String,
+    field_name: String,
+    region: Option<String>,
+    max_points: Option<usize>,
+    options: Option<Options>,
+) -> PyResult<(Bound<'py, PyAny>, Bound<'py, PyAny>)> {
+    max_points.map(check_points).transpose()?;
+    let (times, values, width) = options::detach(py, &options, || -> PyResult<(Vec<f64>, Vec<f64>, usize)> {
+        let root = Path::new(&case_root);
+        let dirs = time_dirs(root)?;
+
+        let progress = Progress::start(dirs.len());
+        let means: Vec<Option<Vec<f64>>> = dirs
+            .par_iter()
+            .map(|(_, name)| {
+                options::check_cancelled()?;
+                Ok(field_means(&time_dir(root, name, region.as_deref()).join(&field_name).to_string_lossy()))
+            })
+            .inspect(|_| progress.step())
+            .collect::<PyResult<_>>()?;
+
+        // The first time step that parses fixes the field rank
+        let width = means.iter().flatten().map(|m| m.len()).next().unwrap_or(1);
+        let mut times = Vec::new();
+        let mut values = Vec::new();
+        for ((t, _), m) in dirs.iter().zip(means) {
+            if let Some(m) = m.filter(|m| m.len() == width) {
+                times.push(*t);
+                values.extend(m);
+            }
+        }
+        if let Some(max_points) = max_points {
+            let series: Vec<Vec<f64>> = (0..width).map(|k| values.iter().skip(k).step_by(width).copied().collect()).collect();
+            let series: Vec<&[f64]> = series.iter().map(Vec::as_slice).collect();
+            let kept = lttb_indices(&times, &series, max_points);
+            if kept.len() < times.len() {
+                times = pick(&times, &kept);
+                values = kept.iter().flat_map(|&i| values[i * width..(i + 1) * width].iter().copied()).collect();
+            }
+        }
+        Ok((times, values, width))
+    })?;
+
+    let rows = times.len();
+    let values = if width == 1 {
+        values.into_pyarray(py).into_any()
+    } else {
+        values.into_pyarray(py).reshape([rows, width])?.into_any()
+    };
+    Ok((times.into_pyarray(py).into_any(), values))
+}
+
+// A field file the solver has finished writing ends with the closing brace of
+// boundaryField, optionally followed by the `// *****` banner line. Anything
+// else is taken as still being written. Compressed files only need to exist.
+pub fn field_file_complete(path: &Path) -> bool {
+    let path = match resolve_field_path(path) {
+        Some(p) => p,
+        None => return false,
+    };
+    let mut file = match File::open(&path) {
+        Ok(f) => f,
+        Err(_) => return false,
+    };
+    let len = match file.metadata() {
+        Ok(m) => m.len(),
+        Err(_) => return false,
+    };
+    if len == 0 {
+        return false;
+    }
+    if path.extension().is_some_and(|e| e == "gz") {
+        return true;
+    }
+
+    let tail_len = len.min(1024);
+    let mut tail = vec![0; tail_len as usize];
+    if file.seek(SeekFrom::Start(len - tail_len)).is_err() || file.read_exact(&mut tail).is_err() {
+        return false;
+    }
+    let tail = String::from_utf8_lossy(&tail);
+    let mut tail = tail.trim_end();
+    if let Some(i) = tail.rfind('\n') {
+        if tail[i + 1..].trim_start().starts_with("//") {
+            tail = tail[..i].trim_end();
+        }
+    }
+    tail.ends_with('}')
+}
+
+fn has_any_file(dir: &Path) -> bool {
+    std::fs::read_dir(dir)
+        .map(|mut entries| entries.any(|e| e.is_ok_and(|e| e.file_type().is_ok_and(|t| t.is_file()))))
+        .unwrap_or(false)
+}
+
+/// Newest time directory whose `required_fields` are all present and fully
+/// written, skipping output a running solver is still writing. Without
+/// `required_fields` the newest directory containing any file is returned.
+/// Returns None if no time directory qualifies. With `region`, the fields
+/// are looked for in <time>/<region>.
+#[pyfunction]
+#[pyo3(signature = (case_root, required_fields=None, region=None))]
+pub fn latest_time(py: Python, case_root: String, required_fields: Option<Vec<String>>, region: Option<String>) -> PyResult<Option<String>> {
+    py.detach(|| {
+        let root = Path::new(&case_root);
+        let dirs = time_dirs(root)?;
+
+        for (_, name) in dirs.into_iter().rev() {
+            let dir = time_dir(root, &name, region.as_deref());
+            let ready = match &required_fields {
+                Some(fields) => fields.iter().all(|f| field_file_complete(&dir.join(f))),
+                None => has_any_file(&dir),
+            };
+            if ready {
+                return Ok(Some(name));
+            }
+        }
+        Ok(None)
+    })
+}
+
+// Running mean and co-moments over time steps of a field's values,
+// mergeable (Chan et al.) so time directories can be read in parallel.
+// Co-moments are the upper triangle of each item's component products
+// (xx xy xz yy yz zz for vectors), OpenFOAM's symmTensor order.
+#[derive(Default)]
+struct TimeAccumulator {
+    count: usize,
+    mean: Vec<f64>,
+    comoment: Vec<f64>,
+}
+
+fn upper_triangle(width: usize) -> Vec<(usize, usize)> {
+    (0..width).flat_map(|i| (i..width).map(move |j| (i, j))).collect()
+}
+
+impl TimeAccumulator {
+    fn push(mut self, values: Vec<f64>, width: usize, pairs: &[(usize, usize)]) -> Result<Self, String> {
+        if self.count == 0 {
+            self.comoment = vec![0.0; values.len() / width * pairs.len()];
+            self.mean = values;
+            self.count = 1;
+            return Ok(self);
+        }
+        if values.len() != self.mean.len() {
+            return Err(format!("{} values where earlier times have {}", values.len() / width, self.mean.len() / width));
+        }
+        self.count += 1;
+        let n = self.count as f64;
+        let mut before = vec![0.0; width];
+        for (item, x) in values.chunks_exact(width).enumerate() {
+            let mean = &mut self.mean[item * width..(item + 1) * width];
+            for k in 0..width {
+                before[k] = x[k] - mean[k];
+                mean[k] += before[k] / n;
+            }
+            for (p, &(i, j)) in pairs.iter().enumerate() {
+                self.comoment[item * pairs.len() + p] += before[i] * (x[j] - mean[j]);
+            }
+        }
+        Ok(self)
+    }
+
+    fn merge(self, other: Self, width: usize, pairs: &[(usize, usize)]) -> Result<Self, String> {
+        if self.count == 0 {
+            return Ok(other);
+        }
+        if other.count == 0 {
+            return Ok(self);
+        }
+        if self.mean.len() != other.mean.len() {
+            return Err("the field has a different number of values at different times".to_string());
+        }
+        let (na, nb) = (self.count as f64, other.count as f64);
+        let n = na + nb;
+        let mut merged = self;
+        let mut delta = vec![0.0; width];
+        for item in 0..merged.mean.len() / width {
+            for (k, d) in delta.iter_mut().enumerate() {
+                let i = item * width + k;
+                *d = other.mean[i] - merged.mean[i];
+                merged.mean[i] += *d * nb / n;
+            }
+            for (p, &(i, j)) in pairs.iter().enumerate() {
+                let c = item * pairs.len() + p;
+                merged.comoment[c] += other.comoment[c] + delta[i] * delta[j] * na * nb / n;
+            }
+        }
+        merged.count += other.count;
+        Ok(merged)
+    }
+}
+
+// Times used, n_cells, components, mean, Prime2Mean and the files written
+type Averaged = (Vec<String>, usize, usize, Vec<f64>, Option<Vec<f64>>, Vec<String>);
+
+fn time_average(
+    root: &Path,
+    region: Option<&str>,
+    field: &str,
+    range: (Option<f64>, Option<f64>),
+    variance: bool,
+    write: bool,
+    binary: bool,
+) -> PyResult<Averaged> {
+    let (t_start, t_end) = range;
+    let times: Vec<String> = time_dirs(root)?
+        .into_iter()
+        .filter(|(t, _)| t_start.is_none_or(|s| *t >= s) && t_end.is_none_or(|e| *t <= e))
+        .map(|(_, name)| name)
+        .filter(|name| resolve_field_path(&time_dir(root, name, region).join(field)).is_some())
+        .collect();
+    let Some(last) = times.last() else {
+        return Err(PyValueError::new_err(format!("no time directory in range has '{}'", field)));
+    };
+    let mesh = load_mesh(root, region)?;
+    let patches = load_patches(root, region)?;
+    let first = load_field(&time_dir(root, last, region).join(field))?
+        .ok_or_else(|| PyValueError::new_err(format!("{}: unreadable field", last)))?;
+    let width = first.width;
+    if !first.class.starts_with("vol") {
+        return Err(PyValueError::new_err(format!("{} is not a volume field", first.class)));
+    }
+    if variance && width > 3 {
+        return Err(PyValueError::new_err("variance is only available for scalar and vector fields"));
+    }
+    let pairs = if variance { upper_triangle(width) } else { Vec::new() };
+
+    // Internal values then every patch's face values, one time step
+    let progress = Progress::start(times.len());
+    let read = |time: &String| -> Result<Vec<f64>, String> {
+        if options::cancelled() {
+            return Err(String::new());
+        }
+        let path = time_dir(root, time, region).join(field);
+        let error = |e: String| format!("{}: {}", path.display(), e);
+        let file = load_field(&path).map_err(|e| e.to_string())?.ok_or_else(|| error("missing".into()))?;
+        if file.width != width {
+            return Err(error(format!("{} components where other times have {}", file.width, width)));
+        }
+        let values = vol_field_values(&mesh, &patches, &file).map_err(error);
+        progress.step();
+        values
+    };
+    let total = times
+        .par_iter()
+        .try_fold(TimeAccumulator::default, |acc, time| acc.push(read(time)?, width, &pairs))
+        .try_reduce(TimeAccumulator::default, |a, b| a.merge(b, width, &pairs))
+        .map_err(|e| options::check_cancelled().err().unwrap_or_else(|| PyValueError::new_err(e)))?;
+
+    let n = total.count as f64;
+    let prime2mean: Option<Vec<f64>> = variance.then(|| total.comoment.iter().map(|c| c / n).collect());
+    let mut written = Vec::new();
+    if write {
+        let dimensions = first.dimensions.unwrap_or([0.0; 7]);
+        let mut outputs = vec![(format!("{}Mean", field), total.mean.clone(), width, dimensions)];
+        if let Some(p2m) = &prime2mean {
+            outputs.push((format!("{}Prime2Mean", field), p2m.clone(), pairs.len(), dimensions.map(|d| 2.0 * d)));
+        }
+        for (name, values, w, dimensions) in outputs {
+            let split = mesh.n_cells * w;
+            let output = FieldOutput {
+                kind: "vol",
+                object: name.clone(),
+                location: last.clone(),
+                dimensions,
+                width: w,
+                internal: FieldValue::NonUniform(values[..split].to_vec()),
+                patches: calculated_patches(&patches, &values[split..], w),
+                binary,
+            };
+            let path = time_dir(root, last, region).join(&name);
+            write_atomic(&path, output.to_bytes())?;
+            written.push(path.to_string_lossy().into_owned());
+        }
+    }
+    let mut mean = total.mean;
+    mean.truncate(mesh.n_cells * width);
+    let prime2mean = prime2mean.map(|mut p| {
+        p.truncate(mesh.n_cells * pairs.len());
+        p
+    });
+    Ok((times, mesh.n_cells, width, mean, prime2mean, written))
+}
+
+/// Time average of a volume field over the time directories from `t_start`
+/// to `t_end` (inclusive, default all), e.g. LES statistics. Directories are
+/// read in parallel and combined in one pass; with `variance` the
+/// (co)variance is accumulated too, per value for scalars and as a
+/// symmTensor for vectors like OpenFOAM's Prime2Mean. Returns a dict with
+/// the `times` used, `mean` ((n_cells,) or (n_cells, components)) and
+/// `prime2mean`. With `write` the results are also written as
+/// <field>Mean and <field>Prime2Mean (calculated patches holding the
+/// averaged boundary values) into the last time directory, and `written`
+/// lists the files. With `region`, the mesh and fields of that region are
+/// used and written.
+#[pyfunction]
+#[pyo3(signature = (case_root, field, t_start = None, t_end = None, variance = false, write = false, binary = false, region = None, options = None))]
+#[allow(clippy::too_many_arguments)]
+pub fn time_average_field<'py>(
+    py: Python<'py>,
+    case_root: String,
+    field: String,
+    t_start: Option<f64>,
+    t_end: Option<f64>,
+    variance: bool,
+    write: bool,
+    binary: bool,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let (times, n_cells, width, mean, prime2mean, written) = options::detach(py, &options, || {
+        time_average(Path::new(&case_root), region.as_deref(), &field, (t_start, t_end), variance, write, binary)
+    })?;
+
+    let out = PyDict::new(py);
+    out.set_item("times", times)?;
+    let as_array = |values: Vec<f64>, w: usize| -> PyResult<Bound<'py, PyAny>> {
+        let array = values.into_pyarray(py);
+        Ok(if w == 1 { array.into_any() } else { array.reshape([n_cells, w])?.into_any() })
+    };
+    out.set_item("mean", as_array(mean, width)?)?;
+    if let Some(p) = prime2mean {
+        let w = p.len() / n_cells.max(1);
+        out.set_item("prime2mean", as_array(p, w.max(1))?)?;
+    }
+    out.set_item("written", written)?;
+    Ok(out)
+}
@@ -0,0 +1,308 @@
+// Pre-run checks of an uploaded case: the dictionaries a solver reads at
+// startup, the mesh, the start time directory and its fields. Problems are
+// collected rather than raised, so they can all be shown at once.
+
+use pyo3::exceptions::PyFileNotFoundError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rayon::prelude::*;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::boundary::read_boundary_field;
+use crate::decomposed::{collated_dirs, processor_dirs};
+use crate::dict::{load_dict, Dict};
+use crate::mesh::{load_boundary, mesh_dir, region_names, Patch};
+use crate::options::{self, Options};
+use crate::snappy::Report;
+use crate::source::resolve_field_path;
+use crate::time::{field_classes, time_dir, time_dirs};
+
+const MESH_FILES: [&str; 5] = ["points", "faces", "owner", "neighbour", "boundary"];
+// Patches decomposePar writes boundaryField entries for itself
+const PROCESSOR_PATCHES: [&str; 2] = ["processor", "processorCyclic"];
+
+struct Validation {
+    report: Report,
+    application: Option<String>,
+    start_from: String,
+    start_time: Option<String>,
+    regions: Vec<String>,
+    serial_mesh: bool,
+    n_processors: usize,
+    times: Vec<String>,
+    fields: Vec<(String, String)>,
+}
+
+// A path relative to the case, for messages
+fn rel(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).to_string_lossy().into_owned()
+}
+
+fn missing_mesh_files(dir: &Path) -> Vec<&'static str> {
+    MESH_FILES.into_iter().filter(|f| resolve_field_path(&dir.join(f)).is_none()).collect()
+}
+
+// Whether a boundaryField key applies to a patch: its name, one of its
+// groups, or a regex key such as "(inlet|outlet)" or ".*"
+fn key_matches(key: &str, patch: &Patch) -> bool {
+    key == patch.name
+        || patch.in_groups.iter().any(|g| g == key)
+        || Regex::new(&format!("^(?:{})$", key)).is_ok_and(|re| re.is_match(&patch.name))
+}
+
+// Rank count of a collated case from its processors<N> directory name
+fn collated_count(dirs: &[PathBuf]) -> usize {
+    let name = dirs.first().and_then(|d| d.file_name()).and_then(|n| n.to_str()).unwrap_or("");
+    let rest = name.strip_prefix("processors").unwrap_or("");
+    rest.split('_').next().and_then(|n| n.parse().ok()).unwrap_or(0)
+}
+
+// The time directory a run starts from, following controlDict's startFrom
+fn start_dir(times: &[(f64, String)], start_from: &str, start_time: Option<f64>) -> Option<String> {
+    let found = match start_from {
+        "firstTime" => times.first(),
+        "latestTime" => times.last(),
+        _ => {
+            let t = start_time?;
+            times.iter().find(|(v, _)| (v - t).abs() <= 1e-9 * t.abs().max(1.0))
+        }
+    };
+    found.map(|(_, name)| name.clone())
+}
+
+fn check_control_dict(control: &Dict, report: &mut Report) {
+    if control.word("application").is_none() {
+        report.warning("system/controlDict has no application".into());
+    }
+    if control.number("deltaT").is_none() {
+        report.error("system/controlDict has no deltaT".into());
+    }
+    if matches!(control.word("stopAt"), None | Some("endTime")) && control.number("endTime").is_none() {
+        report.error("system/controlDict has no endTime".into());
+    }
+    match control.word("startFrom") {
+        None | Some("firstTime" | "latestTime") => {}
+        Some("startTime") if control.number("startTime").is_none() => {
+            report.error("system/controlDict has startFrom startTime but no startTime".into());
+        }
+        Some("startTime") => {}
+        Some(other) => report.error(format!(
+            "system/controlDict startFrom '{}' is not firstTime, startTime or latestTime",
+            other
+        )),
+    }
+}
+
+// Every patch of the mesh needs a boundaryField entry in each field
+fn check_boundary_fields(root: &Path, dir: &Path, fields: &[(String, String)], patches: &[Patch], report: &mut Report) {
+    let problems: Vec<String> = fields
+        .par_iter()
+        .filter_map(|(name, _)| {
+            let path = dir.join(name);
+            let shown = rel(root, &path);
+            let boundary = match read_boundary_field(&path) {
+                Ok(Some(b)) => b,
+                Ok(None) => return Some(format!("{} has no boundaryField", shown)),
+                Err(e) => return Some(format!("{}: {}", shown, e)),
+            };
+            let missing: Vec<&str> = patches
+                .iter()
+                .filter(|p| !PROCESSOR_PATCHES.contains(&p.patch_type.as_str()))
+                .filter(|p| !boundary.entries.iter().any(|e| key_matches(&e.key, p)))
+                .map(|p| p.name.as_str())
+                .collect();
+            (!missing.is_empty()).then(|| format!("{} has no boundaryField entry for {}", shown, missing.join(", ")))
+        })
+        .collect();
+    for problem in problems {
+        report.error(problem);
+    }
+}
+
+// Field names at `start` in each processor directory that differ from
+// processor0's
+fn check_processor_fields(
+    root: &Path,
+    processors: &[PathBuf],
+    start: &str,
+    region: Option<&str>,
+    report: &mut Report,
+) -> std::io::Result<()> {
+    let names = |dir: &Path| -> std::io::Result<BTreeSet<String>> {
+        Ok(field_classes(&time_dir(dir, start, region))?.into_iter().map(|(n, _)| n).collect())
+    };
+    let Some((first, rest)) = processors.split_first() else {
+        return Ok(());
+    };
+    let reference = names(first)?;
+    for dir in rest {
+        let here = names(dir)?;
+        let missing: Vec<&str> = reference.difference(&here).map(String::as_str).collect();
+        let extra: Vec<&str> = here.difference(&reference).map(String::as_str).collect();
+        let shown = rel(root, &time_dir(dir, start, region));
+        let first = rel(root, &time_dir(first, start, region));
+        if !missing.is_empty() {
+            report.error(format!("{} is missing {} (present in {})", shown, missing.join(", "), first));
+        }
+        if !extra.is_empty() {
+            report.error(format!("{} has {} that {} lacks", shown, extra.join(", "), first));
+        }
+    }
+    Ok(())
+}
+
+fn validate(root: &Path) -> PyResult<Validation> {
+    let mut report = Report::default();
+    let system = root.join("system");
+    let control = match load_dict(&system.join("controlDict").to_string_lossy()) {
+        Ok(Some(d)) => Some(d),
+        Ok(None) => {
+            report.error("system/controlDict is missing".into());
+            None
+        }
+        Err(e) => {
+            report.error(e.to_string());
+            None
+        }
+    };
+    if let Some(control) = &control {
+        check_control_dict(control, &mut report);
+    }
+    for name in ["fvSchemes", "fvSolution"] {
+        if resolve_field_path(&system.join(name)).is_none() {
+            report.error(format!("system/{} is missing", name));
+        }
+    }
+    let control = control.unwrap_or_default();
+    let application = control.word("application").map(str::to_string);
+    // OpenFOAM's default when startFrom is left out
+    let start_from = control.word("startFrom").unwrap_or("latestTime").to_string();
+
+    // Mesh: constant/polyMesh (constant/<region>/polyMesh for each region of
+    // a multi-region case), or the same in every processor directory
+    let regions = region_names(root)?;
+    let areas: Vec<Option<&str>> = if regions.is_empty() {
+        vec![None]
+    } else {
+        regions.iter().map(|r| Some(r.as_str())).collect()
+    };
+    let serial_missing: Vec<(PathBuf, Vec<&str>)> =
+        areas.iter().map(|&a| (mesh_dir(root, a), missing_mesh_files(&mesh_dir(root, a)))).collect();
+    let serial_mesh = serial_missing.iter().all(|(_, missing)| missing.is_empty());
+    let processors = processor_dirs(root)?;
+    let collated = collated_dirs(root)?;
+    for dir in processors.iter().chain(&collated) {
+        for &area in &areas {
+            let missing = missing_mesh_files(&mesh_dir(dir, area));
+            if !missing.is_empty() {
+                report.error(format!("{} is missing {}", rel(root, &mesh_dir(dir, area)), missing.join(", ")));
+            }
+        }
+    }
+    let decomposed = !processors.is_empty() || !collated.is_empty();
+    let any_mesh = serial_missing.iter().any(|(_, missing)| missing.len() < MESH_FILES.len());
+    if any_mesh {
+        for (dir, missing) in serial_missing.iter().filter(|(_, missing)| !missing.is_empty()) {
+            if missing.len() < MESH_FILES.len() {
+                report.error(format!("{} is missing {}", rel(root, dir), missing.join(", ")));
+            } else {
+                report.error(format!("{} is missing", rel(root, dir)));
+            }
+        }
+    } else if !decomposed {
+        if resolve_field_path(&system.join("blockMeshDict")).is_some() {
+            report.warning("no mesh yet: run blockMesh (system/blockMeshDict) first".into());
+        } else {
+            report.error("no mesh: constant/polyMesh is empty and there is no system/blockMeshDict".into());
+        }
+    }
+    let n_processors = if processors.is_empty() { collated_count(&collated) } else { processors.len() };
+
+    // A decomposed-only case runs from its processor directories
+    let run_dir = match processors.first().or(collated.first()) {
+        Some(dir) if decomposed && !serial_mesh => dir.clone(),
+        _ => root.to_path_buf(),
+    };
+    let collated_run = run_dir != root && processors.is_empty();
+    let times = time_dirs(&run_dir)?;
+    let start_time = start_dir(&times, &start_from, control.number("startTime"));
+    if start_time.is_none() {
+        let shown = rel(root, &run_dir);
+        let place = if shown.is_empty() { String::new() } else { format!(" in {}", shown) };
+        let mut message = match (start_from.as_str(), control.number("startTime")) {
+            ("startTime", Some(t)) => format!("no time directory for startTime {}{}", t, place),
+            _ => format!("no time directories{}", place),
+        };
+        if root.join("0.orig").is_dir() {
+            message.push_str(" (0.orig is present: copy it to 0)");
+        }
+        report.error(message);
+    }
+
+    // Collated time directories hold decomposedBlockData, not fields
+    let mut fields = Vec::new();
+    if let (Some(start), false) = (&start_time, collated_run) {
+        for &area in &areas {
+            let dir = time_dir(&run_dir, start, area);
+            let found = field_classes(&dir)?;
+            if found.is_empty() {
+                report.error(format!("time directory {} has no field files", rel(root, &dir)));
+            }
+            check_processor_fields(root, &processors, start, area, &mut report)?;
+            if let Some(patches) = load_boundary(&mesh_dir(&run_dir, area))? {
+                check_boundary_fields(root, &dir, &found, &patches, &mut report);
+            }
+            // A region's fields are listed as "<region>/<field>"
+            let prefix = area.map_or(String::new(), |r| format!("{}/", r));
+            fields.extend(found.into_iter().map(|(name, class)| (format!("{}{}", prefix, name), class)));
+        }
+    }
+
+    Ok(Validation {
+        report,
+        application,
+        start_from,
+        start_time,
+        regions,
+        serial_mesh,
+        n_processors,
+        times: times.into_iter().map(|(_, name)| name).collect(),
+        fields,
+    })
+}
+
+/// Check that a case is ready to run: system/controlDict (application,
+/// deltaT, endTime and a usable startFrom/startTime), fvSchemes and
+/// fvSolution, a mesh in constant/polyMesh or in every processor directory,
+/// and the start time directory with its fields, which must be the same in
+/// every processor directory and have a boundaryField entry for every patch.
+/// Multi-region cases are checked region by region.
+/// Returns a dict with `valid` (False when any issue is an error), `issues`
+/// (a list of {"severity", "message"}), `application`, `start_from`,
+/// `start_time` (the directory the run would start from, or None),
+/// `regions`, `serial_mesh`, `n_processors`, `times` and `fields` (name, or
+/// "<region>/<name>", to class at the start time). Raises FileNotFoundError if `case_root` isn't a directory.
+#[pyfunction]
+#[pyo3(signature = (case_root, options = None))]
+pub fn validate_case<'py>(py: Python<'py>, case_root: String, options: Option<Options>) -> PyResult<Bound<'py, PyDict>> {
+    let root = Path::new(&case_root);
+    if !root.is_dir() {
+        return Err(PyFileNotFoundError::new_err(format!("{}: no such case directory", case_root)));
+    }
+    let v = options::detach(py, &options, || validate(root))?;
+
+    let out = PyDict::new(py);
+    out.set_item("valid", v.report.valid())?;
+    out.set_item("issues", v.report.to_py(py)?)?;
+    out.set_item("application", v.application)?;
+    out.set_item("start_from", v.start_from)?;
+    out.set_item("start_time", v.start_time)?;
+    out.set_item("regions", v.regions)?;
+    out.set_item("serial_mesh", v.serial_mesh)?;
+    out.set_item("n_processors", v.n_processors)?;
+    out.set_item("times", v.times)?;
+    out.set_item("fields", v.fields.into_iter().collect::<BTreeMap<_, _>>())?;
+    Ok(out)
+}
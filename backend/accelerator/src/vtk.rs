@@ -0,0 +1,540 @@
+use flate2::read::ZlibDecoder;
+use numpy::{IntoPyArray, PyArrayMethods};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::io::Read;
+use std::path::Path;
+
+use crate::list::reserve_bounded;
+
+// A named point or cell data array, `components` values per tuple
+pub struct DataArray {
+    pub name: String,
+    pub components: usize,
+    pub values: Vec<f64>,
+}
+
+// Polygonal surface as written by the `surfaces` function object. Faces are
+// in compact form: face i uses connectivity[offsets[i]..offsets[i + 1]].
+#[derive(Default)]
+pub struct Surface {
+    pub points: Vec<f64>,
+    pub offsets: Vec<i64>,
+    pub connectivity: Vec<i64>,
+    pub point_data: Vec<DataArray>,
+    pub cell_data: Vec<DataArray>,
+}
+
+type ParseResult<T> = Result<T, String>;
+
+// Byte size of a legacy or XML type name
+fn type_size(name: &str) -> Option<usize> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "int8" | "uint8" | "char" | "unsigned_char" | "bit" => 1,
+        "int16" | "uint16" | "short" | "unsigned_short" => 2,
+        "int32" | "uint32" | "int" | "unsigned_int" | "float" | "float32" => 4,
+        "int64" | "uint64" | "long" | "unsigned_long" | "vtktypeint64" | "vtktypeuint64" | "double" | "float64" => 8,
+        _ => return None,
+    })
+}
+
+fn is_float(name: &str) -> bool {
+    matches!(name.to_ascii_lowercase().as_str(), "float" | "float32" | "double" | "float64")
+}
+
+fn is_unsigned(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.starts_with('u') || name.starts_with("unsigned") || name == "vtktypeuint64"
+}
+
+// Decode packed binary values of `type_name` to f64
+fn decode_binary(bytes: &[u8], type_name: &str, big_endian: bool) -> ParseResult<Vec<f64>> {
+    let size = type_size(type_name).ok_or_else(|| format!("unsupported data type {}", type_name))?;
+    let float = is_float(type_name);
+    let unsigned = is_unsigned(type_name);
+    Ok(bytes
+        .chunks_exact(size)
+        .map(|c| {
+            let mut b = [0u8; 8];
+            if big_endian {
+                b[8 - size..].copy_from_slice(c);
+                b.reverse();
+            } else {
+                b[..size].copy_from_slice(c);
+            }
+            let bits = u64::from_le_bytes(b);
+            match (float, size, unsigned) {
+                (true, 4, _) => f32::from_bits(bits as u32) as f64,
+                (true, _, _) => f64::from_bits(bits),
+                (false, _, true) => bits as f64,
+                // Sign-extend from the value's width
+                (false, _, false) => ((bits << (64 - 8 * size)) as i64 >> (64 - 8 * size)) as f64,
+            }
+        })
+        .collect())
+}
+
+// ---------------------------------------------------------------- legacy .vtk
+
+struct Legacy<'a> {
+    data: &'a [u8],
+    pos: usize,
+    binary: bool,
+}
+
+impl<'a> Legacy<'a> {
+    fn word(&mut self) -> Option<&'a str> {
+        while self.pos < self.data.len() && self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < self.data.len() && !self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        (self.pos > start).then(|| std::str::from_utf8(&self.data[start..self.pos]).ok())?
+    }
+
+    fn count(&mut self) -> ParseResult<usize> {
+        self.word().and_then(|w| w.parse().ok()).ok_or_else(|| "expected a count".to_string())
+    }
+
+    fn skip_line(&mut self) {
+        match self.data[self.pos..].iter().position(|&b| b == b'\n') {
+            Some(nl) => self.pos += nl + 1,
+            None => self.pos = self.data.len(),
+        }
+    }
+
+    // `n` values of `type_name`: whitespace separated in ASCII files, packed
+    // big-endian after the end of the header line in binary ones
+    fn values(&mut self, n: usize, type_name: &str) -> ParseResult<Vec<f64>> {
+        if self.binary {
+            self.skip_line();
+            let size = type_size(type_name).ok_or_else(|| format!("unsupported data type {}", type_name))?;
+            let end = self.pos + n * size;
+            let bytes = self.data.get(self.pos..end).ok_or("truncated binary data")?;
+            self.pos = end;
+            return decode_binary(bytes, type_name, true);
+        }
+        (0..n)
+            .map(|_| self.word().and_then(|w| w.parse().ok()).ok_or_else(|| "truncated ASCII data".to_string()))
+            .collect()
+    }
+
+    // Cell list in either the classic "n size / count ids..." layout or the
+    // VTK 5.1 OFFSETS / CONNECTIVITY layout
+    fn cells(&mut self) -> ParseResult<(Vec<i64>, Vec<i64>)> {
+        let first = self.count()?;
+        let second = self.count()?;
+        let mark = self.pos;
+        if self.word() == Some("OFFSETS") {
+            let offsets_type = self.word().ok_or("missing OFFSETS type")?;
+            let offsets = self.values(first, offsets_type)?;
+            if self.word() != Some("CONNECTIVITY") {
+                return Err("missing CONNECTIVITY".into());
+            }
+            let conn_type = self.word().ok_or("missing CONNECTIVITY type")?;
+            let conn = self.values(second, conn_type)?;
+            return Ok((offsets.iter().map(|&v| v as i64).collect(), conn.iter().map(|&v| v as i64).collect()));
+        }
+        self.pos = mark;
+        let flat = self.values(second, "int")?;
+        let mut offsets = vec![0i64];
+        let mut conn = Vec::with_capacity(second - first.min(second));
+        let mut i = 0;
+        for _ in 0..first {
+            let n = *flat.get(i).ok_or("truncated cell list")? as usize;
+            let ids = flat.get(i + 1..i + 1 + n).ok_or("truncated cell list")?;
+            conn.extend(ids.iter().map(|&v| v as i64));
+            offsets.push(conn.len() as i64);
+            i += 1 + n;
+        }
+        Ok((offsets, conn))
+    }
+
+    // Attribute arrays following POINT_DATA n / CELL_DATA n
+    fn attribute(&mut self, keyword: &str, n: usize) -> ParseResult<Vec<DataArray>> {
+        let name = self.word().ok_or("missing array name")?.to_string();
+        let type_name = self.word().ok_or("missing array type")?;
+        let components = match keyword {
+            "SCALARS" => {
+                // Optional component count, then the lookup table line
+                let mark = self.pos;
+                let c = match self.word() {
+                    Some(w) if w.parse::<usize>().is_ok() => w.parse().unwrap_or(1),
+                    _ => {
+                        self.pos = mark;
+                        1
+                    }
+                };
+                let mark = self.pos;
+                if self.word() == Some("LOOKUP_TABLE") {
+                    self.word();
+                } else {
+                    self.pos = mark;
+                }
+                c
+            }
+            "VECTORS" | "NORMALS" => 3,
+            _ => 9,
+        };
+        let values = self.values(n * components, type_name)?;
+        Ok(vec![DataArray { name, components, values }])
+    }
+
+    // FIELD FieldData k, then k "name components tuples type" arrays
+    fn field(&mut self) -> ParseResult<Vec<DataArray>> {
+        self.word();
+        let k = self.count()?;
+        let mut arrays = Vec::with_capacity(reserve_bounded(k, self.data.len().saturating_sub(self.pos)));
+        for _ in 0..k {
+            let name = self.word().ok_or("missing field array name")?.to_string();
+            let components = self.count()?;
+            let tuples = self.count()?;
+            let type_name = self.word().ok_or("missing field array type")?;
+            let values = self.values(components * tuples, type_name)?;
+            arrays.push(DataArray { name, components, values });
+        }
+        Ok(arrays)
+    }
+}
+
+fn parse_legacy(data: &[u8]) -> ParseResult<Surface> {
+    let mut lines = data.splitn(4, |&b| b == b'\n');
+    let version = lines.next().unwrap_or_default();
+    if !version.starts_with(b"# vtk") {
+        return Err("not a legacy VTK file".into());
+    }
+    lines.next();
+    let format = String::from_utf8_lossy(lines.next().unwrap_or_default()).trim().to_ascii_uppercase();
+    let header_len = data.len() - lines.next().map_or(0, <[u8]>::len);
+    let mut r = Legacy { data, pos: header_len, binary: format == "BINARY" };
+
+    let mut surface = Surface::default();
+    // Which attribute section arrays belong to and how many tuples it has
+    let mut section: Option<(bool, usize)> = None;
+    while let Some(word) = r.word() {
+        match word {
+            "DATASET" => {
+                let kind = r.word().unwrap_or_default();
+                if kind != "POLYDATA" {
+                    return Err(format!("unsupported dataset {}", kind));
+                }
+            }
+            "POINTS" => {
+                let n = r.count()?;
+                let type_name = r.word().ok_or("missing POINTS type")?;
+                surface.points = r.values(n * 3, type_name)?;
+            }
+            "POLYGONS" => (surface.offsets, surface.connectivity) = r.cells()?,
+            // Surfaces from OpenFOAM only hold polygons; other cells are skipped
+            "VERTICES" | "LINES" | "TRIANGLE_STRIPS" => {
+                r.cells()?;
+            }
+            "POINT_DATA" => section = Some((true, r.count()?)),
+            "CELL_DATA" => section = Some((false, r.count()?)),
+            "SCALARS" | "VECTORS" | "NORMALS" | "TENSORS" | "FIELD" => {
+                let (is_point, n) = section.ok_or("data array outside POINT_DATA/CELL_DATA")?;
+                let arrays = if word == "FIELD" { r.field()? } else { r.attribute(word, n)? };
+                if is_point {
+                    surface.point_data.extend(arrays);
+                } else {
+                    surface.cell_data.extend(arrays);
+                }
+            }
+            "METADATA" => {
+                // INFORMATION / COMPONENT_NAMES block, ended by a blank line
+                while r.pos < data.len() {
+                    r.skip_line();
+                    if data.get(r.pos).is_none_or(|&b| b == b'\n' || b == b'\r') {
+                        break;
+                    }
+                }
+            }
+            other => return Err(format!("unexpected keyword {}", other)),
+        }
+    }
+    Ok(surface)
+}
+
+// ---------------------------------------------------------------- XML .vtp
+
+// Attribute `name` of an XML start tag
+fn attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let key = format!("{}=\"", name);
+    let mut from = 0;
+    while let Some(i) = tag[from..].find(&key) {
+        let at = from + i;
+        if at > 0 && tag.as_bytes()[at - 1].is_ascii_whitespace() {
+            let start = at + key.len();
+            let end = start + tag[start..].find('"')?;
+            return Some(&tag[start..end]);
+        }
+        from = at + key.len();
+    }
+    None
+}
+
+// Start tag and inner text of the first <name ...>...</name> element in
+// `xml`, plus the index just past it
+fn element<'a>(xml: &'a str, name: &str) -> Option<(&'a str, &'a str, usize)> {
+    let open = format!("<{}", name);
+    let mut from = 0;
+    loop {
+        let start = from + xml[from..].find(&open)?;
+        let after = xml.as_bytes().get(start + open.len()).copied();
+        if after.is_some_and(|b| b == b'>' || b == b'/' || b.is_ascii_whitespace()) {
+            let tag_end = start + xml[start..].find('>')?;
+            let tag = &xml[start + 1..tag_end];
+            if tag.ends_with('/') {
+                return Some((tag, "", tag_end + 1));
+            }
+            // An unclosed element runs to the end, as VTKFile does when the
+            // text is cut off at AppendedData
+            let close = format!("</{}>", name);
+            let body_end = xml[tag_end + 1..].find(&close).map_or(xml.len(), |i| tag_end + 1 + i);
+            return Some((tag, &xml[tag_end + 1..body_end], (body_end + close.len()).min(xml.len())));
+        }
+        from = start + open.len();
+    }
+}
+
+// Every <DataArray> of a section as (start tag, inner text)
+fn data_arrays(xml: &str) -> Vec<(&str, &str)> {
+    let mut arrays = Vec::new();
+    let mut rest = xml;
+    while let Some((tag, body, end)) = element(rest, "DataArray") {
+        arrays.push((tag, body));
+        rest = &rest[end..];
+    }
+    arrays
+}
+
+fn base64_decode(text: &[u8]) -> ParseResult<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for &c in text {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return Err("invalid base64 data".into()),
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+// Layout of binary blocks shared by one file
+struct Encoding {
+    header_bytes: usize,
+    big_endian: bool,
+    compressed: bool,
+}
+
+impl Encoding {
+    fn header_value(&self, bytes: &[u8]) -> usize {
+        decode_binary(bytes, if self.header_bytes == 8 { "UInt64" } else { "UInt32" }, self.big_endian)
+            .ok()
+            .and_then(|v| v.first().copied())
+            .unwrap_or(0.0) as usize
+    }
+
+    // Size of the block header: one byte count, or block count, sizes and
+    // one compressed size per block when compressed
+    fn header_len(&self, first: &[u8]) -> usize {
+        if self.compressed {
+            let blocks = self.header_value(first);
+            (3 + blocks) * self.header_bytes
+        } else {
+            self.header_bytes
+        }
+    }
+
+    // Payload following a header already split off
+    fn payload(&self, header: &[u8], body: &[u8]) -> ParseResult<Vec<u8>> {
+        let h = |i: usize| self.header_value(&header[i * self.header_bytes..]);
+        if !self.compressed {
+            return body.get(..h(0)).map(<[u8]>::to_vec).ok_or_else(|| "truncated binary data".into());
+        }
+        let mut out = Vec::new();
+        let mut pos = 0;
+        for i in 0..h(0) {
+            let size = h(3 + i);
+            let block = body.get(pos..pos + size).ok_or("truncated compressed data")?;
+            ZlibDecoder::new(block).read_to_end(&mut out).map_err(|e| e.to_string())?;
+            pos += size;
+        }
+        Ok(out)
+    }
+
+    // Raw appended data: header and payload back to back
+    fn raw(&self, data: &[u8]) -> ParseResult<Vec<u8>> {
+        let first = data.get(..self.header_bytes).ok_or("truncated appended data")?;
+        let len = self.header_len(first);
+        let header = data.get(..len).ok_or("truncated appended data")?;
+        self.payload(header, &data[len..])
+    }
+
+    // Base64 text. VTK encodes the header and the payload separately, so the
+    // header occupies whole 4-character groups of its own.
+    fn base64(&self, text: &[u8]) -> ParseResult<Vec<u8>> {
+        let text: Vec<u8> = text.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+        let chars = |bytes: usize| bytes.div_ceil(3) * 4;
+        let first = base64_decode(text.get(..chars(self.header_bytes)).ok_or("truncated base64 data")?)?;
+        let len = self.header_len(&first);
+        let header = base64_decode(text.get(..chars(len)).ok_or("truncated base64 data")?)?;
+        self.payload(&header, &base64_decode(&text[chars(len)..])?)
+    }
+}
+
+// Values of one <DataArray>
+fn xml_values(tag: &str, body: &str, enc: &Encoding, appended: Option<(&[u8], bool)>) -> ParseResult<Vec<f64>> {
+    let type_name = attr(tag, "type").ok_or("DataArray without type")?;
+    match attr(tag, "format").unwrap_or("ascii") {
+        "ascii" => body
+            .split_ascii_whitespace()
+            .map(|w| w.parse().map_err(|_| format!("bad ASCII value {}", w)))
+            .collect(),
+        "binary" => decode_binary(&enc.base64(body.as_bytes())?, type_name, enc.big_endian),
+        "appended" => {
+            let (data, raw) = appended.ok_or("missing AppendedData")?;
+            let offset: usize = attr(tag, "offset").and_then(|o| o.parse().ok()).ok_or("bad appended offset")?;
+            let data = data.get(offset..).ok_or("appended offset out of range")?;
+            let bytes = if raw { enc.raw(data)? } else { enc.base64(data)? };
+            decode_binary(&bytes, type_name, enc.big_endian)
+        }
+        other => Err(format!("unsupported DataArray format {}", other)),
+    }
+}
+
+fn parse_xml(data: &[u8]) -> ParseResult<Surface> {
+    // Raw appended data can hold any byte, so only the part before it is text
+    let appended_at = data.windows(13).position(|w| w == b"<AppendedData").unwrap_or(data.len());
+    let xml = String::from_utf8_lossy(&data[..appended_at]);
+
+    let (file_tag, _, _) = element(&xml, "VTKFile").ok_or("missing VTKFile element")?;
+    if attr(file_tag, "type") != Some("PolyData") {
+        return Err("only PolyData .vtp files are supported".into());
+    }
+    let enc = Encoding {
+        header_bytes: if attr(file_tag, "header_type") == Some("UInt64") { 8 } else { 4 },
+        big_endian: attr(file_tag, "byte_order") == Some("BigEndian"),
+        compressed: attr(file_tag, "compressor").is_some_and(|c| c.contains("ZLib")),
+    };
+    let appended = if appended_at < data.len() {
+        let tail = &data[appended_at..];
+        let tag_end = tail.iter().position(|&b| b == b'>').ok_or("bad AppendedData")?;
+        let raw = !String::from_utf8_lossy(&tail[..tag_end]).contains("base64");
+        let underscore = tail.iter().position(|&b| b == b'_').ok_or("AppendedData without '_' marker")?;
+        Some((&tail[underscore + 1..], raw))
+    } else {
+        None
+    };
+
+    let (_, piece, _) = element(&xml, "Piece").ok_or("missing Piece element")?;
+    let mut surface = Surface::default();
+    if let Some((_, points, _)) = element(piece, "Points") {
+        let (tag, body) = data_arrays(points).into_iter().next().ok_or("Points without DataArray")?;
+        surface.points = xml_values(tag, body, &enc, appended)?;
+    }
+    if let Some((_, polys, _)) = element(piece, "Polys") {
+        for (tag, body) in data_arrays(polys) {
+            let values: Vec<i64> = xml_values(tag, body, &enc, appended)?.iter().map(|&v| v as i64).collect();
+            match attr(tag, "Name") {
+                Some("connectivity") => surface.connectivity = values,
+                // XML offsets are end positions; prepend the leading 0
+                Some("offsets") => surface.offsets = std::iter::once(0).chain(values).collect(),
+                _ => {}
+            }
+        }
+    }
+    for (section, is_point) in [("PointData", true), ("CellData", false)] {
+        let Some((_, body, _)) = element(piece, section) else {
+            continue;
+        };
+        for (tag, inner) in data_arrays(body) {
+            let array = DataArray {
+                name: attr(tag, "Name").unwrap_or("").to_string(),
+                components: attr(tag, "NumberOfComponents").and_then(|c| c.parse().ok()).unwrap_or(1),
+                values: xml_values(tag, inner, &enc, appended)?,
+            };
+            if is_point {
+                surface.point_data.push(array);
+            } else {
+                surface.cell_data.push(array);
+            }
+        }
+    }
+    Ok(surface)
+}
+
+pub fn parse_surface(data: &[u8]) -> ParseResult<Surface> {
+    let start = data.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(0);
+    let surface = if data[start..].starts_with(b"<") { parse_xml(data)? } else { parse_legacy(data)? };
+    if surface.points.len() % 3 != 0 {
+        return Err("point coordinates are not a multiple of 3".into());
+    }
+    let n_points = (surface.points.len() / 3) as i64;
+    if surface.connectivity.iter().any(|&i| i < 0 || i >= n_points) {
+        return Err("polygon vertex out of range".into());
+    }
+    Ok(surface)
+}
+
+fn arrays_to_dict<'py>(py: Python<'py>, arrays: Vec<DataArray>) -> PyResult<Bound<'py, PyDict>> {
+    let out = PyDict::new(py);
+    for a in arrays {
+        let tuples = a.values.len() / a.components.max(1);
+        let values = if a.components <= 1 {
+            a.values.into_pyarray(py).into_any()
+        } else {
+            a.values.into_pyarray(py).reshape([tuples, a.components])?.into_any()
+        };
+        out.set_item(a.name, values)?;
+    }
+    Ok(out)
+}
+
+/// Read a sampled surface written by the `surfaces` function object, either
+/// legacy .vtk (ASCII or binary) or XML .vtp (ascii, base64 or appended,
+/// optionally zlib-compressed). Returns a dict with `points` (N, 3), the
+/// polygons in compact form as `face_offsets` and `face_labels`, and
+/// `point_data` / `cell_data` dicts of arrays, (n,) for scalars and
+/// (n, components) otherwise. Returns None if the file is missing.
+#[pyfunction]
+pub fn read_vtk_surface<'py>(py: Python<'py>, path: String) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let surface = py.detach(|| -> PyResult<Option<Surface>> {
+        let data = match std::fs::read(&path) {
+            Ok(d) => d,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        parse_surface(&data)
+            .map(Some)
+            .map_err(|e| PyValueError::new_err(format!("{}: {}", Path::new(&path).display(), e)))
+    })?;
+    let Some(s) = surface else {
+        return Ok(None);
+    };
+
+    let n_points = s.points.len() / 3;
+    let out = PyDict::new(py);
+    out.set_item("points", s.points.into_pyarray(py).reshape([n_points, 3])?)?;
+    out.set_item("face_offsets", s.offsets.into_pyarray(py))?;
+    out.set_item("face_labels", s.connectivity.into_pyarray(py))?;
+    out.set_item("point_data", arrays_to_dict(py, s.point_data)?)?;
+    out.set_item("cell_data", arrays_to_dict(py, s.cell_data)?)?;
+    Ok(Some(out))
+}
@@ -0,0 +1,280 @@
+// Watching a running case for new output. A notify watcher on the case root
+// wakes a Rust thread, which lets a burst of writes settle and then calls back
+// for every time directory the solver has finished writing and every log file
+// that has grown. Nothing is read between writes, so an idle case on NFS costs
+// no traffic; where the filesystem sends no events, the thread rescans on a
+// timer instead.
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher as _};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::shutdown;
+use crate::time::{field_classes, field_file_complete, time_dirs};
+
+// How often the thread looks at its stop flag while nothing happens
+const TICK: Duration = Duration::from_millis(100);
+
+// A time directory counts as written once it holds a field and every field in
+// it, and in its region subdirectories, is complete
+fn time_dir_written(dir: &Path) -> bool {
+    let mut dirs = vec![dir.to_path_buf()];
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        dirs.extend(
+            entries
+                .flatten()
+                .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()) && e.file_name() != "uniform")
+                .map(|e| e.path()),
+        );
+    }
+    let mut any = false;
+    for dir in dirs {
+        for (name, _) in field_classes(&dir).unwrap_or_default() {
+            if !field_file_complete(&dir.join(name)) {
+                return false;
+            }
+            any = true;
+        }
+    }
+    any
+}
+
+// What the case looked like at the last scan: time directories already
+// reported (or there from the start) and the size of each log file
+struct Scan {
+    root: PathBuf,
+    reported: HashSet<String>,
+    logs: BTreeMap<String, u64>,
+    // A new time directory is still being written
+    waiting: bool,
+}
+
+impl Scan {
+    fn new(root: PathBuf) -> Scan {
+        let mut scan = Scan { root, reported: HashSet::new(), logs: BTreeMap::new(), waiting: false };
+        scan.reported = scan.times().into_iter().map(|(_, name)| name).collect();
+        scan.logs = scan.log_sizes();
+        scan
+    }
+
+    // Time directories of the case, and of processor0 for a decomposed run
+    fn times(&self) -> Vec<(PathBuf, String)> {
+        [self.root.clone(), self.root.join("processor0")]
+            .into_iter()
+            .flat_map(|dir| {
+                let times = time_dirs(&dir).unwrap_or_default();
+                times.into_iter().map(move |(_, name)| (dir.join(&name), name))
+            })
+            .collect()
+    }
+
+    // log and log.* files in the case root
+    fn log_sizes(&self) -> BTreeMap<String, u64> {
+        let Ok(entries) = std::fs::read_dir(&self.root) else {
+            return BTreeMap::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|e| {
+                let name = e.file_name().into_string().ok()?;
+                let meta = e.metadata().ok()?;
+                (meta.is_file() && (name == "log" || name.starts_with("log."))).then_some((name, meta.len()))
+            })
+            .collect()
+    }
+
+    // Events since the last scan, as (kind, name): ("time", "0.5") for a time
+    // directory written in full, ("log", "log.foamRun") for a log that grew
+    // or was started over
+    fn changes(&mut self) -> Vec<(&'static str, String)> {
+        let mut events = Vec::new();
+        self.waiting = false;
+        for (dir, name) in self.times() {
+            if self.reported.contains(&name) {
+                continue;
+            }
+            if time_dir_written(&dir) {
+                self.reported.insert(name.clone());
+                events.push(("time", name));
+            } else {
+                self.waiting = true;
+            }
+        }
+        let logs = self.log_sizes();
+        for (name, size) in &logs {
+            if self.logs.get(name) != Some(size) && *size > 0 {
+                events.push(("log", name.clone()));
+            }
+        }
+        self.logs = logs;
+        events
+    }
+}
+
+// Watch the case root (and processor0) for changes; None where the platform
+// or filesystem gives no events
+fn start_notify(root: &Path) -> Option<Events> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).ok()?;
+    watcher.watch(root, RecursiveMode::NonRecursive).ok()?;
+    let processor0 = root.join("processor0");
+    if processor0.is_dir() {
+        watcher.watch(&processor0, RecursiveMode::NonRecursive).ok()?;
+    }
+    Some((watcher, rx))
+}
+
+type Events = (RecommendedWatcher, mpsc::Receiver<notify::Result<notify::Event>>);
+
+fn run(mut scan: Scan, notify: Option<Events>, callback: Py<PyAny>, debounce: Duration, poll: Option<Duration>, stop: Arc<AtomicBool>) {
+    // Without events, rescan on a timer no faster than once a second
+    let poll = poll.or_else(|| notify.is_none().then(|| debounce.max(Duration::from_secs(1))));
+
+    // Set by the first event of a burst; the scan runs `debounce` later
+    let mut due: Option<Instant> = None;
+    let mut next_poll = Instant::now();
+    while !stop.load(Ordering::Relaxed) {
+        let now = Instant::now();
+        if let Some(interval) = poll {
+            if now >= next_poll {
+                due = Some(now);
+                next_poll = now + interval;
+            }
+        }
+        if due.is_some_and(|at| at <= now) {
+            due = None;
+            let events = scan.changes();
+            // Fields still being written are looked at again after a while,
+            // whether or not more events come
+            if scan.waiting {
+                due = Some(now + debounce);
+            }
+            if !events.is_empty() {
+                let delivered = shutdown::attach(|py| {
+                    for (kind, name) in events {
+                        if let Err(e) = callback.call1(py, (kind, name)) {
+                            e.write_unraisable(py, Some(callback.bind(py)));
+                        }
+                    }
+                });
+                if delivered.is_none() {
+                    break;
+                }
+            }
+            continue;
+        }
+
+        let wait = due.map_or(TICK, |at| at.saturating_duration_since(now).min(TICK));
+        match &notify {
+            Some((_, events)) => match events.recv_timeout(wait) {
+                Ok(_) => {
+                    due.get_or_insert(Instant::now() + debounce);
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            None => std::thread::sleep(wait),
+        }
+    }
+}
+
+/// A running watch_case(). Stops when stop() is called, when its `with`
+/// block ends, or when it is garbage collected.
+#[pyclass(module = "accelerator")]
+pub struct Watcher {
+    case_root: String,
+    stop: Arc<AtomicBool>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[pymethods]
+impl Watcher {
+    /// Stop watching and wait for the watcher thread to finish. Calling it
+    /// from the callback itself stops the watcher after the callback returns.
+    fn stop(&self, py: Python<'_>) {
+        self.stop.store(true, Ordering::Relaxed);
+        let thread = self.thread.lock().unwrap().take();
+        if let Some(thread) = thread {
+            if thread.thread().id() == std::thread::current().id() {
+                return;
+            }
+            py.detach(|| {
+                let _ = thread.join();
+            });
+        }
+    }
+
+    #[getter]
+    fn running(&self) -> bool {
+        !self.stop.load(Ordering::Relaxed) && self.thread.lock().unwrap().as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(&self, py: Python<'_>, _exc_type: Py<PyAny>, _exc: Py<PyAny>, _tb: Py<PyAny>) -> bool {
+        self.stop(py);
+        false
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Watcher({:?}, running={})", self.case_root, if self.running() { "True" } else { "False" })
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Watch a case while a solver runs and call `callback(kind, name)` from a
+/// Rust thread as output appears: `("time", "0.5")` once a new time
+/// directory (in the case root or processor0) holds only fully written
+/// fields, and `("log", "log.foamRun")` when a log file in the case root
+/// grows. Events within `debounce` seconds of the first one are handled
+/// together. Time directories already present are not reported. The case
+/// root is watched through the OS's file events; on filesystems that send
+/// none for remote writes, such as NFS, pass `poll` to rescan every `poll`
+/// seconds instead. Exceptions raised by the callback are reported through
+/// sys.unraisablehook and do not stop the watcher. Returns a Watcher, also
+/// usable as a context manager.
+#[pyfunction]
+#[pyo3(signature = (case_root, callback, debounce = 0.5, poll = None))]
+pub fn watch_case(py: Python<'_>, case_root: String, callback: Py<PyAny>, debounce: f64, poll: Option<f64>) -> PyResult<Watcher> {
+    if !callback.bind(py).is_callable() {
+        return Err(PyValueError::new_err("callback must be callable"));
+    }
+    let seconds = |name: &str, s: f64| {
+        Duration::try_from_secs_f64(s).map_err(|_| PyValueError::new_err(format!("{} must be a non-negative number of seconds", name)))
+    };
+    let debounce = seconds("debounce", debounce)?;
+    let poll = poll.map(|p| seconds("poll", p)).transpose()?.map(|p| p.max(TICK));
+    if !Path::new(&case_root).is_dir() {
+        return Err(PyValueError::new_err(format!("{}: not a directory", case_root)));
+    }
+    shutdown::drain_at_exit(py)?;
+
+    // Events are on before the first scan so nothing written in between is missed
+    let (notify, scan) = py.detach(|| {
+        let root = PathBuf::from(&case_root);
+        let notify = if poll.is_none() { start_notify(&root) } else { None };
+        (notify, Scan::new(root))
+    });
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let flag = stop.clone();
+    let thread = std::thread::Builder::new()
+        .name("accelerator-watch".to_string())
+        .spawn(move || run(scan, notify, callback, debounce, poll, flag))
+        .map_err(|e| PyValueError::new_err(format!("cannot start the watcher thread: {}", e)))?;
+    Ok(Watcher { case_root, stop, thread: Mutex::new(Some(thread)) })
+}
@@ -0,0 +1,295 @@
+// cellZones, faceZones and pointZones of a mesh: named subsets of its cells,
+// faces or points, such as the rotating zone of an impeller, kept in
+// constant/polyMesh next to the mesh itself. topoSet's cellSets, faceSets and
+// pointSets are plain label lists in constant/polyMesh/sets.
+
+use numpy::{IntoPyArray, PyArray1};
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::path::{Path, PathBuf};
+
+use std::collections::BTreeMap;
+
+use crate::header::{header_end, parse_header};
+use crate::list::{label_list_at, list_body, read_label_list, reserve_bounded, skip_ws_comments, ListBody, ListFormat};
+use crate::mesh::mesh_dir;
+use crate::options::{self, Options};
+use crate::source::open_field_file;
+use crate::time::classes_ending;
+use crate::for_each_number;
+
+pub struct Zone {
+    pub name: String,
+    pub labels: Vec<i64>,
+    // faceZones only: whether each face is flipped relative to the zone
+    pub flip_map: Option<Vec<bool>>,
+}
+
+// The word (keyword, zone name, `List<label>`) at `pos`
+fn word_at(data: &[u8], pos: usize) -> &[u8] {
+    let len = data[pos..].iter().take_while(|b| !b.is_ascii_whitespace() && !b"{}();".contains(b)).count();
+    &data[pos..pos + len]
+}
+
+// A List<bool> at `pos`: 0/1 in ASCII, one byte per entry in binary
+fn bool_list_at(data: &[u8], pos: usize, fmt: ListFormat) -> Option<(Vec<bool>, usize)> {
+    let (body, next) = list_body(data, pos, fmt, 1)?;
+    let flips = match body {
+        ListBody::Items(_, bytes) if fmt.binary => bytes.iter().map(|b| *b != 0).collect(),
+        ListBody::Items(count, bytes) => {
+            let mut flips = Vec::with_capacity(reserve_bounded(count, bytes.len()));
+            for_each_number(bytes, |v| flips.push(v != 0.0));
+            if flips.len() != count {
+                return None;
+            }
+            flips
+        }
+        ListBody::Uniform(count, value) => vec![std::str::from_utf8(value).ok()?.trim() != "0"; count],
+    };
+    Some((flips, next))
+}
+
+// Zones of a zone file, `N ( name { type ...; <labels_key> List<label> n(...);
+// flipMap List<bool> n(...); } ... )`, in ASCII or binary
+pub fn parse_zones(data: &[u8], labels_key: &str) -> Option<Vec<Zone>> {
+    let fmt = ListFormat::of(data);
+    let mut pos = skip_ws_comments(data, header_end(data));
+    pos = skip_ws_comments(data, pos + word_at(data, pos).len());
+    if data.get(pos) != Some(&b'(') {
+        return None;
+    }
+    pos += 1;
+
+    let mut zones = Vec::new();
+    loop {
+        pos = skip_ws_comments(data, pos);
+        if *data.get(pos)? == b')' {
+            break;
+        }
+        let name = String::from_utf8_lossy(word_at(data, pos)).trim_matches('"').to_string();
+        pos = skip_ws_comments(data, pos + word_at(data, pos).len());
+        if data.get(pos) != Some(&b'{') {
+            return None;
+        }
+        pos += 1;
+
+        let mut zone = Zone { name, labels: Vec::new(), flip_map: None };
+        loop {
+            pos = skip_ws_comments(data, pos);
+            if *data.get(pos)? == b'}' {
+                pos += 1;
+                break;
+            }
+            let key = word_at(data, pos);
+            if key.is_empty() {
+                return None;
+            }
+            pos = skip_ws_comments(data, pos + key.len());
+            // The list type, if written, comes before the list itself
+            if data[pos..].starts_with(b"List<") {
+                pos = skip_ws_comments(data, pos + word_at(data, pos).len());
+            }
+            if key == labels_key.as_bytes() {
+                let (labels, next) = label_list_at(data, pos, fmt)?;
+                zone.labels = labels;
+                pos = next;
+            } else if key == b"flipMap" {
+                let (flips, next) = bool_list_at(data, pos, fmt)?;
+                zone.flip_map = Some(flips);
+                pos = next;
+            } else {
+                pos += data[pos..].iter().position(|b| *b == b';')?;
+            }
+            pos = skip_ws_comments(data, pos);
+            if data.get(pos) == Some(&b';') {
+                pos += 1;
+            }
+        }
+        zones.push(zone);
+    }
+    Some(zones)
+}
+
+// Zones of one kind ("cellZones", "faceZones" or "pointZones") in a mesh
+// directory; None if the file is missing
+pub fn load_zones(mesh_dir: &Path, kind: &str) -> PyResult<Option<Vec<Zone>>> {
+    let path = mesh_dir.join(kind);
+    let Some(data) = open_field_file(&path.to_string_lossy())? else {
+        return Ok(None);
+    };
+    let labels_key = format!("{}Labels", kind.trim_end_matches("Zones"));
+    parse_zones(&data, &labels_key)
+        .map(Some)
+        .ok_or_else(|| PyValueError::new_err(format!("{}: malformed or truncated zone list", path.display())))
+}
+
+// The mesh directory of the case a field file belongs to: <case>/<time>/<field>
+// uses constant/polyMesh and <case>/<time>/<region>/<field> the region's mesh
+pub fn field_mesh_dir(path: &Path) -> PathBuf {
+    let is_time = |d: &Path| d.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.parse::<f64>().is_ok());
+    let dir = path.parent().unwrap_or(Path::new("."));
+    match dir.parent() {
+        Some(time) if !is_time(dir) && is_time(time) => {
+            mesh_dir(time.parent().unwrap_or(Path::new(".")), dir.file_name().and_then(|n| n.to_str()))
+        }
+        Some(case_root) => mesh_dir(case_root, None),
+        None => mesh_dir(Path::new("."), None),
+    }
+}
+
+// A topoSet set in <mesh>/sets as (class, labels), the class being cellSet,
+// faceSet or pointSet; None if there is no such set
+pub fn load_set(mesh_dir: &Path, name: &str) -> PyResult<Option<(String, Vec<i64>)>> {
+    let path = mesh_dir.join("sets").join(name);
+    let Some(data) = open_field_file(&path.to_string_lossy())? else {
+        return Ok(None);
+    };
+    let class = parse_header(&data).and_then(|h| h.get("class").map(str::to_string)).unwrap_or_default();
+    let labels = read_label_list(&data)
+        .ok_or_else(|| PyValueError::new_err(format!("{}: malformed or truncated set", path.display())))?;
+    Ok(Some((class, labels)))
+}
+
+// Cells picked by a cellZone or a cellSet of the mesh in `mesh_dir`; None if
+// neither is given
+pub fn selected_cells(mesh_dir: &Path, cell_zone: Option<&str>, cell_set: Option<&str>) -> PyResult<Option<Vec<usize>>> {
+    let labels = match (cell_zone, cell_set) {
+        (None, None) => return Ok(None),
+        (Some(_), Some(_)) => return Err(PyValueError::new_err("give a cell_zone or a cell_set, not both")),
+        (Some(name), None) => {
+            let zones = load_zones(mesh_dir, "cellZones")?.unwrap_or_default();
+            zones
+                .into_iter()
+                .find(|z| z.name == name)
+                .ok_or_else(|| PyKeyError::new_err(format!("no cellZone '{}' in {}", name, mesh_dir.display())))?
+                .labels
+        }
+        (None, Some(name)) => match load_set(mesh_dir, name)? {
+            Some((class, labels)) if class == "cellSet" => labels,
+            Some((class, _)) => return Err(PyValueError::new_err(format!("'{}' is a {}, not a cellSet", name, class))),
+            None => return Err(PyKeyError::new_err(format!("no cellSet '{}' in {}", name, mesh_dir.join("sets").display()))),
+        },
+    };
+    Ok(Some(labels.into_iter().map(|l| l as usize).collect()))
+}
+
+// The values of a zone's (or set's) cells, `width` components each. A single tuple is a
+// uniform field and applies to every cell.
+pub fn zone_values(values: &[f64], width: usize, cells: &[usize]) -> Result<Vec<f64>, String> {
+    if values.len() == width {
+        return Ok(values.repeat(cells.len()));
+    }
+    let n = values.len() / width;
+    let mut out = Vec::with_capacity(cells.len() * width);
+    for &c in cells {
+        if c >= n {
+            return Err(format!("cell label {} is out of range for {} values", c, n));
+        }
+        out.extend_from_slice(&values[c * width..(c + 1) * width]);
+    }
+    Ok(out)
+}
+
+fn read_zones(py: Python<'_>, case_root: &str, region: Option<&str>, kind: &str, options: &Option<Options>) -> PyResult<Option<Vec<Zone>>> {
+    options::detach(py, options, || load_zones(&mesh_dir(Path::new(case_root), region), kind))
+}
+
+fn labels_dict<'py>(py: Python<'py>, zones: Option<Vec<Zone>>) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let Some(zones) = zones else {
+        return Ok(None);
+    };
+    let out = PyDict::new(py);
+    for zone in zones {
+        out.set_item(zone.name, zone.labels.into_pyarray(py))?;
+    }
+    Ok(Some(out))
+}
+
+/// Read constant/polyMesh/cellZones as a dict of zone name to an int64
+/// array of its cells. ASCII, binary and gzip-compressed files are
+/// supported. Returns None if there is no cellZones file. With `region`,
+/// the mesh in constant/<region>/polyMesh is read.
+#[pyfunction]
+#[pyo3(signature = (case_root, region = None, options = None))]
+pub fn read_cell_zones<'py>(
+    py: Python<'py>,
+    case_root: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let zones = read_zones(py, &case_root, region.as_deref(), "cellZones", &options)?;
+    labels_dict(py, zones)
+}
+
+/// Read constant/polyMesh/pointZones as a dict of zone name to an int64
+/// array of its points. Returns None if there is no pointZones file. With
+/// `region`, the mesh in constant/<region>/polyMesh is read.
+#[pyfunction]
+#[pyo3(signature = (case_root, region = None, options = None))]
+pub fn read_point_zones<'py>(
+    py: Python<'py>,
+    case_root: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let zones = read_zones(py, &case_root, region.as_deref(), "pointZones", &options)?;
+    labels_dict(py, zones)
+}
+
+// (faces, flip_map) as handed back to Python
+type FaceZone<'py> = (Bound<'py, PyArray1<i64>>, Bound<'py, PyArray1<bool>>);
+
+/// Read constant/polyMesh/faceZones as a dict of zone name to (faces,
+/// flip_map): an int64 array of the zone's faces and a bool array that is
+/// True where a face points against the zone's orientation. Returns None if
+/// there is no faceZones file. With `region`, the mesh in
+/// constant/<region>/polyMesh is read.
+#[pyfunction]
+#[pyo3(signature = (case_root, region = None, options = None))]
+pub fn read_face_zones<'py>(
+    py: Python<'py>,
+    case_root: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let Some(zones) = read_zones(py, &case_root, region.as_deref(), "faceZones", &options)? else {
+        return Ok(None);
+    };
+    let out = PyDict::new(py);
+    for zone in zones {
+        let flips = zone.flip_map.unwrap_or_else(|| vec![false; zone.labels.len()]);
+        let entry: FaceZone = (zone.labels.into_pyarray(py), flips.into_pyarray(py));
+        out.set_item(zone.name, entry)?;
+    }
+    Ok(Some(out))
+}
+
+/// topoSet sets of a case (constant/polyMesh/sets) as a dict of name to
+/// class: cellSet, faceSet or pointSet. Empty if there are none. With
+/// `region`, the sets of constant/<region>/polyMesh are listed.
+#[pyfunction]
+#[pyo3(signature = (case_root, region = None, options = None))]
+pub fn list_sets(py: Python, case_root: String, region: Option<String>, options: Option<Options>) -> PyResult<BTreeMap<String, String>> {
+    options::detach(py, &options, || {
+        let dir = mesh_dir(Path::new(&case_root), region.as_deref()).join("sets");
+        Ok(classes_ending(&dir, "Set")?.into_iter().collect())
+    })
+}
+
+/// Labels of the topoSet set `name` (cells, faces or points, as its class in
+/// list_sets() says) as an int64 array. ASCII, binary and gzip-compressed
+/// sets are supported. Returns None if there is no such set. With `region`,
+/// the sets of constant/<region>/polyMesh are read.
+#[pyfunction]
+#[pyo3(signature = (case_root, name, region = None, options = None))]
+pub fn read_set<'py>(
+    py: Python<'py>,
+    case_root: String,
+    name: String,
+    region: Option<String>,
+    options: Option<Options>,
+) -> PyResult<Option<Bound<'py, PyArray1<i64>>>> {
+    let set = options::detach(py, &options, || load_set(&mesh_dir(Path::new(&case_root), region.as_deref()), &name))?;
+    Ok(set.map(|(_, labels)| labels.into_pyarray(py)))
+}
@@ -1352,6 +1352,24 @@ def get_available_fields(case_dir: str) -> List[str]:
         return []
 
     latest_time = time_dirs[-1]
+
+    # ⚡ Bolt Optimization: Let the Rust accelerator pick out the field files
+    # by their FoamFile headers
+    if RUST_ACCELERATOR:
+        try:
+            fields = list(accelerator.list_fields(str(case_dir), latest_time))
+            if not fields:
+                # Multi-region cases keep their fields in <time>/<region>
+                for region in accelerator.list_regions(str(case_dir)):
+                    names = accelerator.list_fields(
+                        str(case_dir), latest_time, region=region
+                    )
+                    fields.extend(f"{region}/{name}" for name in names)
+            return fields
+        except Exception:
+            # Fallback to listing every file
+            pass
+
     time_path = Path(case_dir) / latest_time
 
     # ⚡ Bolt Optimization: Use cached scanning
@@ -0,0 +1,86 @@
+"""asyncio variants of the Rust accelerator's readers: parses run on Rust
+threads and are awaited on the event loop."""
+
+import asyncio
+import subprocess
+import sys
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+FIELD = """FoamFile
+{
+    version     2.0;
+    format      ascii;
+    class       volScalarField;
+    object      %s;
+}
+internalField nonuniform List<scalar> 3(%d %d %d);
+"""
+
+
+def test_gather_many_parses(tmp_path):
+    paths = []
+    for i in range(20):
+        path = tmp_path / ("T%d" % i)
+        path.write_text(FIELD % (path.name, i, i, i))
+        paths.append(str(path))
+
+    async def parse_all():
+        return await asyncio.gather(*(accelerator.parse_scalar_field_async(p) for p in paths))
+
+    assert asyncio.run(parse_all()) == [pytest.approx(i) for i in range(20)]
+
+
+def test_errors_are_raised_on_await(tmp_path):
+    async def parse_missing():
+        return await accelerator.parse_scalar_field_async(str(tmp_path / "missing"))
+
+    with pytest.raises(accelerator.FieldNotFound):
+        asyncio.run(parse_missing())
+
+
+def test_needs_a_running_loop(tmp_path):
+    with pytest.raises(RuntimeError):
+        accelerator.parse_scalar_field_async(str(tmp_path / "T"))
+
+
+def test_cancelling_the_task_cancels_the_token(tmp_path):
+    path = tmp_path / "T"
+    path.write_text(FIELD % ("T", 1, 2, 3))
+    token = accelerator.CancelToken()
+
+    async def cancel_parse():
+        future = accelerator.parse_scalar_field_async(str(path), options=accelerator.Options(cancel=token))
+        future.cancel()
+        await asyncio.sleep(0)
+
+    asyncio.run(cancel_parse())
+    assert token.cancelled
+
+
+EXIT_MIDWAY = """
+import asyncio, atexit, sys
+import accelerator
+
+async def start_reads():
+    options = accelerator.Options(cache=False)
+    for _ in range(200):
+        accelerator.parse_scalar_field_async(sys.argv[1], options=options)
+
+handlers = atexit._ncallbacks()
+asyncio.run(start_reads())
+print(atexit._ncallbacks() - handlers)
+"""
+
+
+def test_exit_with_reads_under_way(tmp_path):
+    # One exit handler waits for the reads holding the GIL and drops the rest,
+    # so the interpreter never finalizes under a worker
+    path = tmp_path / "T"
+    path.write_text(FIELD.replace("3(%d %d %d)", "%s") % ("T", "200000(" + " 1.5" * 200000 + ")"))
+
+    done = subprocess.run([sys.executable, "-c", EXIT_MIDWAY, str(path)], capture_output=True, text=True, timeout=60)
+    assert done.returncode == 0, done.stderr
+    assert done.stdout == "1\n"
@@ -0,0 +1,47 @@
+"""set_boundary_condition in the Rust accelerator rewrites one patch and
+leaves every other byte of the field file alone."""
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+HEADER = b"FoamFile\n{\n    version     2.0;\n    format      %s;\n    class       volScalarField;\n    object      T;\n}\n"
+
+# Latin-1 degree signs in the comments aren't valid UTF-8
+BODY = (
+    b"// inlet at 20 \xb0C\n"
+    b"dimensions      [0 0 0 1 0 0 0];\n\n"
+    b"internalField   nonuniform List<scalar> 3(293.15 293.5 294);\n\n"
+    b"boundaryField\n{\n"
+    b"    inlet\n    {\n        type            fixedValue;\n        value           uniform 293.15; // 20 \xb0C\n    }\n"
+    b"    outlet\n    {\n        type            zeroGradient;\n    }\n"
+    b"    walls\n    {\n        type            fixedValue;\n        value           uniform 300;\n    }\n"
+    b"}\n"
+)
+OUTLET = b"    outlet\n    {\n        type            zeroGradient;\n    }\n"
+
+
+def test_only_the_patch_changes(tmp_path):
+    path = tmp_path / "0" / "T"
+    path.parent.mkdir()
+    original = HEADER % b"ascii" + BODY
+    path.write_bytes(original)
+
+    accelerator.set_boundary_condition(str(path), "outlet", {"type": "inletOutlet", "inletValue": 293.15})
+
+    data = path.read_bytes()
+    before, after = original.split(OUTLET)
+    assert data.startswith(before + b"    outlet\n    {\n") and data.endswith(after)
+    assert b"inletOutlet" in data[len(before) : len(data) - len(after)]
+    patches = accelerator.read_boundary_conditions(str(tmp_path), "0")["T"]
+    assert patches["outlet"] == {"type": "inletOutlet", "inletValue": 293.15}
+
+
+def test_binary_file_rejected(tmp_path):
+    path = tmp_path / "T"
+    original = HEADER % b"binary" + BODY
+    path.write_bytes(original)
+
+    with pytest.raises(accelerator.UnsupportedFormat):
+        accelerator.set_boundary_condition(str(path), "outlet", {"type": "inletOutlet", "inletValue": 293.15})
+    assert path.read_bytes() == original
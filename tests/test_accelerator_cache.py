@@ -0,0 +1,60 @@
+"""Result cache of the Rust accelerator: repeated reads of an unchanged field
+are served from memory, rewrites are picked up."""
+
+import os
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+FIELD = """FoamFile
+{
+    version     2.0;
+    format      ascii;
+    class       volScalarField;
+    object      p;
+}
+dimensions [0 2 -2 0 0 0 0];
+internalField nonuniform List<scalar> 4(%s);
+"""
+
+
+@pytest.fixture(autouse=True)
+def empty_cache():
+    accelerator.cache_clear()
+    yield
+    accelerator.cache_clear()
+
+
+def write_field(path, values):
+    path.write_text(FIELD % " ".join(str(v) for v in values))
+    return str(path)
+
+
+def test_unchanged_file_is_read_once(tmp_path):
+    path = write_field(tmp_path / "p", [1, 2, 3, 4])
+    assert accelerator.parse_scalar_field(path) == pytest.approx(2.5)
+    assert accelerator.parse_scalar_field(path) == pytest.approx(2.5)
+    info = accelerator.cache_info()
+    assert (info["hits"], info["misses"], info["entries"]) == (1, 1, 1)
+
+
+def test_rewrite_invalidates(tmp_path):
+    path = write_field(tmp_path / "p", [1, 2, 3, 4])
+    stat = os.stat(path)
+    assert accelerator.parse_scalar_field(path) == pytest.approx(2.5)
+
+    # Same size, later mtime: a solver overwriting the field in place
+    write_field(tmp_path / "p", [5, 6, 7, 8])
+    os.utime(path, ns=(stat.st_atime_ns, stat.st_mtime_ns + 1_000_000_000))
+    assert accelerator.parse_scalar_field(path) == pytest.approx(6.5)
+
+
+def test_bypass_and_clear(tmp_path):
+    path = write_field(tmp_path / "p", [1, 2, 3, 4])
+    accelerator.parse_scalar_field(path)
+    accelerator.parse_scalar_field(path, options=accelerator.Options(cache=False))
+    assert accelerator.cache_info()["hits"] == 0
+
+    accelerator.cache_clear()
+    assert accelerator.cache_info()["entries"] == 0
@@ -0,0 +1,27 @@
+"""Colormaps applied in the Rust accelerator."""
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+np = pytest.importorskip("numpy")
+
+
+def test_apply_colormap_rgba():
+    rgba = accelerator.apply_colormap(np.array([0.0, 0.5, 1.0, np.nan]), "viridis")
+
+    assert len(rgba) == 16
+    assert rgba[:4] == bytes([68, 1, 84, 255])
+    assert rgba[8:12] == bytes([253, 231, 37, 255])
+    assert rgba[12:] == bytes([128, 128, 128, 255])
+
+
+def test_apply_colormap_clamps_and_reverses():
+    rgba = accelerator.apply_colormap(np.array([-5.0, 5.0]), "viridis_r", vmin=0.0, vmax=1.0)
+
+    assert rgba[:4] == bytes([253, 231, 37, 255])
+    assert rgba[4:] == bytes([68, 1, 84, 255])
+
+
+def test_apply_colormap_unknown():
+    with pytest.raises(ValueError):
+        accelerator.apply_colormap(np.zeros(3), "nope")
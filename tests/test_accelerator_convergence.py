@@ -0,0 +1,76 @@
+"""check_convergence(): convergence criteria judged on a case's log and
+function object output."""
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+STEP = (
+    "Time = {t}\n\n"
+    "smoothSolver:  Solving for Ux, Initial residual = {r}, Final residual = 1e-09, No Iterations 2\n"
+    "GAMG:  Solving for p, Initial residual = {p}, Final residual = 1e-09, No Iterations 5\n"
+    "time step continuity errors : sum local = {c}, global = 0, cumulative = 0\n\n"
+)
+
+
+@pytest.fixture
+def steady_case(tmp_path):
+    (tmp_path / "log.simpleFoam").write_text(
+        "".join(STEP.format(t=t, r=10 ** (-t / 20), p=10 ** (-t / 40), c=1e-3 * 10 ** (-t / 20)) for t in range(1, 201))
+    )
+    coeffs = tmp_path / "postProcessing" / "forceCoeffs1" / "0"
+    coeffs.mkdir(parents=True)
+    rows = "".join("%d %g %g\n" % (t, 0.5 + 0.1 * 10 ** (-t / 20), 0.01 * (-1) ** t) for t in range(1, 201))
+    (coeffs / "coefficient.dat").write_text("# Time Cd Cl\n" + rows)
+    return tmp_path
+
+
+def test_converged_residuals_continuity_and_plateau(steady_case):
+    criteria = accelerator.ConvergenceCriteria(
+        residuals={"U": 1e-6, "p": 1e-4},
+        steps=20,
+        continuity=1e-9,
+        plateaus=[accelerator.Plateau("forceCoeffs1", "Cd", 1e-4, window=50)],
+    )
+
+    verdict = accelerator.check_convergence(str(steady_case), criteria)
+
+    assert verdict.converged
+    assert verdict.time == 200
+    assert verdict.log.endswith("log.simpleFoam")
+    assert [(c.criterion, c.name) for c in verdict.checks] == [
+        ("residual", "Ux"),
+        ("residual", "p"),
+        ("continuity", "sum_local"),
+        ("plateau", "forceCoeffs1/Cd"),
+    ]
+    assert all(c.samples >= c.required for c in verdict.checks)
+
+
+def test_not_converged_reports_evidence(steady_case):
+    criteria = accelerator.ConvergenceCriteria(
+        residuals={"p": 1e-6, "k": 1e-3},
+        plateaus=[accelerator.Plateau("forceCoeffs1", "Cl", 1e-3, window=50, relative=False)],
+    )
+
+    verdict = accelerator.check_convergence(str(steady_case), criteria)
+
+    assert not verdict.converged
+    checks = {c.name: c for c in verdict.checks}
+    assert not checks["p"].passed and checks["p"].value == pytest.approx(1e-5)
+    assert not checks["k"].passed and "not solved" in checks["k"].detail
+    assert not checks["forceCoeffs1/Cl"].passed and checks["forceCoeffs1/Cl"].value == pytest.approx(0.02)
+
+
+def test_missing_log(tmp_path):
+    verdict = accelerator.check_convergence(str(tmp_path), accelerator.ConvergenceCriteria(residual=1e-3))
+
+    assert not verdict.converged
+    assert [c.criterion for c in verdict.checks] == ["log"]
+
+
+def test_criteria_need_something_to_check():
+    with pytest.raises(ValueError):
+        accelerator.ConvergenceCriteria()
+    with pytest.raises(ValueError):
+        accelerator.ConvergenceCriteria(residual=-1.0)
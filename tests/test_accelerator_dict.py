@@ -0,0 +1,76 @@
+"""Dictionary edits in the Rust accelerator: set_dict_entry and write_dict
+change only the entries they are given."""
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+CONTROL_DICT = """FoamFile
+{
+    version     2.0;
+    format      ascii;
+    class       dictionary;
+    object      controlDict;
+}
+// * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * * //
+
+#include "initialConditions"
+
+application     simpleFoam;  // steady-state solver
+endTime         100;
+/* written every step while debugging */
+writeControl    timeStep;
+
+solvers
+{
+    p { tolerance 1e-6; relTol 0.1; } // pressure
+}
+"""
+
+
+@pytest.fixture
+def control_dict(tmp_path):
+    path = tmp_path / "controlDict"
+    path.write_text(CONTROL_DICT)
+    (tmp_path / "initialConditions").write_text("startTime 0;\n")
+    return path
+
+
+def test_set_dict_entry_changes_only_the_value(control_dict):
+    accelerator.set_dict_entry(str(control_dict), "endTime", 500)
+    accelerator.set_dict_entry(str(control_dict), "solvers.p.tolerance", 1e-7)
+
+    expected = CONTROL_DICT.replace("endTime         100;", "endTime         500;").replace("1e-6", "1e-7")
+    assert control_dict.read_text() == expected
+
+
+def test_set_dict_entry_adds_missing_entries(control_dict):
+    accelerator.set_dict_entry(str(control_dict), ["solvers", "U", "tolerance"], 1e-5)
+
+    text = control_dict.read_text()
+    # Everything before the new block is untouched, trailing comment included
+    assert text.startswith(CONTROL_DICT[: CONTROL_DICT.index("// pressure") + len("// pressure")])
+    parsed = accelerator.parse_dict(str(control_dict))
+    assert parsed["solvers"] == {"p": {"tolerance": 1e-6, "relTol": 0.1}, "U": {"tolerance": 1e-5}}
+
+
+def test_write_dict_merges_and_keeps_the_rest(control_dict):
+    accelerator.write_dict(str(control_dict), {"writeInterval": 50, "solvers": {"p": {"relTol": 0}}})
+
+    text = control_dict.read_text()
+    assert text.startswith(CONTROL_DICT.split("solvers")[0])
+    assert '#include "initialConditions"' in text
+    assert "p { tolerance 1e-6; relTol 0; } // pressure" in text
+    parsed = accelerator.parse_dict(str(control_dict))
+    assert (parsed["startTime"], parsed["endTime"], parsed["writeInterval"]) == (0, 100, 50)
+
+
+def test_write_dict_new_file_has_header(tmp_path):
+    path = tmp_path / "fvSolution"
+    accelerator.write_dict(str(path), {"solvers": {"p": {"solver": "PCG"}}, "nOuterCorrectors": 2})
+
+    assert path.read_text().startswith("FoamFile\n{\n")
+    parsed = accelerator.parse_dict(str(path))
+    assert parsed["FoamFile"]["class"] == "dictionary" and parsed["FoamFile"]["object"] == "fvSolution"
+    assert parsed["solvers"] == {"p": {"solver": "PCG"}}
+    assert parsed["nOuterCorrectors"] == 2
@@ -0,0 +1,59 @@
+"""LTTB downsampling of long histories in the Rust accelerator."""
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+STEP = "Time = %g\n\nsmoothSolver:  Solving for Ux, Initial residual = %g, Final residual = 1e-06, No Iterations 2\n"
+
+
+def test_parse_solver_log_max_points(tmp_path):
+    log = tmp_path / "log.simpleFoam"
+    log.write_text("".join(STEP % (t, 10.0 if t == 1234 else 10 ** (-t / 1000)) for t in range(1, 5001)))
+
+    steps = accelerator.parse_solver_log(str(log), max_points=100)
+
+    # The ends and the spike survive
+    assert len(steps) == 100
+    times = [s.time for s in steps]
+    assert times[0] == 1 and times[-1] == 5000 and 1234 in times
+    assert times == sorted(times)
+    assert len(accelerator.parse_solver_log(str(log), max_points=10000)) == 5000
+
+
+def test_parse_solver_log_max_points_too_few(tmp_path):
+    log = tmp_path / "log.simpleFoam"
+    log.write_text(STEP % (1, 0.5))
+
+    with pytest.raises(ValueError):
+        accelerator.parse_solver_log(str(log), max_points=2)
+
+
+def test_downsample_lttb():
+    np = pytest.importorskip("numpy")
+    x = np.arange(100000, dtype=float)
+    y = np.sin(x / 5000)
+    y[31337] = 50.0
+
+    xs, ys = accelerator.downsample_lttb(x, y, 1000)
+
+    assert len(xs) == len(ys) == 1000
+    assert xs[0] == 0 and xs[-1] == 99999 and 31337 in xs
+    assert np.all(np.diff(xs) > 0)
+
+
+def test_field_time_series_max_points(tmp_path):
+    pytest.importorskip("numpy")
+    field = "FoamFile { format ascii; class volVectorField; object U; }\ninternalField uniform (%g 0 %g);\n"
+    for t in range(1, 51):
+        (tmp_path / str(t)).mkdir()
+        (tmp_path / str(t) / "U").write_text(field % (t, 100.0 if t == 17 else 0.0))
+
+    times, values = accelerator.field_time_series(str(tmp_path), "U", max_points=10)
+
+    # Rows stay whole, and the spike in the z component survives
+    assert times.shape == (10,) and values.shape == (10, 3)
+    assert times[0] == 1 and times[-1] == 50 and 17 in times.tolist()
+    assert (values[:, 0] == times).all()
+    with pytest.raises(ValueError):
+        accelerator.field_time_series(str(tmp_path), "U", max_points=2)
@@ -0,0 +1,60 @@
+"""Exceptions raised by the Rust accelerator's field readers, and the lenient
+mode that keeps the old None/zeros results."""
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+HEADER = """FoamFile
+{
+    version     2.0;
+    format      %s;
+    class       volVectorField;
+    object      U;
+}
+"""
+
+
+def test_exception_hierarchy():
+    assert issubclass(accelerator.FieldNotFound, FileNotFoundError)
+    assert issubclass(accelerator.FieldParseError, ValueError)
+    assert issubclass(accelerator.UnsupportedFormat, accelerator.FieldParseError)
+    assert issubclass(accelerator.FieldCountError, accelerator.FieldParseError)
+
+
+def test_missing_file_raises_field_not_found(tmp_path):
+    path = str(tmp_path / "U")
+    with pytest.raises(accelerator.FieldNotFound):
+        accelerator.parse_vector_field(path)
+    with pytest.raises(FileNotFoundError):
+        accelerator.parse_scalar_field(path)
+    assert accelerator.parse_vector_field(path, lenient=True) == (0.0, 0.0, 0.0)
+
+
+def test_unreadable_file_raises_field_parse_error(tmp_path):
+    empty = tmp_path / "U"
+    empty.write_text("")
+    with pytest.raises(accelerator.FieldParseError, match="empty"):
+        accelerator.parse_vector_field(str(empty))
+
+    broken = tmp_path / "V"
+    broken.write_text(HEADER % "ascii" + "internalField nonuniform List<vector> 2((1 0 0)")
+    with pytest.raises(ValueError):
+        accelerator.parse_vector_field(str(broken))
+    assert accelerator.parse_vector_field(str(empty), lenient=True) == (0.0, 0.0, 0.0)
+
+
+def test_binary_file_raises_unsupported_format(tmp_path):
+    path = tmp_path / "U"
+    path.write_bytes((HEADER % "binary").encode() + b"internalField nonuniform List<vector> 1(" + bytes(24) + b");\n")
+    with pytest.raises(accelerator.UnsupportedFormat) as error:
+        accelerator.parse_vector_field(str(path))
+    assert isinstance(error.value, accelerator.FieldParseError) and isinstance(error.value, ValueError)
+
+
+def test_tensor_file_read_as_vector(tmp_path):
+    path = tmp_path / "U"
+    path.write_text(HEADER % "ascii" + "internalField nonuniform List<tensor> 1((1 0 0 0 1 0 0 0 1));\n")
+    with pytest.raises(accelerator.FieldParseError, match="vector"):
+        accelerator.parse_vector_field(str(path))
+    assert accelerator.parse_vector_field(str(path), lenient=True) == (0.0, 0.0, 0.0)
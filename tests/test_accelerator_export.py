@@ -0,0 +1,201 @@
+"""Exports of a time step from the Rust accelerator for ParaView."""
+
+import base64
+import json
+import struct
+import zlib
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+HEADER = "FoamFile\n{\n    format ascii;\n    class %s;\n    object %s;\n}\n"
+
+# A single unit hexahedron, all six faces on one patch
+POINTS = "8((0 0 0) (1 0 0) (1 1 0) (0 1 0) (0 0 1) (1 0 1) (1 1 1) (0 1 1))"
+FACES = "6(4(0 3 2 1) 4(4 5 6 7) 4(0 1 5 4) 4(3 7 6 2) 4(0 4 7 3) 4(1 2 6 5))"
+BOUNDARY = "1(walls { type wall; nFaces 6; startFace 0; })"
+
+
+@pytest.fixture
+def hex_case(tmp_path):
+    mesh = tmp_path / "constant" / "polyMesh"
+    mesh.mkdir(parents=True)
+    (mesh / "points").write_text(HEADER % ("vectorField", "points") + POINTS)
+    (mesh / "faces").write_text(HEADER % ("faceList", "faces") + FACES)
+    (mesh / "owner").write_text(HEADER % ("labelList", "owner") + "6(0 0 0 0 0 0)")
+    (mesh / "neighbour").write_text(HEADER % ("labelList", "neighbour") + "0()")
+    (mesh / "boundary").write_text(HEADER % ("polyBoundaryMesh", "boundary") + BOUNDARY)
+    (tmp_path / "0").mkdir()
+    for name, cls, value in [("T", "volScalarField", "300"), ("U", "volVectorField", "(1 0 0)")]:
+        (tmp_path / "0" / name).write_text(
+            HEADER % (cls, name) + "dimensions [0 0 0 0 0 0 0];\ninternalField uniform %s;\n"
+            "boundaryField\n{\n    walls { type zeroGradient; }\n}\n" % value
+        )
+    return tmp_path
+
+
+def test_export_vtk_legacy(hex_case):
+    out = hex_case / "snapshot.vtk"
+    result = accelerator.export_vtk(str(hex_case), "0", ["T", "U"], str(out), binary=False)
+
+    assert (result["points"], result["cells"]) == (8, 1)
+    text = out.read_text()
+    assert "DATASET UNSTRUCTURED_GRID" in text
+    assert "CELL_TYPES 1\n12\n" in text
+    assert "T 1 1 float\n300" in text
+    assert "U 3 1 float\n1 0 0" in text
+
+
+def test_export_vtk_all_fields_binary(hex_case):
+    out = hex_case / "snapshot.vtk"
+    accelerator.export_vtk(str(hex_case), "0", None, str(out))
+
+    data = out.read_bytes()
+    assert b"BINARY" in data and b"FIELD attributes 3" in data
+
+
+def test_export_vtu_base64_uncompressed(hex_case):
+    out = hex_case / "snapshot.vtu"
+    accelerator.export_vtu(str(hex_case), "0", ["T"], str(out), encoding="base64", compress=False)
+
+    text = out.read_text()
+    assert 'type="UnstructuredGrid"' in text and "compressor" not in text
+    array = text.split('Name="T" NumberOfComponents="1" format="binary">')[1].split("<")[0]
+    size, value = struct.unpack("<Qf", base64.b64decode(array))
+    assert (size, value) == (4, 300.0)
+
+
+def test_export_vtm_blocks(hex_case):
+    out = hex_case / "snapshot.vtm"
+    result = accelerator.export_vtu(str(hex_case), "0", ["T", "U"], str(out))
+
+    assert result["files"][-1] == str(out)
+    assert (hex_case / "snapshot" / "internal.vtu").is_file()
+    patch = hex_case / "snapshot" / "boundary" / "walls.vtp"
+    assert 'file="snapshot/boundary/walls.vtp"' in out.read_text()
+    data = patch.read_bytes()
+    assert b'NumberOfPoints="8"' in data and b'NumberOfPolys="6"' in data
+    # T on the six faces: one zlib block of six floats in the appended data
+    offset = int(data.split(b'Name="T" NumberOfComponents="1" format="appended" offset="')[1].split(b'"')[0])
+    raw = data.split(b'<AppendedData encoding="raw">\n_', 1)[1][offset:]
+    blocks, _, last, size = struct.unpack("<4Q", raw[:32])
+    assert (blocks, last) == (1, 24)
+    assert struct.unpack("<6f", zlib.decompress(raw[32 : 32 + size])) == (300.0,) * 6
+
+
+def test_export_vtu_rejects_patches_without_vtm(hex_case):
+    with pytest.raises(ValueError):
+        accelerator.export_vtu(str(hex_case), "0", ["T"], str(hex_case / "a.vtu"), patches=["walls"])
+
+
+def test_export_gltf_vertex_colours(tmp_path):
+    pytest.importorskip("numpy")
+    surface = {"positions": [0, 0, 0, 1, 0, 0, 0, 1, 0], "indices": [0, 1, 2], "values": [0.0, 5.0, 10.0]}
+    out = tmp_path / "frame.glb"
+    result = accelerator.export_gltf(surface, "values", "viridis", str(out))
+
+    assert (result["vertices"], result["triangles"], result["range"]) == (3, 1, (0.0, 10.0))
+    data = out.read_bytes()
+    magic, version, length, json_length = struct.unpack("<4sIII", data[:16])
+    assert (magic, version, length) == (b"glTF", 2, len(data))
+    gltf = json.loads(data[20 : 20 + json_length])
+    primitive = gltf["meshes"][0]["primitives"][0]
+    assert set(primitive["attributes"]) == {"POSITION", "COLOR_0"}
+    assert gltf["meshes"][0]["extras"] == {"field": "values", "range": [0, 10]}
+    view = gltf["bufferViews"][gltf["accessors"][primitive["attributes"]["COLOR_0"]]["bufferView"]]
+    colours = data[28 + json_length + view["byteOffset"] :][:12]
+    # viridis runs from dark purple to yellow
+    assert colours[:4] == bytes([68, 1, 84, 255]) and colours[8:] == bytes([253, 231, 37, 255])
+
+
+def test_export_gltf_unknown_colormap(tmp_path):
+    with pytest.raises(ValueError):
+        accelerator.export_gltf({"positions": [], "indices": []}, None, "nope", str(tmp_path / "a.glb"))
+
+
+def test_decimate_surface_flat_grid():
+    pytest.importorskip("numpy")
+    n = 20
+    positions = [c for i in range(n + 1) for j in range(n + 1) for c in (i / n, j / n, 0.0)]
+    indices = []
+    for i in range(n):
+        for j in range(n):
+            a, b, c, d = i * (n + 1) + j, (i + 1) * (n + 1) + j, (i + 1) * (n + 1) + j + 1, i * (n + 1) + j + 1
+            indices += [a, b, c, a, c, d]
+    surface = {"positions": positions, "indices": indices, "values": [p for p in positions[0::3]]}
+    reduced = accelerator.decimate_surface(surface, 200)
+
+    assert reduced["n_triangles"] <= 200
+    assert reduced["range"] == pytest.approx((0.0, 1.0))
+    # The square's outline survives, and the surface still faces +z
+    xy = set(zip(reduced["positions"][0::3].round(6), reduced["positions"][1::3].round(6)))
+    assert {(0, 0), (1, 0), (0, 1), (1, 1)} <= xy
+    assert (reduced["normals"][2::3] > 0.99).all()
+
+
+
+def test_export_ensight(hex_case):
+    out = hex_case / "ensight"
+    result = accelerator.export_ensight(str(hex_case), None, ["T", "U"], str(out))
+
+    assert (result["parts"], result["elements"], result["times"]) == (["internalMesh", "walls"], 1, 1)
+    case = (out / (hex_case.name + ".case")).read_text()
+    assert "model: %s.geo" % hex_case.name in case
+    assert "scalar per element: 1 T data/****/T" in case
+    assert "vector per element: 1 U data/****/U" in case
+    assert case.rstrip().endswith("time values:\n0")
+
+    geo = (out / (hex_case.name + ".geo")).read_bytes()
+    assert geo.startswith(b"C Binary")
+    # Header, then part 1: its number, name, coordinates and 8 points
+    part = 5 * 80
+    assert geo[part : part + 4] == b"part" and struct.unpack("<i", geo[part + 80 : part + 84]) == (1,)
+    coordinates = part + 84 + 80
+    assert struct.unpack("<i", geo[coordinates + 80 : coordinates + 84]) == (8,)
+    hexa = coordinates + 84 + 8 * 3 * 4
+    assert geo[hexa : hexa + 5] == b"hexa8"
+    count, *nodes = struct.unpack("<9i", geo[hexa + 80 : hexa + 116])
+    assert count == 1 and sorted(nodes) == list(range(1, 9))
+    assert b"quad4" in geo[hexa:]
+
+    T = (out / "data" / "0000" / "T").read_bytes()
+    # Description, part 1, hexa8 and its value, then the six wall faces
+    assert struct.unpack("<f", T[80 + 84 + 80 : 80 + 84 + 84]) == (300.0,)
+    assert struct.unpack("<6f", T[-24:]) == (300.0,) * 6
+    U = (out / "data" / "0000" / "U").read_bytes()
+    assert struct.unpack("<6f", U[-24:]) == (0.0,) * 6
+
+
+def _png_pixels(png):
+    assert png[:8] == b"\x89PNG\r\n\x1a\n"
+    width, height = struct.unpack(">II", png[16:24])
+    data, at = b"", 8
+    while at < len(png):
+        (length,) = struct.unpack(">I", png[at : at + 4])
+        if png[at + 4 : at + 8] == b"IDAT":
+            data += png[at + 8 : at + 8 + length]
+        at += 12 + length
+    rows = zlib.decompress(data)
+    return width, height, [rows[r * (4 * width + 1) + 1 : (r + 1) * (4 * width + 1)] for r in range(height)]
+
+
+def test_render_slice_png(hex_case):
+    width, height, rows = _png_pixels(accelerator.render_slice_png(str(hex_case), "0", "T", "z", resolution=32))
+
+    # A uniform field sits in the middle of the map across the whole square
+    assert (width, height) == (32, 32)
+    assert all(row == bytes([33, 145, 140, 255]) * 32 for row in rows)
+
+
+def test_render_slice_png_letterboxed(hex_case):
+    png = accelerator.render_slice_png(str(hex_case), "0", "U", ([0.5, 0.5, 0.5], [1, 0, 0]), resolution=(40, 20))
+    width, height, rows = _png_pixels(png)
+
+    assert (width, height) == (40, 20)
+    assert rows[10][:4] == bytes(4) and rows[10][80:84] != bytes(4)
+
+
+def test_render_slice_png_bad_plane(hex_case):
+    with pytest.raises(ValueError):
+        accelerator.render_slice_png(str(hex_case), "0", "T", "w")
@@ -0,0 +1,140 @@
+"""internalField lookup in the Rust accelerator for files whose value sits far
+from the keyword: long comment banners, many header entries, comments
+between the tokens."""
+
+import math
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+HEADER = """FoamFile
+{
+    version     2.0;
+    format      ascii;
+    class       %s;
+    object      %s;
+}
+"""
+
+BANNER = "/*" + "-" * 76 + "*\\\n" + "| long banner line |\n" * 60 + "\\*" + "-" * 76 + "*/\n"
+
+
+def write_field(tmp_path, name, body, cls="volScalarField"):
+    path = tmp_path / name
+    path.write_text(BANNER + HEADER % (cls, name) + body)
+    return str(path)
+
+
+def test_long_banner_and_header_entries(tmp_path):
+    entries = "".join("    note%d \"%s\";\n" % (i, "x" * 40) for i in range(30))
+    path = tmp_path / "T"
+    path.write_text(
+        BANNER
+        + HEADER.replace("}\n", entries + "}\n") % ("volScalarField", "T")
+        + "dimensions [0 0 0 1 0 0 0];\n\ninternalField   nonuniform List<scalar> 3(1 2 3);\n"
+    )
+    assert accelerator.parse_scalar_field(str(path)) == pytest.approx(2.0)
+
+
+def test_value_more_than_500_bytes_after_keyword(tmp_path):
+    comment = "/* " + "initial condition notes " * 40 + "*/"
+    path = write_field(tmp_path, "T", "internalField %s\n    nonuniform List<scalar> 2(4 6);\n" % comment)
+    assert accelerator.parse_scalar_field(path) == pytest.approx(5.0)
+
+    path = write_field(tmp_path, "p", "internalField" + " " * 800 + "\n" * 50 + "uniform 3.5;\n")
+    assert accelerator.parse_scalar_field(path) == pytest.approx(3.5)
+
+
+def test_comments_between_tokens(tmp_path):
+    body = (
+        "internalField // set by setFields\n"
+        "    nonuniform /* generated */ List<vector> // three cells\n"
+        "    3\n"
+        "    (\n(1 0 0)\n(2 0 0)\n(3 0 0)\n)\n;\n"
+    )
+    path = write_field(tmp_path, "U", body, cls="volVectorField")
+    assert accelerator.parse_vector_field(path) == pytest.approx((2.0, 0.0, 0.0))
+
+    path = write_field(tmp_path, "V", "internalField /* (9 9 9) */ uniform (0 0 1); // note\n", cls="volVectorField")
+    assert accelerator.parse_vector_field(path) == pytest.approx((0.0, 0.0, 1.0))
+
+
+def test_keyword_in_banner_is_skipped(tmp_path):
+    body = "// internalField is initialised below, see setFieldsDict\n\ninternalField uniform 7;\n"
+    path = write_field(tmp_path, "T", body)
+    assert accelerator.parse_scalar_field(path) == pytest.approx(7.0)
+
+
+def test_missing_value_raises(tmp_path):
+    path = write_field(tmp_path, "T", "internalField   /* to be filled in */ ;\n")
+    with pytest.raises(accelerator.FieldParseError):
+        accelerator.parse_scalar_field(path)
+    assert accelerator.parse_scalar_field(path, lenient=True) is None
+
+
+def test_commented_out_alternatives_are_ignored(tmp_path):
+    body = (
+        "// internalField   uniform 300;\n"
+        "/* internalField nonuniform List<scalar> 2(100 100); */\n"
+        "internalField   nonuniform List<scalar>\n4\n(\n1 // first cell (inlet)\n2 /* 99 (x) */\n3\n4\n)\n;\n"
+        "boundaryField\n{\n    // boundaryField notes\n}\n"
+    )
+    path = write_field(tmp_path, "T", body)
+    assert accelerator.parse_scalar_field(path) == pytest.approx(2.5)
+
+    body = "//internalField uniform (5 5 5);\ninternalField nonuniform List<vector> 2\n(\n(1 0 0) // (9 9 9)\n/* (7 7 7) */ (3 0 0)\n);\n"
+    path = write_field(tmp_path, "U", body, cls="volVectorField")
+    assert accelerator.parse_vector_field(path) == pytest.approx((2.0, 0.0, 0.0))
+
+
+def test_commented_out_dimensions_are_ignored(tmp_path):
+    body = "/* dimensions [1 0 0 0 0 0 0]; */\ndimensions [0 0 0 1 0 0 0];\ninternalField uniform 1;\n"
+    path = write_field(tmp_path, "T", body)
+    assert accelerator.parse_field_dimensions(path) == (0, 0, 0, 1, 0, 0, 0)
+
+
+def test_empty_list_is_not_a_missing_file(tmp_path):
+    for value in ["List<scalar> 0()", "0()", "List<scalar> 0{0}"]:
+        path = write_field(tmp_path, "T", "internalField nonuniform %s;\n" % value)
+
+        assert math.isnan(accelerator.parse_scalar_field(path))
+        stats = accelerator.field_stats(path)
+        assert stats.count == 0 and math.isnan(stats.mean)
+        info = accelerator.field_info(path)
+        assert (info["uniform"], info["count"]) == (False, 0)
+
+
+def test_empty_vector_list(tmp_path):
+    path = write_field(tmp_path, "U", "internalField nonuniform List<vector> 0{(0 0 0)};\n", cls="volVectorField")
+    assert all(math.isnan(c) for c in accelerator.parse_vector_field(path))
+
+
+def test_uniform_list_shorthand(tmp_path):
+    path = write_field(tmp_path, "T", "internalField nonuniform List<scalar> 3{2.5};\n")
+    assert accelerator.parse_scalar_field(path) == pytest.approx(2.5)
+
+
+def test_uniform_list_shorthand_expands(tmp_path):
+    pytest.importorskip("numpy")
+    path = write_field(tmp_path, "T", "internalField nonuniform List<scalar> 3{2.5};\n")
+    assert accelerator.read_scalar_field(path).tolist() == [2.5] * 3
+
+    path = write_field(tmp_path, "U", "internalField nonuniform List<vector> 4{(1 0 0)};\n", cls="volVectorField")
+    assert accelerator.read_vector_field(path).tolist() == [[1.0, 0.0, 0.0]] * 4
+
+
+def test_count_mismatch_raises(tmp_path):
+    # A list cut short while the solver is still writing it
+    path = write_field(tmp_path, "T", "internalField nonuniform List<scalar> 4(1 2 3);\n")
+    with pytest.raises(accelerator.FieldCountError) as error:
+        accelerator.parse_scalar_field(path)
+    assert (error.value.expected, error.value.found) == (4, 3)
+    assert "truncated" in str(error.value)
+    with pytest.raises(accelerator.FieldCountError):
+        accelerator.field_stats(path)
+
+    path = write_field(tmp_path, "U", "internalField nonuniform List<vector> 1((1 0 0)(2 0 0));\n", cls="volVectorField")
+    with pytest.raises(accelerator.FieldCountError) as error:
+        accelerator.parse_vector_field(path)
+    assert (error.value.expected, error.value.found) == (1, 2)
@@ -0,0 +1,56 @@
+"""LogFollower: time steps of a solver log as the solver writes them."""
+
+import threading
+import time
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+STEP = "Time = %g\n\nsmoothSolver:  Solving for Ux, Initial residual = 0.5, Final residual = 0.01, No Iterations 2\n"
+
+
+def test_follows_a_log_through_rotation_to_end(tmp_path):
+    path = tmp_path / "log.foamRun"
+
+    def solver():
+        time.sleep(0.2)
+        with open(path, "w") as log:
+            for t in (0.1, 0.2):
+                log.write(STEP % t)
+                log.flush()
+                time.sleep(0.1)
+        path.rename(tmp_path / "log.foamRun.1")
+        path.write_text(STEP % 0.3 + "\nEnd\n")
+
+    thread = threading.Thread(target=solver)
+    thread.start()
+    steps = list(accelerator.LogFollower(str(path), poll_interval=0.05, timeout=5))
+    thread.join()
+
+    assert [s.time for s in steps] == [0.1, 0.2, 0.3]
+    assert "Ux" in steps[0].residuals
+
+
+def test_timeout_and_from_start(tmp_path):
+    path = tmp_path / "log"
+    path.write_text(STEP % 0.1 + STEP % 0.2)
+
+    assert [s.time for s in accelerator.LogFollower(str(path), timeout=0.2)] == [0.1]
+    assert list(accelerator.LogFollower(str(path), timeout=0.2, from_start=False)) == []
+
+
+def test_truncation_drops_an_unfinished_line(tmp_path):
+    path = tmp_path / "log"
+    path.write_text(STEP % 0.1 + "smoothSolver:  Solving for Uy, Initial residual = 0.9")
+
+    def solver():
+        time.sleep(0.2)
+        path.write_text("Time = 0.2\nEnd\n")
+
+    thread = threading.Thread(target=solver)
+    thread.start()
+    steps = list(accelerator.LogFollower(str(path), poll_interval=0.05, timeout=5))
+    thread.join()
+
+    assert [s.time for s in steps] == [0.1, 0.2]
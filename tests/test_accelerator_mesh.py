@@ -0,0 +1,125 @@
+"""polyMesh readers of the Rust accelerator on a small hand-written mesh."""
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+HEADER = "FoamFile\n{\n    format ascii;\n    class %s;\n    object %s;\n}\n"
+
+# Two unit hexahedra stacked along z: the shared face first, then the five
+# outer faces of each cell, all on one patch
+POINTS = (
+    "12((0 0 0) (1 0 0) (1 1 0) (0 1 0) (0 0 1) (1 0 1) (1 1 1) (0 1 1)"
+    " (0 0 2) (1 0 2) (1 1 2) (0 1 2))"
+)
+FACES = (
+    "11(4(4 5 6 7)"
+    " 4(0 3 2 1) 4(0 1 5 4) 4(3 7 6 2) 4(0 4 7 3) 4(1 2 6 5)"
+    " 4(8 9 10 11) 4(4 5 9 8) 4(7 11 10 6) 4(4 8 11 7) 4(5 6 10 9))"
+)
+OWNER = "11(0 0 0 0 0 0 1 1 1 1 1)"
+NEIGHBOUR = "1(1)"
+BOUNDARY = "1(walls { type wall; nFaces 10; startFace 1; })"
+
+
+def write_mesh(root, faces=FACES, owner=OWNER, neighbour=NEIGHBOUR, faces_class="faceList", note=None):
+    mesh = root / "constant" / "polyMesh"
+    mesh.mkdir(parents=True, exist_ok=True)
+    owner_header = HEADER % ("labelList", "owner")
+    if note:
+        owner_header = owner_header.replace("}\n", '    note "%s";\n}\n' % note)
+    (mesh / "points").write_text(HEADER % ("vectorField", "points") + POINTS)
+    (mesh / "faces").write_text(HEADER % (faces_class, "faces") + faces)
+    (mesh / "owner").write_text(owner_header + owner)
+    (mesh / "neighbour").write_text(HEADER % ("labelList", "neighbour") + neighbour)
+    (mesh / "boundary").write_text(HEADER % ("polyBoundaryMesh", "boundary") + BOUNDARY)
+    return root
+
+
+def test_decreasing_face_offsets_rejected(tmp_path):
+    # faceCompactList: offsets then labels; face 1 would end before it starts
+    offsets = "12(0 4 2 12 16 20 24 28 32 36 40 44)"
+    labels = "44(" + " ".join(["0 1 2 3"] * 11) + ")"
+    write_mesh(tmp_path, faces=offsets + "\n" + labels, faces_class="faceCompactList")
+
+    with pytest.raises(ValueError, match="face offsets"):
+        accelerator.compute_cell_geometry(str(tmp_path))
+
+
+def test_face_offsets_past_labels_rejected(tmp_path):
+    offsets = "12(0 4 8 12 16 20 24 28 32 36 40 48)"
+    labels = "44(" + " ".join(["0 1 2 3"] * 11) + ")"
+    write_mesh(tmp_path, faces=offsets + "\n" + labels, faces_class="faceCompactList")
+
+    with pytest.raises(ValueError, match="face offsets out of range"):
+        accelerator.compute_cell_geometry(str(tmp_path))
+
+
+def test_neighbour_longer_than_owner_rejected(tmp_path):
+    write_mesh(tmp_path, neighbour="12(" + "1 " * 12 + ")")
+
+    with pytest.raises(ValueError, match="neighbour has 12 entries for 11 faces"):
+        accelerator.compute_cell_geometry(str(tmp_path))
+
+
+def test_owner_past_declared_cells_rejected(tmp_path):
+    # The owner note says one cell, but the labels name two
+    write_mesh(tmp_path, note="nPoints:12  nCells:1  nFaces:11  nInternalFaces:1")
+
+    with pytest.raises(ValueError, match="owner labels out of range"):
+        accelerator.compute_cell_geometry(str(tmp_path))
+
+
+def test_mesh_summary_counts(tmp_path):
+    summary = accelerator.mesh_summary(str(write_mesh(tmp_path)))
+
+    assert (summary["n_points"], summary["n_faces"], summary["n_internal_faces"], summary["n_cells"]) == (12, 11, 1, 2)
+    assert summary["bounds"] == ((0.0, 0.0, 0.0), (1.0, 1.0, 2.0))
+    assert [(p["name"], p["type"], p["n_faces"], p["start_face"]) for p in summary["patches"]] == [("walls", "wall", 10, 1)]
+
+
+def test_read_boundary(tmp_path):
+    (walls,) = accelerator.read_boundary(str(write_mesh(tmp_path)))
+
+    assert (walls["name"], walls["type"], walls["n_faces"], walls["start_face"]) == ("walls", "wall", 10, 1)
+
+
+def test_read_mesh_topology_counts(tmp_path):
+    pytest.importorskip("numpy")
+    topology = accelerator.read_mesh_topology(str(write_mesh(tmp_path)))
+
+    assert (topology["n_faces"], topology["n_internal_faces"], topology["n_cells"]) == (11, 1, 2)
+    assert topology["face_offsets"].tolist() == list(range(0, 45, 4))
+    assert topology["face_labels"][:4].tolist() == [4, 5, 6, 7]
+    assert topology["owner"].tolist() == [0] * 6 + [1] * 5
+    assert topology["neighbour"].tolist() == [1]
+
+
+def test_read_points_and_faces(tmp_path):
+    pytest.importorskip("numpy")
+    root = str(write_mesh(tmp_path))
+    points = accelerator.read_points(root)
+    offsets, labels = accelerator.read_faces(root)
+
+    assert points.shape == (12, 3) and points[-1].tolist() == [0.0, 1.0, 2.0]
+    assert (len(offsets) - 1, len(labels)) == (11, 44)
+    assert accelerator.read_owner(root).shape == (11,)
+    assert accelerator.read_neighbour(root).tolist() == [1]
+
+
+def test_cell_volumes(tmp_path):
+    pytest.importorskip("numpy")
+    volumes, centres = accelerator.compute_cell_geometry(str(write_mesh(tmp_path)))
+
+    assert volumes.tolist() == pytest.approx([1.0, 1.0])
+    assert centres.tolist() == pytest.approx([[0.5, 0.5, 0.5], [0.5, 0.5, 1.5]])
+
+
+def test_truncated_points_rejected(tmp_path):
+    write_mesh(tmp_path)
+    points = tmp_path / "constant" / "polyMesh" / "points"
+    # A count far beyond the body must not be trusted
+    points.write_text(HEADER % ("vectorField", "points") + "99999999999((0 0 0))")
+
+    with pytest.raises(ValueError, match="malformed or truncated point list"):
+        accelerator.read_points(str(tmp_path))
@@ -0,0 +1,28 @@
+"""Options of the Rust accelerator's parsing functions."""
+
+import os
+from concurrent.futures import ThreadPoolExecutor
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+FIELD = "FoamFile { format ascii; class volScalarField; object p; }\ninternalField nonuniform List<scalar> 3(1 2 3);\n"
+
+
+def test_threads_bounded_by_cores():
+    assert accelerator.Options(threads=2).threads == 2
+    with pytest.raises(ValueError, match="per core"):
+        accelerator.Options(threads=os.cpu_count() * 1000)
+    with pytest.raises(ValueError):
+        accelerator.Options(threads=0)
+
+
+def test_concurrent_calls_share_pools(tmp_path):
+    path = tmp_path / "p"
+    path.write_text(FIELD)
+    options = accelerator.Options(threads=2, cache=False)
+
+    with ThreadPoolExecutor(8) as pool:
+        means = list(pool.map(lambda _: accelerator.parse_scalar_field(str(path), options=options), range(64)))
+    assert means == [2.0] * 64
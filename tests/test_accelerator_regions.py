@@ -0,0 +1,97 @@
+"""Multi-region (chtMultiRegionFoam) cases in the Rust accelerator: regions
+are found and their fields read from <time>/<region>."""
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+FIELD = """FoamFile
+{
+    format      ascii;
+    class       volScalarField;
+    object      T;
+}
+dimensions [0 0 0 1 0 0 0];
+internalField uniform %s;
+boundaryField
+{
+    ".*" { type zeroGradient; }
+}
+"""
+
+
+@pytest.fixture
+def cht_case(tmp_path):
+    (tmp_path / "constant").mkdir()
+    (tmp_path / "constant" / "regionProperties").write_text(
+        "FoamFile { class dictionary; object regionProperties; }\n"
+        "regions ( fluid (air) solid (heater) );\n"
+    )
+    for region, temperature in [("air", 300), ("heater", 350)]:
+        (tmp_path / "constant" / region / "polyMesh").mkdir(parents=True)
+        (tmp_path / "0" / region).mkdir(parents=True)
+        (tmp_path / "0" / region / "T").write_text(FIELD % temperature)
+    return tmp_path
+
+
+def test_list_regions(cht_case):
+    assert accelerator.list_regions(str(cht_case)) == ["air", "heater"]
+
+    # Without regionProperties, region meshes are found under constant/
+    (cht_case / "constant" / "regionProperties").unlink()
+    assert accelerator.list_regions(str(cht_case)) == ["air", "heater"]
+
+
+def test_region_fields(cht_case):
+    root = str(cht_case)
+    assert accelerator.list_fields(root, "0") == {}
+    assert accelerator.list_fields(root, "0", region="heater") == {"T": "volScalarField"}
+
+    case = accelerator.Case(root, region="heater")
+    assert case.fields("0") == ["T"]
+    assert case.field("0", "T").stats().mean == pytest.approx(350)
+
+
+# A single unit hexahedron, all six faces on one patch
+POINTS = "8((0 0 0) (1 0 0) (1 1 0) (0 1 0) (0 0 1) (1 0 1) (1 1 1) (0 1 1))"
+FACES = "6(4(0 3 2 1) 4(4 5 6 7) 4(0 1 5 4) 4(3 7 6 2) 4(0 4 7 3) 4(1 2 6 5))"
+BOUNDARY = "1(walls { type wall; nFaces 6; startFace 0; })"
+
+
+def write_hex_mesh(mesh):
+    header = "FoamFile { format ascii; class %s; object %s; }\n"
+    (mesh / "points").write_text(header % ("vectorField", "points") + POINTS)
+    (mesh / "faces").write_text(header % ("faceList", "faces") + FACES)
+    (mesh / "owner").write_text(header % ("labelList", "owner") + "6(0 0 0 0 0 0)")
+    (mesh / "neighbour").write_text(header % ("labelList", "neighbour") + "0()")
+    (mesh / "boundary").write_text(header % ("polyBoundaryMesh", "boundary") + BOUNDARY)
+
+
+def test_region_surfaces_and_time_average(cht_case):
+    pytest.importorskip("numpy")
+    root = str(cht_case)
+    write_hex_mesh(cht_case / "constant" / "heater" / "polyMesh")
+    (cht_case / "1" / "heater").mkdir(parents=True)
+    (cht_case / "1" / "heater" / "T").write_text(FIELD % 450)
+
+    patch = accelerator.extract_patch_surface(root, "walls", "0", "T", region="heater")
+    assert (patch["n_vertices"], patch["n_triangles"]) == (8, 12)
+    assert patch["range"] == pytest.approx((350, 350))
+
+    section = accelerator.slice_plane(root, "0", "T", [0.5, 0.5, 0.5], [0, 0, 1], region="heater")
+    assert section["n_triangles"] > 0 and section["range"] == pytest.approx((350, 350))
+
+    assert accelerator.isosurface(root, "0", "T", 300.0, region="heater")["n_triangles"] == 0
+
+    average = accelerator.time_average_field(root, "T", region="heater")
+    assert average["times"] == ["0", "1"]
+    assert list(average["mean"]) == pytest.approx([400.0])
+
+
+def test_region_render_slice_png(cht_case):
+    write_hex_mesh(cht_case / "constant" / "heater" / "polyMesh")
+
+    png = accelerator.render_slice_png(str(cht_case), "0", "T", "z", resolution=8, region="heater")
+    assert png[:8] == b"\x89PNG\r\n\x1a\n"
+    with pytest.raises(FileNotFoundError):
+        accelerator.render_slice_png(str(cht_case), "0", "T", "z", resolution=8)
@@ -0,0 +1,110 @@
+"""run_solver(): a solver process whose output is logged and parsed on Rust
+threads."""
+
+import sys
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+SOLVER = """
+import sys, time
+for t in (0.1, 0.2):
+    print("Time = %g" % t)
+    print("smoothSolver:  Solving for Ux, Initial residual = 0.5, Final residual = 0.01, No Iterations 2", flush=True)
+    print("warning at %g" % t, file=sys.stderr, flush=True)
+    time.sleep(0.05)
+print("End")
+sys.exit(int(sys.argv[1]))
+"""
+
+
+def test_steps_are_parsed_and_output_logged(tmp_path):
+    seen = []
+    run = accelerator.run_solver(str(tmp_path), [sys.executable, "-c", SOLVER, "0"], on_step=lambda s: seen.append(s.time))
+
+    assert run.wait(timeout=10) == 0
+    assert run.status == "finished"
+    assert [s.time for s in run.steps()] == [0.1, 0.2]
+    assert [s.time for s in run.steps(1)] == [0.2]
+    assert seen == [0.1, 0.2]
+    log = open(run.log_path).read()
+    assert "warning at 0.2" in log and log.rstrip().endswith("End")
+
+
+def test_failure_and_kill(tmp_path):
+    run = accelerator.run_solver(str(tmp_path), [sys.executable, "-c", SOLVER, "3"], log=str(tmp_path / "log"))
+    assert run.wait(timeout=10) == 3
+    assert run.status == "failed"
+
+    run = accelerator.run_solver(str(tmp_path), [sys.executable, "-c", "import time; time.sleep(30)"])
+    run.kill()
+    assert run.wait(timeout=10) is not None
+    assert run.status == "killed"
+
+
+def test_missing_program(tmp_path):
+    with pytest.raises(OSError):
+        accelerator.run_solver(str(tmp_path), ["no-such-solver"])
+
+
+def test_monitor_samples_the_process_tree(tmp_path):
+    import time
+
+    if not sys.platform.startswith("linux"):
+        pytest.skip("monitoring reads /proc")
+    run = accelerator.run_solver(str(tmp_path), [sys.executable, "-c", "import time; time.sleep(30)"])
+    monitor = run.monitor(interval=0.1)
+    time.sleep(0.5)
+    sample = monitor.latest()
+    run.kill()
+    run.wait(timeout=10)
+
+    assert sample["processes"] == 1
+    assert sample["rss"] > 0
+    assert sample["cpu_percent"] >= 0
+
+
+MPIRUN = """
+import sys
+print(" ".join(sys.argv[1:]))
+print("[1] --> FOAM FATAL ERROR:", file=sys.stderr)
+print("[1] Maximum number of iterations exceeded", file=sys.stderr)
+print("MPI_ABORT was invoked on rank 1 in communicator MPI_COMM_WORLD", file=sys.stderr)
+sys.exit(1)
+"""
+
+
+def test_parallel_run_reports_the_aborted_rank(tmp_path):
+    (tmp_path / "system").mkdir()
+    for rank in range(2):
+        (tmp_path / ("processor%d" % rank)).mkdir()
+    launcher = tmp_path / "mpirun.py"
+    launcher.write_text(MPIRUN)
+
+    with pytest.raises(ValueError):
+        accelerator.run_parallel(str(tmp_path), ["foamRun"], 4)
+    assert not (tmp_path / "system" / "decomposeParDict").exists()
+
+    run = accelerator.run_parallel(str(tmp_path), ["foamRun"], 2, mpirun=[sys.executable, str(launcher)])
+    assert run.wait(timeout=10) == 1
+    assert run.tail()[0] == "-np 2 foamRun -parallel"
+    assert "numberOfSubdomains 2;" in (tmp_path / "system" / "decomposeParDict").read_text()
+    [report] = run.rank_report()
+    assert report.rank == 1 and report.aborted
+    assert report.errors[0].message == "Maximum number of iterations exceeded"
+
+
+def test_parallel_run_keeps_an_existing_decomposition(tmp_path):
+    (tmp_path / "system").mkdir()
+    for rank in range(2):
+        (tmp_path / ("processor%d" % rank)).mkdir()
+    dictionary = tmp_path / "system" / "decomposeParDict"
+    text = "// decomposed by hand\nnumberOfSubdomains 2;\nmethod hierarchical;\n"
+    dictionary.write_text(text)
+    launcher = tmp_path / "mpirun.py"
+    launcher.write_text(MPIRUN)
+
+    run = accelerator.run_parallel(str(tmp_path), ["foamRun"], 2, method="scotch", mpirun=[sys.executable, str(launcher)])
+    run.wait(timeout=10)
+    assert dictionary.read_text() == text
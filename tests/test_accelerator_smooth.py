@@ -0,0 +1,45 @@
+"""Smoothing of monitored signals in the Rust accelerator."""
+
+import math
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+np = pytest.importorskip("numpy")
+
+
+def test_moving_average_skips_gaps():
+    smoothed = accelerator.moving_average(np.array([1.0, 2.0, np.nan, 4.0, 5.0]), 3)
+
+    assert smoothed[:2].tolist() == [1.0, 1.5]
+    assert math.isnan(smoothed[2])
+    assert smoothed[3:].tolist() == [3.0, 4.5]
+
+
+def test_moving_average_centred_min_periods():
+    values = np.arange(5.0)
+
+    assert accelerator.moving_average(values, 3, center=True).tolist() == [0.5, 1.0, 2.0, 3.0, 3.5]
+    assert np.isnan(accelerator.moving_average(values, 3, min_periods=3)[:2]).all()
+
+
+def test_moving_average_after_a_spike():
+    # A diverged sample leaves the small ones after it averaged exactly
+    smoothed = accelerator.moving_average(np.array([1e17, 0.1, 0.2, 0.3, 0.4]), 2)
+
+    assert smoothed[2:].tolist() == pytest.approx([0.15, 0.25, 0.35], rel=1e-12)
+
+
+def test_exponential_moving_average_carries_across_gaps():
+    smoothed = accelerator.exponential_moving_average(np.array([np.nan, 2.0, 4.0, np.nan, 8.0]), alpha=0.5)
+
+    assert math.isnan(smoothed[0]) and math.isnan(smoothed[3])
+    assert smoothed[[1, 2, 4]].tolist() == [2.0, 3.0, 5.5]
+    assert accelerator.exponential_moving_average(np.array([2.0, 4.0]), span=3).tolist() == [2.0, 3.0]
+
+
+def test_exponential_moving_average_needs_one_factor():
+    with pytest.raises(ValueError):
+        accelerator.exponential_moving_average(np.zeros(3))
+    with pytest.raises(ValueError):
+        accelerator.exponential_moving_average(np.zeros(3), alpha=0.5, span=3)
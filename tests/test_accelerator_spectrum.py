@@ -0,0 +1,38 @@
+"""Power spectra of monitored signals in the Rust accelerator."""
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+np = pytest.importorskip("numpy")
+
+
+def test_compute_psd_uneven_sine():
+    # Time steps that vary, as with an adjustable time step
+    times = np.cumsum(np.tile([0.004, 0.006, 0.008], 1200))
+    values = 1.5 + 2.0 * np.sin(2 * np.pi * 7.3 * times)
+
+    result = accelerator.compute_psd(times, values, "hann", length=0.1, velocity=2.0)
+
+    assert result["peak_frequency"] == pytest.approx(7.3, abs=0.02)
+    assert result["strouhal"] == pytest.approx(7.3 * 0.1 / 2.0, abs=0.001)
+    # The density integrates to the variance of the sine
+    df = result["frequency"][1] - result["frequency"][0]
+    assert result["psd"].sum() * df == pytest.approx(2.0, rel=0.05)
+
+
+def test_compute_psd_welch_segments():
+    times = np.arange(4096) * 0.01
+    values = np.sin(2 * np.pi * 5.0 * times)
+
+    single = accelerator.compute_psd(times, values)
+    averaged = accelerator.compute_psd(times, values, "blackman", segments=7)
+
+    assert len(averaged["frequency"]) < len(single["frequency"])
+    assert averaged["peak_frequency"] == pytest.approx(5.0, abs=0.05)
+
+
+def test_compute_psd_rejects_bad_input():
+    with pytest.raises(ValueError):
+        accelerator.compute_psd(np.arange(3.0), np.zeros(3))
+    with pytest.raises(ValueError):
+        accelerator.compute_psd(np.arange(100.0), np.zeros(100), "kaiser")
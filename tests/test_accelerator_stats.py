@@ -0,0 +1,44 @@
+"""Whole-field statistics in the Rust accelerator: scalar readers refuse
+vector and tensor fields rather than pooling their components."""
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+HEADER = """FoamFile
+{
+    version     2.0;
+    format      ascii;
+    class       %s;
+    object      %s;
+}
+"""
+
+
+def write_field(tmp_path, name, values, cls="volScalarField"):
+    path = tmp_path / name
+    path.write_text(HEADER % (cls, name) + "internalField nonuniform List<%s> %d(%s);\n" % (
+        "scalar" if cls == "volScalarField" else "vector", len(values), " ".join(values)))
+    return str(path)
+
+
+SCALAR = ["1", "2", "3", "4"]
+VECTOR = ["(1 0 0)", "(0 2 0)", "(0 0 3)"]
+
+
+def test_field_stats_rejects_vectors(tmp_path):
+    stats = accelerator.field_stats(write_field(tmp_path, "p", SCALAR))
+    assert stats.count == 4 and stats.mean == pytest.approx(2.5)
+    with pytest.raises(accelerator.FieldParseError, match="scalar"):
+        accelerator.field_stats(write_field(tmp_path, "U", VECTOR, "volVectorField"))
+
+
+def test_field_histogram_rejects_vectors(tmp_path):
+    with pytest.raises(accelerator.FieldParseError, match="scalar"):
+        accelerator.field_histogram(write_field(tmp_path, "U", VECTOR, "volVectorField"), bins=4)
+
+
+def test_field_quantiles_rejects_vectors(tmp_path):
+    assert accelerator.field_quantiles(write_field(tmp_path, "p", SCALAR), [0.0, 1.0]) == pytest.approx([1.0, 4.0])
+    with pytest.raises(accelerator.FieldParseError, match="scalar"):
+        accelerator.field_quantiles(write_field(tmp_path, "U", VECTOR, "volVectorField"), [0.5])
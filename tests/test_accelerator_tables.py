@@ -0,0 +1,121 @@
+"""Arrow tables and Parquet files of monitored quantities from the Rust accelerator."""
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+LOG = """Time = 1
+
+smoothSolver:  Solving for Ux, Initial residual = 1, Final residual = 0.01, No Iterations 3
+GAMG:  Solving for p, Initial residual = 1, Final residual = 0.005, No Iterations 10
+time step continuity errors : sum local = 1e-05, global = 1e-18, cumulative = 1e-18
+ExecutionTime = 0.5 s  ClockTime = 1 s
+
+Time = 2
+
+GAMG:  Solving for p, Initial residual = 0.5, Final residual = 0.004, No Iterations 8
+ExecutionTime = 0.9 s  ClockTime = 1 s
+
+End
+"""
+
+PROBES = """# Probe 0 (0 0 0)
+# Probe 1 (1 0 0)
+#       Time  0  1
+1 (1 2 3) (4 5 6)
+2 (7 8 9) (10 11 12)
+"""
+
+
+@pytest.fixture
+def log(tmp_path):
+    path = tmp_path / "log.simpleFoam"
+    path.write_text(LOG)
+    return path
+
+
+@pytest.fixture
+def probes(tmp_path):
+    path = tmp_path / "U"
+    path.write_text(PROBES)
+    return path
+
+
+def test_residuals_table_columns(log):
+    table = accelerator.residuals_table(str(log))
+
+    assert len(table) == 2
+    assert table.column_names[:4] == ["time", "Ux_initial", "Ux_final", "Ux_iterations"]
+    assert "continuity_cumulative" in table.column_names and table.column_names[-1] == "clock_time"
+
+
+def test_residuals_table_to_pyarrow(log):
+    pa = pytest.importorskip("pyarrow")
+    table = pa.table(accelerator.residuals_table(str(log)))
+
+    assert table.column("Ux_initial").to_pylist() == [1.0, None]
+    assert table.column("p_iterations").to_pylist() == [10, 8]
+    assert table.column("continuity_local").to_pylist() == [1e-05, None]
+
+
+def test_probes_table(probes):
+    table = accelerator.probes_table(str(probes))
+
+    assert table.column_names == ["time", "U0_x", "U0_y", "U0_z", "U1_x", "U1_y", "U1_z"]
+    assert table.metadata == {"locations": "0 0 0;1 0 0"}
+    pa = pytest.importorskip("pyarrow")
+    assert pa.record_batch(table).column(4).to_pylist() == [4.0, 10.0]
+
+
+def test_probes_table_missing(tmp_path):
+    with pytest.raises(FileNotFoundError):
+        accelerator.probes_table(str(tmp_path / "U"))
+
+
+def test_to_parquet(log, tmp_path):
+    out = tmp_path / "residuals.parquet"
+    accelerator.residuals_table(str(log)).to_parquet(str(out))
+
+    data = out.read_bytes()
+    assert data[:4] == b"PAR1" and data[-4:] == b"PAR1"
+    pq = pytest.importorskip("pyarrow.parquet")
+    assert pq.read_table(out).column("p_initial").to_pylist() == [1.0, 0.5]
+
+
+HEADER = "FoamFile\n{\n    format ascii;\n    class %s;\n    object %s;\n}\n"
+
+
+@pytest.fixture
+def case(tmp_path):
+    for time, T in [("0", "uniform 300"), ("0.5", "nonuniform List<scalar> 3(300 310 320)")]:
+        (tmp_path / time).mkdir()
+        (tmp_path / time / "T").write_text(HEADER % ("volScalarField", "T") + "internalField %s;\n" % T)
+    (tmp_path / "0.5" / "U").write_text(HEADER % ("volVectorField", "U") + "internalField uniform (1 2 2);\n")
+    return tmp_path
+
+
+def test_export_case_summary_csv(case):
+    out = case / "report.csv"
+    result = accelerator.export_case_summary(str(case), None, str(out))
+
+    assert (result["fields"], result["rows"]) == (["T", "U"], 3)
+    lines = out.read_text().splitlines()
+    assert lines[0] == "time,field,count,min,max,std,mean,mean_x,mean_y,mean_z"
+    assert lines[2].startswith("0.5,T,3,300,320,") and lines[2].endswith(",310,,,")
+    assert lines[3] == "0.5,U,1,3,3,0,,1,2,2"
+
+
+def test_export_case_summary_json(case):
+    import json
+
+    out = case / "report.json"
+    accelerator.export_case_summary(str(case), ["U", "T"], str(out))
+
+    records = json.loads(out.read_text())
+    assert [(r["time"], r["field"]) for r in records] == [(0, "T"), (0.5, "U"), (0.5, "T")]
+    assert records[1]["mean"] == [1, 2, 2]
+
+
+def test_export_case_summary_bad_format(case):
+    with pytest.raises(ValueError):
+        accelerator.export_case_summary(str(case), None, str(case / "report.txt"), format="xlsx")
@@ -0,0 +1,66 @@
+"""watch_case(): callbacks from a Rust thread as a running case writes
+time directories and logs."""
+
+import time
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+
+FIELD = """FoamFile
+{
+    version     2.0;
+    format      ascii;
+    class       volScalarField;
+    object      T;
+}
+internalField uniform 300;
+boundaryField
+{
+}
+"""
+
+
+def wait_for(events, count, timeout=5.0):
+    deadline = time.monotonic() + timeout
+    while len(events) < count and time.monotonic() < deadline:
+        time.sleep(0.05)
+    return events
+
+
+def watch_a_run(tmp_path, poll):
+    (tmp_path / "0").mkdir()
+    (tmp_path / "0" / "T").write_text(FIELD)
+    events = []
+
+    with accelerator.watch_case(str(tmp_path), lambda *e: events.append(e), debounce=0.1, poll=poll) as watcher:
+        assert watcher.running
+        (tmp_path / "0.5").mkdir()
+        (tmp_path / "0.5" / "T").write_text(FIELD[: len(FIELD) // 2])
+        time.sleep(0.6)
+        assert events == []
+
+        (tmp_path / "0.5" / "T").write_text(FIELD)
+        (tmp_path / "log.foamRun").write_text("Time = 0.5\n")
+        wait_for(events, 2)
+
+    assert not watcher.running
+    assert sorted(events) == [("log", "log.foamRun"), ("time", "0.5")]
+
+
+def test_reports_written_time_directories_and_log_growth(tmp_path):
+    watch_a_run(tmp_path, poll=None)
+
+
+def test_polling_reports_the_same(tmp_path):
+    watch_a_run(tmp_path, poll=0.2)
+
+
+def test_callback_errors_do_not_stop_the_watcher(tmp_path):
+    def fail(kind, name):
+        raise RuntimeError(name)
+
+    with accelerator.watch_case(str(tmp_path), fail, debounce=0.05) as watcher:
+        (tmp_path / "log").write_text("x\n")
+        time.sleep(0.4)
+        assert watcher.running
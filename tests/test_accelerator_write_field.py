@@ -0,0 +1,71 @@
+"""write_scalar_field and write_vector_field in the Rust accelerator, read
+back through the field readers in ASCII and binary."""
+
+import pytest
+
+accelerator = pytest.importorskip("accelerator")
+np = pytest.importorskip("numpy")
+
+PRESSURE = [0, 0, 1, -2, 0, 0, 0]
+VELOCITY = [0, 1, -1, 0, 0, 0, 0]
+SCALARS = [1.5, -2.0, 3e-7, 1e20, 0.1]
+VECTORS = [[1.0, 0.0, -0.5], [2.5e-5, 3.0, 1e12]]
+
+
+def values_of(root, name):
+    # Field.values() reads binary files as well as ASCII ones
+    return accelerator.Case(str(root)).field("0", name).values().tolist()
+
+
+def test_scalar_round_trip_ascii(tmp_path):
+    path = tmp_path / "0" / "p"
+    path.parent.mkdir()
+    accelerator.write_scalar_field(str(path), np.array(SCALARS), PRESSURE, {"outlet": "zeroGradient"})
+
+    assert accelerator.read_scalar_field(str(path)).tolist() == SCALARS
+    assert values_of(tmp_path, "p") == SCALARS
+    assert accelerator.parse_field_dimensions(str(path)) == tuple(PRESSURE)
+
+
+def test_scalar_round_trip_binary(tmp_path):
+    path = tmp_path / "0" / "p"
+    path.parent.mkdir()
+    accelerator.write_scalar_field(str(path), np.array(SCALARS), PRESSURE, {"outlet": "zeroGradient"}, binary=True)
+
+    assert b"format      binary;" in path.read_bytes()
+    assert values_of(tmp_path, "p") == SCALARS
+
+
+def test_vector_round_trip_ascii_and_binary(tmp_path):
+    (tmp_path / "0").mkdir()
+    for name, binary in [("U", False), ("Ub", True)]:
+        path = tmp_path / "0" / name
+        accelerator.write_vector_field(str(path), np.array(VECTORS), VELOCITY, {"walls": "noSlip"}, binary=binary)
+        assert values_of(tmp_path, name) == VECTORS
+    assert accelerator.read_vector_field(str(tmp_path / "0" / "U")).tolist() == VECTORS
+
+
+def test_uniform_values_and_boundary(tmp_path):
+    (tmp_path / "0").mkdir()
+    spec = {"inlet": {"type": "fixedValue", "value": 300.0}, "outlet": {"type": "fixedValue", "value": np.array([1.0, 2.0])}}
+    for name, binary in [("T", False), ("Tb", True)]:
+        path = tmp_path / "0" / name
+        accelerator.write_scalar_field(str(path), np.full(4, 300.0), [0, 0, 0, 1, 0, 0, 0], spec, binary=binary)
+        assert values_of(tmp_path, name) == [300.0] * 4
+
+    patches = accelerator.read_boundary_conditions(str(tmp_path), "0")["T"]
+    assert patches["inlet"] == {"type": "fixedValue", "value": 300.0}
+    assert patches["outlet"]["value"].tolist() == [1.0, 2.0]
+
+
+def test_empty_field_round_trip(tmp_path):
+    (tmp_path / "0").mkdir()
+    for binary in [False, True]:
+        scalar, vector = tmp_path / "0" / "p", tmp_path / "0" / "U"
+        accelerator.write_scalar_field(str(scalar), np.zeros(0), PRESSURE, {}, binary=binary)
+        accelerator.write_vector_field(str(vector), np.zeros((0, 3)), VELOCITY, {}, binary=binary)
+        assert values_of(tmp_path, "p") == []
+        assert len(values_of(tmp_path, "U")) == 0
+        if not binary:
+            assert accelerator.read_scalar_field(str(scalar)).shape == (0,)
+            assert accelerator.read_vector_field(str(vector)).shape == (0, 3)
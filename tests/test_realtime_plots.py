@@ -156,8 +156,9 @@ def test_get_available_fields(tmp_path):
     file2 = time_dir / "U"
     hidden_file = time_dir / ".hidden"
 
-    file1.write_text("dummy")
-    file2.write_text("dummy")
+    header = "FoamFile\n{\n    format ascii;\n    class %s;\n}\ninternalField uniform %s;\n"
+    file1.write_text(header % ("volScalarField", "0"))
+    file2.write_text(header % ("volVectorField", "(0 0 0)"))
     hidden_file.write_text("dummy")
 
     fields = get_available_fields(tmp_path)